@@ -10,6 +10,8 @@ extern "C" {
 pub(crate) struct Config {
     pub(crate) username: Option<String>,
     pub(crate) public_address: String,
+    pub(crate) secure: bool,
+    pub(crate) csrf_token: Option<String>,
     pub(crate) allowed_login_methods:
         std::collections::HashSet<crate::protocol::AuthType>,
     pub(crate) oauth_login_urls:
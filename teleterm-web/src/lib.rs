@@ -10,14 +10,16 @@ use crate::prelude::*;
 #[allow(clippy::large_enum_variant)]
 #[derive(Clone)]
 enum Msg {
-    Login(String),
+    Login(String, String),
     LoggedIn(seed::fetch::ResponseDataResult<crate::protocol::LoginResponse>),
     Refresh,
     List(seed::fetch::ResponseDataResult<Vec<crate::protocol::Session>>),
+    Filter(String),
     StartWatching(String),
     Watch(String, crate::ws::WebSocketEvent),
     StopWatching,
     Logout,
+    LogoutEverywhere,
     LoggedOut(seed::fetch::FetchObject<()>),
 }
 
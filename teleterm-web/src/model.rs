@@ -32,6 +32,7 @@ enum State {
 pub(crate) struct Model {
     config: crate::config::Config,
     state: State,
+    filter: String,
 }
 
 impl Model {
@@ -43,6 +44,7 @@ impl Model {
         let self_ = Self {
             config,
             state: State::Login,
+            filter: "".to_string(),
         };
         if logged_in {
             self_.list(orders);
@@ -56,9 +58,9 @@ impl Model {
         orders: &mut impl Orders<crate::Msg>,
     ) {
         match msg {
-            crate::Msg::Login(username) => {
+            crate::Msg::Login(username, password) => {
                 log::debug!("login for username {}", username);
-                self.login(&username, orders);
+                self.login(&username, &password, orders);
             }
             crate::Msg::LoggedIn(response) => match response {
                 Ok(response) => {
@@ -83,6 +85,10 @@ impl Model {
                     log::error!("error getting sessions: {:?}", e);
                 }
             },
+            crate::Msg::Filter(filter) => {
+                log::debug!("filtering by {}", filter);
+                self.filter = filter;
+            }
             crate::Msg::StartWatching(id) => {
                 log::debug!("watching {}", id);
                 self.watch(&id, orders);
@@ -121,7 +127,11 @@ impl Model {
             }
             crate::Msg::Logout => {
                 log::debug!("logout");
-                self.logout(orders);
+                self.logout(false, orders);
+            }
+            crate::Msg::LogoutEverywhere => {
+                log::debug!("logout everywhere");
+                self.logout(true, orders);
             }
             crate::Msg::LoggedOut(..) => {
                 log::debug!("logged out");
@@ -159,14 +169,21 @@ impl Model {
         self.config.username.as_ref().map(|s| s.as_str())
     }
 
-    pub(crate) fn sessions(&self) -> &[crate::protocol::Session] {
+    pub(crate) fn sessions(&self) -> Vec<&crate::protocol::Session> {
         if let State::List(sessions) = &self.state {
             sessions
+                .iter()
+                .filter(|session| matches_filter(session, &self.filter))
+                .collect()
         } else {
-            &[]
+            vec![]
         }
     }
 
+    pub(crate) fn filter(&self) -> &str {
+        &self.filter
+    }
+
     pub(crate) fn screen(&self) -> Option<&vt100::Screen> {
         if let State::Watch(conn) = &self.state {
             Some(conn.term.screen())
@@ -190,6 +207,21 @@ impl Model {
         self.config.allowed_login_methods.contains(&ty)
     }
 
+    // plain logins are checked by the web server against its own password
+    // file, and password logins are passed through to the teleterm server,
+    // but both use the same login form
+    pub(crate) fn password_login_method(
+        &self,
+    ) -> Option<crate::protocol::AuthType> {
+        [
+            crate::protocol::AuthType::Plain,
+            crate::protocol::AuthType::Password,
+        ]
+        .iter()
+        .copied()
+        .find(|ty| self.allowed_login_method(*ty))
+    }
+
     pub(crate) fn oauth_login_url(
         &self,
         ty: crate::protocol::AuthType,
@@ -197,35 +229,72 @@ impl Model {
         self.config.oauth_login_urls.get(&ty).map(|s| s.as_str())
     }
 
-    fn login(&self, username: &str, orders: &mut impl Orders<crate::Msg>) {
-        let url = format!(
-            "http://{}/login?username={}",
-            self.config.public_address, username
-        );
+    // requests which change the login state need to include the csrf token
+    // that the server gave us
+    fn post(&self, url: String) -> seed::fetch::Request {
+        let request =
+            seed::Request::new(url).method(seed::fetch::Method::Post);
+        if let Some(token) = &self.config.csrf_token {
+            request.header("X-CSRF-Token", token)
+        } else {
+            request
+        }
+    }
+
+    fn http_url(&self, path: &str) -> String {
+        let scheme = if self.config.secure { "https" } else { "http" };
+        format!("{}://{}{}", scheme, self.config.public_address, path)
+    }
+
+    fn ws_url(&self, path: &str) -> String {
+        let scheme = if self.config.secure { "wss" } else { "ws" };
+        format!("{}://{}{}", scheme, self.config.public_address, path)
+    }
+
+    fn login(
+        &self,
+        username: &str,
+        password: &str,
+        orders: &mut impl Orders<crate::Msg>,
+    ) {
+        let auth_type = if let Some(auth_type) = self.password_login_method()
+        {
+            auth_type
+        } else {
+            return;
+        };
+        let url = self.http_url("/login");
         orders.perform_cmd(
-            seed::Request::new(url).fetch_json_data(crate::Msg::LoggedIn),
+            self.post(url)
+                .send_json(&crate::protocol::LoginRequest {
+                    username: username.to_string(),
+                    password: password.to_string(),
+                    auth_type,
+                })
+                .fetch_json_data(crate::Msg::LoggedIn),
         );
     }
 
     fn list(&self, orders: &mut impl Orders<crate::Msg>) {
-        let url = format!("http://{}/list", self.config.public_address);
+        let url = self.http_url("/list");
         orders.perform_cmd(
             seed::Request::new(url).fetch_json_data(crate::Msg::List),
         );
     }
 
     fn watch(&mut self, id: &str, orders: &mut impl Orders<crate::Msg>) {
-        let url =
-            format!("ws://{}/watch?id={}", self.config.public_address, id);
+        let url = self.ws_url(&format!("/watch?id={}", id));
         let ws = crate::ws::connect(&url, id, crate::Msg::Watch, orders);
         self.state = State::Watch(WatchConn::new(ws));
     }
 
-    fn logout(&self, orders: &mut impl Orders<crate::Msg>) {
-        let url = format!("http://{}/logout", self.config.public_address);
-        orders.perform_cmd(
-            seed::Request::new(url).fetch(crate::Msg::LoggedOut),
-        );
+    fn logout(&self, everywhere: bool, orders: &mut impl Orders<crate::Msg>) {
+        let url = if everywhere {
+            self.http_url("/logout?everywhere=true")
+        } else {
+            self.http_url("/logout")
+        };
+        orders.perform_cmd(self.post(url).fetch(crate::Msg::LoggedOut));
     }
 
     fn process(&mut self, bytes: &[u8]) {
@@ -241,3 +310,18 @@ impl Model {
         }
     }
 }
+
+// XXX copied from teleterm
+fn matches_filter(session: &crate::protocol::Session, filter: &str) -> bool {
+    let fields: Vec<_> =
+        [&session.username, &session.title, &session.description]
+            .iter()
+            .copied()
+            .chain(session.tags.iter())
+            .map(|field| field.to_lowercase())
+            .collect();
+    filter
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .all(|word| fields.iter().any(|field| field.contains(&word)))
+}
@@ -2,10 +2,22 @@
 // crate or something? but ideally in a way that doesn't require pulling in
 // tokio
 
-#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, serde::Deserialize)]
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Eq,
+    Hash,
+    PartialEq,
+    serde::Deserialize,
+    serde::Serialize,
+)]
 pub enum AuthType {
     Plain,
     RecurseCenter,
+    Password,
+    SshKey,
+    Token,
 }
 
 #[derive(Clone, Debug, serde::Deserialize)]
@@ -24,6 +36,9 @@ pub(crate) struct Session {
     pub idle_time: u32,
     pub title: String,
     pub watchers: u32,
+    pub paused: bool,
+    pub description: String,
+    pub tags: Vec<String>,
 }
 
 #[derive(Clone, Debug, serde::Deserialize)]
@@ -32,6 +47,13 @@ pub(crate) struct Size {
     pub cols: u16,
 }
 
+#[derive(Clone, Debug, serde::Serialize)]
+pub(crate) struct LoginRequest {
+    pub username: String,
+    pub password: String,
+    pub auth_type: AuthType,
+}
+
 #[derive(Clone, Debug, serde::Deserialize)]
 pub(crate) struct LoginResponse {
     pub username: String,
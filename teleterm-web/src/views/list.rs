@@ -2,7 +2,15 @@ use crate::prelude::*;
 
 pub(crate) fn render(model: &crate::model::Model) -> Vec<Node<crate::Msg>> {
     vec![
-        crate::views::sessions::render(model.sessions()),
+        seed::input![
+            seed::attrs! {
+                At::Type => "search",
+                At::Placeholder => "filter",
+                At::Value => model.filter(),
+            },
+            input_ev(Ev::Input, crate::Msg::Filter),
+        ],
+        crate::views::sessions::render(&model.sessions()),
         seed::button![simple_ev(Ev::Click, crate::Msg::Refresh), "refresh"],
     ]
 }
@@ -1,7 +1,7 @@
 use crate::prelude::*;
 
 pub(crate) fn render(model: &crate::model::Model) -> Vec<Node<crate::Msg>> {
-    let plain = model.allowed_login_method(crate::protocol::AuthType::Plain);
+    let password = model.password_login_method().is_some();
     let recurse_center_url = if model
         .allowed_login_method(crate::protocol::AuthType::RecurseCenter)
     {
@@ -12,10 +12,10 @@ pub(crate) fn render(model: &crate::model::Model) -> Vec<Node<crate::Msg>> {
 
     let mut view = vec![];
 
-    if plain {
-        view.extend(render_plain());
+    if password {
+        view.extend(render_password());
     }
-    if plain && recurse_center_url.is_some() {
+    if password && recurse_center_url.is_some() {
         view.push(seed::p!["or"])
     }
     if let Some(url) = recurse_center_url {
@@ -25,7 +25,7 @@ pub(crate) fn render(model: &crate::model::Model) -> Vec<Node<crate::Msg>> {
     view
 }
 
-fn render_plain() -> Vec<Node<crate::Msg>> {
+fn render_password() -> Vec<Node<crate::Msg>> {
     vec![seed::form![
         seed::label![seed::attrs! { At::For => "username" }, "username"],
         seed::input![seed::attrs! {
@@ -33,6 +33,11 @@ fn render_plain() -> Vec<Node<crate::Msg>> {
             At::Type => "text",
             At::AutoFocus => true.as_at_value(),
         }],
+        seed::label![seed::attrs! { At::For => "password" }, "password"],
+        seed::input![seed::attrs! {
+            At::Id => "password",
+            At::Type => "password",
+        }],
         seed::input![
             seed::attrs! { At::Type => "submit", At::Value => "login" }
         ],
@@ -42,7 +47,11 @@ fn render_plain() -> Vec<Node<crate::Msg>> {
                 &seed::document().get_element_by_id("username").unwrap(),
             )
             .value();
-            crate::Msg::Login(username)
+            let password = seed::to_input(
+                &seed::document().get_element_by_id("password").unwrap(),
+            )
+            .value();
+            crate::Msg::Login(username, password)
         }),
     ]]
 }
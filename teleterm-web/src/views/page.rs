@@ -9,6 +9,10 @@ pub(crate) fn render(model: &crate::model::Model) -> Vec<Node<crate::Msg>> {
             simple_ev(Ev::Click, crate::Msg::Logout),
             "logout"
         ]);
+        view.push(seed::button![
+            simple_ev(Ev::Click, crate::Msg::LogoutEverywhere),
+            "logout everywhere"
+        ]);
     } else {
         view.push(seed::p!["not logged in"]);
     }
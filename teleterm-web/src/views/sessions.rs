@@ -1,9 +1,9 @@
 use crate::prelude::*;
 
 pub(crate) fn render(
-    sessions: &[crate::protocol::Session],
+    sessions: &[&crate::protocol::Session],
 ) -> Node<crate::Msg> {
-    let rows: Vec<_> = sessions.iter().map(row).collect();
+    let rows: Vec<_> = sessions.iter().map(|session| row(session)).collect();
     seed::table![
         seed::attrs! { At::Class => "list" },
         seed::tr![
@@ -12,6 +12,8 @@ pub(crate) fn render(
             seed::th!["idle"],
             seed::th!["watchers"],
             seed::th!["title"],
+            seed::th!["description"],
+            seed::th!["tags"],
         ],
         rows
     ]
@@ -24,7 +26,13 @@ fn row(session: &crate::protocol::Session) -> Node<crate::Msg> {
         seed::td![format!("{}x{}", session.size.cols, session.size.rows)],
         seed::td![format_time(session.idle_time)],
         seed::td![format!("{}", session.watchers)],
-        seed::td![session.title],
+        seed::td![if session.paused {
+            format!("(paused) {}", session.title)
+        } else {
+            session.title.clone()
+        }],
+        seed::td![session.description],
+        seed::td![session.tags.join(", ")],
     ]
 }
 
@@ -0,0 +1,403 @@
+use crate::prelude::*;
+use tokio::io::{AsyncRead as _, AsyncWrite as _};
+
+// https://github.com/asciinema/asciinema/blob/develop/doc/asciicast-v2.md
+const VERSION: u32 = 2;
+const READ_CHUNK_SIZE: usize = 4096;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct Header {
+    pub version: u32,
+    pub width: u16,
+    pub height: u16,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<std::collections::BTreeMap<String, String>>,
+}
+
+impl Header {
+    pub fn new(size: crate::term::Size, timestamp: Option<u64>) -> Self {
+        let env = ["SHELL", "TERM"]
+            .iter()
+            .filter_map(|var| {
+                std::env::var(var).ok().map(|val| (var.to_string(), val))
+            })
+            .collect();
+        Self {
+            version: VERSION,
+            width: size.cols,
+            height: size.rows,
+            timestamp,
+            title: None,
+            env: Some(env),
+        }
+    }
+
+    pub fn size(&self) -> crate::term::Size {
+        crate::term::Size {
+            rows: self.height,
+            cols: self.width,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Output(Vec<u8>),
+    Input(Vec<u8>),
+    Resize(crate::term::Size),
+    Marker(String),
+}
+
+pub fn parse_header(line: &[u8]) -> Result<Header> {
+    let header: Header =
+        serde_json::from_slice(line).context(crate::error::ParseAsciicast)?;
+    if header.version != VERSION {
+        return Err(Error::UnsupportedAsciicastVersion {
+            version: header.version,
+        });
+    }
+    Ok(header)
+}
+
+// returns None for blank lines and for event types we don't know about,
+// which the spec says should be ignored
+pub fn parse_event(
+    line: &[u8],
+) -> Result<Option<(std::time::Duration, Event)>> {
+    if line.iter().all(u8::is_ascii_whitespace) {
+        return Ok(None);
+    }
+    let (time, code, data): (f64, String, String) =
+        serde_json::from_slice(line).context(crate::error::ParseAsciicast)?;
+    if !time.is_finite() || time < 0.0 {
+        return Err(Error::InvalidAsciicastTime { time });
+    }
+    let time = std::time::Duration::from_secs_f64(time);
+    let event = match code.as_str() {
+        "o" => Event::Output(data.into_bytes()),
+        "i" => Event::Input(data.into_bytes()),
+        "r" => Event::Resize(parse_size(&data)?),
+        "m" => Event::Marker(data),
+        _ => return Ok(None),
+    };
+    Ok(Some((time, event)))
+}
+
+pub fn format_header(header: &Header) -> Vec<u8> {
+    let mut line = serde_json::to_vec(header).unwrap();
+    line.push(b'\n');
+    line
+}
+
+// output and input must already be valid utf8 (see Utf8Decoder)
+pub fn format_event(time: std::time::Duration, event: &Event) -> Vec<u8> {
+    let (code, data) = match event {
+        Event::Output(data) => ("o", String::from_utf8_lossy(data)),
+        Event::Input(data) => ("i", String::from_utf8_lossy(data)),
+        Event::Resize(size) => ("r", size.to_string().into()),
+        Event::Marker(label) => ("m", label.into()),
+    };
+    format!(
+        "[{}.{:06}, \"{}\", {}]\n",
+        time.as_secs(),
+        time.subsec_micros(),
+        code,
+        serde_json::to_string(&data).unwrap()
+    )
+    .into_bytes()
+}
+
+fn parse_size(data: &str) -> Result<crate::term::Size> {
+    let mut parts = data.splitn(2, 'x');
+    let cols = parts.next().and_then(|cols| cols.parse().ok());
+    let rows = parts.next().and_then(|rows| rows.parse().ok());
+    if let (Some(cols), Some(rows)) = (cols, rows) {
+        Ok(crate::term::Size { rows, cols })
+    } else {
+        Err(Error::InvalidAsciicastSize {
+            size: data.to_string(),
+        })
+    }
+}
+
+// asciicast stores terminal output as json strings, but output can be split
+// in the middle of a multibyte character, so hold on to incomplete
+// characters until the rest of them show up
+#[derive(Default)]
+pub struct Utf8Decoder {
+    buf: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn decode(&mut self, data: &[u8]) -> String {
+        self.buf.extend_from_slice(data);
+        let mut decoded = String::new();
+        loop {
+            match std::str::from_utf8(&self.buf) {
+                Ok(s) => {
+                    decoded.push_str(s);
+                    self.buf.clear();
+                    break;
+                }
+                Err(e) => {
+                    let valid = e.valid_up_to();
+                    decoded.push_str(
+                        std::str::from_utf8(&self.buf[..valid]).unwrap(),
+                    );
+                    if let Some(len) = e.error_len() {
+                        decoded.push(std::char::REPLACEMENT_CHARACTER);
+                        self.buf.drain(..valid + len);
+                    } else {
+                        self.buf.drain(..valid);
+                        break;
+                    }
+                }
+            }
+        }
+        decoded
+    }
+}
+
+// reads an asciicast file a line at a time, keeping track of where in the
+// file each event came from so that it can be read again later
+pub struct Reader<R: tokio::io::AsyncRead> {
+    input: R,
+    buf: Vec<u8>,
+    offset: u64,
+    header: Option<Header>,
+    eof: bool,
+}
+
+impl<R: tokio::io::AsyncRead> Reader<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            input,
+            buf: vec![],
+            offset: 0,
+            header: None,
+            eof: false,
+        }
+    }
+
+    pub fn header(&self) -> Option<&Header> {
+        self.header.as_ref()
+    }
+
+    // returns each event along with the offset and length of the line it
+    // was read from
+    pub fn poll_read(
+        &mut self,
+    ) -> futures::Poll<Option<(u64, u64, std::time::Duration, Event)>, Error>
+    {
+        loop {
+            let pos = self.buf.iter().position(|&c| c == b'\n');
+            let end = if let Some(pos) = pos {
+                pos + 1
+            } else if self.eof {
+                // the last line doesn't need to end with a newline
+                if self.buf.is_empty() {
+                    return Ok(futures::Async::Ready(None));
+                }
+                self.buf.len()
+            } else {
+                let mut chunk = [0; READ_CHUNK_SIZE];
+                let n = futures::try_ready!(self
+                    .input
+                    .poll_read(&mut chunk)
+                    .context(crate::error::ReadFile));
+                if n == 0 {
+                    self.eof = true;
+                }
+                self.buf.extend_from_slice(&chunk[..n]);
+                continue;
+            };
+
+            let line: Vec<_> = self.buf.drain(..end).collect();
+            let offset = self.offset;
+            let len = line.len() as u64;
+            self.offset += len;
+
+            if self.header.is_none() {
+                self.header = Some(parse_header(&line)?);
+            } else if let Some((time, event)) = parse_event(&line)? {
+                return Ok(futures::Async::Ready(Some((
+                    offset, len, time, event,
+                ))));
+            }
+        }
+    }
+}
+
+// writes an asciicast file with the same interface as ttyrec::Writer
+pub struct Writer<W: tokio::io::AsyncWrite> {
+    output: W,
+    start: std::time::Instant,
+    offset: std::time::Duration,
+    decoder: Utf8Decoder,
+    input_decoder: Utf8Decoder,
+    to_write: std::collections::VecDeque<u8>,
+}
+
+impl<W: tokio::io::AsyncWrite> Writer<W> {
+    pub fn new(output: W, header: &Header) -> Self {
+        let mut writer = Self::resume(output, std::time::Duration::default());
+        writer.to_write.extend(format_header(header));
+        writer
+    }
+
+    // continues an existing file whose last event was at offset, without
+    // writing a new header
+    pub fn resume(output: W, offset: std::time::Duration) -> Self {
+        Self {
+            output,
+            start: std::time::Instant::now(),
+            offset,
+            decoder: Utf8Decoder::new(),
+            input_decoder: Utf8Decoder::new(),
+            to_write: std::collections::VecDeque::new(),
+        }
+    }
+
+    // times are relative to when the header was written, which is also
+    // when the header's terminal size was taken
+    fn time(&self) -> std::time::Duration {
+        self.offset + self.start.elapsed()
+    }
+
+    // returns the number of bytes added to the file
+    fn push(&mut self, event: &Event) -> usize {
+        let line = format_event(self.time(), event);
+        let len = line.len();
+        self.to_write.extend(line);
+        len
+    }
+
+    pub fn frame(&mut self, data: &[u8]) -> usize {
+        let data = self.decoder.decode(data);
+        if data.is_empty() {
+            0
+        } else {
+            self.push(&Event::Output(data.into_bytes()))
+        }
+    }
+
+    pub fn input(&mut self, data: &[u8]) -> usize {
+        let data = self.input_decoder.decode(data);
+        if data.is_empty() {
+            0
+        } else {
+            self.push(&Event::Input(data.into_bytes()))
+        }
+    }
+
+    pub fn resize(&mut self, size: crate::term::Size) -> usize {
+        self.push(&Event::Resize(size))
+    }
+
+    pub fn needs_write(&self) -> bool {
+        !self.to_write.is_empty()
+    }
+
+    pub fn poll_write(&mut self) -> futures::Poll<(), Error> {
+        while !self.to_write.is_empty() {
+            let (a, b) = self.to_write.as_slices();
+            let buf = if a.is_empty() { b } else { a };
+            let n = futures::try_ready!(self
+                .output
+                .poll_write(buf)
+                .context(crate::error::WriteFile));
+            self.to_write.drain(..n);
+        }
+        Ok(futures::Async::Ready(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_header() {
+        let header = parse_header(
+            br#"{"version": 2, "width": 80, "height": 24, "timestamp": 1504467315, "env": {"TERM": "xterm-256color"}}"#,
+        )
+        .unwrap();
+        assert_eq!(header.size(), crate::term::Size { rows: 24, cols: 80 });
+        assert_eq!(header.timestamp, Some(1_504_467_315));
+        assert!(parse_header(
+            br#"{"version": 1, "width": 80, "height": 24}"#
+        )
+        .is_err());
+        assert_eq!(parse_header(&format_header(&header)).unwrap(), header);
+    }
+
+    #[test]
+    fn test_parse_event() {
+        let millis = std::time::Duration::from_millis;
+        assert_eq!(
+            parse_event(br#"[1.5, "o", "hello \u001b[1mworld\r\n"]"#)
+                .unwrap(),
+            Some((
+                millis(1500),
+                Event::Output(b"hello \x1b[1mworld\r\n".to_vec())
+            ))
+        );
+        assert_eq!(
+            parse_event(br#"[2.25, "r", "100x40"]"#).unwrap(),
+            Some((
+                millis(2250),
+                Event::Resize(crate::term::Size {
+                    rows: 40,
+                    cols: 100
+                })
+            ))
+        );
+        assert_eq!(parse_event(br#"[3, "x", "unknown"]"#).unwrap(), None);
+        assert_eq!(parse_event(b"\n").unwrap(), None);
+        assert!(parse_event(br#"[-1, "o", "a"]"#).is_err());
+        assert!(parse_event(br#"[1, "r", "big"]"#).is_err());
+    }
+
+    #[test]
+    fn test_format_event() {
+        let time = std::time::Duration::from_micros(1_000_001);
+        for event in &[
+            Event::Output("\x1b[H\"é\"\r\n".as_bytes().to_vec()),
+            Event::Input(b"ls\r".to_vec()),
+            Event::Resize(crate::term::Size { rows: 24, cols: 80 }),
+            Event::Marker("bookmark".to_string()),
+        ] {
+            let line = format_event(time, event);
+            assert_eq!(
+                parse_event(&line).unwrap(),
+                Some((time, event.clone()))
+            );
+        }
+        assert_eq!(
+            format_event(time, &Event::Output(b"a\x1b".to_vec())),
+            b"[1.000001, \"o\", \"a\\u001b\"]\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_utf8_decoder() {
+        let mut decoder = Utf8Decoder::new();
+        assert_eq!(decoder.decode(b"abc"), "abc");
+        assert_eq!(decoder.decode(b"a\xc3"), "a");
+        assert_eq!(decoder.decode(b"\xa9b"), "éb");
+        assert_eq!(decoder.decode(b"\xffc"), "\u{fffd}c");
+        assert_eq!(decoder.decode(b"\xe2\x82"), "");
+        assert_eq!(decoder.decode(b"\xac"), "€");
+    }
+}
@@ -1 +1,131 @@
+pub mod htpasswd;
+pub mod password;
 pub mod recurse_center;
+pub mod ssh_key;
+pub mod token;
+pub mod token_cache;
+
+use crate::prelude::*;
+use std::io::{Read as _, Write as _};
+use std::os::unix::fs::OpenOptionsExt as _;
+
+// the user information returned by an oauth provider
+pub struct UserInfo {
+    pub name: String,
+    pub groups: Vec<String>,
+}
+
+// checks a username and password against some local source of credentials
+pub trait Verifier: Send + Sync {
+    fn verify(&self, username: &str, password: &str) -> Result<bool>;
+}
+
+const MAX_FAILED_LOGINS: u32 = 5;
+const FAILED_LOGIN_WINDOW: std::time::Duration =
+    std::time::Duration::from_secs(300);
+
+// checking a password hash is deliberately slow, so this runs the verifier
+// on the blocking pool rather than on the event loop
+pub fn verify_blocking(
+    verifier: std::sync::Arc<dyn Verifier>,
+    username: &str,
+    password: &str,
+) -> impl futures::Future<Item = bool, Error = Error> {
+    let username = username.to_string();
+    let password = password.to_string();
+    futures::future::poll_fn(move || {
+        tokio_threadpool::blocking(|| verifier.verify(&username, &password))
+            .context(crate::error::BlockingTask)
+    })
+    .and_then(|res| res)
+}
+
+// counts failed password logins per username, so that passwords can't be
+// guessed faster than MAX_FAILED_LOGINS per FAILED_LOGIN_WINDOW
+#[derive(Default)]
+pub struct FailedLogins {
+    failures: std::sync::Mutex<
+        std::collections::HashMap<String, (u32, std::time::Instant)>,
+    >,
+}
+
+impl FailedLogins {
+    pub fn locked(&self, username: &str) -> bool {
+        self.failures.lock().unwrap().get(username).map_or(
+            false,
+            |(count, since)| {
+                *count >= MAX_FAILED_LOGINS
+                    && since.elapsed() < FAILED_LOGIN_WINDOW
+            },
+        )
+    }
+
+    pub fn record(&self, username: &str) {
+        let mut failures = self.failures.lock().unwrap();
+        // forget old failures so this doesn't grow without bound
+        failures
+            .retain(|_, (_, since)| since.elapsed() < FAILED_LOGIN_WINDOW);
+        let failure = failures
+            .entry(username.to_string())
+            .or_insert((0, std::time::Instant::now()));
+        failure.0 += 1;
+    }
+
+    pub fn clear(&self, username: &str) {
+        self.failures.lock().unwrap().remove(username);
+    }
+}
+
+fn read_file(filename: &str) -> Result<String> {
+    let mut file = std::fs::File::open(filename).context(
+        crate::error::OpenFileSync {
+            filename: filename.to_string(),
+        },
+    )?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)
+        .context(crate::error::ReadFileSync)?;
+    Ok(contents)
+}
+
+// writes to a temporary file and renames it into place, so that a server
+// reading the file concurrently never sees a partial write. the file only
+// needs to be readable by its owner, since it contains secrets.
+fn write_file(filename: &str, contents: &str) -> Result<()> {
+    let tmp_filename = format!("{}.tmp", filename);
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp_filename)
+        .context(crate::error::CreateFileSync {
+            filename: tmp_filename.clone(),
+        })?;
+    file.write_all(contents.as_bytes())
+        .context(crate::error::WriteFileSync)?;
+    file.sync_all().context(crate::error::WriteFileSync)?;
+    std::fs::rename(&tmp_filename, filename)
+        .context(crate::error::WriteFileSync)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_failed_logins() {
+        let failed_logins = FailedLogins::default();
+        for _ in 0..MAX_FAILED_LOGINS - 1 {
+            failed_logins.record("doy");
+        }
+        assert!(!failed_logins.locked("doy"));
+        failed_logins.record("doy");
+        assert!(failed_logins.locked("doy"));
+        assert!(!failed_logins.locked("someoneelse"));
+
+        failed_logins.clear("doy");
+        assert!(!failed_logins.locked("doy"));
+    }
+}
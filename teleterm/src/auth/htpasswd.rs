@@ -0,0 +1,86 @@
+use crate::prelude::*;
+
+// verifies passwords against an apache-style htpasswd file. only bcrypt
+// ($2y$ and friends, as generated by `htpasswd -B`) and sha1 ({SHA}) hashes
+// are supported. the file is reread on every login attempt, so changes to it
+// take effect immediately.
+pub struct Htpasswd {
+    filename: String,
+}
+
+impl Htpasswd {
+    pub fn new(filename: &str) -> Self {
+        Self {
+            filename: filename.to_string(),
+        }
+    }
+}
+
+impl super::Verifier for Htpasswd {
+    fn verify(&self, username: &str, password: &str) -> Result<bool> {
+        let contents = super::read_file(&self.filename)?;
+        Ok(find_hash(&contents, username)
+            .map_or(false, |hash| verify_hash(hash, password)))
+    }
+}
+
+fn find_hash<'a>(contents: &'a str, username: &str) -> Option<&'a str> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, ':');
+            Some((parts.next()?, parts.next()?))
+        })
+        .find(|(name, _)| *name == username)
+        .map(|(_, hash)| hash)
+}
+
+fn verify_hash(hash: &str, password: &str) -> bool {
+    if hash.starts_with("$2") {
+        bcrypt::verify(password, hash).unwrap_or(false)
+    } else if hash.starts_with("{SHA}") {
+        let mut digest = sha1::Sha1::default();
+        digest.update(password.as_bytes());
+        let expected = base64::encode(&digest.digest().bytes());
+        let hash = &hash[5..];
+        hash.len() == expected.len()
+            && openssl::memcmp::eq(hash.as_bytes(), expected.as_bytes())
+    } else {
+        log::warn!("unsupported htpasswd hash format");
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_find_hash() {
+        let contents = "# comment\n\
+                        doy:$2y$05$abc\n\
+                        \n\
+                        sartak:{SHA}def:ghi\n";
+        assert_eq!(find_hash(contents, "doy"), Some("$2y$05$abc"));
+        assert_eq!(find_hash(contents, "sartak"), Some("{SHA}def:ghi"));
+        assert_eq!(find_hash(contents, "toft"), None);
+        assert_eq!(find_hash(contents, "# comment"), None);
+    }
+
+    #[test]
+    fn test_verify_hash() {
+        // generated with `htpasswd -nbs doy hunter2`
+        let sha = "{SHA}87u9ZqY9S/F0eUBXjsPQEDUw4h0=";
+        assert!(verify_hash(sha, "hunter2"));
+        assert!(!verify_hash(sha, "hunter3"));
+
+        let bcrypt = bcrypt::hash("hunter2", 4).unwrap();
+        assert!(verify_hash(&bcrypt, "hunter2"));
+        assert!(!verify_hash(&bcrypt, "hunter3"));
+
+        assert!(!verify_hash("$apr1$abc$def", "hunter2"));
+        assert!(!verify_hash("plaintext", "plaintext"));
+    }
+}
@@ -0,0 +1,186 @@
+use crate::prelude::*;
+
+const SALT_LEN: usize = 16;
+
+// verifies passwords against a file of `username:hash` lines, where each
+// hash is an encoded argon2id hash as written by `tt passwd`. like the
+// htpasswd verifier, the file is reread on every login attempt, so changes
+// to it take effect immediately.
+pub struct Credentials {
+    filename: String,
+}
+
+impl Credentials {
+    pub fn new(filename: &str) -> Self {
+        Self {
+            filename: filename.to_string(),
+        }
+    }
+
+    // adds the user if they don't already exist, and replaces their
+    // password otherwise
+    pub fn set(&self, username: &str, password: &str) -> Result<()> {
+        let hash = hash_password(password)?;
+        let contents = self.read_if_exists()?;
+        self.write(&set_hash(&contents, username, &hash))
+    }
+
+    // returns whether the user existed
+    pub fn remove(&self, username: &str) -> Result<bool> {
+        let contents = self.read_if_exists()?;
+        let (contents, removed) = remove_hash(&contents, username);
+        if removed {
+            self.write(&contents)?;
+        }
+        Ok(removed)
+    }
+
+    fn read(&self) -> Result<String> {
+        super::read_file(&self.filename)
+    }
+
+    fn read_if_exists(&self) -> Result<String> {
+        if std::path::Path::new(&self.filename).exists() {
+            self.read()
+        } else {
+            Ok(String::new())
+        }
+    }
+
+    fn write(&self, contents: &str) -> Result<()> {
+        super::write_file(&self.filename, contents)
+    }
+}
+
+impl super::Verifier for Credentials {
+    fn verify(&self, username: &str, password: &str) -> Result<bool> {
+        let contents = self.read()?;
+        Ok(find_hash(&contents, username)
+            .map_or(false, |hash| verify_hash(hash, password)))
+    }
+}
+
+// reads a password from the terminal without echoing it
+pub fn prompt(prompt: &str) -> Result<String> {
+    rpassword::read_password_from_tty(Some(prompt))
+        .context(crate::error::ReadPassword)
+}
+
+fn hash_password(password: &str) -> Result<String> {
+    let salt: [u8; SALT_LEN] = rand::random();
+    let config = argon2::Config {
+        variant: argon2::Variant::Argon2id,
+        ..argon2::Config::default()
+    };
+    argon2::hash_encoded(password.as_bytes(), &salt, &config)
+        .context(crate::error::HashPassword)
+}
+
+fn verify_hash(hash: &str, password: &str) -> bool {
+    match argon2::verify_encoded(hash, password.as_bytes()) {
+        Ok(valid) => valid,
+        Err(e) => {
+            log::warn!("invalid password hash: {}", e);
+            false
+        }
+    }
+}
+
+fn parse_line(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let mut parts = line.splitn(2, ':');
+    Some((parts.next()?, parts.next()?))
+}
+
+fn find_hash<'a>(contents: &'a str, username: &str) -> Option<&'a str> {
+    contents
+        .lines()
+        .filter_map(parse_line)
+        .find(|(name, _)| *name == username)
+        .map(|(_, hash)| hash)
+}
+
+fn set_hash(contents: &str, username: &str, hash: &str) -> String {
+    let mut found = false;
+    let mut lines: Vec<_> = contents
+        .lines()
+        .map(|line| match parse_line(line) {
+            Some((name, _)) if name == username => {
+                found = true;
+                format!("{}:{}", username, hash)
+            }
+            _ => line.to_string(),
+        })
+        .collect();
+    if !found {
+        lines.push(format!("{}:{}", username, hash));
+    }
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+fn remove_hash(contents: &str, username: &str) -> (String, bool) {
+    let mut removed = false;
+    let contents = contents
+        .lines()
+        .filter(|line| match parse_line(line) {
+            Some((name, _)) if name == username => {
+                removed = true;
+                false
+            }
+            _ => true,
+        })
+        .map(|line| format!("{}\n", line))
+        .collect();
+    (contents, removed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_set_hash() {
+        let contents = "# comment\n\
+                        doy:abc\n\
+                        sartak:def\n";
+        assert_eq!(
+            set_hash(contents, "doy", "ghi"),
+            "# comment\ndoy:ghi\nsartak:def\n"
+        );
+        assert_eq!(
+            set_hash(contents, "toft", "ghi"),
+            "# comment\ndoy:abc\nsartak:def\ntoft:ghi\n"
+        );
+        assert_eq!(set_hash("", "doy", "abc"), "doy:abc\n");
+    }
+
+    #[test]
+    fn test_remove_hash() {
+        let contents = "# comment\n\
+                        doy:abc\n\
+                        sartak:def\n";
+        assert_eq!(
+            remove_hash(contents, "doy"),
+            ("# comment\nsartak:def\n".to_string(), true)
+        );
+        assert_eq!(
+            remove_hash(contents, "toft"),
+            (contents.to_string(), false)
+        );
+        assert_eq!(find_hash(contents, "sartak"), Some("def"));
+        assert_eq!(find_hash(contents, "# comment"), None);
+    }
+
+    #[test]
+    fn test_verify_hash() {
+        let hash = hash_password("hunter2").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(verify_hash(&hash, "hunter2"));
+        assert!(!verify_hash(&hash, "hunter3"));
+        assert_ne!(hash, hash_password("hunter2").unwrap());
+        assert!(!verify_hash("$2y$05$abc", "hunter2"));
+    }
+}
@@ -14,16 +14,19 @@ pub fn oauth_config(
     )
 }
 
-pub fn get_username(
+pub fn get_user(
     access_token: &str,
-) -> Box<dyn futures::Future<Item = String, Error = Error> + Send> {
+) -> Box<dyn futures::Future<Item = super::UserInfo, Error = Error> + Send> {
     let fut = reqwest::r#async::Client::new()
         .get("https://www.recurse.com/api/v1/profiles/me")
         .bearer_auth(access_token)
         .send()
         .context(crate::error::GetRecurseCenterProfile)
         .and_then(|mut res| res.json().context(crate::error::ParseJson))
-        .map(|user: User| user.name());
+        .map(|user: User| super::UserInfo {
+            name: user.name(),
+            groups: user.groups(),
+        });
     Box::new(fut)
 }
 
@@ -54,4 +57,17 @@ impl User {
             self.name.to_string()
         }
     }
+
+    // every batch the user has been a part of
+    fn groups(&self) -> Vec<String> {
+        let mut groups: Vec<_> = self
+            .stints
+            .iter()
+            .filter_map(|s| s.batch.as_ref())
+            .map(|batch| batch.short_name.clone())
+            .collect();
+        groups.sort();
+        groups.dedup();
+        groups
+    }
 }
@@ -0,0 +1,105 @@
+use crate::prelude::*;
+use std::convert::TryInto as _;
+use std::io::{Read as _, Write as _};
+
+// message numbers from draft-miller-ssh-agent
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+
+const SSH_AGENT_RSA_SHA2_256: u32 = 2;
+
+// a minimal synchronous ssh-agent client, which only supports listing keys
+// and signing data
+pub struct Agent {
+    sock: std::os::unix::net::UnixStream,
+}
+
+impl Agent {
+    pub fn connect_env() -> Result<Option<Self>> {
+        let path = if let Some(path) = std::env::var_os("SSH_AUTH_SOCK") {
+            path
+        } else {
+            return Ok(None);
+        };
+        let sock = std::os::unix::net::UnixStream::connect(path)
+            .context(crate::error::SshAgent)?;
+        Ok(Some(Self { sock }))
+    }
+
+    pub fn identities(&mut self) -> Result<Vec<Vec<u8>>> {
+        let response = self.request(&[SSH_AGENTC_REQUEST_IDENTITIES])?;
+        parse_identities(&response)
+            .context(crate::error::InvalidSshAgentResponse)
+    }
+
+    pub fn sign(
+        &mut self,
+        public_key: &[u8],
+        data: &[u8],
+    ) -> Result<Vec<u8>> {
+        // rsa keys default to sha1 signatures unless we ask for sha2
+        let flags = match super::read_string(public_key) {
+            Some((ty, _)) if ty == super::RSA => SSH_AGENT_RSA_SHA2_256,
+            _ => 0,
+        };
+        let mut request = vec![SSH_AGENTC_SIGN_REQUEST];
+        super::write_string(public_key, &mut request);
+        super::write_string(data, &mut request);
+        super::write_u32(flags, &mut request);
+
+        let response = self.request(&request)?;
+        parse_signature(&response)
+            .context(crate::error::InvalidSshAgentResponse)
+    }
+
+    fn request(&mut self, request: &[u8]) -> Result<Vec<u8>> {
+        let mut packet = vec![];
+        super::write_string(request, &mut packet);
+        self.sock
+            .write_all(&packet)
+            .context(crate::error::SshAgent)?;
+
+        let mut len_buf = [0_u8; std::mem::size_of::<u32>()];
+        self.sock
+            .read_exact(&mut len_buf)
+            .context(crate::error::SshAgent)?;
+        let len = u32::from_be_bytes(len_buf);
+        let mut response = vec![0; len.try_into().unwrap()];
+        self.sock
+            .read_exact(&mut response)
+            .context(crate::error::SshAgent)?;
+
+        if response.first() == Some(&SSH_AGENT_FAILURE) {
+            return Err(Error::SshAgentFailure);
+        }
+        Ok(response)
+    }
+}
+
+fn parse_identities(response: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let (ty, data) = response.split_first()?;
+    if *ty != SSH_AGENT_IDENTITIES_ANSWER {
+        return None;
+    }
+    let (count, mut data) = super::read_u32(data)?;
+    let mut keys = vec![];
+    for _ in 0..count {
+        let (key, rest) = super::read_string(data)?;
+        let (_comment, rest) = super::read_string(rest)?;
+        keys.push(key.to_vec());
+        data = rest;
+    }
+    Some(keys)
+}
+
+fn parse_signature(response: &[u8]) -> Option<Vec<u8>> {
+    let (ty, data) = response.split_first()?;
+    if *ty != SSH_AGENT_SIGN_RESPONSE {
+        return None;
+    }
+    let (signature, _) = super::read_string(data)?;
+    Some(signature.to_vec())
+}
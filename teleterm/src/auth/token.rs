@@ -0,0 +1,181 @@
+use crate::prelude::*;
+
+const TOKEN_PREFIX: &str = "tt_";
+const TOKEN_LEN: usize = 32;
+const ID_LEN: usize = 4;
+
+// api tokens for non-interactive logins, stored in a file of
+// `id:username:hash` lines. the hash is a plain sha256 of the token, since
+// tokens are long random strings rather than something a person chose, so a
+// slow password hash doesn't buy anything. like the other verifiers, the
+// file is reread on every login attempt, so revoking a token takes effect
+// immediately.
+pub struct Tokens {
+    filename: String,
+}
+
+pub struct TokenInfo {
+    pub id: String,
+    pub username: String,
+}
+
+impl Tokens {
+    pub fn new(filename: &str) -> Self {
+        Self {
+            filename: filename.to_string(),
+        }
+    }
+
+    // returns the id of the new token along with the token itself. only the
+    // hash is stored, so the token can't be retrieved again later.
+    pub fn create(&self, username: &str) -> Result<(String, String)> {
+        let id = to_hex(&rand::random::<[u8; ID_LEN]>());
+        let token = format!(
+            "{}{}",
+            TOKEN_PREFIX,
+            base64::encode_config(
+                &rand::random::<[u8; TOKEN_LEN]>(),
+                base64::URL_SAFE_NO_PAD
+            )
+        );
+        let mut contents = self.read_if_exists()?;
+        contents.push_str(&format!(
+            "{}:{}:{}\n",
+            id,
+            username,
+            hash_token(&token)
+        ));
+        super::write_file(&self.filename, &contents)?;
+        Ok((id, token))
+    }
+
+    // returns whether the token existed
+    pub fn revoke(&self, id: &str) -> Result<bool> {
+        let contents = self.read_if_exists()?;
+        let (contents, removed) = remove_token(&contents, id);
+        if removed {
+            super::write_file(&self.filename, &contents)?;
+        }
+        Ok(removed)
+    }
+
+    pub fn list(&self) -> Result<Vec<TokenInfo>> {
+        let contents = self.read_if_exists()?;
+        Ok(contents
+            .lines()
+            .filter_map(parse_line)
+            .map(|entry| TokenInfo {
+                id: entry.id.to_string(),
+                username: entry.username.to_string(),
+            })
+            .collect())
+    }
+
+    // returns the user that the token was created for
+    pub fn verify(&self, token: &str) -> Result<Option<String>> {
+        let contents = super::read_file(&self.filename)?;
+        Ok(find_token(&contents, &hash_token(token))
+            .map(std::string::ToString::to_string))
+    }
+
+    fn read_if_exists(&self) -> Result<String> {
+        if std::path::Path::new(&self.filename).exists() {
+            super::read_file(&self.filename)
+        } else {
+            Ok(String::new())
+        }
+    }
+}
+
+struct Entry<'a> {
+    id: &'a str,
+    username: &'a str,
+    hash: &'a str,
+}
+
+fn parse_line(line: &str) -> Option<Entry> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let mut parts = line.splitn(2, ':');
+    let id = parts.next()?;
+    let mut parts = parts.next()?.rsplitn(2, ':');
+    let hash = parts.next()?;
+    let username = parts.next()?;
+    Some(Entry { id, username, hash })
+}
+
+fn find_token<'a>(contents: &'a str, hash: &str) -> Option<&'a str> {
+    contents
+        .lines()
+        .filter_map(parse_line)
+        .find(|entry| {
+            entry.hash.len() == hash.len()
+                && openssl::memcmp::eq(entry.hash.as_bytes(), hash.as_bytes())
+        })
+        .map(|entry| entry.username)
+}
+
+fn remove_token(contents: &str, id: &str) -> (String, bool) {
+    let mut removed = false;
+    let contents = contents
+        .lines()
+        .filter(|line| match parse_line(line) {
+            Some(entry) if entry.id == id => {
+                removed = true;
+                false
+            }
+            _ => true,
+        })
+        .map(|line| format!("{}\n", line))
+        .collect();
+    (contents, removed)
+}
+
+fn hash_token(token: &str) -> String {
+    to_hex(&openssl::sha::sha256(token.as_bytes()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hash_token() {
+        assert_eq!(
+            hash_token("tt_abc"),
+            "636b65faff6f286abacc20fcfe0913e5c2fead167b2d81c558a889abaec53042"
+        );
+    }
+
+    #[test]
+    fn test_find_token() {
+        let contents = "# comment\n\
+                        0a1b2c3d:doy:abc\n\
+                        4e5f6a7b:ci:bot:def\n";
+        assert_eq!(find_token(contents, "abc"), Some("doy"));
+        assert_eq!(find_token(contents, "def"), Some("ci:bot"));
+        assert_eq!(find_token(contents, "ghi"), None);
+        assert_eq!(find_token(contents, "ab"), None);
+    }
+
+    #[test]
+    fn test_remove_token() {
+        let contents = "# comment\n\
+                        0a1b2c3d:doy:abc\n\
+                        4e5f6a7b:doy:def\n";
+        assert_eq!(
+            remove_token(contents, "0a1b2c3d"),
+            ("# comment\n4e5f6a7b:doy:def\n".to_string(), true)
+        );
+        assert_eq!(
+            remove_token(contents, "doy"),
+            (contents.to_string(), false)
+        );
+    }
+}
@@ -0,0 +1,350 @@
+use crate::prelude::*;
+use std::io::Write as _;
+use std::os::unix::fs::DirBuilderExt as _;
+use std::os::unix::fs::PermissionsExt as _;
+
+const ENCRYPTED_PREFIX: &str = "teleterm-encrypted-v1:";
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const KEYRING_SERVICE: &str = "teleterm";
+const KEYRING_KEY: &str = "oauth-token-key";
+const KEYRING_LABEL: &str = "teleterm oauth token encryption key";
+const SECRET_TOOL: &str = "secret-tool";
+
+// where the key used to encrypt cached tokens comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Encryption {
+    None,
+    KeyFile(String),
+    // stored via secret-tool, which talks to whatever implements the
+    // freedesktop secret service api on this machine (gnome-keyring,
+    // kwallet, keepassxc, etc)
+    Keyring,
+}
+
+// stores small secrets (refresh tokens, client ids) in files which are only
+// readable by their owner, optionally encrypted with aes-256-gcm. files
+// written before encryption was enabled are still readable, and are
+// encrypted the next time they are written. the key is loaded once and
+// then shared between clones.
+#[derive(Clone)]
+pub struct TokenCache {
+    encryption: Encryption,
+    key: std::sync::Arc<std::sync::Mutex<Option<Vec<u8>>>>,
+}
+
+// don't let the key end up in debug output
+impl std::fmt::Debug for TokenCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("TokenCache")
+            .field("encryption", &self.encryption)
+            .finish()
+    }
+}
+
+impl TokenCache {
+    pub fn new(encryption: Encryption) -> Self {
+        Self {
+            encryption,
+            key: std::sync::Arc::new(std::sync::Mutex::new(None)),
+        }
+    }
+
+    pub fn plain() -> Self {
+        Self::new(Encryption::None)
+    }
+
+    pub fn read(&self, filename: &std::path::Path) -> Result<Option<String>> {
+        if !filename.exists() {
+            return Ok(None);
+        }
+        let filename = filename.to_string_lossy();
+        enforce_permissions(&filename)?;
+        let contents = super::read_file(&filename)?;
+        if contents.starts_with(ENCRYPTED_PREFIX) {
+            let key =
+                self.key(false)?.context(crate::error::MissingTokenKey {
+                    filename: filename.to_string(),
+                })?;
+            decrypt(&key, &contents[ENCRYPTED_PREFIX.len()..]).map(Some)
+        } else {
+            Ok(Some(contents))
+        }
+    }
+
+    pub fn write(
+        &self,
+        filename: &std::path::Path,
+        contents: &str,
+    ) -> Result<()> {
+        let filename = filename.to_string_lossy();
+        if let Some(key) = self.key(true)? {
+            let contents =
+                format!("{}{}", ENCRYPTED_PREFIX, encrypt(&key, contents)?);
+            super::write_file(&filename, &contents)
+        } else {
+            super::write_file(&filename, contents)
+        }
+    }
+
+    // a new key is only ever created when writing, since anything already
+    // encrypted would be unreadable with a new key
+    fn key(&self, create: bool) -> Result<Option<Vec<u8>>> {
+        if self.encryption == Encryption::None {
+            return Ok(None);
+        }
+        let mut cached = self.key.lock().unwrap();
+        if let Some(key) = &*cached {
+            return Ok(Some(key.clone()));
+        }
+
+        let key = match &self.encryption {
+            Encryption::None => unreachable!(),
+            Encryption::KeyFile(filename) => {
+                if std::path::Path::new(filename).exists() {
+                    enforce_permissions(filename)?;
+                    Some(super::read_file(filename)?)
+                } else if create {
+                    create_parent_dir(filename)?;
+                    let key = generate_key();
+                    super::write_file(filename, &key)?;
+                    Some(key)
+                } else {
+                    None
+                }
+            }
+            Encryption::Keyring => {
+                if let Some(key) = keyring_lookup()? {
+                    Some(key)
+                } else if create {
+                    let key = generate_key();
+                    keyring_store(&key)?;
+                    Some(key)
+                } else {
+                    None
+                }
+            }
+        };
+        let key = if let Some(key) = key {
+            key
+        } else {
+            return Ok(None);
+        };
+        let key = base64::decode(key.trim())
+            .context(crate::error::ParseTokenKey)?;
+        if key.len() != KEY_LEN {
+            return Err(Error::InvalidTokenKey);
+        }
+        *cached = Some(key.clone());
+        Ok(Some(key))
+    }
+}
+
+// the default key file lives in the config dir, which may not exist yet
+fn create_parent_dir(filename: &str) -> Result<()> {
+    if let Some(dir) = std::path::Path::new(filename).parent() {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)
+            .with_context(|| crate::error::CreateDir {
+                filename: dir.to_string_lossy(),
+            })?;
+    }
+    Ok(())
+}
+
+// cached tokens are as good as a password, so refuse to leave them readable
+// by anyone else, even if the file was created by an older version (or by
+// hand) with a more permissive umask
+fn enforce_permissions(filename: &str) -> Result<()> {
+    let metadata =
+        std::fs::metadata(filename).context(crate::error::OpenFileSync {
+            filename: filename.to_string(),
+        })?;
+    let mode = metadata.permissions().mode();
+    if mode & 0o077 != 0 {
+        log::warn!(
+            "{} was accessible by other users (mode {:o}), fixing",
+            filename,
+            mode & 0o777
+        );
+        std::fs::set_permissions(
+            filename,
+            std::fs::Permissions::from_mode(0o600),
+        )
+        .context(crate::error::SetPermissions {
+            filename: filename.to_string(),
+        })?;
+    }
+    Ok(())
+}
+
+fn generate_key() -> String {
+    base64::encode(&rand::random::<[u8; KEY_LEN]>())
+}
+
+// the nonce and tag are stored alongside the ciphertext, as
+// base64(nonce || tag || ciphertext)
+fn encrypt(key: &[u8], plaintext: &str) -> Result<String> {
+    let nonce = rand::random::<[u8; NONCE_LEN]>();
+    let mut tag = [0; TAG_LEN];
+    let ciphertext = openssl::symm::encrypt_aead(
+        openssl::symm::Cipher::aes_256_gcm(),
+        key,
+        Some(&nonce),
+        &[],
+        plaintext.as_bytes(),
+        &mut tag,
+    )
+    .context(crate::error::EncryptToken)?;
+    let mut data = vec![];
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&tag);
+    data.extend_from_slice(&ciphertext);
+    Ok(base64::encode(&data))
+}
+
+fn decrypt(key: &[u8], data: &str) -> Result<String> {
+    let data =
+        base64::decode(data.trim()).context(crate::error::ParseTokenCache)?;
+    if data.len() < NONCE_LEN + TAG_LEN {
+        return Err(Error::DecryptTokenTruncated);
+    }
+    let (nonce, data) = data.split_at(NONCE_LEN);
+    let (tag, ciphertext) = data.split_at(TAG_LEN);
+    let plaintext = openssl::symm::decrypt_aead(
+        openssl::symm::Cipher::aes_256_gcm(),
+        key,
+        Some(nonce),
+        &[],
+        ciphertext,
+        tag,
+    )
+    .context(crate::error::DecryptToken)?;
+    Ok(String::from_utf8_lossy(&plaintext).to_string())
+}
+
+fn keyring_lookup() -> Result<Option<String>> {
+    let output = std::process::Command::new(SECRET_TOOL)
+        .args(&["lookup", "service", KEYRING_SERVICE, "key", KEYRING_KEY])
+        .output()
+        .context(crate::error::RunKeyring)?;
+    if output.status.success() && !output.stdout.is_empty() {
+        return Ok(Some(String::from_utf8_lossy(&output.stdout).to_string()));
+    }
+    // secret-tool exits with a failure status and prints nothing at all
+    // when the item doesn't exist. anything else (the secret service not
+    // running, the keyring being locked, etc) has to be an error, since
+    // creating a new key would make the existing tokens unreadable.
+    if output.stdout.is_empty() && output.stderr.is_empty() {
+        return Ok(None);
+    }
+    Err(Error::KeyringLookup {
+        status: output.status,
+        stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    })
+}
+
+fn keyring_store(key: &str) -> Result<()> {
+    let mut child = std::process::Command::new(SECRET_TOOL)
+        .args(&[
+            "store",
+            "--label",
+            KEYRING_LABEL,
+            "service",
+            KEYRING_SERVICE,
+            "key",
+            KEYRING_KEY,
+        ])
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .context(crate::error::RunKeyring)?;
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(key.as_bytes())
+        .context(crate::error::RunKeyring)?;
+    let status = child.wait().context(crate::error::RunKeyring)?;
+    if !status.success() {
+        return Err(Error::KeyringStore { status });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let key = base64::decode(&generate_key()).unwrap();
+        let encrypted = encrypt(&key, "refresh\naccess\n").unwrap();
+        assert_eq!(decrypt(&key, &encrypted).unwrap(), "refresh\naccess\n");
+    }
+
+    #[test]
+    fn test_wrong_key() {
+        let key = base64::decode(&generate_key()).unwrap();
+        let other_key = base64::decode(&generate_key()).unwrap();
+        let encrypted = encrypt(&key, "refresh\naccess\n").unwrap();
+        assert!(decrypt(&other_key, &encrypted).is_err());
+    }
+
+    #[test]
+    fn test_tampered() {
+        let key = base64::decode(&generate_key()).unwrap();
+        let encrypted = encrypt(&key, "refresh\naccess\n").unwrap();
+        let mut data = base64::decode(&encrypted).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        assert!(decrypt(&key, &base64::encode(&data)).is_err());
+        assert!(decrypt(&key, &base64::encode(&data[..20])).is_err());
+    }
+
+    #[test]
+    fn test_key_file() {
+        let dir = std::env::temp_dir()
+            .join(format!("teleterm-test-token-key-{}", std::process::id()));
+        let key_file = dir.join("config").join("key");
+        let token_file = dir.join("token");
+        let encryption =
+            Encryption::KeyFile(key_file.to_string_lossy().to_string());
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // reading never creates a key
+        let cache = TokenCache::new(encryption.clone());
+        assert!(cache.key(false).unwrap().is_none());
+        assert!(!key_file.exists());
+
+        cache.write(&token_file, "refresh\naccess\n").unwrap();
+        assert!(key_file.exists());
+        assert_eq!(
+            std::fs::metadata(key_file.parent().unwrap())
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o777,
+            0o700
+        );
+        assert_eq!(
+            cache.read(&token_file).unwrap().unwrap(),
+            "refresh\naccess\n"
+        );
+
+        // the key is cached once loaded
+        std::fs::remove_file(&key_file).unwrap();
+        assert_eq!(
+            cache.clone().read(&token_file).unwrap().unwrap(),
+            "refresh\naccess\n"
+        );
+        // but without it, the token can't be read, and isn't replaced by
+        // a new key
+        assert!(TokenCache::new(encryption).read(&token_file).is_err());
+        assert!(!key_file.exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+use crate::prelude::*;
+use std::io::{Read as _, Write as _};
+
+// bookmarks are kept in a separate file next to the ttyrec, so that the
+// recording itself stays readable by other ttyrec tools. each line holds the
+// number of seconds since the first frame of the recording, a tab, and the
+// name of the bookmark (which may be empty).
+const EXTENSION: &str = "bookmarks";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bookmark {
+    pub time: std::time::Duration,
+    pub name: String,
+}
+
+pub struct Bookmarks {
+    filename: String,
+    bookmarks: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+    pub fn load(ttyrec_filename: &str) -> Result<Self> {
+        let filename = sidecar_filename(ttyrec_filename);
+        let bookmarks = if std::path::Path::new(&filename).exists() {
+            let mut file = std::fs::File::open(&filename).context(
+                crate::error::OpenFileSync {
+                    filename: filename.clone(),
+                },
+            )?;
+            let mut contents = String::new();
+            file.read_to_string(&mut contents)
+                .context(crate::error::ReadFileSync)?;
+            parse(&contents)
+        } else {
+            vec![]
+        };
+        Ok(Self {
+            filename,
+            bookmarks,
+        })
+    }
+
+    pub fn list(&self) -> &[Bookmark] {
+        &self.bookmarks
+    }
+
+    pub fn add(
+        &mut self,
+        time: std::time::Duration,
+        name: &str,
+    ) -> Result<()> {
+        let bookmark = Bookmark {
+            time,
+            name: name.to_string(),
+        };
+        write_line(&self.filename, &bookmark)?;
+        let pos = self
+            .bookmarks
+            .iter()
+            .position(|bookmark| bookmark.time > time)
+            .unwrap_or_else(|| self.bookmarks.len());
+        self.bookmarks.insert(pos, bookmark);
+        Ok(())
+    }
+}
+
+// used while recording, where we never need to read the existing bookmarks
+pub fn append(
+    ttyrec_filename: &str,
+    time: std::time::Duration,
+    name: &str,
+) -> Result<()> {
+    write_line(
+        &sidecar_filename(ttyrec_filename),
+        &Bookmark {
+            time,
+            name: name.to_string(),
+        },
+    )
+}
+
+// bookmarks from a previous recording to the same file would point at
+// unrelated parts of the new recording
+pub fn clear(ttyrec_filename: &str) -> Result<()> {
+    let filename = sidecar_filename(ttyrec_filename);
+    match std::fs::remove_file(&filename) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).context(crate::error::RemoveFileSync { filename }),
+    }
+}
+
+pub fn sidecar_filename(ttyrec_filename: &str) -> String {
+    format!("{}.{}", ttyrec_filename, EXTENSION)
+}
+
+fn write_line(filename: &str, bookmark: &Bookmark) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(filename)
+        .context(crate::error::CreateFileSync {
+            filename: filename.to_string(),
+        })?;
+    file.write_all(format_line(bookmark).as_bytes())
+        .context(crate::error::WriteFileSync)?;
+    Ok(())
+}
+
+fn format_line(bookmark: &Bookmark) -> String {
+    // names are entered interactively, so they can't contain newlines, but
+    // don't let a stray one corrupt the rest of the file
+    format!(
+        "{}.{:06}\t{}\n",
+        bookmark.time.as_secs(),
+        bookmark.time.subsec_micros(),
+        bookmark.name.replace('\n', " ")
+    )
+}
+
+fn parse(contents: &str) -> Vec<Bookmark> {
+    let mut bookmarks: Vec<_> = contents
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, '\t');
+            let time = parse_time(parts.next()?.trim())?;
+            Some(Bookmark {
+                time,
+                name: parts.next().unwrap_or("").to_string(),
+            })
+        })
+        .collect();
+    // markers dropped while recording are always in order, but bookmarks
+    // added during playback are appended wherever they are
+    bookmarks.sort_by_key(|bookmark| bookmark.time);
+    bookmarks
+}
+
+// parsed by hand rather than as a float so that times round trip exactly
+fn parse_time(s: &str) -> Option<std::time::Duration> {
+    let mut parts = s.splitn(2, '.');
+    let secs = parts.next()?.parse().ok()?;
+    let micros = if let Some(frac) = parts.next() {
+        if frac.is_empty()
+            || frac.len() > 6
+            || !frac.bytes().all(|c| c.is_ascii_digit())
+        {
+            return None;
+        }
+        format!("{:0<6}", frac).parse().ok()?
+    } else {
+        0
+    };
+    Some(
+        std::time::Duration::from_secs(secs)
+            + std::time::Duration::from_micros(micros),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let bookmarks = vec![
+            Bookmark {
+                time: std::time::Duration::from_micros(1_500_000),
+                name: "start".to_string(),
+            },
+            Bookmark {
+                time: std::time::Duration::from_secs(90),
+                name: "".to_string(),
+            },
+            Bookmark {
+                time: std::time::Duration::from_micros(100_000_001),
+                name: "tabs\tand spaces".to_string(),
+            },
+        ];
+        let contents: String = bookmarks.iter().map(format_line).collect();
+        assert_eq!(
+            contents,
+            "1.500000\tstart\n90.000000\t\n100.000001\ttabs\tand spaces\n"
+        );
+        assert_eq!(parse(&contents), bookmarks);
+    }
+
+    #[test]
+    fn test_parse() {
+        let secs = std::time::Duration::from_secs;
+        let bookmarks =
+            parse("30.5\tlater\nbogus\n-1\tnegative\n1.\t\n10\tearlier\n");
+        assert_eq!(
+            bookmarks,
+            vec![
+                Bookmark {
+                    time: secs(10),
+                    name: "earlier".to_string()
+                },
+                Bookmark {
+                    time: secs(30) + std::time::Duration::from_millis(500),
+                    name: "later".to_string()
+                },
+            ]
+        );
+    }
+}
@@ -2,6 +2,8 @@ use crate::prelude::*;
 use rand::Rng as _;
 use std::io::Read as _;
 
+pub mod tls;
+
 const HEARTBEAT_DURATION: std::time::Duration =
     std::time::Duration::from_secs(30);
 const RECONNECT_BACKOFF_BASE: std::time::Duration =
@@ -11,6 +13,7 @@ const RECONNECT_BACKOFF_MAX: std::time::Duration =
     std::time::Duration::from_secs(60);
 
 const OAUTH_LISTEN_ADDRESS: &str = "127.0.0.1:44141";
+const OAUTH_LISTEN_ADDRESS_FALLBACK: &str = "127.0.0.1:0";
 const OAUTH_BROWSER_SUCCESS_MESSAGE: &str = "authenticated successfully! now close this page and return to your terminal.";
 
 enum ReadSocket<
@@ -272,21 +275,103 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
             match msg {
                 crate::protocol::Message::OauthCliRequest { url, id } => {
                     let mut state = None;
-                    let parsed_url = url::Url::parse(&url).unwrap();
+                    let mut parsed_url = url::Url::parse(&url).unwrap();
                     for (k, v) in parsed_url.query_pairs() {
                         if k == "state" {
-                            state = Some(v);
+                            state = Some(v.to_string());
                         }
                     }
-                    open::that(url).context(crate::error::OpenLink)?;
+
+                    if !is_headless() {
+                        let (listener, redirect_port) =
+                            bind_oauth_listener()?;
+                        if let Some(port) = redirect_port {
+                            set_redirect_port(&mut parsed_url, port);
+                        }
+                        if open::that(parsed_url.as_str()).is_ok() {
+                            return Ok((
+                                component_future::Async::DidWork,
+                                Some(self.wait_for_oauth_response(
+                                    listener,
+                                    redirect_port,
+                                    state,
+                                    &id,
+                                )),
+                            ));
+                        }
+                    }
+
+                    // if we can't open a browser on this machine, the user
+                    // will have to do it themselves and paste the code back
+                    let save_id = save_client_auth_id(
+                        self.auth.auth_type(),
+                        self.auth.oauth_profile(),
+                        &id,
+                    );
+                    let fut = read_oauth_code(&url, state).and_then(|code| {
+                        save_id.map(move |_| {
+                            crate::protocol::Message::oauth_cli_response(
+                                &code, None,
+                            )
+                        })
+                    });
                     return Ok((
                         component_future::Async::DidWork,
-                        Some(self.wait_for_oauth_response(
-                            state.map(|s| s.to_string()),
-                            &id,
-                        )?),
+                        Some(Box::new(fut)),
                     ));
                 }
+                crate::protocol::Message::OauthCliDeviceRequest {
+                    url,
+                    user_code,
+                    id,
+                } => {
+                    // the terminal is probably in raw mode at this point
+                    eprint!(
+                        "To log in, visit {} and enter the code {}\r\n",
+                        url, user_code
+                    );
+                    let msg =
+                        crate::protocol::Message::oauth_cli_device_response();
+                    return Ok((
+                        component_future::Async::DidWork,
+                        Some(Box::new(
+                            save_client_auth_id(
+                                self.auth.auth_type(),
+                                self.auth.oauth_profile(),
+                                &id,
+                            )
+                            .map(|_| msg),
+                        )),
+                    ));
+                }
+                crate::protocol::Message::SshKeyChallenge { challenge } => {
+                    let (key_file, server_name) = match &self.auth {
+                        crate::protocol::Auth::SshKey {
+                            key_file,
+                            server_name,
+                            ..
+                        } => (
+                            key_file
+                                .as_ref()
+                                .map(std::string::String::as_str),
+                            server_name.as_str(),
+                        ),
+                        _ => (None, ""),
+                    };
+                    let (public_key, signature) =
+                        crate::auth::ssh_key::sign_challenge(
+                            key_file,
+                            server_name,
+                            &challenge,
+                        )?;
+                    self.to_send.push_back(
+                        crate::protocol::Message::ssh_key_response(
+                            &public_key,
+                            &signature,
+                        ),
+                    );
+                    return Ok((component_future::Async::DidWork, None));
+                }
                 crate::protocol::Message::LoggedIn { username } => {
                     log::info!(
                         "successfully logged into server as {}",
@@ -316,15 +401,13 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
 
     fn wait_for_oauth_response(
         &self,
+        listener: tokio::net::TcpListener,
+        redirect_port: Option<u16>,
         state: Option<String>,
         id: &str,
-    ) -> Result<
-        Box<
-            dyn futures::Future<
-                    Item = crate::protocol::Message,
-                    Error = Error,
-                > + Send,
-        >,
+    ) -> Box<
+        dyn futures::Future<Item = crate::protocol::Message, Error = Error>
+            + Send,
     > {
         lazy_static::lazy_static! {
             static ref RE: regex::Regex = regex::Regex::new(
@@ -333,13 +416,12 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
         }
 
         let auth_type = self.auth.auth_type();
+        let profile = self
+            .auth
+            .oauth_profile()
+            .map(std::string::ToString::to_string);
         let id = id.to_string();
-        let address = OAUTH_LISTEN_ADDRESS
-            .parse()
-            .context(crate::error::ParseAddr)?;
-        let listener = tokio::net::TcpListener::bind(&address)
-            .context(crate::error::Bind { address })?;
-        Ok(Box::new(
+        Box::new(
             listener
                 .incoming()
                 .into_future()
@@ -357,11 +439,7 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
                     let path = &RE
                         .captures(&buf)
                         .context(crate::error::ParseHttpRequest)?[1];
-                    let base = url::Url::parse(&format!(
-                        "http://{}",
-                        OAUTH_LISTEN_ADDRESS
-                    ))
-                    .unwrap();
+                    let base = url::Url::parse("http://127.0.0.1/").unwrap();
                     let url = base
                         .join(path)
                         .context(crate::error::ParseHttpRequestPath)?;
@@ -384,12 +462,20 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
                         return Err(Error::ParseHttpRequestMissingCode);
                     };
                     Ok((
-                        crate::protocol::Message::oauth_cli_response(&code),
+                        crate::protocol::Message::oauth_cli_response(
+                            &code,
+                            redirect_port,
+                        ),
                         lines.into_inner().into_inner(),
                     ))
                 })
                 .and_then(move |(msg, sock)| {
-                    save_client_auth_id(auth_type, &id).map(|_| (msg, sock))
+                    save_client_auth_id(
+                        auth_type,
+                        profile.as_ref().map(std::string::String::as_str),
+                        &id,
+                    )
+                    .map(|_| (msg, sock))
                 })
                 .and_then(|(msg, sock)| {
                     let response = format!(
@@ -400,7 +486,7 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
                         .context(crate::error::WriteSocket)
                         .map(|_| msg)
                 }),
-        ))
+        )
     }
 }
 
@@ -651,37 +737,169 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
 
 pub fn load_client_auth_id(
     auth: crate::protocol::AuthType,
-) -> Option<String> {
-    client_id_file(auth, true).and_then(|id_file| {
-        std::fs::File::open(id_file).ok().and_then(|mut file| {
-            let mut id = vec![];
-            file.read_to_end(&mut id).ok().map(|_| {
-                std::string::String::from_utf8_lossy(&id).to_string()
-            })
+    profile: Option<&str>,
+) -> Result<Option<String>> {
+    if let Some(id_file) = client_id_file(auth, profile, true) {
+        crate::auth::token_cache::TokenCache::plain().read(&id_file)
+    } else {
+        Ok(None)
+    }
+}
+
+// the token file takes precedence, and the environment variable is a
+// fallback for ci systems which expose secrets that way
+pub fn load_api_token(token_file: Option<&str>) -> Result<String> {
+    let token = if let Some(token_file) = token_file {
+        let mut file = std::fs::File::open(token_file).context(
+            crate::error::OpenFileSync {
+                filename: token_file.to_string(),
+            },
+        )?;
+        let mut token = String::new();
+        file.read_to_string(&mut token)
+            .context(crate::error::ReadFileSync)?;
+        token
+    } else {
+        std::env::var("TELETERM_TOKEN").unwrap_or_default()
+    };
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(Error::MissingToken);
+    }
+    Ok(token.to_string())
+}
+
+// if the default port is already in use (by another login in progress, for
+// instance), let the os pick one instead. in that case, the port is returned
+// so that the server can be told to use the same redirect url when
+// exchanging the code.
+fn bind_oauth_listener() -> Result<(tokio::net::TcpListener, Option<u16>)> {
+    let address = OAUTH_LISTEN_ADDRESS
+        .parse()
+        .context(crate::error::ParseAddr)?;
+    if let Ok(listener) = tokio::net::TcpListener::bind(&address) {
+        return Ok((listener, None));
+    }
+    let address = OAUTH_LISTEN_ADDRESS_FALLBACK
+        .parse()
+        .context(crate::error::ParseAddr)?;
+    let listener = tokio::net::TcpListener::bind(&address)
+        .context(crate::error::Bind { address })?;
+    let port = listener
+        .local_addr()
+        .context(crate::error::Bind { address })?
+        .port();
+    Ok((listener, Some(port)))
+}
+
+fn set_redirect_port(url: &mut url::Url, port: u16) {
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(k, v)| {
+            let mut v = v.to_string();
+            if k == "redirect_uri" {
+                if let Ok(mut redirect) = url::Url::parse(&v) {
+                    if redirect.set_port(Some(port)).is_ok() {
+                        v = redirect.to_string();
+                    }
+                }
+            }
+            (k.to_string(), v)
         })
+        .collect();
+    url.query_pairs_mut().clear().extend_pairs(pairs);
+}
+
+// logged in over ssh without x forwarding, so opening a browser would
+// either fail or open it somewhere the user can't see it
+fn is_headless() -> bool {
+    std::env::var_os("SSH_CONNECTION").is_some()
+        && std::env::var_os("DISPLAY").is_none()
+}
+
+fn read_oauth_code(
+    url: &str,
+    state: Option<String>,
+) -> impl futures::Future<Item = String, Error = Error> {
+    eprint!(
+        "To log in, visit {}\r\nthen paste the url you were redirected to \
+         (or just the code from it) here: ",
+        url
+    );
+
+    futures::future::poll_fn(|| {
+        tokio_threadpool::blocking(read_tty_line)
+            .context(crate::error::BlockingTask)
     })
+    .and_then(|res| res)
+    .and_then(move |line| parse_oauth_code(&line, state.as_ref()))
 }
 
+// the terminal may already be in raw mode, so we can't rely on line
+// buffering and need to accept either line ending
+fn read_tty_line() -> Result<String> {
+    let filename = "/dev/tty";
+    let tty = std::fs::File::open(filename)
+        .context(crate::error::OpenFileSync { filename })?;
+    let mut line = vec![];
+    for c in tty.bytes() {
+        let c = c.context(crate::error::ReadTerminal)?;
+        if c == b'\r' || c == b'\n' {
+            break;
+        }
+        line.push(c);
+    }
+    eprint!("\r\n");
+    Ok(String::from_utf8_lossy(&line).trim().to_string())
+}
+
+fn parse_oauth_code(line: &str, state: Option<&String>) -> Result<String> {
+    if let Ok(url) = url::Url::parse(line) {
+        let mut req_code = None;
+        let mut req_state = None;
+        for (k, v) in url.query_pairs() {
+            if k == "code" {
+                req_code = Some(v.to_string());
+            }
+            if k == "state" {
+                req_state = Some(v.to_string());
+            }
+        }
+        if state != req_state.as_ref() {
+            return Err(Error::ParseHttpRequestCsrf);
+        }
+        req_code.context(crate::error::ParseHttpRequestMissingCode)
+    } else if line.is_empty() {
+        Err(Error::ParseHttpRequestMissingCode)
+    } else {
+        Ok(line.to_string())
+    }
+}
+
+// the id is what the server uses to look up our refresh token, so it needs
+// to be kept just as private
 fn save_client_auth_id(
     auth: crate::protocol::AuthType,
+    profile: Option<&str>,
     id: &str,
 ) -> impl futures::Future<Item = (), Error = Error> {
-    let id_file = client_id_file(auth, false).unwrap();
+    let id_file = client_id_file(auth, profile, false).unwrap();
     let id = id.to_string();
-    tokio::fs::File::create(id_file.clone())
-        .with_context(move || crate::error::CreateFile {
-            filename: id_file.to_string_lossy().to_string(),
-        })
-        .and_then(|file| {
-            tokio::io::write_all(file, id).context(crate::error::WriteFile)
-        })
-        .map(|_| ())
+    futures::future::lazy(move || {
+        crate::auth::token_cache::TokenCache::plain().write(&id_file, &id)
+    })
 }
 
+// the default profile uses the same file name as before profiles existed
 fn client_id_file(
     auth: crate::protocol::AuthType,
+    profile: Option<&str>,
     must_exist: bool,
 ) -> Option<std::path::PathBuf> {
-    let filename = format!("client-oauth-{}", auth.name());
+    let filename = if let Some(profile) = profile {
+        format!("client-oauth-{}-{}", auth.name(), profile)
+    } else {
+        format!("client-oauth-{}", auth.name())
+    };
     crate::dirs::Dirs::new().data_file(&filename, must_exist)
 }
@@ -0,0 +1,264 @@
+use crate::prelude::*;
+use std::io::{Read as _, Write as _};
+
+const KNOWN_HOSTS_FILENAME: &str = "tls-known-hosts";
+
+#[derive(Debug, Clone)]
+enum Verify {
+    Chain,
+    Pinned { fingerprints: Vec<String> },
+    TrustOnFirstUse,
+}
+
+impl Verify {
+    fn check(
+        &self,
+        host: &str,
+        address: std::net::SocketAddr,
+        stream: &tokio_tls::TlsStream<tokio::net::TcpStream>,
+    ) -> Result<()> {
+        match self {
+            Self::Chain => Ok(()),
+            Self::Pinned { fingerprints } => {
+                let fingerprint = peer_fingerprint(stream)?;
+                if fingerprints.contains(&fingerprint) {
+                    Ok(())
+                } else {
+                    Err(Error::TlsCertificatePinMismatch {
+                        host: host.to_string(),
+                        fingerprint,
+                    })
+                }
+            }
+            Self::TrustOnFirstUse => {
+                let fingerprint = peer_fingerprint(stream)?;
+                check_known_host(
+                    &format!("{}:{}", host, address.port()),
+                    &fingerprint,
+                )
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Connector {
+    connector: native_tls::TlsConnector,
+    verify: Verify,
+}
+
+impl Connector {
+    pub fn new(
+        ca_file: Option<&str>,
+        pinned_certs: &[String],
+        trust_on_first_use: bool,
+    ) -> Result<Self> {
+        let mut builder = native_tls::TlsConnector::builder();
+        if let Some(ca_file) = ca_file {
+            for cert in read_ca_file(ca_file)? {
+                builder.add_root_certificate(cert);
+            }
+        }
+
+        let verify = if !pinned_certs.is_empty() {
+            Verify::Pinned {
+                fingerprints: pinned_certs
+                    .iter()
+                    .map(|fingerprint| normalize_fingerprint(fingerprint))
+                    .collect(),
+            }
+        } else if trust_on_first_use {
+            Verify::TrustOnFirstUse
+        } else {
+            Verify::Chain
+        };
+
+        // when verifying by fingerprint, the certificate is checked
+        // directly after the handshake, so the usual chain validation
+        // would just prevent self-signed certificates from working
+        match verify {
+            Verify::Chain => {}
+            _ => {
+                builder.danger_accept_invalid_certs(true);
+                builder.danger_accept_invalid_hostnames(true);
+            }
+        }
+
+        let connector =
+            builder.build().context(crate::error::CreateConnector)?;
+        Ok(Self { connector, verify })
+    }
+
+    pub fn connect(
+        &self,
+        host: &str,
+        address: std::net::SocketAddr,
+    ) -> Box<
+        dyn futures::Future<
+                Item = tokio_tls::TlsStream<tokio::net::TcpStream>,
+                Error = Error,
+            > + Send,
+    > {
+        let host = host.to_string();
+        let connector = tokio_tls::TlsConnector::from(self.connector.clone());
+        let verify = self.verify.clone();
+        Box::new(
+            tokio::net::tcp::TcpStream::connect(&address)
+                .context(crate::error::Connect { address })
+                .and_then(move |stream| {
+                    connector
+                        .connect(&host, stream)
+                        .context(crate::error::ConnectTls {
+                            host: host.clone(),
+                        })
+                        .and_then(move |stream| {
+                            verify.check(&host, address, &stream)?;
+                            Ok(stream)
+                        })
+                }),
+        )
+    }
+}
+
+fn read_ca_file(filename: &str) -> Result<Vec<native_tls::Certificate>> {
+    let mut file = std::fs::File::open(filename)
+        .context(crate::error::OpenFileSync { filename })?;
+    let mut contents = vec![];
+    file.read_to_end(&mut contents)
+        .context(crate::error::ReadFileSync)?;
+
+    // native_tls can only parse a single certificate out of a pem file, but
+    // ca bundles typically contain several
+    let certs = openssl::x509::X509::stack_from_pem(&contents)
+        .context(crate::error::ParsePemCertificate)?;
+    if certs.is_empty() {
+        return Err(Error::MissingPemCertificate);
+    }
+    certs
+        .iter()
+        .map(|cert| {
+            let der =
+                cert.to_der().context(crate::error::ParsePemCertificate)?;
+            native_tls::Certificate::from_der(&der)
+                .context(crate::error::CreateCertificate)
+        })
+        .collect()
+}
+
+fn peer_fingerprint(
+    stream: &tokio_tls::TlsStream<tokio::net::TcpStream>,
+) -> Result<String> {
+    let cert = stream
+        .get_ref()
+        .peer_certificate()
+        .context(crate::error::GetPeerCertificate)?
+        .context(crate::error::MissingPeerCertificate)?;
+    let der = cert.to_der().context(crate::error::GetPeerCertificate)?;
+    Ok(format_fingerprint(&openssl::sha::sha256(&der)))
+}
+
+fn format_fingerprint(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// accepts the colon-separated uppercase format printed by
+// `openssl x509 -fingerprint -sha256` as well as plain hex
+fn normalize_fingerprint(fingerprint: &str) -> String {
+    fingerprint
+        .chars()
+        .filter(|c| *c != ':')
+        .collect::<String>()
+        .to_lowercase()
+}
+
+fn check_known_host(name: &str, fingerprint: &str) -> Result<()> {
+    let filename = crate::dirs::Dirs::new()
+        .data_file(KNOWN_HOSTS_FILENAME, false)
+        .unwrap();
+    let known_hosts = if filename.exists() {
+        let mut file = std::fs::File::open(&filename).with_context(|| {
+            crate::error::OpenFileSync {
+                filename: filename.to_string_lossy(),
+            }
+        })?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)
+            .context(crate::error::ReadFileSync)?;
+        parse_known_hosts(&contents)
+    } else {
+        std::collections::HashMap::new()
+    };
+
+    if let Some(known_fingerprint) = known_hosts.get(name) {
+        if known_fingerprint == fingerprint {
+            Ok(())
+        } else {
+            Err(Error::TlsKnownHostMismatch {
+                host: name.to_string(),
+                fingerprint: fingerprint.to_string(),
+                filename: filename.to_string_lossy().to_string(),
+            })
+        }
+    } else {
+        log::info!(
+            "trusting previously unknown certificate for {} ({})",
+            name,
+            fingerprint
+        );
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&filename)
+            .with_context(|| crate::error::CreateFileSync {
+                filename: filename.to_string_lossy(),
+            })?;
+        writeln!(file, "{} {}", name, fingerprint)
+            .context(crate::error::WriteFileSync)?;
+        Ok(())
+    }
+}
+
+fn parse_known_hosts(
+    contents: &str,
+) -> std::collections::HashMap<String, String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let name = parts.next()?;
+            let fingerprint = parts.next()?;
+            Some((name.to_string(), normalize_fingerprint(fingerprint)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_normalize_fingerprint() {
+        assert_eq!(normalize_fingerprint("ab01cd"), "ab01cd");
+        assert_eq!(normalize_fingerprint("AB:01:CD"), "ab01cd");
+        assert_eq!(
+            normalize_fingerprint(&format_fingerprint(&[0xab, 0x01, 0xcd])),
+            "ab01cd"
+        );
+    }
+
+    #[test]
+    fn test_parse_known_hosts() {
+        let known_hosts = parse_known_hosts(
+            "# comment\n\
+             \n\
+             example.com:4144 AB:CD\n\
+             \x20 localhost:4144 0123  \n\
+             broken\n",
+        );
+        assert_eq!(known_hosts.len(), 2);
+        assert_eq!(known_hosts["example.com:4144"], "abcd");
+        assert_eq!(known_hosts["localhost:4144"], "0123");
+    }
+}
@@ -1,9 +1,13 @@
 use crate::prelude::*;
 
+mod convert;
+mod passwd;
 mod play;
 mod record;
+mod render;
 mod server;
 mod stream;
+mod token;
 mod watch;
 mod web;
 
@@ -53,6 +57,30 @@ const COMMANDS: &[Command] = &[
         config: &play::config,
         log_level: "error",
     },
+    Command {
+        name: "convert",
+        cmd: &convert::cmd,
+        config: &convert::config,
+        log_level: "error",
+    },
+    Command {
+        name: "render",
+        cmd: &render::cmd,
+        config: &render::config,
+        log_level: "error",
+    },
+    Command {
+        name: "passwd",
+        cmd: &passwd::cmd,
+        config: &passwd::config,
+        log_level: "error",
+    },
+    Command {
+        name: "token",
+        cmd: &token::cmd,
+        config: &token::config,
+        log_level: "error",
+    },
 ];
 
 pub fn parse<'a>() -> Result<clap::ArgMatches<'a>> {
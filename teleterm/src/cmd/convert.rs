@@ -0,0 +1,226 @@
+use crate::prelude::*;
+use std::io::Write as _;
+
+const INPUT_OPTION: &str = "input";
+const INPUT_FORMAT_OPTION: &str = "input-format";
+const OUTPUT_OPTION: &str = "output";
+const OUTPUT_FORMAT_OPTION: &str = "output-format";
+
+#[derive(serde::Deserialize, Debug, Default)]
+pub struct Config {
+    #[serde(skip)]
+    input: String,
+
+    #[serde(skip)]
+    input_format: Option<crate::config::RecordingFormat>,
+
+    #[serde(skip)]
+    output: String,
+
+    #[serde(skip)]
+    output_format: Option<crate::config::RecordingFormat>,
+}
+
+impl Config {
+    fn convert(&self) -> Result<()> {
+        let input_format = self.input_format.unwrap_or_else(|| {
+            crate::config::RecordingFormat::from_filename(&self.input)
+        });
+        let output_format = self.output_format.unwrap_or_else(|| {
+            crate::config::RecordingFormat::from_filename(&self.output)
+        });
+
+        let transcript =
+            crate::recording::read_transcript(&self.input, input_format)?;
+        match output_format {
+            crate::config::RecordingFormat::Ttyrec => {
+                write_ttyrec(&self.output, &transcript)
+            }
+            crate::config::RecordingFormat::Asciicast => {
+                write_asciicast(&self.output, &transcript)
+            }
+        }
+    }
+}
+
+impl crate::config::Config for Config {
+    fn merge_args<'a>(
+        &mut self,
+        matches: &clap::ArgMatches<'a>,
+    ) -> Result<()> {
+        self.input = matches.value_of(INPUT_OPTION).unwrap().to_string();
+        self.output = matches.value_of(OUTPUT_OPTION).unwrap().to_string();
+        if matches.is_present(INPUT_FORMAT_OPTION) {
+            self.input_format =
+                Some(crate::config::RecordingFormat::try_from(
+                    matches.value_of(INPUT_FORMAT_OPTION).unwrap(),
+                )?);
+        }
+        if matches.is_present(OUTPUT_FORMAT_OPTION) {
+            self.output_format =
+                Some(crate::config::RecordingFormat::try_from(
+                    matches.value_of(OUTPUT_FORMAT_OPTION).unwrap(),
+                )?);
+        }
+        Ok(())
+    }
+
+    fn run(
+        &self,
+    ) -> Box<dyn futures::Future<Item = (), Error = Error> + Send> {
+        Box::new(futures::future::result(self.convert()))
+    }
+}
+
+pub fn cmd<'a, 'b>(app: clap::App<'a, 'b>) -> clap::App<'a, 'b> {
+    let input_help = "Recording to read";
+    let output_help = "File to write the converted recording to";
+    let input_format_help = "Format of the input file, either ttyrec or asciicast (defaults to asciicast for files ending in .cast, and ttyrec otherwise)";
+    let output_format_help = "Format of the output file, either ttyrec or asciicast (defaults to asciicast for files ending in .cast, and ttyrec otherwise)";
+
+    app.about("Convert a recording between the ttyrec and asciicast formats")
+        .arg(
+            clap::Arg::with_name(INPUT_FORMAT_OPTION)
+                .long(INPUT_FORMAT_OPTION)
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(&["ttyrec", "asciicast"])
+                .help(input_format_help),
+        )
+        .arg(
+            clap::Arg::with_name(OUTPUT_FORMAT_OPTION)
+                .long(OUTPUT_FORMAT_OPTION)
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(&["ttyrec", "asciicast"])
+                .help(output_format_help),
+        )
+        .arg(
+            clap::Arg::with_name(INPUT_OPTION)
+                .value_name("INPUT")
+                .required(true)
+                .help(input_help),
+        )
+        .arg(
+            clap::Arg::with_name(OUTPUT_OPTION)
+                .value_name("OUTPUT")
+                .required(true)
+                .help(output_help),
+        )
+}
+
+pub fn config(
+    config: Option<config::Config>,
+) -> Result<Box<dyn crate::config::Config>> {
+    let config: Config = if let Some(config) = config {
+        config
+            .try_into()
+            .context(crate::error::CouldntParseConfig)?
+    } else {
+        Config::default()
+    };
+    Ok(Box::new(config))
+}
+
+fn write_ttyrec(
+    filename: &str,
+    transcript: &crate::recording::Transcript,
+) -> Result<()> {
+    let mut file =
+        std::io::BufWriter::new(std::fs::File::create(filename).context(
+            crate::error::CreateFileSync {
+                filename: filename.to_string(),
+            },
+        )?);
+    crate::bookmarks::clear(filename)?;
+    crate::keystrokes::clear(filename)?;
+    let mut input_log = None;
+
+    // ttyrec frame times are absolute, so use the asciicast timestamp if
+    // there is one
+    let start =
+        std::time::Duration::from_secs(transcript.timestamp.unwrap_or(0));
+    file.write_all(&crate::recording::ttyrec_frame(
+        start,
+        &transcript.size.resize_sequence(),
+    ))
+    .context(crate::error::WriteFileSync)?;
+    for (time, event) in &transcript.events {
+        match event {
+            crate::asciicast::Event::Output(data) => {
+                file.write_all(&crate::recording::ttyrec_frame(
+                    start + *time,
+                    data,
+                ))
+                .context(crate::error::WriteFileSync)?;
+            }
+            crate::asciicast::Event::Resize(size) => {
+                file.write_all(&crate::recording::ttyrec_frame(
+                    start + *time,
+                    &size.resize_sequence(),
+                ))
+                .context(crate::error::WriteFileSync)?;
+            }
+            crate::asciicast::Event::Marker(name) => {
+                crate::bookmarks::append(filename, *time, name)?;
+            }
+            // ttyrec has no way to represent input, so it goes in a
+            // separate file like tt record writes
+            crate::asciicast::Event::Input(data) => {
+                if input_log.is_none() {
+                    input_log =
+                        Some(crate::keystrokes::Log::open(filename, false)?);
+                }
+                input_log.as_mut().unwrap().append(*time, data)?;
+            }
+        }
+    }
+    file.flush().context(crate::error::WriteFileSync)?;
+    Ok(())
+}
+
+fn write_asciicast(
+    filename: &str,
+    transcript: &crate::recording::Transcript,
+) -> Result<()> {
+    let mut file =
+        std::io::BufWriter::new(std::fs::File::create(filename).context(
+            crate::error::CreateFileSync {
+                filename: filename.to_string(),
+            },
+        )?);
+
+    let mut header =
+        crate::asciicast::Header::new(transcript.size, transcript.timestamp);
+    // the environment we're converting in has nothing to do with the one
+    // the recording was made in
+    header.env = None;
+    file.write_all(&crate::asciicast::format_header(&header))
+        .context(crate::error::WriteFileSync)?;
+
+    let mut output_decoder = crate::asciicast::Utf8Decoder::new();
+    let mut input_decoder = crate::asciicast::Utf8Decoder::new();
+    for (time, event) in &transcript.events {
+        let event = match event {
+            crate::asciicast::Event::Output(data) => {
+                let data = output_decoder.decode(data);
+                if data.is_empty() {
+                    continue;
+                }
+                crate::asciicast::Event::Output(data.into_bytes())
+            }
+            crate::asciicast::Event::Input(data) => {
+                let data = input_decoder.decode(data);
+                if data.is_empty() {
+                    continue;
+                }
+                crate::asciicast::Event::Input(data.into_bytes())
+            }
+            event => event.clone(),
+        };
+        file.write_all(&crate::asciicast::format_event(*time, &event))
+            .context(crate::error::WriteFileSync)?;
+    }
+    file.flush().context(crate::error::WriteFileSync)?;
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use crate::prelude::*;
+
+const CREDENTIALS_FILE_OPTION: &str = "credentials-file";
+const DELETE_OPTION: &str = "delete";
+const USERNAME_OPTION: &str = "username";
+
+#[derive(serde::Deserialize, Debug, Default)]
+pub struct Config {
+    #[serde(default)]
+    server: crate::config::Server,
+
+    #[serde(skip)]
+    username: String,
+
+    #[serde(skip)]
+    delete: bool,
+}
+
+impl Config {
+    fn credentials(&self) -> Result<crate::auth::password::Credentials> {
+        let filename = self
+            .server
+            .credentials_file
+            .as_ref()
+            .context(crate::error::MissingCredentialsFile)?;
+        Ok(crate::auth::password::Credentials::new(filename))
+    }
+
+    fn update(&self) -> Result<()> {
+        let credentials = self.credentials()?;
+        if self.delete {
+            if credentials.remove(&self.username)? {
+                println!("Removed user {}", self.username);
+            } else {
+                println!("User {} not found", self.username);
+            }
+        } else {
+            let password = crate::auth::password::prompt("New password: ")?;
+            let confirm = crate::auth::password::prompt("Retype password: ")?;
+            if password != confirm {
+                return Err(Error::PasswordMismatch);
+            }
+            credentials.set(&self.username, &password)?;
+            println!("Updated password for user {}", self.username);
+        }
+        Ok(())
+    }
+}
+
+impl crate::config::Config for Config {
+    fn merge_args<'a>(
+        &mut self,
+        matches: &clap::ArgMatches<'a>,
+    ) -> Result<()> {
+        if matches.is_present(CREDENTIALS_FILE_OPTION) {
+            self.server.credentials_file = matches
+                .value_of(CREDENTIALS_FILE_OPTION)
+                .map(std::string::ToString::to_string);
+        }
+        self.username =
+            matches.value_of(USERNAME_OPTION).unwrap().to_string();
+        self.delete = matches.is_present(DELETE_OPTION);
+        Ok(())
+    }
+
+    fn run(
+        &self,
+    ) -> Box<dyn futures::Future<Item = (), Error = Error> + Send> {
+        Box::new(futures::future::result(self.update()))
+    }
+}
+
+pub fn cmd<'a, 'b>(app: clap::App<'a, 'b>) -> clap::App<'a, 'b> {
+    let credentials_file_help = "File containing the password hashes to update (defaults to the credentials_file option in the [server] section of the config file)";
+    let delete_help = "Remove the user instead of setting their password";
+    let username_help = "User whose password should be set";
+
+    app.about("Manage users for the 'password' authentication method")
+        .arg(
+            clap::Arg::with_name(CREDENTIALS_FILE_OPTION)
+                .long(CREDENTIALS_FILE_OPTION)
+                .takes_value(true)
+                .value_name("FILE")
+                .help(credentials_file_help),
+        )
+        .arg(
+            clap::Arg::with_name(DELETE_OPTION)
+                .long(DELETE_OPTION)
+                .help(delete_help),
+        )
+        .arg(
+            clap::Arg::with_name(USERNAME_OPTION)
+                .required(true)
+                .value_name("USERNAME")
+                .help(username_help),
+        )
+}
+
+pub fn config(
+    config: Option<config::Config>,
+) -> Result<Box<dyn crate::config::Config>> {
+    let config: Config = if let Some(config) = config {
+        config
+            .try_into()
+            .context(crate::error::CouldntParseConfig)?
+    } else {
+        Config::default()
+    };
+    Ok(Box::new(config))
+}
@@ -1,7 +1,38 @@
 use crate::prelude::*;
+use std::convert::TryFrom as _;
 use std::io::Write as _;
 
+const FILES_OPTION: &str = "files";
+
 const PLAYBACK_RATIO_INCR: f32 = 1.5;
+const SEEK_SMALL: std::time::Duration = std::time::Duration::from_secs(5);
+const SEEK_MEDIUM: std::time::Duration = std::time::Duration::from_secs(30);
+const SEEK_LARGE: std::time::Duration = std::time::Duration::from_secs(300);
+const SKIP_MARKER_DURATION: std::time::Duration =
+    std::time::Duration::from_secs(3);
+const BOOKMARKS_WIDTH: usize = 36;
+const KEYS_WIDTH: usize = 30;
+const KEYS_DURATION: std::time::Duration = std::time::Duration::from_secs(2);
+
+const HELP_LINES: &[&str] = &[
+    "q: quit",
+    "Space: pause/unpause",
+    "Backspace: hide/show ui",
+    "</>: previous/next frame",
+    "0/$: first/last frame",
+    "←/→, ↓/↑: seek 5s, 30s",
+    "PgDn/PgUp: seek 5m",
+    "g/click timeline: go to time",
+    "s/b: skip idle/next burst",
+    "m/l: add/list bookmarks",
+    "[/]: prev/next bookmark",
+    "{/}: prev/next file",
+    "k: show/hide typed keys",
+    "+/-: increase/decrease speed",
+    "=: normal speed",
+    "/: search",
+    "n/p: next/previous match",
+];
 
 #[derive(serde::Deserialize, Debug, Default)]
 pub struct Config {
@@ -10,6 +41,41 @@ pub struct Config {
 
     #[serde(default)]
     play: crate::config::Play,
+
+    #[serde(skip)]
+    files: Vec<String>,
+}
+
+impl Config {
+    // the files given on the command line, with directories replaced by the
+    // recordings in them. without any, this is just the configured ttyrec
+    // file.
+    fn playlist(
+        &self,
+    ) -> Result<Vec<(String, crate::config::RecordingFormat)>> {
+        if self.files.is_empty() {
+            return Ok(vec![(
+                self.ttyrec.filename.clone(),
+                self.ttyrec.format(),
+            )]);
+        }
+
+        let mut filenames = vec![];
+        for file in &self.files {
+            if std::path::Path::new(file).is_dir() {
+                filenames.extend(recordings_in_dir(file)?);
+            } else {
+                filenames.push(file.clone());
+            }
+        }
+        Ok(filenames
+            .into_iter()
+            .map(|filename| {
+                let format = self.ttyrec.format_for(&filename);
+                (filename, format)
+            })
+            .collect())
+    }
 }
 
 impl crate::config::Config for Config {
@@ -19,24 +85,37 @@ impl crate::config::Config for Config {
     ) -> Result<()> {
         self.ttyrec.merge_args(matches)?;
         self.play.merge_args(matches)?;
+        if let Some(files) = matches.values_of(FILES_OPTION) {
+            self.files =
+                files.map(std::string::ToString::to_string).collect();
+        }
         Ok(())
     }
 
     fn run(
         &self,
     ) -> Box<dyn futures::Future<Item = (), Error = Error> + Send> {
-        Box::new(PlaySession::new(
-            &self.ttyrec.filename,
-            self.play.play_at_start,
-            self.play.playback_ratio,
-            self.play.max_frame_length,
-        ))
+        let size = match crate::term::Size::get() {
+            Ok(size) => size,
+            Err(e) => return Box::new(futures::future::err(e)),
+        };
+        let playlist = match self.playlist() {
+            Ok(playlist) => playlist,
+            Err(e) => return Box::new(futures::future::err(e)),
+        };
+        Box::new(PlaySession::new(playlist, size, &self.play))
     }
 }
 
 pub fn cmd<'a, 'b>(app: clap::App<'a, 'b>) -> clap::App<'a, 'b> {
+    let files_help = "Recordings to play one after another, or directories to play all of the recordings in (defaults to the file given by --filename)";
     crate::config::Ttyrec::cmd(crate::config::Play::cmd(
-        app.about("Play recorded terminal sessions"),
+        app.about("Play recorded terminal sessions").arg(
+            clap::Arg::with_name(FILES_OPTION)
+                .value_name("FILES")
+                .multiple(true)
+                .help(files_help),
+        ),
     ))
 }
 
@@ -53,95 +132,27 @@ pub fn config(
     Ok(Box::new(config))
 }
 
-struct Frame {
-    dur: std::time::Duration,
-    full: Vec<u8>,
-    diff: Vec<u8>,
-}
-
-impl Frame {
-    fn adjusted_dur(
-        &self,
-        scale: f32,
-        clamp: Option<std::time::Duration>,
-    ) -> std::time::Duration {
-        let scaled = self.dur.div_f32(scale);
-        clamp.map_or(scaled, |clamp| scaled.min(clamp))
-    }
-}
-
-#[derive(Default)]
-struct Ttyrec {
-    frames: Vec<Frame>,
-}
-
-impl Ttyrec {
-    fn new() -> Self {
-        Self::default()
-    }
-
-    fn add_frame(&mut self, frame: Frame) {
-        self.frames.push(frame);
-    }
-
-    fn frame(&self, idx: usize) -> Option<&Frame> {
-        self.frames.get(idx)
-    }
-
-    fn frames(
-        &self,
-    ) -> impl DoubleEndedIterator<Item = &Frame> + ExactSizeIterator<Item = &Frame>
-    {
-        self.frames.iter()
-    }
-
-    fn matches_from<'a>(
-        &'a self,
-        idx: usize,
-        re: &'a regex::bytes::Regex,
-    ) -> impl Iterator<Item = (usize, &Frame)> + 'a {
-        self.frames()
-            .enumerate()
-            .skip(idx)
-            .filter(move |(_, frame)| re.is_match(&frame.full))
-    }
-
-    fn rmatches_from<'a>(
-        &'a self,
-        idx: usize,
-        re: &'a regex::bytes::Regex,
-    ) -> impl Iterator<Item = (usize, &Frame)> + 'a {
-        self.frames()
-            .enumerate()
-            .rev()
-            .skip(self.frames.len() - idx)
-            .filter(move |(_, frame)| re.is_match(&frame.full))
-    }
-
-    fn count_matches_from(
-        &self,
-        idx: usize,
-        re: &regex::bytes::Regex,
-    ) -> usize {
-        self.matches_from(idx, re).count()
-    }
-
-    fn len(&self) -> usize {
-        self.frames.len()
-    }
-}
-
 struct SearchState {
     query: regex::bytes::Regex,
-    count: usize,
-    total_frame_count: usize,
+    // frames can still be loaded after the search starts, so keep track of
+    // how far we've searched
+    searched: usize,
+    matches: Vec<usize>,
     idx: Option<usize>,
 }
 
 struct Player {
     playback_ratio: f32,
     max_frame_length: Option<std::time::Duration>,
-    ttyrec: Ttyrec,
+    skip_idle: bool,
+    idle_threshold: std::time::Duration,
+    idle_playback_ratio: f32,
+    // how much idle time was skipped most recently, and when
+    skipped: Option<(std::time::Duration, std::time::Instant)>,
+    ttyrec: crate::recording::Recording,
+    // the size of the terminal we're playing back in, which may not match
+    // the size the recording was made at
+    size: crate::term::Size,
     idx: usize,
     timer: Option<tokio::timer::Delay>,
     base_time: std::time::Instant,
@@ -152,20 +163,30 @@ struct Player {
 
 impl Player {
     fn new(
-        play_at_start: bool,
-        playback_ratio: f32,
-        max_frame_length: Option<std::time::Duration>,
+        filename: &str,
+        format: crate::config::RecordingFormat,
+        size: crate::term::Size,
+        config: &crate::config::Play,
     ) -> Self {
         let now = std::time::Instant::now();
         Self {
-            playback_ratio,
-            max_frame_length,
-            ttyrec: Ttyrec::new(),
+            playback_ratio: config.playback_ratio,
+            max_frame_length: config.max_frame_length,
+            skip_idle: config.skip_idle,
+            idle_threshold: config.idle_threshold,
+            idle_playback_ratio: config.idle_playback_ratio,
+            skipped: None,
+            ttyrec: crate::recording::Recording::new(filename, format, size),
+            size,
             idx: 0,
             timer: None,
             base_time: now,
             played_amount: std::time::Duration::default(),
-            paused: if play_at_start { None } else { Some(now) },
+            paused: if config.play_at_start {
+                None
+            } else {
+                Some(now)
+            },
             search_state: None,
         }
     }
@@ -174,21 +195,147 @@ impl Player {
         self.idx
     }
 
-    fn current_frame(&self) -> Option<&Frame> {
-        self.ttyrec.frame(self.idx)
+    fn current_screen(&mut self) -> Result<Option<Vec<u8>>> {
+        if self.idx < self.ttyrec.len() {
+            self.screen(self.idx).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn screen(&mut self, idx: usize) -> Result<Vec<u8>> {
+        if self.fits(idx) {
+            self.ttyrec.screen(idx)
+        } else {
+            self.ttyrec.screen_cropped(idx, self.size)
+        }
+    }
+
+    // redraws the whole screen whenever the size of the recording changes,
+    // since diffs only make sense between screens of the same size
+    fn frame_data(&mut self, idx: usize) -> Result<Vec<u8>> {
+        if idx == 0
+            || !self.fits(idx)
+            || self.ttyrec.size(idx - 1) != self.ttyrec.size(idx)
+        {
+            self.screen(idx)
+        } else {
+            self.ttyrec.diff(idx)
+        }
+    }
+
+    // the size the recording was made at, as of the current frame
+    fn recorded_size(&self) -> Option<crate::term::Size> {
+        self.ttyrec
+            .size(self.idx.min(self.ttyrec.len().saturating_sub(1)))
+    }
+
+    fn fits(&self, idx: usize) -> bool {
+        self.ttyrec
+            .size(idx)
+            .map_or(true, |size| size.fits_in(self.size))
+    }
+
+    // returns whether the size changed
+    fn set_terminal_size(&mut self, size: crate::term::Size) -> bool {
+        let changed = size != self.size;
+        self.size = size;
+        changed
+    }
+
+    fn set_initial_size(&mut self, size: crate::term::Size) {
+        self.ttyrec.set_initial_size(size);
+    }
+
+    fn add_file(
+        &mut self,
+        filename: &str,
+        format: crate::config::RecordingFormat,
+    ) {
+        self.ttyrec.add_file(filename, format);
+    }
+
+    fn num_files(&self) -> usize {
+        self.ttyrec.num_files()
+    }
+
+    fn filename(&self, file_idx: usize) -> &str {
+        self.ttyrec.filename(file_idx)
+    }
+
+    fn file_start_time(&self, file_idx: usize) -> std::time::Duration {
+        self.ttyrec.start_time(file_idx)
+    }
+
+    // the file that the current frame was read from
+    fn current_file_idx(&self) -> usize {
+        self.ttyrec
+            .file_idx(self.idx.min(self.ttyrec.len().saturating_sub(1)))
+    }
+
+    fn frame_at(&self, time: std::time::Duration) -> usize {
+        self.ttyrec.frame_at(time)
     }
 
     fn num_frames(&self) -> usize {
         self.ttyrec.len()
     }
 
+    // the timestamp of the frame that is displayed when paused
+    fn current_time(&self) -> std::time::Duration {
+        self.ttyrec
+            .time(self.idx)
+            .unwrap_or_else(|| self.total_time())
+    }
+
+    // this increases while the file is still being loaded
+    fn total_time(&self) -> std::time::Duration {
+        self.ttyrec
+            .time(self.ttyrec.len().saturating_sub(1))
+            .unwrap_or_default()
+    }
+
+    fn is_idle(&self, dur: std::time::Duration) -> bool {
+        self.skip_idle && dur > self.idle_threshold
+    }
+
+    fn adjusted_dur(&self, idx: usize) -> Option<std::time::Duration> {
+        self.ttyrec.dur(idx).map(|dur| {
+            // the start of an idle stretch plays at the normal speed, so
+            // that it's still clear that nothing was happening
+            let dur = if self.is_idle(dur) {
+                self.idle_threshold
+                    + (dur - self.idle_threshold)
+                        .div_f32(self.idle_playback_ratio)
+            } else {
+                dur
+            };
+            let scaled = dur.div_f32(self.playback_ratio);
+            self.max_frame_length
+                .map_or(scaled, |clamp| scaled.min(clamp))
+        })
+    }
+
     fn base_time_incr(&mut self, incr: std::time::Duration) {
         self.base_time += incr;
         self.set_timer();
     }
 
-    fn add_frame(&mut self, frame: Frame) {
-        self.ttyrec.add_frame(frame);
+    fn add_frame(&mut self, time: std::time::Duration, data: &[u8]) {
+        self.ttyrec.add_frame(time, data);
+        if self.timer.is_none() {
+            self.set_timer();
+        }
+    }
+
+    fn add_frame_at(
+        &mut self,
+        offset: u64,
+        len: u64,
+        time: std::time::Duration,
+        data: &[u8],
+    ) {
+        self.ttyrec.add_frame_at(offset, len, time, data);
         if self.timer.is_none() {
             self.set_timer();
         }
@@ -240,78 +387,170 @@ impl Player {
         self.clear_match_idx();
     }
 
-    fn next_match(&mut self) {
-        let idx = if let Some(state) = &self.search_state {
-            self.ttyrec
-                .matches_from(self.idx + 1, &state.query)
-                .next()
-                .map(|(idx, _)| idx)
-        } else {
+    fn seek_to(&mut self, time: std::time::Duration) {
+        if self.ttyrec.len() == 0 {
             return;
-        };
-        let idx = if let Some(idx) = idx {
+        }
+        self.idx = self.ttyrec.frame_at(time);
+        self.recalculate_times();
+        self.set_timer();
+        self.clear_match_idx();
+    }
+
+    fn seek_to_frame(&mut self, idx: usize) {
+        if idx >= self.ttyrec.len() {
+            return;
+        }
+        self.idx = idx;
+        self.recalculate_times();
+        self.set_timer();
+        self.clear_match_idx();
+    }
+
+    // files without any frames start at the same frame as the file after
+    // them, so seeking to them lands on the next file that has frames
+    fn next_file(&mut self) {
+        let file_idx = self.current_file_idx() + 1;
+        if file_idx < self.ttyrec.num_files() {
+            self.seek_to_frame(self.ttyrec.first_frame(file_idx));
+        }
+    }
+
+    // like skipping back a track, this goes to the start of the current
+    // file first, and only then to the previous one
+    fn prev_file(&mut self) {
+        let file_idx = self.current_file_idx();
+        let start = self.ttyrec.first_frame(file_idx);
+        if self.idx > start {
+            self.seek_to_frame(start);
+        } else if let Some(prev) = (0..file_idx)
+            .rev()
+            .map(|file_idx| self.ttyrec.first_frame(file_idx))
+            .find(|&first_frame| first_frame < start)
+        {
+            self.seek_to_frame(prev);
+        }
+    }
+
+    fn seek_forward(&mut self, amount: std::time::Duration) {
+        self.seek_to(self.current_time() + amount);
+    }
+
+    fn seek_back(&mut self, amount: std::time::Duration) {
+        // the current frame was usually written a bit before the current
+        // time, so make sure we actually go back far enough to reach the
+        // previous frame when the frames are far apart
+        let target =
+            self.current_time().checked_sub(amount).unwrap_or_default();
+        let idx = self.idx;
+        self.seek_to(target);
+        if self.idx == idx {
+            self.back();
+        }
+    }
+
+    fn toggle_skip_idle(&mut self) {
+        self.skip_idle = !self.skip_idle;
+        self.set_timer();
+    }
+
+    // jumps to the end of the next idle stretch
+    fn next_burst(&mut self) {
+        let idx = if let Some(idx) =
+            self.ttyrec.next_idle(self.idx, self.idle_threshold)
+        {
             idx
         } else {
             return;
         };
+        let skipped = self.ttyrec.time(idx).unwrap() - self.current_time();
 
         self.idx = idx;
         self.recalculate_times();
+        // the new frame is drawn immediately, so don't wait out the idle
+        // stretch before it again
+        if let Some(dur) = self.adjusted_dur(self.idx) {
+            self.played_amount = self.played_amount.saturating_sub(dur);
+        }
         self.set_timer();
+        self.clear_match_idx();
+        self.skipped = Some((skipped, std::time::Instant::now()));
+    }
 
+    fn skip_marker(&self) -> Option<std::time::Duration> {
+        self.skipped
+            .filter(|(_, at)| at.elapsed() < SKIP_MARKER_DURATION)
+            .map(|(skipped, _)| skipped)
+    }
+
+    // returns whether there was a marker which needs to be erased
+    fn clear_expired_skip_marker(&mut self) -> bool {
+        if self.skipped.is_some() && self.skip_marker().is_none() {
+            self.skipped = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    // searches any frames which were loaded since the last time we searched
+    fn update_search(&mut self) -> Result<()> {
         if let Some(state) = &mut self.search_state {
-            if let Some(idx) = &mut state.idx {
-                state.idx = Some(*idx + 1);
-            } else {
-                if state.total_frame_count != self.ttyrec.len() {
-                    state.count =
-                        self.ttyrec.count_matches_from(0, &state.query);
-                }
-                state.idx = Some(
-                    state.count
-                        - self
-                            .ttyrec
-                            .count_matches_from(self.idx, &state.query),
-                );
+            let len = self.ttyrec.len();
+            if state.searched < len {
+                let matches =
+                    self.ttyrec.matches(&state.query, state.searched, len)?;
+                state.matches.extend(matches);
+                state.searched = len;
             }
         }
+        Ok(())
     }
 
-    fn prev_match(&mut self) {
-        let idx = if let Some(state) = &self.search_state {
-            self.ttyrec
-                .rmatches_from(self.idx, &state.query)
-                .next()
-                .map(|(idx, _)| idx)
+    fn next_match(&mut self) -> Result<()> {
+        self.update_search()?;
+        let idx = self.idx;
+        let state = if let Some(state) = &mut self.search_state {
+            state
         } else {
-            return;
+            return Ok(());
         };
-        let idx = if let Some(idx) = idx {
-            idx
+        let pos = if let Some(pos) =
+            state.matches.iter().position(|&frame| frame > idx)
+        {
+            pos
         } else {
-            return;
+            return Ok(());
         };
+        state.idx = Some(pos);
 
-        self.idx = idx;
+        self.idx = state.matches[pos];
         self.recalculate_times();
         self.set_timer();
+        Ok(())
+    }
 
-        if let Some(state) = &mut self.search_state {
-            if let Some(idx) = &mut state.idx {
-                state.idx = Some(*idx - 1);
-            } else {
-                if state.total_frame_count != self.ttyrec.len() {
-                    state.count =
-                        self.ttyrec.count_matches_from(0, &state.query);
-                }
-                state.idx = Some(
-                    state.count
-                        - self
-                            .ttyrec
-                            .count_matches_from(self.idx, &state.query),
-                );
-            }
-        }
+    fn prev_match(&mut self) -> Result<()> {
+        self.update_search()?;
+        let idx = self.idx;
+        let state = if let Some(state) = &mut self.search_state {
+            state
+        } else {
+            return Ok(());
+        };
+        let pos = if let Some(pos) =
+            state.matches.iter().rposition(|&frame| frame < idx)
+        {
+            pos
+        } else {
+            return Ok(());
+        };
+        state.idx = Some(pos);
+
+        self.idx = state.matches[pos];
+        self.recalculate_times();
+        self.set_timer();
+        Ok(())
     }
 
     fn toggle_pause(&mut self) {
@@ -329,13 +568,11 @@ impl Player {
 
     fn recalculate_times(&mut self) {
         let now = std::time::Instant::now();
-        self.played_amount = self
-            .ttyrec
-            .frames
-            .iter()
-            .map(|f| f.dur)
-            .take(self.idx)
-            .sum();
+        self.played_amount = if self.idx == 0 {
+            std::time::Duration::default()
+        } else {
+            self.ttyrec.time(self.idx - 1).unwrap_or_default()
+        };
         self.base_time = now - self.played_amount;
         if let Some(paused) = &mut self.paused {
             *paused = now;
@@ -343,29 +580,23 @@ impl Player {
     }
 
     fn set_timer(&mut self) {
-        if let Some(frame) = self.ttyrec.frame(self.idx) {
+        if let Some(dur) = self.adjusted_dur(self.idx) {
             self.timer = Some(tokio::timer::Delay::new(
-                self.base_time
-                    + self.played_amount
-                    + frame.adjusted_dur(
-                        self.playback_ratio,
-                        self.max_frame_length,
-                    ),
+                self.base_time + self.played_amount + dur,
             ));
         } else {
             self.timer = None;
         }
     }
 
-    fn set_search_query(&mut self, re: regex::bytes::Regex) {
-        let count = self.ttyrec.count_matches_from(0, &re);
+    fn set_search_query(&mut self, re: regex::bytes::Regex) -> Result<()> {
         self.search_state = Some(SearchState {
             query: re,
-            count,
-            total_frame_count: self.ttyrec.len(),
+            searched: 0,
+            matches: vec![],
             idx: None,
         });
-        self.next_match();
+        self.next_match()
     }
 
     fn clear_match_idx(&mut self) {
@@ -375,8 +606,8 @@ impl Player {
     }
 
     fn poll(&mut self) -> futures::Poll<Option<Vec<u8>>, Error> {
-        let frame = if let Some(frame) = self.ttyrec.frame(self.idx) {
-            frame
+        let dur = if let Some(dur) = self.adjusted_dur(self.idx) {
+            dur
         } else {
             return Ok(futures::Async::Ready(None));
         };
@@ -387,11 +618,15 @@ impl Player {
         };
 
         futures::try_ready!(timer.poll().context(crate::error::Sleep));
-        let ret = frame.diff.clone();
+        let ret = self.frame_data(self.idx)?;
+        if let Some(raw_dur) = self.ttyrec.dur(self.idx) {
+            if self.is_idle(raw_dur) {
+                self.skipped = Some((raw_dur, std::time::Instant::now()));
+            }
+        }
 
         self.idx += 1;
-        self.played_amount +=
-            frame.adjusted_dur(self.playback_ratio, self.max_frame_length);
+        self.played_amount += dur;
         self.set_timer();
         self.clear_match_idx();
 
@@ -401,60 +636,128 @@ impl Player {
 
 #[allow(clippy::large_enum_variant)]
 enum FileState {
-    Closed {
-        filename: String,
-    },
+    Closed,
     Opening {
         filename: String,
         fut: tokio::fs::file::OpenFuture<String>,
     },
     Open {
-        reader: ttyrec::Reader<tokio::fs::File>,
-        parser: vt100::Parser,
+        reader: Reader,
     },
     Eof,
 }
 
+enum Reader {
+    Ttyrec(ttyrec::Reader<tokio::fs::File>),
+    Asciicast(crate::asciicast::Reader<tokio::fs::File>),
+}
+
 enum InputState {
     Normal,
     Search { query: String },
+    Goto { query: String },
+    Bookmark { query: String },
 }
 
 struct PlaySession {
+    playlist: Vec<(String, crate::config::RecordingFormat)>,
+    // the file in the playlist which is currently being read
+    file_idx: usize,
     file: FileState,
     player: Player,
     raw_screen: Option<crossterm::screen::RawScreen>,
     alternate_screen: Option<crossterm::screen::AlternateScreen>,
     key_reader: crate::key_reader::KeyReader,
-    last_frame_time: std::time::Duration,
-    last_frame_screen: Option<vt100::Screen>,
+    mouse_mode: bool,
     input_state: InputState,
     hide_ui: bool,
+    // one per file read so far, with times relative to the start of that
+    // file, so that they stay valid when the file is played on its own
+    bookmarks: Vec<crate::bookmarks::Bookmarks>,
+    show_bookmarks: bool,
+    keystrokes: crate::keystrokes::Keystrokes,
+    show_keys: bool,
 }
 
 impl PlaySession {
     fn new(
-        filename: &str,
-        play_at_start: bool,
-        playback_ratio: f32,
-        max_frame_length: Option<std::time::Duration>,
+        playlist: Vec<(String, crate::config::RecordingFormat)>,
+        size: crate::term::Size,
+        config: &crate::config::Play,
     ) -> Self {
+        let (filename, format) = &playlist[0];
+        let player = Player::new(filename, *format, size, config);
         Self {
-            file: FileState::Closed {
-                filename: filename.to_string(),
-            },
-            player: Player::new(
-                play_at_start,
-                playback_ratio,
-                max_frame_length,
-            ),
+            playlist,
+            file_idx: 0,
+            file: FileState::Closed,
+            player,
             raw_screen: None,
             alternate_screen: None,
             key_reader: crate::key_reader::KeyReader::new(),
-            last_frame_time: std::time::Duration::default(),
-            last_frame_screen: None,
+            mouse_mode: false,
             input_state: InputState::Normal,
             hide_ui: false,
+            bookmarks: vec![],
+            show_bookmarks: false,
+            keystrokes: crate::keystrokes::Keystrokes::new(),
+            show_keys: false,
+        }
+    }
+
+    // the bookmarks from every file, with times relative to the start of
+    // the playlist
+    fn bookmark_list(&self) -> Vec<crate::bookmarks::Bookmark> {
+        self.bookmarks
+            .iter()
+            .enumerate()
+            .flat_map(|(file_idx, bookmarks)| {
+                let start = self.player.file_start_time(file_idx);
+                bookmarks.list().iter().map(move |bookmark| {
+                    crate::bookmarks::Bookmark {
+                        time: start + bookmark.time,
+                        name: bookmark.name.clone(),
+                    }
+                })
+            })
+            .collect()
+    }
+
+    // bookmarks are saved alongside the file the current frame is from
+    fn add_bookmark(&mut self, name: &str) -> Result<()> {
+        let file_idx = self.player.current_file_idx();
+        let time = self.player.current_time()
+            - self.player.file_start_time(file_idx);
+        if let Some(bookmarks) = self.bookmarks.get_mut(file_idx) {
+            bookmarks.add(time, name)?;
+        }
+        Ok(())
+    }
+
+    // compares by frame rather than by time, since seeking to a bookmark
+    // lands on the frame written just before it
+    fn next_bookmark(&mut self) {
+        let idx = self.player.current_frame_idx();
+        let player = &self.player;
+        if let Some(bookmark) = self
+            .bookmark_list()
+            .iter()
+            .find(|bookmark| player.frame_at(bookmark.time) > idx)
+        {
+            self.player.seek_to(bookmark.time);
+        }
+    }
+
+    fn prev_bookmark(&mut self) {
+        let idx = self.player.current_frame_idx();
+        let player = &self.player;
+        if let Some(bookmark) = self
+            .bookmark_list()
+            .iter()
+            .rev()
+            .find(|bookmark| player.frame_at(bookmark.time) < idx)
+        {
+            self.player.seek_to(bookmark.time);
         }
     }
 
@@ -513,6 +816,99 @@ impl PlaySession {
             ) => {
                 self.player.last();
             }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::Left,
+            ) => {
+                self.player.seek_back(SEEK_SMALL);
+            }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::Right,
+            ) => {
+                self.player.seek_forward(SEEK_SMALL);
+            }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::Down,
+            ) => {
+                self.player.seek_back(SEEK_MEDIUM);
+            }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::Up,
+            ) => {
+                self.player.seek_forward(SEEK_MEDIUM);
+            }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::PageDown,
+            ) => {
+                self.player.seek_back(SEEK_LARGE);
+            }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::PageUp,
+            ) => {
+                self.player.seek_forward(SEEK_LARGE);
+            }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::Char('g'),
+            ) => {
+                self.input_state = InputState::Goto {
+                    query: String::new(),
+                };
+            }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::Char('m'),
+            ) => {
+                self.input_state = InputState::Bookmark {
+                    query: String::new(),
+                };
+            }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::Char('l'),
+            ) => {
+                self.show_bookmarks = !self.show_bookmarks;
+            }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::Char('['),
+            ) => {
+                self.prev_bookmark();
+            }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::Char(']'),
+            ) => {
+                self.next_bookmark();
+            }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::Char('{'),
+            ) => {
+                self.player.prev_file();
+            }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::Char('}'),
+            ) => {
+                self.player.next_file();
+            }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::Char('k'),
+            ) => {
+                self.show_keys = !self.show_keys;
+            }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::Char('s'),
+            ) => {
+                self.player.toggle_skip_idle();
+            }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::Char('b'),
+            ) => {
+                self.player.next_burst();
+            }
+            crossterm::input::InputEvent::Mouse(
+                crossterm::input::MouseEvent::Press(
+                    crossterm::input::MouseButton::Left,
+                    col,
+                    row,
+                ),
+            ) => {
+                self.click(*col, *row)?;
+            }
             crossterm::input::InputEvent::Keyboard(
                 crossterm::input::KeyEvent::Char('/'),
             ) => {
@@ -523,19 +919,19 @@ impl PlaySession {
             crossterm::input::InputEvent::Keyboard(
                 crossterm::input::KeyEvent::Char('n'),
             ) => {
-                self.player.next_match();
+                self.player.next_match()?;
             }
             crossterm::input::InputEvent::Keyboard(
                 crossterm::input::KeyEvent::Char('p'),
             ) => {
-                self.player.prev_match();
+                self.player.prev_match()?;
             }
             _ => {}
         }
         Ok(false)
     }
 
-    fn search_keypress(
+    fn prompt_keypress(
         &mut self,
         e: &crossterm::input::InputEvent,
     ) -> Result<bool> {
@@ -548,7 +944,9 @@ impl PlaySession {
             crossterm::input::InputEvent::Keyboard(
                 crossterm::input::KeyEvent::Char(c),
             ) => match &mut self.input_state {
-                InputState::Search { query } => {
+                InputState::Search { query }
+                | InputState::Goto { query }
+                | InputState::Bookmark { query } => {
                     query.push(*c);
                 }
                 _ => unreachable!(),
@@ -556,25 +954,36 @@ impl PlaySession {
             crossterm::input::InputEvent::Keyboard(
                 crossterm::input::KeyEvent::Backspace,
             ) => match &mut self.input_state {
-                InputState::Search { query } => {
+                InputState::Search { query }
+                | InputState::Goto { query }
+                | InputState::Bookmark { query } => {
                     query.pop();
                 }
                 _ => unreachable!(),
             },
             crossterm::input::InputEvent::Keyboard(
                 crossterm::input::KeyEvent::Enter,
-            ) => {
-                let query =
-                    if let InputState::Search { query } = &self.input_state {
-                        query.to_string()
-                    } else {
-                        unreachable!()
-                    };
-                if let Ok(re) = regex::bytes::Regex::new(&query) {
+            ) => match &self.input_state {
+                InputState::Search { query } => {
+                    if let Ok(re) = regex::bytes::Regex::new(query) {
+                        self.input_state = InputState::Normal;
+                        self.player.set_search_query(re)?;
+                    }
+                }
+                InputState::Goto { query } => {
+                    if let Some(time) = parse_time(query) {
+                        self.input_state = InputState::Normal;
+                        self.player.seek_to(time);
+                    }
+                }
+                InputState::Bookmark { query } => {
+                    let name = query.trim().to_string();
                     self.input_state = InputState::Normal;
-                    self.player.set_search_query(re);
+                    self.add_bookmark(&name)?;
+                    self.show_bookmarks = true;
                 }
-            }
+                _ => unreachable!(),
+            },
             _ => {}
         }
         Ok(false)
@@ -583,17 +992,38 @@ impl PlaySession {
     fn keypress(&mut self, e: &crossterm::input::InputEvent) -> Result<bool> {
         match &mut self.input_state {
             InputState::Normal => self.normal_keypress(e),
-            InputState::Search { .. } => self.search_keypress(e),
+            InputState::Search { .. }
+            | InputState::Goto { .. }
+            | InputState::Bookmark { .. } => self.prompt_keypress(e),
+        }
+    }
+
+    // clicking on the timeline seeks to that point in the recording
+    fn click(&mut self, col: u16, row: u16) -> Result<()> {
+        if !self.player.paused() || self.hide_ui {
+            return Ok(());
         }
+
+        let size = crate::term::Size::get()?;
+        let (start, width) = self.timeline_bounds(size);
+        // crossterm reports mouse positions as 1-based
+        if row != size.rows || col < start || col >= start + width {
+            return Ok(());
+        }
+        let fraction = f64::from(col - start) / f64::from(width - 1).max(1.0);
+        self.player
+            .seek_to(self.player.total_time().mul_f64(fraction));
+        Ok(())
     }
 
-    fn redraw(&self) -> Result<()> {
-        let frame = if let Some(frame) = self.player.current_frame() {
-            frame
+    fn redraw(&mut self) -> Result<()> {
+        self.player.set_terminal_size(crate::term::Size::get()?);
+        let screen = if let Some(screen) = self.player.current_screen()? {
+            screen
         } else {
             return Ok(());
         };
-        self.write(&frame.full)?;
+        self.write(&screen)?;
         self.draw_ui()?;
         Ok(())
     }
@@ -610,30 +1040,217 @@ impl PlaySession {
     fn draw_ui(&self) -> Result<()> {
         let size = crate::term::Size::get()?;
 
+        self.draw_letterbox(size)?;
+
         if self.player.paused() && !self.hide_ui {
             self.write(b"\x1b7\x1b[37;44m\x1b[?25l")?;
 
             self.draw_status()?;
             self.draw_help(size)?;
+            self.draw_timeline(size)?;
 
             self.write(b"\x1b8")?;
         }
 
+        if !self.hide_ui {
+            self.draw_size_warning(size)?;
+            self.draw_skip_marker(size)?;
+            if self.show_bookmarks {
+                self.draw_bookmarks(size)?;
+            }
+            if self.show_keys {
+                self.draw_keys(size)?;
+            }
+        }
         self.draw_search(size)?;
 
         Ok(())
     }
 
+    // outlines the area the recording is drawn in, when it was made in a
+    // smaller terminal than the current one
+    fn draw_letterbox(&self, size: crate::term::Size) -> Result<()> {
+        let recorded = if let Some(recorded) = self.player.recorded_size() {
+            recorded
+        } else {
+            return Ok(());
+        };
+        if recorded == size || !recorded.fits_in(size) {
+            return Ok(());
+        }
+
+        self.write(b"\x1b7\x1b[m\x1b[90m")?;
+        if recorded.cols < size.cols {
+            for row in 1..=recorded.rows {
+                self.write(
+                    format!("\x1b[{};{}H│", row, recorded.cols + 1)
+                        .as_bytes(),
+                )?;
+            }
+        }
+        if recorded.rows < size.rows {
+            self.write(format!("\x1b[{};1H", recorded.rows + 1).as_bytes())?;
+            self.write("─".repeat(usize::from(recorded.cols)).as_bytes())?;
+            if recorded.cols < size.cols {
+                self.write("┘".as_bytes())?;
+            }
+        }
+        self.write(b"\x1b[m\x1b8")?;
+
+        Ok(())
+    }
+
+    fn draw_size_warning(&self, size: crate::term::Size) -> Result<()> {
+        let recorded = if let Some(recorded) = self.player.recorded_size() {
+            recorded
+        } else {
+            return Ok(());
+        };
+        if recorded.fits_in(size) {
+            return Ok(());
+        }
+
+        let msg = format!(" recorded at {}, cropped to {} ", recorded, size);
+        let msg: String = msg.chars().take(usize::from(size.cols)).collect();
+        self.write(b"\x1b7\x1b[30;43m\x1b[1;1H")?;
+        self.write(msg.as_bytes())?;
+        self.write(b"\x1b[m\x1b8")?;
+
+        Ok(())
+    }
+
+    fn draw_skip_marker(&self, size: crate::term::Size) -> Result<()> {
+        let skipped = if let Some(skipped) = self.player.skip_marker() {
+            skipped
+        } else {
+            return Ok(());
+        };
+        let msg = format!(" skipped {} ", format_skipped(skipped));
+        let col = size
+            .cols
+            .saturating_sub(u16::try_from(msg.len()).unwrap_or(0))
+            + 1;
+
+        self.write(b"\x1b7\x1b[30;43m")?;
+        self.write(format!("\x1b[1;{}H", col).as_bytes())?;
+        self.write(msg.as_bytes())?;
+        self.write(b"\x1b8")?;
+
+        Ok(())
+    }
+
+    fn draw_bookmarks(&self, size: crate::term::Size) -> Result<()> {
+        let bookmarks = self.bookmark_list();
+        let hours = self.player.total_time().as_secs() >= 3600;
+        let idx = self.player.current_frame_idx();
+        let max_lines = usize::from(size.rows.saturating_sub(9)).max(1);
+
+        // keep the bookmarks around the current position visible
+        let current = bookmarks
+            .iter()
+            .rposition(|bookmark| self.player.frame_at(bookmark.time) <= idx);
+        let skip = current
+            .map_or(0, |current| current.saturating_sub(max_lines / 2))
+            .min(bookmarks.len().saturating_sub(max_lines));
+        let lines: Vec<String> = if bookmarks.is_empty() {
+            vec!["no bookmarks".to_string()]
+        } else {
+            bookmarks
+                .iter()
+                .enumerate()
+                .skip(skip)
+                .take(max_lines)
+                .map(|(i, bookmark)| {
+                    format!(
+                        "{} {} {}",
+                        if Some(i) == current { "▶" } else { " " },
+                        format_time(bookmark.time, hours),
+                        bookmark.name
+                    )
+                    .chars()
+                    .take(BOOKMARKS_WIDTH)
+                    .collect()
+                })
+                .collect()
+        };
+
+        self.write(b"\x1b7\x1b[37;44m")?;
+        self.write(b"\x1b[6;2H")?;
+        self.write("╭".as_bytes())?;
+        self.write("─".repeat(BOOKMARKS_WIDTH + 2).as_bytes())?;
+        self.write("╮".as_bytes())?;
+        let mut row = 7;
+        for line in lines {
+            self.write(format!("\x1b[{};2H", row).as_bytes())?;
+            self.write(
+                format!("│ {:width$} │", line, width = BOOKMARKS_WIDTH)
+                    .as_bytes(),
+            )?;
+            row += 1;
+        }
+        self.write(format!("\x1b[{};2H", row).as_bytes())?;
+        self.write("╰".as_bytes())?;
+        self.write("─".repeat(BOOKMARKS_WIDTH + 2).as_bytes())?;
+        self.write("╯".as_bytes())?;
+        self.write(b"\x1b8")?;
+
+        Ok(())
+    }
+
+    // always drawn at the same width, so that keys which have expired get
+    // drawn over without needing to redraw the whole screen
+    fn draw_keys(&self, size: crate::term::Size) -> Result<()> {
+        let keys: String = self
+            .keystrokes
+            .recent(self.player.current_time(), KEYS_DURATION)
+            .iter()
+            .map(|keystroke| crate::keystrokes::describe(&keystroke.data))
+            .collect();
+        // show the most recent keys when there are too many to fit
+        let len = keys.chars().count();
+        let keys: String =
+            keys.chars().skip(len.saturating_sub(KEYS_WIDTH)).collect();
+
+        self.write(b"\x1b7\x1b[37;44m")?;
+        self.write(
+            format!("\x1b[{};2H", size.rows.saturating_sub(2)).as_bytes(),
+        )?;
+        self.write(
+            format!(" {:>width$} ", keys, width = KEYS_WIDTH).as_bytes(),
+        )?;
+        self.write(b"\x1b[m\x1b8")?;
+
+        Ok(())
+    }
+
     fn draw_status(&self) -> Result<()> {
-        let msg = format!(
+        let mut msg = format!(
             "paused (frame {}/{})",
             self.player.current_frame_idx() + 1,
             self.player.num_frames()
         );
+        // each file in a playlist is shown like a chapter
+        if self.playlist.len() > 1 {
+            let file_idx = self.player.current_file_idx();
+            let filename = self.player.filename(file_idx);
+            let name =
+                std::path::Path::new(filename).file_name().map_or_else(
+                    || filename.to_string(),
+                    |name| name.to_string_lossy().into_owned(),
+                );
+            msg = format!(
+                "{} - file {}/{}: {}",
+                msg,
+                file_idx + 1,
+                self.playlist.len(),
+                name
+            );
+        }
+        let width = msg.chars().count();
 
         self.write(b"\x1b[2;2H")?;
         self.write("╭".as_bytes())?;
-        self.write("─".repeat(2 + msg.len()).as_bytes())?;
+        self.write("─".repeat(2 + width).as_bytes())?;
         self.write("╮".as_bytes())?;
 
         self.write(b"\x1b[3;2H")?;
@@ -641,64 +1258,30 @@ impl PlaySession {
 
         self.write(b"\x1b[4;2H")?;
         self.write("╰".as_bytes())?;
-        self.write("─".repeat(2 + msg.len()).as_bytes())?;
+        self.write("─".repeat(2 + width).as_bytes())?;
         self.write("╯".as_bytes())?;
 
         Ok(())
     }
 
     fn draw_help(&self, size: crate::term::Size) -> Result<()> {
-        self.write(
-            format!("\x1b[{};{}H", size.rows - 12, size.cols - 32).as_bytes(),
-        )?;
+        let height = u16::try_from(HELP_LINES.len()).unwrap() + 3;
+        let top = size.rows.saturating_sub(height);
+        let left = size.cols - 32;
+
+        self.write(format!("\x1b[{};{}H", top, left).as_bytes())?;
         self.write("╭".as_bytes())?;
         self.write("─".repeat(30).as_bytes())?;
         self.write("╮".as_bytes())?;
 
-        self.write(
-            format!("\x1b[{};{}H", size.rows - 11, size.cols - 32).as_bytes(),
-        )?;
+        self.write(format!("\x1b[{};{}H", top + 1, left).as_bytes())?;
         self.write("│             Keys             │".as_bytes())?;
-        self.write(
-            format!("\x1b[{};{}H", size.rows - 10, size.cols - 32).as_bytes(),
-        )?;
-        self.write("│ q: quit                      │".as_bytes())?;
-        self.write(
-            format!("\x1b[{};{}H", size.rows - 9, size.cols - 32).as_bytes(),
-        )?;
-        self.write("│ Space: pause/unpause         │".as_bytes())?;
-        self.write(
-            format!("\x1b[{};{}H", size.rows - 8, size.cols - 32).as_bytes(),
-        )?;
-        self.write("│ Backspace: hide/show ui      │".as_bytes())?;
-        self.write(
-            format!("\x1b[{};{}H", size.rows - 7, size.cols - 32).as_bytes(),
-        )?;
-        self.write("│ </>: previous/next frame     │".as_bytes())?;
-        self.write(
-            format!("\x1b[{};{}H", size.rows - 6, size.cols - 32).as_bytes(),
-        )?;
-        self.write("│ 0/$: first/last frame        │".as_bytes())?;
-        self.write(
-            format!("\x1b[{};{}H", size.rows - 5, size.cols - 32).as_bytes(),
-        )?;
-        self.write("│ +/-: increase/decrease speed │".as_bytes())?;
-        self.write(
-            format!("\x1b[{};{}H", size.rows - 4, size.cols - 32).as_bytes(),
-        )?;
-        self.write("│ =: normal speed              │".as_bytes())?;
-        self.write(
-            format!("\x1b[{};{}H", size.rows - 3, size.cols - 32).as_bytes(),
-        )?;
-        self.write("│ /: search                    │".as_bytes())?;
-        self.write(
-            format!("\x1b[{};{}H", size.rows - 2, size.cols - 32).as_bytes(),
-        )?;
-        self.write("│ n/p: next/previous match     │".as_bytes())?;
+        for (row, line) in (top + 2..).zip(HELP_LINES) {
+            self.write(format!("\x1b[{};{}H", row, left).as_bytes())?;
+            self.write(format!("│ {:28} │", line).as_bytes())?;
+        }
 
-        self.write(
-            format!("\x1b[{};{}H", size.rows - 1, size.cols - 32).as_bytes(),
-        )?;
+        self.write(format!("\x1b[{};{}H", size.rows - 1, left).as_bytes())?;
         self.write("╰".as_bytes())?;
         self.write("─".repeat(30).as_bytes())?;
         self.write("╯".as_bytes())?;
@@ -706,6 +1289,57 @@ impl PlaySession {
         Ok(())
     }
 
+    fn timeline_labels(&self) -> (String, String) {
+        let total = self.player.total_time();
+        let hours = total.as_secs() >= 3600;
+        let total = format_time(total, hours);
+        let elapsed = format!(
+            "{:>width$}",
+            format_time(self.player.current_time(), hours),
+            width = total.len()
+        );
+        (elapsed, total)
+    }
+
+    // returns the column that the bar itself starts at, and its width
+    fn timeline_bounds(&self, size: crate::term::Size) -> (u16, u16) {
+        let (elapsed, total) = self.timeline_labels();
+        let elapsed_len = u16::try_from(elapsed.len()).unwrap_or(0);
+        let total_len = u16::try_from(total.len()).unwrap_or(0);
+        let start = elapsed_len + 3;
+        let width = size.cols.saturating_sub(elapsed_len + total_len + 4);
+        (start, width)
+    }
+
+    fn draw_timeline(&self, size: crate::term::Size) -> Result<()> {
+        let (elapsed, total) = self.timeline_labels();
+        let (_, width) = self.timeline_bounds(size);
+        let width = usize::from(width);
+        let total_time = self.player.total_time().as_micros();
+        let filled = if total_time == 0 {
+            0
+        } else {
+            let current_time = self.player.current_time().as_micros();
+            usize::try_from(current_time * (width as u128) / total_time)
+                .unwrap_or(width)
+                .min(width)
+        };
+
+        self.write(format!("\x1b[{};1H", size.rows).as_bytes())?;
+        self.write(
+            format!(
+                " {} {}{} {} ",
+                elapsed,
+                "█".repeat(filled),
+                "░".repeat(width - filled),
+                total
+            )
+            .as_bytes(),
+        )?;
+
+        Ok(())
+    }
+
     fn draw_search(&self, size: crate::term::Size) -> Result<()> {
         match &self.input_state {
             InputState::Normal => {
@@ -722,10 +1356,11 @@ impl PlaySession {
                     self.write("─".repeat(30).as_bytes())?;
                     self.write("╮".as_bytes())?;
 
+                    let count = state.matches.len();
                     let msg = if let Some(idx) = state.idx {
-                        format!("match ({}/{})", idx + 1, state.count)
+                        format!("match ({}/{})", idx + 1, count)
                     } else {
-                        format!("match (-/{})", state.count)
+                        format!("match (-/{})", count)
                     };
                     self.write(
                         format!("\x1b[{};{}H", 3, size.cols - 32).as_bytes(),
@@ -761,7 +1396,14 @@ impl PlaySession {
                     self.write(b"\x1b8")?;
                 }
             }
-            InputState::Search { query } => {
+            InputState::Search { query }
+            | InputState::Goto { query }
+            | InputState::Bookmark { query } => {
+                let label = match self.input_state {
+                    InputState::Goto { .. } => "go to time ([[h:]m:]s):",
+                    InputState::Bookmark { .. } => "bookmark name:",
+                    _ => "search:",
+                };
                 self.write(b"\x1b7\x1b[37;44m")?;
                 self.write(
                     format!("\x1b[{};{}H", 2, size.cols - 32).as_bytes(),
@@ -773,7 +1415,7 @@ impl PlaySession {
                 self.write(
                     format!("\x1b[{};{}H", 3, size.cols - 32).as_bytes(),
                 )?;
-                self.write("│ search:                      │".as_bytes())?;
+                self.write(format!("│ {:28} │", label).as_bytes())?;
 
                 self.write(
                     format!("\x1b[{};{}H", 4, size.cols - 32).as_bytes(),
@@ -828,7 +1470,24 @@ impl PlaySession {
 
     fn poll_open_file(&mut self) -> component_future::Poll<(), Error> {
         match &mut self.file {
-            FileState::Closed { filename } => {
+            FileState::Closed => {
+                let (filename, format) = &self.playlist[self.file_idx];
+                if self.file_idx > 0 {
+                    self.player.add_file(filename, *format);
+                }
+                let start = self.player.file_start_time(self.file_idx);
+                self.bookmarks
+                    .push(crate::bookmarks::Bookmarks::load(filename)?);
+                // asciicast files store input along with everything else,
+                // so those keystrokes are loaded along with the frames
+                if let crate::config::RecordingFormat::Ttyrec = format {
+                    for keystroke in
+                        crate::keystrokes::Keystrokes::load(filename)?.list()
+                    {
+                        self.keystrokes
+                            .add(start + keystroke.time, &keystroke.data);
+                    }
+                }
                 self.file = FileState::Opening {
                     filename: filename.to_string(),
                     fut: tokio::fs::File::open(filename.to_string()),
@@ -843,10 +1502,15 @@ impl PlaySession {
                             filename: filename.to_string(),
                         }
                     }));
-                let size = crate::term::Size::get()?;
-                let reader = ttyrec::Reader::new(file);
-                let parser = vt100::Parser::new(size.rows, size.cols, 0);
-                self.file = FileState::Open { reader, parser };
+                let reader = match self.playlist[self.file_idx].1 {
+                    crate::config::RecordingFormat::Ttyrec => {
+                        Reader::Ttyrec(ttyrec::Reader::new(file))
+                    }
+                    crate::config::RecordingFormat::Asciicast => {
+                        Reader::Asciicast(crate::asciicast::Reader::new(file))
+                    }
+                };
+                self.file = FileState::Open { reader };
                 Ok(component_future::Async::DidWork)
             }
             _ => Ok(component_future::Async::NothingToDo),
@@ -854,42 +1518,85 @@ impl PlaySession {
     }
 
     fn poll_read_file(&mut self) -> component_future::Poll<(), Error> {
-        if let FileState::Open { reader, parser } = &mut self.file {
-            if let Some(frame) = component_future::try_ready!(reader
-                .poll_read()
-                .context(crate::error::ReadTtyrec))
-            {
-                parser.process(&frame.data);
-
-                let frame_time = frame.time - reader.offset().unwrap();
-                let frame_dur = frame_time - self.last_frame_time;
-                self.last_frame_time = frame_time;
-
-                let full = parser.screen().contents_formatted();
-                let diff = if let Some(last_frame_screen) =
-                    &self.last_frame_screen
+        let eof = match &mut self.file {
+            FileState::Open {
+                reader: Reader::Ttyrec(reader),
+            } => {
+                if let Some(frame) = component_future::try_ready!(reader
+                    .poll_read()
+                    .context(crate::error::ReadTtyrec))
                 {
-                    parser.screen().contents_diff(last_frame_screen)
+                    let frame_time = frame.time - reader.offset().unwrap();
+                    self.player.add_frame(frame_time, &frame.data);
+                    false
                 } else {
-                    full.clone()
-                };
-
-                self.last_frame_screen = Some(parser.screen().clone());
-                self.player.add_frame(Frame {
-                    dur: frame_dur,
-                    full,
-                    diff,
-                });
-                if self.player.paused() {
-                    self.draw_ui()?;
+                    true
                 }
+            }
+            FileState::Open {
+                reader: Reader::Asciicast(reader),
+            } => {
+                if let Some((offset, len, time, event)) =
+                    component_future::try_ready!(reader.poll_read())
+                {
+                    if let Some(header) = reader.header() {
+                        self.player.set_initial_size(header.size());
+                    }
+                    // resizes are stored the same way as in ttyrec files
+                    // (see crate::recording::Recording), input is only
+                    // used for the typed keys display, and other events
+                    // don't affect playback
+                    match event {
+                        crate::asciicast::Event::Output(data) => {
+                            self.player
+                                .add_frame_at(offset, len, time, &data);
+                        }
+                        crate::asciicast::Event::Resize(size) => {
+                            self.player.add_frame_at(
+                                offset,
+                                len,
+                                time,
+                                &size.resize_sequence(),
+                            );
+                        }
+                        crate::asciicast::Event::Input(data) => {
+                            self.keystrokes.add(
+                                self.player.file_start_time(self.file_idx)
+                                    + time,
+                                &data,
+                            );
+                        }
+                        _ => {}
+                    }
+                    false
+                } else {
+                    true
+                }
+            }
+            _ => return Ok(component_future::Async::NothingToDo),
+        };
+        if eof {
+            // the rest of the playlist continues on from this file
+            if self.file_idx + 1 < self.playlist.len() {
+                self.file_idx += 1;
+                self.file = FileState::Closed;
             } else {
                 self.file = FileState::Eof;
             }
-            Ok(component_future::Async::DidWork)
-        } else {
-            Ok(component_future::Async::NothingToDo)
+        } else if self.player.paused() {
+            self.draw_ui()?;
         }
+        Ok(component_future::Async::DidWork)
+    }
+
+    fn disable_mouse_mode(&mut self) -> Result<()> {
+        if self.mouse_mode {
+            crossterm::input::input()
+                .disable_mouse_mode()
+                .context(crate::error::DisableMouseMode)?;
+            self.mouse_mode = false;
+        }
+        Ok(())
     }
 
     fn poll_input(&mut self) -> component_future::Poll<(), Error> {
@@ -905,10 +1612,17 @@ impl PlaySession {
                     .context(crate::error::ToAlternateScreen)?,
             );
         }
+        if !self.mouse_mode {
+            crossterm::input::input()
+                .enable_mouse_mode()
+                .context(crate::error::EnableMouseMode)?;
+            self.mouse_mode = true;
+        }
 
         let e = component_future::try_ready!(self.key_reader.poll()).unwrap();
         let quit = self.keypress(&e)?;
         if quit {
+            self.disable_mouse_mode()?;
             self.write(b"\x1b[?25h")?;
             Ok(component_future::Async::Ready(()))
         } else {
@@ -922,9 +1636,19 @@ impl PlaySession {
             return Ok(component_future::Async::NothingToDo);
         }
 
+        // the next frame would be drawn for the wrong size, so start again
+        // from a full screen
+        if self.player.set_terminal_size(crate::term::Size::get()?) {
+            self.redraw()?;
+        }
+
         if let Some(data) = component_future::try_ready!(self.player.poll()) {
             self.write(&data)?;
-            self.draw_ui()?;
+            if self.player.clear_expired_skip_marker() {
+                self.redraw()?;
+            } else {
+                self.draw_ui()?;
+            }
             Ok(component_future::Async::DidWork)
         } else if let FileState::Eof = self.file {
             Ok(component_future::Async::Ready(()))
@@ -934,6 +1658,13 @@ impl PlaySession {
     }
 }
 
+impl Drop for PlaySession {
+    fn drop(&mut self) {
+        // don't leave the terminal reporting mouse events if we exit early
+        let _ = self.disable_mouse_mode();
+    }
+}
+
 #[must_use = "futures do nothing unless polled"]
 impl futures::Future for PlaySession {
     type Item = ();
@@ -943,3 +1674,240 @@ impl futures::Future for PlaySession {
         component_future::poll_future(self, Self::POLL_FNS)
     }
 }
+
+// the recordings in a directory, in order of their filenames, which matches
+// the order that rotated recordings were written in. sidecar files (and
+// anything else which isn't a recording) are skipped based on their
+// extension.
+fn recordings_in_dir(dir: &str) -> Result<Vec<String>> {
+    let entries =
+        std::fs::read_dir(dir).context(crate::error::ReadDirSync {
+            filename: dir.to_string(),
+        })?;
+
+    let mut filenames = vec![];
+    for entry in entries {
+        let entry = entry.context(crate::error::ReadDirSync {
+            filename: dir.to_string(),
+        })?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.')
+            || !crate::config::RecordingFormat::has_extension(&name)
+            || !entry.path().is_file()
+        {
+            continue;
+        }
+        filenames.push(entry.path().to_string_lossy().into_owned());
+    }
+    if filenames.is_empty() {
+        return Err(Error::NoRecordings {
+            dir: dir.to_string(),
+        });
+    }
+    filenames.sort_by(|a, b| filename_cmp(a, b));
+    Ok(filenames)
+}
+
+// compares runs of digits by their numeric value, so that sequence numbers
+// still sort in order once they outgrow their padding (%N is padded to
+// three digits, so 1000 comes after 999)
+fn filename_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        let ord = match (a.peek(), b.peek()) {
+            (None, None) => return std::cmp::Ordering::Equal,
+            (None, Some(_)) => return std::cmp::Ordering::Less,
+            (Some(_), None) => return std::cmp::Ordering::Greater,
+            (Some(ac), Some(bc))
+                if ac.is_ascii_digit() && bc.is_ascii_digit() =>
+            {
+                let a_digits = take_digits(&mut a);
+                let b_digits = take_digits(&mut b);
+                let a_digits = a_digits.trim_start_matches('0');
+                let b_digits = b_digits.trim_start_matches('0');
+                a_digits
+                    .len()
+                    .cmp(&b_digits.len())
+                    .then_with(|| a_digits.cmp(b_digits))
+            }
+            (Some(ac), Some(bc)) => {
+                let ord = ac.cmp(bc);
+                a.next();
+                b.next();
+                ord
+            }
+        };
+        if ord != std::cmp::Ordering::Equal {
+            return ord;
+        }
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.peek().copied().filter(char::is_ascii_digit) {
+        digits.push(c);
+        chars.next();
+    }
+    digits
+}
+
+fn format_time(time: std::time::Duration, hours: bool) -> String {
+    let secs = time.as_secs();
+    if hours {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+fn format_skipped(time: std::time::Duration) -> String {
+    let secs = time.as_secs();
+    if secs >= 3600 {
+        format!("{}h{}m", secs / 3600, secs / 60 % 60)
+    } else if secs >= 60 {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+// accepts seconds, minutes:seconds, or hours:minutes:seconds
+fn parse_time(s: &str) -> Option<std::time::Duration> {
+    let parts: Vec<_> = s.trim().split(':').collect();
+    if parts.len() > 3 {
+        return None;
+    }
+    let mut secs = 0;
+    for part in parts {
+        secs = secs * 60 + part.parse::<u64>().ok()?;
+    }
+    Some(std::time::Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_time() {
+        let secs = std::time::Duration::from_secs;
+        assert_eq!(parse_time("0"), Some(secs(0)));
+        assert_eq!(parse_time("90"), Some(secs(90)));
+        assert_eq!(parse_time("1:30"), Some(secs(90)));
+        assert_eq!(parse_time(" 1:02:03 "), Some(secs(3723)));
+        assert_eq!(parse_time("1:2:3:4"), None);
+        assert_eq!(parse_time("1:"), None);
+        assert_eq!(parse_time("abc"), None);
+        assert_eq!(parse_time(""), None);
+    }
+
+    #[test]
+    fn test_format_time() {
+        let secs = std::time::Duration::from_secs;
+        assert_eq!(format_time(secs(0), false), "00:00");
+        assert_eq!(format_time(secs(83), false), "01:23");
+        assert_eq!(format_time(secs(83), true), "0:01:23");
+        assert_eq!(format_time(secs(37230), true), "10:20:30");
+    }
+
+    #[test]
+    fn test_format_skipped() {
+        let secs = std::time::Duration::from_secs;
+        assert_eq!(format_skipped(secs(7)), "7s");
+        assert_eq!(format_skipped(secs(725)), "12m");
+        assert_eq!(format_skipped(secs(3900)), "1h5m");
+    }
+
+    #[test]
+    fn test_prev_next_file() {
+        let size = crate::term::Size { rows: 24, cols: 80 };
+        let mut player = Player::new(
+            "first.ttyrec",
+            crate::config::RecordingFormat::Ttyrec,
+            size,
+            &crate::config::Play::default(),
+        );
+        let secs = std::time::Duration::from_secs;
+        for i in 0..3 {
+            player.ttyrec.add_frame(secs(i), b"a");
+        }
+        // an empty file starts at the same frame as the file after it
+        player
+            .add_file("empty.ttyrec", crate::config::RecordingFormat::Ttyrec);
+        player
+            .add_file("last.ttyrec", crate::config::RecordingFormat::Ttyrec);
+        for i in 0..3 {
+            player.ttyrec.add_frame(secs(i), b"b");
+        }
+
+        player.next_file();
+        assert_eq!(player.current_frame_idx(), 3);
+        assert_eq!(player.current_file_idx(), 2);
+        player.next_file();
+        assert_eq!(player.current_frame_idx(), 3);
+
+        player.seek_to_frame(4);
+        player.prev_file();
+        assert_eq!(player.current_frame_idx(), 3);
+        player.prev_file();
+        assert_eq!(player.current_frame_idx(), 0);
+        assert_eq!(player.current_file_idx(), 0);
+        player.prev_file();
+        assert_eq!(player.current_frame_idx(), 0);
+
+        player.seek_to_frame(2);
+        player.prev_file();
+        assert_eq!(player.current_frame_idx(), 0);
+    }
+
+    #[test]
+    fn test_recordings_in_dir() {
+        let dir = std::env::temp_dir().join(format!(
+            "teleterm-test-recordings-in-dir-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(dir.join("subdir.ttyrec")).unwrap();
+        for name in &[
+            "rec-1000.ttyrec",
+            "rec-999.ttyrec",
+            "rec-002.cast",
+            "rec-002.ttyrec.bookmarks",
+            "rec-002.ttyrec.input",
+            "rec-002.ttyrec",
+            ".hidden.ttyrec",
+            "notes.txt",
+        ] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+        assert_eq!(
+            recordings_in_dir(&dir.to_string_lossy()).unwrap(),
+            vec![
+                path("rec-002.cast"),
+                path("rec-002.ttyrec"),
+                path("rec-999.ttyrec"),
+                path("rec-1000.ttyrec"),
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("notes.txt"), b"").unwrap();
+        assert!(recordings_in_dir(&dir.to_string_lossy()).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_filename_cmp() {
+        use std::cmp::Ordering;
+        assert_eq!(filename_cmp("a-999", "a-1000"), Ordering::Less);
+        assert_eq!(filename_cmp("a-1000", "a-999"), Ordering::Greater);
+        assert_eq!(filename_cmp("a-002", "a-010"), Ordering::Less);
+        assert_eq!(filename_cmp("a-2", "a-002"), Ordering::Equal);
+        assert_eq!(filename_cmp("a-2.cast", "a-2.ttyrec"), Ordering::Less);
+        assert_eq!(filename_cmp("a", "a-1"), Ordering::Less);
+        assert_eq!(filename_cmp("b-1", "a-2"), Ordering::Greater);
+    }
+}
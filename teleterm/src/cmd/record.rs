@@ -1,4 +1,5 @@
 use crate::prelude::*;
+use std::os::unix::fs::OpenOptionsExt as _;
 use tokio::io::AsyncWrite as _;
 
 #[derive(serde::Deserialize, Debug, Default)]
@@ -6,6 +7,9 @@ pub struct Config {
     #[serde(default)]
     command: crate::config::Command,
 
+    #[serde(default)]
+    record: crate::config::Record,
+
     #[serde(default)]
     ttyrec: crate::config::Ttyrec,
 }
@@ -16,6 +20,7 @@ impl crate::config::Config for Config {
         matches: &clap::ArgMatches<'a>,
     ) -> Result<()> {
         self.command.merge_args(matches)?;
+        self.record.merge_args(matches)?;
         self.ttyrec.merge_args(matches)?;
         Ok(())
     }
@@ -23,17 +28,24 @@ impl crate::config::Config for Config {
     fn run(
         &self,
     ) -> Box<dyn futures::Future<Item = (), Error = Error> + Send> {
-        Box::new(RecordSession::new(
+        match RecordSession::new(
             &self.ttyrec.filename,
+            self.ttyrec.format(),
             &self.command.command,
             &self.command.args,
-        ))
+            &self.record,
+        ) {
+            Ok(session) => Box::new(session),
+            Err(e) => Box::new(futures::future::err(e)),
+        }
     }
 }
 
 pub fn cmd<'a, 'b>(app: clap::App<'a, 'b>) -> clap::App<'a, 'b> {
-    crate::config::Command::cmd(crate::config::Ttyrec::cmd(
-        app.about("Record a terminal session to a file"),
+    crate::config::Command::cmd(crate::config::Record::cmd(
+        crate::config::Ttyrec::cmd(
+            app.about("Record a terminal session to a file"),
+        ),
     ))
 }
 
@@ -50,6 +62,71 @@ pub fn config(
     Ok(Box::new(config))
 }
 
+const BOOKMARK_KEY: u8 = b'm';
+
+// handles the escape keys for tt record, and passes the rest of the input
+// along to be recorded when input recording is enabled
+struct EscapeFilter {
+    filter: crate::escape::Filter,
+    bookmarks: tokio::sync::mpsc::UnboundedSender<()>,
+    keys: Option<tokio::sync::mpsc::UnboundedSender<Vec<u8>>>,
+}
+
+impl EscapeFilter {
+    fn new(
+        escape_key: u8,
+        bookmarks: tokio::sync::mpsc::UnboundedSender<()>,
+        keys: Option<tokio::sync::mpsc::UnboundedSender<Vec<u8>>>,
+    ) -> Self {
+        Self {
+            filter: crate::escape::Filter::new(escape_key),
+            bookmarks,
+            keys,
+        }
+    }
+
+    // appends the bytes which should be passed through to the command to
+    // output, and returns the number of bookmarks which were requested
+    fn filter(
+        &mut self,
+        input: &[u8],
+        output: &mut std::collections::VecDeque<u8>,
+    ) -> usize {
+        let mut bookmarks = 0;
+        for &c in input {
+            match self.filter.key(c) {
+                Some(crate::escape::Key::Input(c)) => output.push_back(c),
+                Some(crate::escape::Key::Command(BOOKMARK_KEY)) => {
+                    bookmarks += 1;
+                }
+                Some(crate::escape::Key::Command(_)) | None => {}
+            }
+        }
+        bookmarks
+    }
+}
+
+impl crate::escape::Process for EscapeFilter {
+    fn process(
+        &mut self,
+        input: &[u8],
+        output: &mut std::collections::VecDeque<u8>,
+    ) {
+        let start = output.len();
+        let bookmarks = self.filter(input, output);
+        // the receiving ends only go away once the session is over
+        for _ in 0..bookmarks {
+            let _ = self.bookmarks.try_send(());
+        }
+        if let Some(keys) = &mut self.keys {
+            if output.len() > start {
+                let _ = keys
+                    .try_send(output.iter().skip(start).copied().collect());
+            }
+        }
+    }
+}
+
 #[allow(clippy::large_enum_variant)]
 enum FileState {
     Closed {
@@ -57,21 +134,130 @@ enum FileState {
     },
     Opening {
         filename: String,
-        fut: tokio::fs::file::CreateFuture<String>,
+        fut: tokio::fs::file::OpenFuture<String>,
+        resume: Option<std::time::Duration>,
     },
     Open {
-        writer: ttyrec::Writer<tokio::fs::File>,
+        writer: Writer,
     },
 }
 
+enum Writer {
+    Ttyrec(crate::recording::TtyrecWriter<tokio::fs::File>),
+    Asciicast(crate::asciicast::Writer<tokio::fs::File>),
+}
+
+impl Writer {
+    // resume is the time of the last frame in the file, when continuing an
+    // existing recording
+    fn new(
+        file: tokio::fs::File,
+        format: crate::config::RecordingFormat,
+        size: crate::term::Size,
+        resume: Option<std::time::Duration>,
+    ) -> Self {
+        match format {
+            crate::config::RecordingFormat::Ttyrec => {
+                let base = resume.unwrap_or_else(|| {
+                    std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap_or_default()
+                });
+                // ttyrec has no header, so the initial size is recorded the
+                // same way as any later resizes
+                let mut writer =
+                    crate::recording::TtyrecWriter::new(file, base);
+                writer.frame(&size.resize_sequence());
+                Self::Ttyrec(writer)
+            }
+            crate::config::RecordingFormat::Asciicast => {
+                if let Some(offset) = resume {
+                    // the terminal may not be the size it was at the end of
+                    // the existing recording
+                    let mut writer =
+                        crate::asciicast::Writer::resume(file, offset);
+                    writer.resize(size);
+                    Self::Asciicast(writer)
+                } else {
+                    let timestamp = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .ok()
+                        .map(|d| d.as_secs());
+                    let header =
+                        crate::asciicast::Header::new(size, timestamp);
+                    Self::Asciicast(crate::asciicast::Writer::new(
+                        file, &header,
+                    ))
+                }
+            }
+        }
+    }
+
+    // these return the number of bytes added to the file
+    fn frame(&mut self, data: &[u8]) -> usize {
+        match self {
+            Self::Ttyrec(writer) => writer.frame(data),
+            Self::Asciicast(writer) => writer.frame(data),
+        }
+    }
+
+    fn resize(&mut self, size: crate::term::Size) -> usize {
+        match self {
+            Self::Ttyrec(writer) => writer.frame(&size.resize_sequence()),
+            Self::Asciicast(writer) => writer.resize(size),
+        }
+    }
+
+    fn needs_write(&self) -> bool {
+        match self {
+            Self::Ttyrec(writer) => writer.needs_write(),
+            Self::Asciicast(writer) => writer.needs_write(),
+        }
+    }
+
+    fn poll_write(&mut self) -> futures::Poll<(), Error> {
+        match self {
+            Self::Ttyrec(writer) => writer.poll_write(),
+            Self::Asciicast(writer) => writer.poll_write(),
+        }
+    }
+}
+
 struct RecordSession {
+    template: crate::filename_template::FilenameTemplate,
+    seq: u32,
+    filename: String,
+    format: crate::config::RecordingFormat,
     file: FileState,
+    // only applies to the first file, since files rotated to are always
+    // new ones
+    append: bool,
+    rotate_size: Option<u64>,
+    rotate_duration: Option<std::time::Duration>,
+    // rotation is otherwise only checked after writing, which would never
+    // happen while the session is idle
+    rotate_timer: Option<tokio::timer::Delay>,
+    file_size: u64,
     frame_data: Vec<u8>,
+    resize: Option<crate::term::Size>,
+    start: Option<std::time::Instant>,
+    // how far into the file this session started, when continuing an
+    // existing recording
+    offset: std::time::Duration,
 
-    process:
-        tokio_pty_process_stream::ResizingProcess<crate::async_stdin::Stdin>,
+    process: tokio_pty_process_stream::ResizingProcess<
+        crate::escape::Input<EscapeFilter>,
+    >,
     raw_screen: Option<crossterm::screen::RawScreen>,
     done: bool,
+    bookmarks: tokio::sync::mpsc::UnboundedReceiver<()>,
+    num_bookmarks: usize,
+    keys: Option<tokio::sync::mpsc::UnboundedReceiver<Vec<u8>>>,
+    // only used for ttyrec files, since asciicast can store input directly
+    input_log: Option<crate::keystrokes::Log>,
+    ptys_before_spawn:
+        Option<std::collections::HashSet<std::os::unix::io::RawFd>>,
+    pty: Option<crate::term::PtyMaster>,
 
     stdout: tokio::io::Stdout,
     to_write_stdout: std::collections::VecDeque<u8>,
@@ -79,32 +265,162 @@ struct RecordSession {
 }
 
 impl RecordSession {
-    fn new(filename: &str, cmd: &str, args: &[String]) -> Self {
-        let input = crate::async_stdin::Stdin::new();
+    fn new(
+        filename: &str,
+        format: crate::config::RecordingFormat,
+        cmd: &str,
+        args: &[String],
+        config: &crate::config::Record,
+    ) -> Result<Self> {
+        let template =
+            crate::filename_template::FilenameTemplate::new(filename);
+        if (config.rotate_size.is_some() || config.rotate_duration.is_some())
+            && !template.has_sequence()
+        {
+            return Err(Error::RotateWithoutSequence {
+                filename: filename.to_string(),
+            });
+        }
+        let (filename, seq) = template.first(config.append);
+
+        let (bookmarks_tx, bookmarks_rx) =
+            tokio::sync::mpsc::unbounded_channel();
+        let (keys_tx, keys_rx) = if config.record_input {
+            let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+            (Some(tx), Some(rx))
+        } else {
+            (None, None)
+        };
+        let input = crate::escape::Input::new(EscapeFilter::new(
+            config.escape_key,
+            bookmarks_tx,
+            keys_tx,
+        ));
+        // the command's pty is the one which shows up between now and when
+        // it starts
+        let ptys_before_spawn = if config.record_input {
+            Some(crate::term::pty_masters())
+        } else {
+            None
+        };
         let process = tokio_pty_process_stream::ResizingProcess::new(
             tokio_pty_process_stream::Process::new(cmd, args, input),
         );
 
-        Self {
-            file: FileState::Closed {
-                filename: filename.to_string(),
-            },
+        Ok(Self {
+            template,
+            seq,
+            filename: filename.clone(),
+            format,
+            file: FileState::Closed { filename },
+            append: config.append,
+            rotate_size: config.rotate_size,
+            rotate_duration: config.rotate_duration,
+            rotate_timer: None,
+            file_size: 0,
             frame_data: vec![],
+            resize: None,
+            start: None,
+            offset: std::time::Duration::default(),
 
             process,
             raw_screen: None,
             done: false,
+            bookmarks: bookmarks_rx,
+            num_bookmarks: 0,
+            keys: keys_rx,
+            input_log: None,
+            ptys_before_spawn,
+            pty: None,
 
             stdout: tokio::io::stdout(),
             to_write_stdout: std::collections::VecDeque::new(),
             needs_flush: false,
-        }
+        })
+    }
+
+    // bookmark and keystroke times are relative to the first frame, like
+    // frame times are when reading the file back
+    fn elapsed(&self) -> std::time::Duration {
+        self.offset
+            + self
+                .start
+                .map_or_else(std::time::Duration::default, |start| {
+                    start.elapsed()
+                })
+    }
+
+    fn should_rotate(&self) -> bool {
+        rotation_due(
+            self.rotate_size,
+            self.rotate_duration,
+            self.file_size,
+            self.start.map(|start| start.elapsed()),
+        )
+    }
+
+    // the current file has been completely written by the time this is
+    // called, so it can just be dropped
+    fn rotate(&mut self) {
+        let (filename, seq) = self.template.next(self.seq);
+        self.seq = seq;
+        self.filename = filename.clone();
+        self.file = FileState::Closed { filename };
+        self.input_log = None;
+        self.rotate_timer = None;
+        self.file_size = 0;
+        self.start = None;
+        self.offset = std::time::Duration::default();
     }
 
     fn record_bytes(&mut self, buf: &[u8]) {
         self.frame_data.extend(buf);
         self.to_write_stdout.extend(buf);
     }
+
+    fn write_frame_data(&mut self) -> Result<()> {
+        if self.frame_data.is_empty() {
+            return Ok(());
+        }
+        if let FileState::Open { writer } = &mut self.file {
+            self.file_size += writer.frame(&self.frame_data) as u64;
+            self.frame_data.clear();
+        }
+        Ok(())
+    }
+
+    fn write_keys(&mut self, data: &[u8]) -> Result<()> {
+        // echo is checked when the keys are recorded rather than when they
+        // are typed, but the command can't have reacted to them yet anyway.
+        // keys typed before the command has started are masked, since there
+        // is no way to tell what they will be read by.
+        let data = if self
+            .pty
+            .as_ref()
+            .map_or(true, crate::term::PtyMaster::reading_password)
+        {
+            crate::keystrokes::mask(data)
+        } else {
+            data.to_vec()
+        };
+        let time = self.elapsed();
+        match &mut self.file {
+            FileState::Open {
+                writer: Writer::Asciicast(writer),
+            } => {
+                self.file_size += writer.input(&data) as u64;
+            }
+            FileState::Open { .. } => {
+                if let Some(input_log) = &mut self.input_log {
+                    input_log.append(time, &data)?;
+                }
+            }
+            // keys typed before the recording starts have nothing to go
+            // along with
+            _ => {}
+        }
+        Ok(())
+    }
 }
 
 impl RecordSession {
@@ -117,6 +433,9 @@ impl RecordSession {
             Error,
         >] = &[
         &Self::poll_open_file,
+        &Self::poll_rotate_timer,
+        &Self::poll_read_bookmarks,
+        &Self::poll_read_keys,
         &Self::poll_read_process,
         &Self::poll_write_terminal,
         &Self::poll_flush_terminal,
@@ -126,13 +445,53 @@ impl RecordSession {
     fn poll_open_file(&mut self) -> component_future::Poll<(), Error> {
         match &mut self.file {
             FileState::Closed { filename } => {
+                let span = if self.append {
+                    crate::recording::recorded_span(filename, self.format)?
+                } else {
+                    None
+                };
+                self.append = false;
+
+                if span.is_none() {
+                    crate::bookmarks::clear(filename)?;
+                    crate::keystrokes::clear(filename)?;
+                }
+                if self.keys.is_some()
+                    && self.format == crate::config::RecordingFormat::Ttyrec
+                {
+                    self.input_log = Some(crate::keystrokes::Log::open(
+                        filename,
+                        span.is_some(),
+                    )?);
+                }
+
+                let mut options = std::fs::OpenOptions::new();
+                options.write(true).create(true);
+                // asciicast recordings contain the recorded input directly
+                if self.keys.is_some() {
+                    options.mode(0o600);
+                }
+                if let Some(span) = &span {
+                    truncate_file(filename, span.len)?;
+                    options.append(true);
+                    self.offset = span.duration();
+                    self.file_size = span.len;
+                } else {
+                    options.truncate(true);
+                }
                 self.file = FileState::Opening {
                     filename: filename.to_string(),
-                    fut: tokio::fs::File::create(filename.to_string()),
+                    fut: tokio::fs::OpenOptions::from(options)
+                        .open(filename.to_string()),
+                    resume: span.map(|span| span.last),
                 };
                 Ok(component_future::Async::DidWork)
             }
-            FileState::Opening { filename, fut } => {
+            FileState::Opening {
+                filename,
+                fut,
+                resume,
+            } => {
                 let file = component_future::try_ready!(fut
                     .poll()
                     .with_context(|| {
@@ -140,9 +499,15 @@ impl RecordSession {
                             filename: filename.clone(),
                         }
                     }));
+                let size = crate::term::Size::get()?;
                 self.file = FileState::Open {
-                    writer: ttyrec::Writer::new(file),
+                    writer: Writer::new(file, self.format, size, *resume),
                 };
+                let start = std::time::Instant::now();
+                self.start = Some(start);
+                self.rotate_timer = self.rotate_duration.map(|duration| {
+                    tokio::timer::Delay::new(start + duration)
+                });
                 Ok(component_future::Async::DidWork)
             }
             FileState::Open { .. } => {
@@ -151,6 +516,61 @@ impl RecordSession {
         }
     }
 
+    // the rotation itself happens in poll_write_file, once the file is
+    // fully written
+    fn poll_rotate_timer(&mut self) -> component_future::Poll<(), Error> {
+        let timer = if let Some(timer) = &mut self.rotate_timer {
+            timer
+        } else {
+            return Ok(component_future::Async::NothingToDo);
+        };
+        component_future::try_ready!(timer
+            .poll()
+            .context(crate::error::TimerRotate));
+        self.rotate_timer = None;
+        Ok(component_future::Async::DidWork)
+    }
+
+    fn poll_read_bookmarks(&mut self) -> component_future::Poll<(), Error> {
+        match component_future::try_ready!(self
+            .bookmarks
+            .poll()
+            .context(crate::error::ReadChannel))
+        {
+            Some(()) => {
+                let time = self.elapsed();
+                self.num_bookmarks += 1;
+                crate::bookmarks::append(
+                    &self.filename,
+                    time,
+                    &format!("marker {}", self.num_bookmarks),
+                )?;
+                Ok(component_future::Async::DidWork)
+            }
+            // the input is owned by the process, so this happens once the
+            // process has exited
+            None => Ok(component_future::Async::NothingToDo),
+        }
+    }
+
+    fn poll_read_keys(&mut self) -> component_future::Poll<(), Error> {
+        let keys = if let Some(keys) = &mut self.keys {
+            keys
+        } else {
+            return Ok(component_future::Async::NothingToDo);
+        };
+        match component_future::try_ready!(keys
+            .poll()
+            .context(crate::error::ReadChannel))
+        {
+            Some(data) => {
+                self.write_keys(&data)?;
+                Ok(component_future::Async::DidWork)
+            }
+            None => Ok(component_future::Async::NothingToDo),
+        }
+    }
+
     fn poll_read_process(&mut self) -> component_future::Poll<(), Error> {
         match component_future::try_ready!(self
             .process
@@ -166,6 +586,14 @@ impl RecordSession {
                             .context(crate::error::ToRawMode)?,
                     );
                 }
+                // refuse to record input at all rather than risk recording
+                // passwords
+                if let Some(before) = self.ptys_before_spawn.take() {
+                    self.pty = Some(
+                        crate::term::PtyMaster::opened_since(&before)
+                            .context(crate::error::FindPty)?,
+                    );
+                }
             }
             Some(tokio_pty_process_stream::Event::CommandExit { .. }) => {
                 self.done = true;
@@ -173,7 +601,14 @@ impl RecordSession {
             Some(tokio_pty_process_stream::Event::Output { data }) => {
                 self.record_bytes(&data);
             }
-            Some(tokio_pty_process_stream::Event::Resize { .. }) => {}
+            Some(tokio_pty_process_stream::Event::Resize {
+                size: (rows, cols),
+            }) => {
+                // output from before the resize was drawn at the old size,
+                // so it needs to be written out first
+                self.write_frame_data()?;
+                self.resize = Some(crate::term::Size { rows, cols });
+            }
             None => {
                 if !self.done {
                     unreachable!()
@@ -224,29 +659,62 @@ impl RecordSession {
             }
         };
 
+        if let Some(size) = self.resize.take() {
+            self.file_size += writer.resize(size) as u64;
+        }
         if !self.frame_data.is_empty() {
-            writer
-                .frame(&self.frame_data)
-                .context(crate::error::WriteTtyrec)?;
+            self.file_size += writer.frame(&self.frame_data) as u64;
             self.frame_data.clear();
         }
 
         if writer.needs_write() {
-            component_future::try_ready!(writer
-                .poll_write()
-                .context(crate::error::WriteTtyrec));
+            component_future::try_ready!(writer.poll_write());
             Ok(component_future::Async::DidWork)
-        } else {
+        } else if self.done {
             // finish writing to the file before actually ending
-            if self.done {
-                Ok(component_future::Async::Ready(()))
-            } else {
-                Ok(component_future::Async::NothingToDo)
-            }
+            Ok(component_future::Async::Ready(()))
+        } else if self.should_rotate() {
+            self.rotate();
+            Ok(component_future::Async::DidWork)
+        } else {
+            Ok(component_future::Async::NothingToDo)
         }
     }
 }
 
+// elapsed is how long the current file has been recording for, if it has
+// been opened yet
+fn rotation_due(
+    rotate_size: Option<u64>,
+    rotate_duration: Option<std::time::Duration>,
+    file_size: u64,
+    elapsed: Option<std::time::Duration>,
+) -> bool {
+    rotate_size.map_or(false, |size| file_size >= size)
+        || rotate_duration.map_or(false, |duration| {
+            elapsed.map_or(false, |elapsed| elapsed >= duration)
+        })
+}
+
+// removes anything after the last complete frame of a recording being
+// continued, so that new frames don't end up inside of a partial one
+fn truncate_file(filename: &str, len: u64) -> Result<()> {
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .open(filename)
+        .context(crate::error::OpenFileSync {
+            filename: filename.to_string(),
+        })?;
+    if file.metadata().context(crate::error::ReadFileSync)?.len() > len {
+        log::warn!(
+            "{} ends with a partially written frame, removing it",
+            filename
+        );
+        file.set_len(len).context(crate::error::WriteFileSync)?;
+    }
+    Ok(())
+}
+
 #[must_use = "futures do nothing unless polled"]
 impl futures::Future for RecordSession {
     type Item = ();
@@ -256,3 +724,58 @@ impl futures::Future for RecordSession {
         component_future::poll_future(self, Self::POLL_FNS)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn filter(filter: &mut EscapeFilter, input: &[u8]) -> (Vec<u8>, usize) {
+        let mut output = std::collections::VecDeque::new();
+        let bookmarks = filter.filter(input, &mut output);
+        (output.into_iter().collect(), bookmarks)
+    }
+
+    #[test]
+    fn test_escape_filter() {
+        let (bookmarks_tx, _bookmarks_rx) =
+            tokio::sync::mpsc::unbounded_channel();
+        let mut f = EscapeFilter::new(b'\x1d', bookmarks_tx, None);
+        assert_eq!(filter(&mut f, b"abc"), (b"abc".to_vec(), 0));
+        assert_eq!(filter(&mut f, b"a\x1dmb\x1dm"), (b"ab".to_vec(), 2));
+        assert_eq!(filter(&mut f, b"a\x1d\x1db"), (b"a\x1db".to_vec(), 0));
+        assert_eq!(filter(&mut f, b"a\x1dzb"), (b"ab".to_vec(), 0));
+        assert_eq!(filter(&mut f, b"a\x1d"), (b"a".to_vec(), 0));
+        assert_eq!(filter(&mut f, b"mb"), (b"b".to_vec(), 1));
+    }
+
+    #[test]
+    fn test_rotation_due() {
+        let secs = std::time::Duration::from_secs;
+        assert!(!rotation_due(None, None, 1_000_000, Some(secs(1000))));
+
+        assert!(!rotation_due(Some(100), None, 99, Some(secs(1000))));
+        assert!(rotation_due(Some(100), None, 100, Some(secs(1))));
+        assert!(rotation_due(Some(100), None, 150, None));
+
+        assert!(!rotation_due(None, Some(secs(60)), 1000, None));
+        assert!(!rotation_due(None, Some(secs(60)), 1000, Some(secs(59))));
+        assert!(rotation_due(None, Some(secs(60)), 0, Some(secs(60))));
+
+        assert!(rotation_due(Some(100), Some(secs(60)), 0, Some(secs(61))));
+        assert!(rotation_due(Some(100), Some(secs(60)), 100, Some(secs(1))));
+    }
+
+    #[test]
+    fn test_truncate_file() {
+        let filename = std::env::temp_dir()
+            .join(format!("teleterm-test-truncate-{}", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        std::fs::write(&filename, b"abcdef").unwrap();
+        truncate_file(&filename, 6).unwrap();
+        assert_eq!(std::fs::read(&filename).unwrap(), b"abcdef".to_vec());
+        truncate_file(&filename, 4).unwrap();
+        assert_eq!(std::fs::read(&filename).unwrap(), b"abcd".to_vec());
+        std::fs::remove_file(&filename).unwrap();
+    }
+}
@@ -0,0 +1,160 @@
+use crate::prelude::*;
+
+const END_OPTION: &str = "end";
+const OUTPUT_OPTION: &str = "output";
+const OUTPUT_FORMAT_OPTION: &str = "output-format";
+const START_OPTION: &str = "start";
+
+#[derive(serde::Deserialize, Debug, Default)]
+pub struct Config {
+    #[serde(default)]
+    render: crate::config::Render,
+
+    #[serde(default)]
+    ttyrec: crate::config::Ttyrec,
+
+    #[serde(skip)]
+    output: String,
+
+    #[serde(skip)]
+    output_format: Option<crate::render::Format>,
+
+    #[serde(skip)]
+    start: Option<std::time::Duration>,
+
+    #[serde(skip)]
+    end: Option<std::time::Duration>,
+}
+
+impl Config {
+    fn render(&self) -> Result<()> {
+        let format = if let Some(format) = self.output_format {
+            format
+        } else {
+            crate::render::Format::from_filename(&self.output).context(
+                crate::error::UnknownRenderFormat {
+                    filename: self.output.clone(),
+                },
+            )?
+        };
+
+        let transcript = crate::recording::read_transcript(
+            &self.ttyrec.filename,
+            self.ttyrec.format(),
+        )?;
+        let frames = crate::render::frames(
+            &transcript,
+            &crate::render::Options {
+                start: self.start,
+                end: self.end,
+                playback_ratio: self.render.playback_ratio,
+                max_frame_length: self.render.max_frame_length,
+            },
+        );
+
+        let file = std::fs::File::create(&self.output).context(
+            crate::error::CreateFileSync {
+                filename: self.output.clone(),
+            },
+        )?;
+        let out = std::io::BufWriter::new(file);
+        match format {
+            crate::render::Format::Svg => {
+                crate::render::svg::render(&frames, out)
+            }
+            crate::render::Format::Html => {
+                crate::render::html::render(&frames, out)
+            }
+            crate::render::Format::Gif => {
+                crate::render::gif::render(&frames, out)
+            }
+        }
+    }
+}
+
+impl crate::config::Config for Config {
+    fn merge_args<'a>(
+        &mut self,
+        matches: &clap::ArgMatches<'a>,
+    ) -> Result<()> {
+        self.render.merge_args(matches)?;
+        self.ttyrec.merge_args(matches)?;
+        self.output = matches.value_of(OUTPUT_OPTION).unwrap().to_string();
+        if matches.is_present(OUTPUT_FORMAT_OPTION) {
+            self.output_format = Some(crate::render::Format::try_from(
+                matches.value_of(OUTPUT_FORMAT_OPTION).unwrap(),
+            )?);
+        }
+        self.start =
+            matches.value_of(START_OPTION).map(parse_secs).transpose()?;
+        self.end =
+            matches.value_of(END_OPTION).map(parse_secs).transpose()?;
+        Ok(())
+    }
+
+    fn run(
+        &self,
+    ) -> Box<dyn futures::Future<Item = (), Error = Error> + Send> {
+        Box::new(futures::future::result(self.render()))
+    }
+}
+
+pub fn cmd<'a, 'b>(app: clap::App<'a, 'b>) -> clap::App<'a, 'b> {
+    let output_help = "File to write the rendered recording to";
+    let output_format_help = "Format to render to, either svg (an animated image), html (a standalone page with a player), or gif (defaults to the extension of the output file)";
+    let start_help =
+        "Start rendering at this number of seconds into the recording";
+    let end_help =
+        "Stop rendering at this number of seconds into the recording";
+
+    crate::config::Render::cmd(crate::config::Ttyrec::cmd(
+        app.about("Render a recording as an animated svg, html page, or gif")
+            .arg(
+                clap::Arg::with_name(OUTPUT_FORMAT_OPTION)
+                    .long(OUTPUT_FORMAT_OPTION)
+                    .takes_value(true)
+                    .value_name("FORMAT")
+                    .possible_values(&["svg", "html", "gif"])
+                    .help(output_format_help),
+            )
+            .arg(
+                clap::Arg::with_name(START_OPTION)
+                    .long(START_OPTION)
+                    .takes_value(true)
+                    .value_name("SECS")
+                    .help(start_help),
+            )
+            .arg(
+                clap::Arg::with_name(END_OPTION)
+                    .long(END_OPTION)
+                    .takes_value(true)
+                    .value_name("SECS")
+                    .help(end_help),
+            )
+            .arg(
+                clap::Arg::with_name(OUTPUT_OPTION)
+                    .value_name("OUTPUT")
+                    .required(true)
+                    .help(output_help),
+            ),
+    ))
+}
+
+pub fn config(
+    config: Option<config::Config>,
+) -> Result<Box<dyn crate::config::Config>> {
+    let config: Config = if let Some(config) = config {
+        config
+            .try_into()
+            .context(crate::error::CouldntParseConfig)?
+    } else {
+        Config::default()
+    };
+    Ok(Box::new(config))
+}
+
+fn parse_secs(s: &str) -> Result<std::time::Duration> {
+    s.parse()
+        .map(std::time::Duration::from_secs)
+        .context(crate::error::ParseTimeRange { input: s })
+}
@@ -1,5 +1,4 @@
 use crate::prelude::*;
-use std::io::Read as _;
 
 #[derive(serde::Deserialize, Debug, Default)]
 pub struct Config {
@@ -40,13 +39,26 @@ impl crate::config::Config for Config {
                     .map(|config| (*ty, config.clone()))
             })
             .collect();
-        if let Some(tls_identity_file) = &self.server.tls_identity_file {
+        let tls_identity = match self.server.tls_identity() {
+            Ok(tls_identity) => tls_identity,
+            Err(e) => return Box::new(futures::future::err(e)),
+        };
+        let authorized_keys = match self.server.authorized_keys() {
+            Ok(authorized_keys) => authorized_keys,
+            Err(e) => return Box::new(futures::future::err(e)),
+        };
+        if let Some(tls_identity) = tls_identity {
             create_server_tls(
                 self.server.listen_address,
                 self.server.read_timeout,
-                tls_identity_file,
+                tls_identity,
+                self.server.tls_reload_interval,
                 self.server.allowed_login_methods.clone(),
                 oauth_configs,
+                self.server.password_verifier(),
+                authorized_keys.clone(),
+                self.server.tokens(),
+                self.server.roles(),
                 self.server.uid,
                 self.server.gid,
             )
@@ -56,6 +68,11 @@ impl crate::config::Config for Config {
                 self.server.read_timeout,
                 self.server.allowed_login_methods.clone(),
                 oauth_configs,
+                self.server.password_verifier(),
+                authorized_keys,
+                self.server.tokens(),
+                self.server.roles(),
+                self.server.allow_unencrypted_passwords,
                 self.server.uid,
                 self.server.gid,
             )
@@ -90,6 +107,13 @@ fn create_server(
         crate::protocol::AuthType,
         crate::oauth::Config,
     >,
+    password_verifier: Option<std::sync::Arc<dyn crate::auth::Verifier>>,
+    authorized_keys: Option<
+        std::sync::Arc<crate::auth::ssh_key::AuthorizedKeys>,
+    >,
+    tokens: Option<std::sync::Arc<crate::auth::token::Tokens>>,
+    roles: crate::config::Roles,
+    allow_unencrypted_passwords: bool,
     uid: Option<users::uid_t>,
     gid: Option<users::gid_t>,
 ) -> Box<dyn futures::Future<Item = (), Error = Error> + Send> {
@@ -104,6 +128,11 @@ fn create_server(
         read_timeout,
         allowed_login_methods,
         oauth_configs,
+        password_verifier,
+        authorized_keys,
+        tokens,
+        roles,
+        allow_unencrypted_passwords,
     );
 
     Box::new(server)
@@ -112,7 +141,8 @@ fn create_server(
 fn create_server_tls(
     address: std::net::SocketAddr,
     read_timeout: std::time::Duration,
-    tls_identity_file: &str,
+    tls_identity: crate::server::tls::Identity,
+    tls_reload_interval: std::time::Duration,
     allowed_login_methods: std::collections::HashSet<
         crate::protocol::AuthType,
     >,
@@ -120,10 +150,16 @@ fn create_server_tls(
         crate::protocol::AuthType,
         crate::oauth::Config,
     >,
+    password_verifier: Option<std::sync::Arc<dyn crate::auth::Verifier>>,
+    authorized_keys: Option<
+        std::sync::Arc<crate::auth::ssh_key::AuthorizedKeys>,
+    >,
+    tokens: Option<std::sync::Arc<crate::auth::token::Tokens>>,
+    roles: crate::config::Roles,
     uid: Option<users::uid_t>,
     gid: Option<users::gid_t>,
 ) -> Box<dyn futures::Future<Item = (), Error = Error> + Send> {
-    let tls_acceptor = match accept_tls(tls_identity_file) {
+    let tls_acceptor = match tls_identity.acceptor() {
         Ok(acceptor) => acceptor,
         Err(e) => return Box::new(futures::future::err(e)),
     };
@@ -133,15 +169,31 @@ fn create_server_tls(
         Err(e) => return Box::new(futures::future::err(e)),
     };
 
-    let acceptor = listener
-        .incoming()
-        .context(crate::error::Acceptor)
-        .map(move |sock| tls_acceptor.accept(sock));
+    // reloading happens as the unprivileged user, so point out a key which
+    // was only readable before dropping privileges now rather than at the
+    // next renewal
+    if uid.is_some() || gid.is_some() {
+        if let Err(e) = tls_identity.check_readable() {
+            log::warn!(
+                "tls identity is not readable after dropping privileges, so it can't be reloaded: {}",
+                e
+            );
+        }
+    }
+
+    let acceptor = listener.incoming().context(crate::error::Acceptor);
     let server = crate::server::tls::Server::new(
         Box::new(acceptor),
+        tls_identity,
+        tls_acceptor,
+        tls_reload_interval,
         read_timeout,
         allowed_login_methods,
         oauth_configs,
+        password_verifier,
+        authorized_keys,
+        tokens,
+        roles,
     );
 
     Box::new(server)
@@ -159,23 +211,6 @@ fn listen(
     Ok(listener)
 }
 
-fn accept_tls(tls_identity_file: &str) -> Result<tokio_tls::TlsAcceptor> {
-    let mut file = std::fs::File::open(tls_identity_file).context(
-        crate::error::OpenFileSync {
-            filename: tls_identity_file,
-        },
-    )?;
-    let mut identity = vec![];
-    file.read_to_end(&mut identity)
-        .context(crate::error::ReadFileSync)?;
-    let identity = native_tls::Identity::from_pkcs12(&identity, "")
-        .context(crate::error::ParseIdentity)?;
-    let acceptor = native_tls::TlsAcceptor::new(identity)
-        .context(crate::error::CreateAcceptor)?;
-
-    Ok(tokio_tls::TlsAcceptor::from(acceptor))
-}
-
 fn drop_privs(
     uid: Option<users::uid_t>,
     gid: Option<users::gid_t>,
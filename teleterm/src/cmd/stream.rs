@@ -8,6 +8,9 @@ pub struct Config {
 
     #[serde(default)]
     command: crate::config::Command,
+
+    #[serde(default)]
+    stream: crate::config::Stream,
 }
 
 impl crate::config::Config for Config {
@@ -17,6 +20,7 @@ impl crate::config::Config for Config {
     ) -> Result<()> {
         self.client.merge_args(matches)?;
         self.command.merge_args(matches)?;
+        self.stream.merge_args(matches)?;
         Ok(())
     }
 
@@ -36,42 +40,89 @@ impl crate::config::Config for Config {
                 }
             }
             crate::protocol::AuthType::RecurseCenter => {
-                let id = crate::client::load_client_auth_id(self.client.auth);
+                let profile = self
+                    .client
+                    .profile
+                    .as_ref()
+                    .map(std::string::String::as_str);
+                let id = match crate::client::load_client_auth_id(
+                    self.client.auth,
+                    profile,
+                ) {
+                    Ok(id) => id,
+                    Err(e) => return Box::new(futures::future::err(e)),
+                };
                 crate::protocol::Auth::recurse_center(
                     id.as_ref().map(std::string::String::as_str),
+                    profile,
                 )
             }
+            crate::protocol::AuthType::Password => {
+                let username = self
+                    .client
+                    .username
+                    .clone()
+                    .context(crate::error::CouldntFindUsername);
+                let password = username.and_then(|username| {
+                    let password = crate::auth::password::prompt(&format!(
+                        "Password for {}: ",
+                        username
+                    ))?;
+                    Ok((username, password))
+                });
+                match password {
+                    Ok((username, password)) => {
+                        crate::protocol::Auth::password(&username, &password)
+                    }
+                    Err(e) => return Box::new(futures::future::err(e)),
+                }
+            }
+            crate::protocol::AuthType::SshKey => {
+                let username = self
+                    .client
+                    .username
+                    .clone()
+                    .context(crate::error::CouldntFindUsername);
+                match username {
+                    Ok(username) => crate::protocol::Auth::ssh_key(
+                        &username,
+                        self.client
+                            .ssh_key_file
+                            .as_ref()
+                            .map(std::string::String::as_str),
+                        self.client.host(),
+                    ),
+                    Err(e) => return Box::new(futures::future::err(e)),
+                }
+            }
+            crate::protocol::AuthType::Token => {
+                match crate::client::load_api_token(
+                    self.client
+                        .token_file
+                        .as_ref()
+                        .map(std::string::String::as_str),
+                ) {
+                    Ok(token) => crate::protocol::Auth::token(&token),
+                    Err(e) => return Box::new(futures::future::err(e)),
+                }
+            }
         };
 
         let host = self.client.host().to_string();
         let address = *self.client.addr();
         if self.client.tls {
-            let connector = match native_tls::TlsConnector::new()
-                .context(crate::error::CreateConnector)
-            {
+            let connector = match self.client.tls_connector() {
                 Ok(connector) => connector,
                 Err(e) => return Box::new(futures::future::err(e)),
             };
-            let connect: crate::client::Connector<_> = Box::new(move || {
-                let host = host.clone();
-                let connector = connector.clone();
-                let connector = tokio_tls::TlsConnector::from(connector);
-                let stream = tokio::net::tcp::TcpStream::connect(&address);
-                Box::new(
-                    stream
-                        .context(crate::error::Connect { address })
-                        .and_then(move |stream| {
-                            connector
-                                .connect(&host, stream)
-                                .context(crate::error::ConnectTls { host })
-                        }),
-                )
-            });
+            let connect: crate::client::Connector<_> =
+                Box::new(move || connector.connect(&host, address));
             Box::new(StreamSession::new(
                 &self.command.command,
                 &self.command.args,
                 connect,
                 &auth,
+                &self.stream,
             ))
         } else {
             let connect: crate::client::Connector<_> = Box::new(move || {
@@ -85,6 +136,7 @@ impl crate::config::Config for Config {
                 &self.command.args,
                 connect,
                 &auth,
+                &self.stream,
             ))
         }
     }
@@ -92,7 +144,7 @@ impl crate::config::Config for Config {
 
 pub fn cmd<'a, 'b>(app: clap::App<'a, 'b>) -> clap::App<'a, 'b> {
     crate::config::Client::cmd(crate::config::Command::cmd(
-        app.about("Stream your terminal"),
+        crate::config::Stream::cmd(app.about("Stream your terminal")),
     ))
 }
 
@@ -112,20 +164,211 @@ pub fn config(
     Ok(Box::new(config))
 }
 
+const METADATA_FIELDS: &[&str] =
+    &["title", "description", "tags (comma separated)"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum EscapeCommand {
+    TogglePause,
+    EditMetadata,
+    EditorInput(Vec<u8>),
+}
+
+impl EscapeCommand {
+    fn from_key(key: u8) -> Option<Self> {
+        match key {
+            b'p' => Some(Self::TogglePause),
+            b't' => Some(Self::EditMetadata),
+            _ => None,
+        }
+    }
+}
+
+// handles the escape keys for tt stream
+struct EscapeFilter {
+    filter: crate::escape::Filter,
+    // set while the streamer is editing the stream metadata, in which case
+    // all input goes to the editor instead of the command. this is set here
+    // as soon as the edit command is seen (so that the rest of the current
+    // read doesn't leak through), and cleared by the session once editing is
+    // done.
+    capturing: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    commands: tokio::sync::mpsc::UnboundedSender<EscapeCommand>,
+}
+
+impl EscapeFilter {
+    fn new(
+        escape_key: u8,
+        capturing: std::sync::Arc<std::sync::atomic::AtomicBool>,
+        commands: tokio::sync::mpsc::UnboundedSender<EscapeCommand>,
+    ) -> Self {
+        Self {
+            filter: crate::escape::Filter::new(escape_key),
+            capturing,
+            commands,
+        }
+    }
+
+    // appends the bytes which should be passed through to the command to
+    // output. escape sequences which aren't commands are dropped entirely.
+    fn filter(
+        &mut self,
+        input: &[u8],
+        output: &mut std::collections::VecDeque<u8>,
+        commands: &mut Vec<EscapeCommand>,
+    ) {
+        for &c in input {
+            if self.capturing.load(std::sync::atomic::Ordering::SeqCst) {
+                if let Some(EscapeCommand::EditorInput(input)) =
+                    commands.last_mut()
+                {
+                    input.push(c);
+                } else {
+                    commands.push(EscapeCommand::EditorInput(vec![c]));
+                }
+                continue;
+            }
+            match self.filter.key(c) {
+                Some(crate::escape::Key::Input(c)) => output.push_back(c),
+                Some(crate::escape::Key::Command(c)) => {
+                    if let Some(command) = EscapeCommand::from_key(c) {
+                        if command == EscapeCommand::EditMetadata {
+                            self.capturing.store(
+                                true,
+                                std::sync::atomic::Ordering::SeqCst,
+                            );
+                        }
+                        commands.push(command);
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+impl crate::escape::Process for EscapeFilter {
+    fn process(
+        &mut self,
+        input: &[u8],
+        output: &mut std::collections::VecDeque<u8>,
+    ) {
+        let mut commands = vec![];
+        self.filter(input, output, &mut commands);
+        for command in commands {
+            // the receiving end only goes away once the session is over, at
+            // which point there is nothing left to do with the command
+            let _ = self.commands.try_send(command);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Metadata {
+    title: String,
+    description: String,
+    tags: Vec<String>,
+}
+
+// a minimal line editor drawn over the bottom line of the local terminal,
+// for editing the stream metadata while streaming
+struct MetadataEditor {
+    field: usize,
+    values: Vec<String>,
+}
+
+impl MetadataEditor {
+    fn new(metadata: &Metadata) -> Self {
+        Self {
+            field: 0,
+            values: vec![
+                metadata.title.clone(),
+                metadata.description.clone(),
+                metadata.tags.join(", "),
+            ],
+        }
+    }
+
+    // returns Some(true) once all fields have been entered, and Some(false)
+    // if editing was cancelled
+    fn process(&mut self, input: &[u8]) -> Option<bool> {
+        for c in String::from_utf8_lossy(input).chars() {
+            match c {
+                '\r' | '\n' => {
+                    self.field += 1;
+                    if self.field >= METADATA_FIELDS.len() {
+                        return Some(true);
+                    }
+                }
+                '\x1b' => return Some(false),
+                '\x7f' | '\x08' => {
+                    self.values[self.field].pop();
+                }
+                '\x15' => {
+                    self.values[self.field].clear();
+                }
+                c if c.is_control() => {}
+                c => {
+                    self.values[self.field].push(c);
+                }
+            }
+        }
+        None
+    }
+
+    fn prompt(&self, size: (u16, u16)) -> Vec<u8> {
+        let (rows, cols) = size;
+        let prompt = format!("{}: ", METADATA_FIELDS[self.field]);
+        let value = &self.values[self.field];
+        // keep the end of the value (where the cursor is) visible
+        let max_len = (cols as usize).saturating_sub(prompt.len() + 1);
+        let skip = value.chars().count().saturating_sub(max_len);
+        let value: String = value.chars().skip(skip).collect();
+        format!(
+            "\x1b[{};1H\x1b[m\x1b[2K\x1b[7m{}\x1b[m{}",
+            rows, prompt, value
+        )
+        .into_bytes()
+    }
+
+    fn into_metadata(self) -> Metadata {
+        let mut values = self.values.into_iter();
+        Metadata {
+            title: values.next().unwrap(),
+            description: values.next().unwrap(),
+            tags: values
+                .next()
+                .unwrap()
+                .split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(std::string::ToString::to_string)
+                .collect(),
+        }
+    }
+}
+
 struct StreamSession<
     S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static,
 > {
     client: crate::client::Client<S>,
     connected: bool,
 
-    process:
-        tokio_pty_process_stream::ResizingProcess<crate::async_stdin::Stdin>,
+    process: tokio_pty_process_stream::ResizingProcess<
+        crate::escape::Input<EscapeFilter>,
+    >,
     raw_screen: Option<crossterm::screen::RawScreen>,
     done: bool,
+    commands: tokio::sync::mpsc::UnboundedReceiver<EscapeCommand>,
 
     term: vt100::Parser,
     last_screen: vt100::Screen,
     needs_screen_update: bool,
+    redactor: crate::redact::Redactor,
+    paused: bool,
+    metadata: Metadata,
+    editor: Option<MetadataEditor>,
+    capturing: std::sync::Arc<std::sync::atomic::AtomicBool>,
 
     stdout: tokio::io::Stdout,
     to_print: std::collections::VecDeque<u8>,
@@ -140,6 +383,7 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
         args: &[String],
         connect: crate::client::Connector<S>,
         auth: &crate::protocol::Auth,
+        config: &crate::config::Stream,
     ) -> Self {
         let term_type =
             std::env::var("TERM").unwrap_or_else(|_| "".to_string());
@@ -150,10 +394,15 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
             crate::protocol::AuthClient::Cli,
         );
 
-        // TODO: tokio::io::stdin is broken (it's blocking)
-        // see https://github.com/tokio-rs/tokio/issues/589
-        // let input = tokio::io::stdin();
-        let input = crate::async_stdin::Stdin::new();
+        let (commands_tx, commands_rx) =
+            tokio::sync::mpsc::unbounded_channel();
+        let capturing =
+            std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let input = crate::escape::Input::new(EscapeFilter::new(
+            config.escape_key,
+            capturing.clone(),
+            commands_tx,
+        ));
 
         let process = tokio_pty_process_stream::ResizingProcess::new(
             tokio_pty_process_stream::Process::new(cmd, args, input),
@@ -169,10 +418,20 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
             process,
             raw_screen: None,
             done: false,
+            commands: commands_rx,
 
             term,
             last_screen: screen,
             needs_screen_update: false,
+            redactor: crate::redact::Redactor::new(&config.redact),
+            paused: false,
+            metadata: Metadata {
+                title: config.title.clone(),
+                description: config.description.clone(),
+                tags: config.tags.clone(),
+            },
+            editor: None,
+            capturing,
 
             stdout: tokio::io::stdout(),
             to_print: std::collections::VecDeque::new(),
@@ -184,6 +443,26 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
         self.to_print.extend(buf);
         self.term.process(buf);
         self.needs_screen_update = true;
+        // the command doesn't know about the editor, so it may have drawn
+        // over it
+        self.draw_editor();
+    }
+
+    fn draw_editor(&mut self) {
+        if let Some(editor) = &self.editor {
+            self.to_print
+                .extend(editor.prompt(self.term.screen().size()));
+        }
+    }
+
+    fn send_metadata(&mut self) {
+        if self.connected {
+            self.client.send_message(crate::protocol::Message::metadata(
+                &self.metadata.title,
+                &self.metadata.description,
+                &self.metadata.tags,
+            ));
+        }
     }
 }
 
@@ -199,6 +478,7 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
             Error,
         >] = &[
         &Self::poll_read_client,
+        &Self::poll_read_commands,
         &Self::poll_read_process,
         &Self::poll_write_terminal,
         &Self::poll_flush_terminal,
@@ -221,6 +501,12 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
                             &self.last_screen.contents_formatted(),
                         ),
                     );
+                    if self.paused {
+                        self.client.send_message(
+                            crate::protocol::Message::paused(true),
+                        );
+                    }
+                    self.send_metadata();
                     Ok(component_future::Async::DidWork)
                 }
                 crate::client::Event::ServerMessage(..) => {
@@ -245,6 +531,62 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
         }
     }
 
+    fn poll_read_commands(&mut self) -> component_future::Poll<(), Error> {
+        match component_future::try_ready!(self
+            .commands
+            .poll()
+            .context(crate::error::ReadChannel))
+        {
+            Some(EscapeCommand::TogglePause) => {
+                self.paused = !self.paused;
+                if self.connected {
+                    self.client.send_message(
+                        crate::protocol::Message::paused(self.paused),
+                    );
+                }
+                if !self.paused {
+                    // the next diff is taken against the last screen sent
+                    // before pausing, so this catches watchers up with
+                    // everything that happened in the meantime
+                    self.needs_screen_update = true;
+                }
+                Ok(component_future::Async::DidWork)
+            }
+            Some(EscapeCommand::EditMetadata) => {
+                self.editor = Some(MetadataEditor::new(&self.metadata));
+                self.draw_editor();
+                Ok(component_future::Async::DidWork)
+            }
+            Some(EscapeCommand::EditorInput(input)) => {
+                let res = if let Some(editor) = &mut self.editor {
+                    editor.process(&input)
+                } else {
+                    self.capturing
+                        .store(false, std::sync::atomic::Ordering::SeqCst);
+                    return Ok(component_future::Async::DidWork);
+                };
+                if let Some(save) = res {
+                    let editor = self.editor.take().unwrap();
+                    if save {
+                        self.metadata = editor.into_metadata();
+                        self.send_metadata();
+                    }
+                    self.capturing
+                        .store(false, std::sync::atomic::Ordering::SeqCst);
+                    // restore whatever the editor was covering up
+                    self.to_print
+                        .extend(self.term.screen().contents_formatted());
+                } else {
+                    self.draw_editor();
+                }
+                Ok(component_future::Async::DidWork)
+            }
+            // the input is owned by the process, so this happens once the
+            // process has exited
+            None => Ok(component_future::Async::NothingToDo),
+        }
+    }
+
     fn poll_read_process(&mut self) -> component_future::Poll<(), Error> {
         match component_future::try_ready!(self
             .process
@@ -274,6 +616,7 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
                 self.client.send_message(crate::protocol::Message::resize(
                     crate::term::Size { rows, cols },
                 ));
+                self.draw_editor();
             }
             None => {
                 if !self.done {
@@ -318,7 +661,7 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
     }
 
     fn poll_write_server(&mut self) -> component_future::Poll<(), Error> {
-        if !self.connected || !self.needs_screen_update {
+        if !self.connected || self.paused || !self.needs_screen_update {
             // ship all data to the server before actually ending
             if self.done {
                 return Ok(component_future::Async::Ready(()));
@@ -327,7 +670,7 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
             }
         }
 
-        let screen = self.term.screen().clone();
+        let screen = self.redactor.redact(self.term.screen());
         self.client
             .send_message(crate::protocol::Message::terminal_output(
                 &screen.contents_diff(&self.last_screen),
@@ -350,3 +693,85 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
         component_future::poll_future(self, Self::POLL_FNS)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn filter(
+        filter: &mut EscapeFilter,
+        input: &[u8],
+    ) -> (Vec<u8>, Vec<EscapeCommand>) {
+        let mut output = std::collections::VecDeque::new();
+        let mut commands = vec![];
+        filter.filter(input, &mut output, &mut commands);
+        (output.into_iter().collect(), commands)
+    }
+
+    #[test]
+    fn test_escape_filter() {
+        let capturing =
+            std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let (commands_tx, _commands_rx) =
+            tokio::sync::mpsc::unbounded_channel();
+        let mut f =
+            EscapeFilter::new(b'\x1d', capturing.clone(), commands_tx);
+        assert_eq!(filter(&mut f, b"abc"), (b"abc".to_vec(), vec![]));
+        assert_eq!(
+            filter(&mut f, b"a\x1dpb"),
+            (b"ab".to_vec(), vec![EscapeCommand::TogglePause])
+        );
+        assert_eq!(
+            filter(&mut f, b"a\x1d\x1db"),
+            (b"a\x1db".to_vec(), vec![])
+        );
+        assert_eq!(filter(&mut f, b"a\x1dzb"), (b"ab".to_vec(), vec![]));
+        // unknown commands don't reach the running command at all
+        assert_eq!(filter(&mut f, b"a\x1dmb"), (b"ab".to_vec(), vec![]));
+        assert_eq!(filter(&mut f, b"\x1d"), (b"".to_vec(), vec![]));
+        assert_eq!(filter(&mut f, b"m"), (b"".to_vec(), vec![]));
+        assert_eq!(filter(&mut f, b"a\x1d"), (b"a".to_vec(), vec![]));
+        assert_eq!(
+            filter(&mut f, b"pb"),
+            (b"b".to_vec(), vec![EscapeCommand::TogglePause])
+        );
+        assert_eq!(
+            filter(&mut f, b"a\x1dtbc"),
+            (
+                b"a".to_vec(),
+                vec![
+                    EscapeCommand::EditMetadata,
+                    EscapeCommand::EditorInput(b"bc".to_vec())
+                ]
+            )
+        );
+        assert_eq!(
+            filter(&mut f, b"d\x1dp"),
+            (
+                b"".to_vec(),
+                vec![EscapeCommand::EditorInput(b"d\x1dp".to_vec())]
+            )
+        );
+        capturing.store(false, std::sync::atomic::Ordering::SeqCst);
+        assert_eq!(filter(&mut f, b"e"), (b"e".to_vec(), vec![]));
+    }
+
+    #[test]
+    fn test_metadata_editor() {
+        let mut editor = MetadataEditor::new(&Metadata {
+            title: "old".to_string(),
+            description: "".to_string(),
+            tags: vec!["a".to_string(), "b".to_string()],
+        });
+        assert_eq!(editor.process(b"\x15new title\r"), None);
+        assert_eq!(editor.process(b"some descriptionn\x7f"), None);
+        assert_eq!(editor.process(b"\r, c ,\r"), Some(true));
+        let metadata = editor.into_metadata();
+        assert_eq!(metadata.title, "new title");
+        assert_eq!(metadata.description, "some description");
+        assert_eq!(metadata.tags, vec!["a", "b", "c"]);
+
+        let mut editor = MetadataEditor::new(&Metadata::default());
+        assert_eq!(editor.process(b"abc\x1b"), Some(false));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::prelude::*;
+
+const LIST_OPTION: &str = "list";
+const REVOKE_OPTION: &str = "revoke";
+const TOKENS_FILE_OPTION: &str = "tokens-file";
+const USERNAME_OPTION: &str = "username";
+
+#[derive(serde::Deserialize, Debug, Default)]
+pub struct Config {
+    #[serde(default)]
+    server: crate::config::Server,
+
+    #[serde(skip)]
+    username: Option<String>,
+
+    #[serde(skip)]
+    revoke: Option<String>,
+
+    #[serde(skip)]
+    list: bool,
+}
+
+impl Config {
+    fn tokens(&self) -> Result<crate::auth::token::Tokens> {
+        let filename = self
+            .server
+            .tokens_file
+            .as_ref()
+            .context(crate::error::MissingTokensFile)?;
+        Ok(crate::auth::token::Tokens::new(filename))
+    }
+
+    fn update(&self) -> Result<()> {
+        let tokens = self.tokens()?;
+        if self.list {
+            for token in tokens.list()? {
+                println!("{} {}", token.id, token.username);
+            }
+        } else if let Some(id) = &self.revoke {
+            if tokens.revoke(id)? {
+                println!("Revoked token {}", id);
+            } else {
+                println!("Token {} not found", id);
+            }
+        } else {
+            let username = self.username.as_ref().unwrap();
+            let (id, token) = tokens.create(username)?;
+            eprintln!("Created token {} for user {}", id, username);
+            eprintln!("This token will not be shown again.");
+            println!("{}", token);
+        }
+        Ok(())
+    }
+}
+
+impl crate::config::Config for Config {
+    fn merge_args<'a>(
+        &mut self,
+        matches: &clap::ArgMatches<'a>,
+    ) -> Result<()> {
+        if matches.is_present(TOKENS_FILE_OPTION) {
+            self.server.tokens_file = matches
+                .value_of(TOKENS_FILE_OPTION)
+                .map(std::string::ToString::to_string);
+        }
+        self.username = matches
+            .value_of(USERNAME_OPTION)
+            .map(std::string::ToString::to_string);
+        self.revoke = matches
+            .value_of(REVOKE_OPTION)
+            .map(std::string::ToString::to_string);
+        self.list = matches.is_present(LIST_OPTION);
+        Ok(())
+    }
+
+    fn run(
+        &self,
+    ) -> Box<dyn futures::Future<Item = (), Error = Error> + Send> {
+        Box::new(futures::future::result(self.update()))
+    }
+}
+
+pub fn cmd<'a, 'b>(app: clap::App<'a, 'b>) -> clap::App<'a, 'b> {
+    let tokens_file_help = "File containing the token hashes to update (defaults to the tokens_file option in the [server] section of the config file)";
+    let list_help = "List the ids and users of existing tokens";
+    let revoke_help = "Revoke the token with id ID";
+    let username_help =
+        "User to create a new token for. The token is printed to stdout.";
+
+    app.about("Manage api tokens for the 'token' authentication method")
+        .arg(
+            clap::Arg::with_name(TOKENS_FILE_OPTION)
+                .long(TOKENS_FILE_OPTION)
+                .takes_value(true)
+                .value_name("FILE")
+                .help(tokens_file_help),
+        )
+        .arg(
+            clap::Arg::with_name(LIST_OPTION)
+                .long(LIST_OPTION)
+                .help(list_help),
+        )
+        .arg(
+            clap::Arg::with_name(REVOKE_OPTION)
+                .long(REVOKE_OPTION)
+                .takes_value(true)
+                .value_name("ID")
+                .conflicts_with(LIST_OPTION)
+                .help(revoke_help),
+        )
+        .arg(
+            clap::Arg::with_name(USERNAME_OPTION)
+                .value_name("USERNAME")
+                .required_unless_one(&[LIST_OPTION, REVOKE_OPTION])
+                .conflicts_with_all(&[LIST_OPTION, REVOKE_OPTION])
+                .help(username_help),
+        )
+}
+
+pub fn config(
+    config: Option<config::Config>,
+) -> Result<Box<dyn crate::config::Config>> {
+    let config: Config = if let Some(config) = config {
+        config
+            .try_into()
+            .context(crate::error::CouldntParseConfig)?
+    } else {
+        Config::default()
+    };
+    Ok(Box::new(config))
+}
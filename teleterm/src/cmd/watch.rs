@@ -34,19 +34,78 @@ impl crate::config::Config for Config {
                 }
             }
             crate::protocol::AuthType::RecurseCenter => {
-                let id = crate::client::load_client_auth_id(self.client.auth);
+                let profile = self
+                    .client
+                    .profile
+                    .as_ref()
+                    .map(std::string::String::as_str);
+                let id = match crate::client::load_client_auth_id(
+                    self.client.auth,
+                    profile,
+                ) {
+                    Ok(id) => id,
+                    Err(e) => return Box::new(futures::future::err(e)),
+                };
                 crate::protocol::Auth::recurse_center(
                     id.as_ref().map(std::string::String::as_str),
+                    profile,
                 )
             }
+            crate::protocol::AuthType::Password => {
+                let username = self
+                    .client
+                    .username
+                    .clone()
+                    .context(crate::error::CouldntFindUsername);
+                let password = username.and_then(|username| {
+                    let password = crate::auth::password::prompt(&format!(
+                        "Password for {}: ",
+                        username
+                    ))?;
+                    Ok((username, password))
+                });
+                match password {
+                    Ok((username, password)) => {
+                        crate::protocol::Auth::password(&username, &password)
+                    }
+                    Err(e) => return Box::new(futures::future::err(e)),
+                }
+            }
+            crate::protocol::AuthType::SshKey => {
+                let username = self
+                    .client
+                    .username
+                    .clone()
+                    .context(crate::error::CouldntFindUsername);
+                match username {
+                    Ok(username) => crate::protocol::Auth::ssh_key(
+                        &username,
+                        self.client
+                            .ssh_key_file
+                            .as_ref()
+                            .map(std::string::String::as_str),
+                        self.client.host(),
+                    ),
+                    Err(e) => return Box::new(futures::future::err(e)),
+                }
+            }
+            crate::protocol::AuthType::Token => {
+                match crate::client::load_api_token(
+                    self.client
+                        .token_file
+                        .as_ref()
+                        .map(std::string::String::as_str),
+                ) {
+                    Ok(token) => crate::protocol::Auth::token(&token),
+                    Err(e) => return Box::new(futures::future::err(e)),
+                }
+            }
         };
 
         let host = self.client.host().to_string();
         let address = *self.client.addr();
         if self.client.tls {
-            let connector = match native_tls::TlsConnector::new()
-                .context(crate::error::CreateConnector)
-            {
+            let connector = match self.client.tls_connector() {
                 Ok(connector) => connector,
                 Err(e) => return Box::new(futures::future::err(e)),
             };
@@ -55,22 +114,7 @@ impl crate::config::Config for Config {
             > = Box::new(move || {
                 let host = host.clone();
                 let connector = connector.clone();
-                Box::new(move || {
-                    let host = host.clone();
-                    let connector = connector.clone();
-                    let connector = tokio_tls::TlsConnector::from(connector);
-                    let stream =
-                        tokio::net::tcp::TcpStream::connect(&address);
-                    Box::new(
-                        stream
-                            .context(crate::error::Connect { address })
-                            .and_then(move |stream| {
-                                connector.connect(&host, stream).context(
-                                    crate::error::ConnectTls { host },
-                                )
-                            }),
-                    )
-                })
+                Box::new(move || connector.connect(&host, address))
             });
             Box::new(WatchSession::new(make_connector, &auth))
         } else {
@@ -200,6 +244,9 @@ struct WatchSession<
     state: State<S>,
     raw_screen: Option<crossterm::screen::RawScreen>,
     needs_redraw: bool,
+
+    filter: String,
+    filter_input: Option<String>,
 }
 
 impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
@@ -233,6 +280,9 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
             state: State::new(),
             raw_screen: None,
             needs_redraw: true,
+
+            filter: "".to_string(),
+            filter_input: None,
         }
     }
 
@@ -269,6 +319,13 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
     ) -> Result<()> {
         match msg {
             crate::protocol::Message::Sessions { sessions } => {
+                let filter = &self.filter;
+                let sessions = sessions
+                    .into_iter()
+                    .filter(|session| {
+                        crate::session_list::matches_filter(session, filter)
+                    })
+                    .collect();
                 self.state.choosing(
                     crate::session_list::SessionList::new(
                         sessions,
@@ -292,10 +349,48 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
         Ok(())
     }
 
+    fn filter_keypress(
+        &mut self,
+        e: &crossterm::input::InputEvent,
+    ) -> Result<bool> {
+        let input = self.filter_input.as_mut().unwrap();
+        match e {
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::Esc,
+            ) => {
+                self.filter_input = None;
+            }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::Enter,
+            ) => {
+                self.filter = self.filter_input.take().unwrap();
+                self.list_client
+                    .send_message(crate::protocol::Message::list_sessions());
+            }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::Backspace,
+            ) => {
+                input.pop();
+            }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::Char(c),
+            ) => {
+                input.push(*c);
+            }
+            _ => {}
+        }
+        self.needs_redraw = true;
+        Ok(false)
+    }
+
     fn list_keypress(
         &mut self,
         e: &crossterm::input::InputEvent,
     ) -> Result<bool> {
+        if self.filter_input.is_some() {
+            return self.filter_keypress(e);
+        }
+
         let sessions =
             if let State::Choosing { sessions, .. } = &mut self.state {
                 sessions
@@ -327,6 +422,12 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
                 sessions.next_page();
                 self.needs_redraw = true;
             }
+            crossterm::input::InputEvent::Keyboard(
+                crossterm::input::KeyEvent::Char('/'),
+            ) => {
+                self.filter_input = Some(self.filter.clone());
+                self.needs_redraw = true;
+            }
             crossterm::input::InputEvent::Keyboard(
                 crossterm::input::KeyEvent::Char(c),
             ) => {
@@ -535,7 +636,8 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
                 format!("\x1b[31m{}\x1b[m", display_size_plain)
             };
             let display_idle = format_time(session.idle_time);
-            let display_title = truncate(&session.title, max_title_width);
+            let display_title =
+                truncate(&format_title(session), max_title_width);
             let display_watch = session.watchers;
 
             print!(
@@ -556,11 +658,18 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
 
             prev_name = Some(&session.username);
         }
-        print!(
-            "({}/{}) space: refresh, q: quit, <: prev page, >: next page --> ",
-            sessions.current_page(),
-            sessions.total_pages(),
-        );
+        if let Some(input) = &self.filter_input {
+            print!("filter (enter: apply, esc: cancel): {}", input);
+        } else {
+            if !self.filter.is_empty() {
+                print!("(filter: {}) ", self.filter);
+            }
+            print!(
+                "({}/{}) space: refresh, q: quit, <: prev page, >: next page, /: filter --> ",
+                sessions.current_page(),
+                sessions.total_pages(),
+            );
+        }
         std::io::stdout()
             .flush()
             .context(crate::error::FlushTerminal)?;
@@ -706,6 +815,24 @@ fn format_time(dur: u32) -> String {
     format!("{}d{:02}h{:02}m{:02}s", days, hours, mins, secs)
 }
 
+fn format_title(session: &crate::protocol::Session) -> String {
+    let mut title =
+        match (session.title.is_empty(), session.description.is_empty()) {
+            (_, true) => session.title.clone(),
+            (true, false) => session.description.clone(),
+            (false, false) => {
+                format!("{} - {}", session.title, session.description)
+            }
+        };
+    if session.paused {
+        title = format!("(paused) {}", title);
+    }
+    if !session.tags.is_empty() {
+        title = format!("{} [{}]", title, session.tags.join(", "));
+    }
+    title
+}
+
 fn truncate(s: &str, len: usize) -> String {
     if s.len() <= len {
         s.to_string()
@@ -749,6 +876,39 @@ mod test {
         assert_eq!(truncate("abcdefghij", 7), "abcd...");
     }
 
+    #[test]
+    fn test_format_title() {
+        let mut session = crate::protocol::Session {
+            id: "id".to_string(),
+            username: "doy".to_string(),
+            term_type: "screen".to_string(),
+            size: crate::term::Size { rows: 24, cols: 80 },
+            idle_time: 0,
+            title: "vim".to_string(),
+            watchers: 0,
+            paused: false,
+            description: "".to_string(),
+            tags: vec![],
+        };
+        assert_eq!(format_title(&session), "vim");
+
+        session.description = "hacking on teleterm".to_string();
+        assert_eq!(format_title(&session), "vim - hacking on teleterm");
+
+        session.tags = vec!["rust".to_string(), "tokio".to_string()];
+        assert_eq!(
+            format_title(&session),
+            "vim - hacking on teleterm [rust, tokio]"
+        );
+
+        session.paused = true;
+        session.title = "".to_string();
+        assert_eq!(
+            format_title(&session),
+            "(paused) hacking on teleterm [rust, tokio]"
+        );
+    }
+
     #[test]
     fn test_format_time() {
         assert_eq!(format_time(0), "0s");
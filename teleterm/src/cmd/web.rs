@@ -30,20 +30,62 @@ impl crate::config::Config for Config {
     fn run(
         &self,
     ) -> Box<dyn futures::Future<Item = (), Error = Error> + Send> {
-        Box::new(crate::web::Server::new(
+        let server_tls = match self.web.server_tls_connector() {
+            Ok(connector) => connector,
+            Err(e) => return Box::new(futures::future::err(e)),
+        };
+        let tls_acceptor = match self.web.tls_identity() {
+            Ok(Some(identity)) => match identity.acceptor() {
+                Ok(acceptor) => Some(acceptor),
+                Err(e) => return Box::new(futures::future::err(e)),
+            },
+            Ok(None) => None,
+            Err(e) => return Box::new(futures::future::err(e)),
+        };
+        let password_verifier = self.web.password_verifier();
+        let mut allowed_login_methods =
+            self.web.allowed_login_methods.clone();
+        if password_verifier.is_none()
+            && allowed_login_methods.remove(&crate::protocol::AuthType::Plain)
+        {
+            log::warn!(
+                "no password_file configured, disabling plain logins for the web interface"
+            );
+        }
+        // browsers have no way to sign ssh key challenges
+        allowed_login_methods.remove(&crate::protocol::AuthType::SshKey);
+        // api tokens are meant for non-interactive clients like ci jobs
+        allowed_login_methods.remove(&crate::protocol::AuthType::Token);
+        let scheme = if tls_acceptor.is_some()
+            || !self.web.trusted_proxies.is_empty()
+        {
+            "https"
+        } else {
+            "http"
+        };
+        let server = crate::web::Server::new(
             self.web.listen_address,
             self.web.public_address.clone(),
+            tls_acceptor,
+            self.web.trusted_proxies.clone(),
+            self.web.cookie_same_site,
+            self.web.session_backend,
+            self.web.session_ttl,
+            self.web.session_gc_interval,
+            &self.web.session_sqlite_file,
+            password_verifier,
             self.web.server_address.clone(),
-            self.web.allowed_login_methods.clone(),
+            server_tls,
+            allowed_login_methods,
             self.oauth_configs
                 .iter()
                 .filter_map(|(ty, configs)| {
                     configs.get(&crate::protocol::AuthClient::Web).map(
                         |config| {
                             let mut config = config.clone();
-                            // TODO: tls
                             let url = url::Url::parse(&format!(
-                                "http://{}/oauth/{}",
+                                "{}://{}/oauth/{}",
+                                scheme,
                                 self.web.public_address,
                                 ty.name()
                             ))
@@ -54,7 +96,11 @@ impl crate::config::Config for Config {
                     )
                 })
                 .collect(),
-        ))
+        );
+        match server {
+            Ok(server) => Box::new(server),
+            Err(e) => Box::new(futures::future::err(e)),
+        }
     }
 }
 
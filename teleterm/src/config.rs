@@ -8,30 +8,78 @@ pub mod wizard;
 const CONFIG_FILENAME: &str = "config.toml";
 
 const ALLOWED_LOGIN_METHODS_OPTION: &str = "allowed-login-methods";
+const ALLOW_UNENCRYPTED_PASSWORDS_OPTION: &str =
+    "allow-unencrypted-passwords";
+const APPEND_OPTION: &str = "append";
 const ARGS_OPTION: &str = "args";
+const AUTHORIZED_KEYS_FILE_OPTION: &str = "authorized-keys-file";
 const COMMAND_OPTION: &str = "command";
 const CONNECT_ADDRESS_OPTION: &str = "connect-address";
+const COOKIE_SAME_SITE_OPTION: &str = "cookie-same-site";
+const CREDENTIALS_FILE_OPTION: &str = "credentials-file";
+const DEFAULT_ROLE_OPTION: &str = "default-role";
+const DESCRIPTION_OPTION: &str = "description";
+const ESCAPE_KEY_OPTION: &str = "escape-key";
 const FILENAME_OPTION: &str = "filename";
+const FORMAT_OPTION: &str = "format";
+const IDLE_PLAYBACK_RATIO_OPTION: &str = "idle-playback-ratio";
+const IDLE_THRESHOLD_OPTION: &str = "idle-threshold";
 const LISTEN_ADDRESS_OPTION: &str = "listen-address";
+const LOGIN_PASSWORD_OPTION: &str = "login-password";
 const LOGIN_PLAIN_OPTION: &str = "login-plain";
 const LOGIN_RECURSE_CENTER_OPTION: &str = "login-recurse-center";
+const LOGIN_SSH_KEY_OPTION: &str = "login-ssh-key";
+const LOGIN_TOKEN_OPTION: &str = "login-token";
 const MAX_FRAME_LENGTH_OPTION: &str = "max-frame-length";
+const PASSWORD_FILE_OPTION: &str = "password-file";
 const PLAY_AT_START_OPTION: &str = "play-at-start";
 const PLAYBACK_RATIO_OPTION: &str = "playback-ratio";
+const PROFILE_OPTION: &str = "profile";
 const PUBLIC_ADDRESS_OPTION: &str = "public-address";
 const READ_TIMEOUT_OPTION: &str = "read-timeout-secs";
+const RECORD_INPUT_OPTION: &str = "record-input";
+const REDACT_OPTION: &str = "redact";
+const ROTATE_DURATION_OPTION: &str = "rotate-duration";
+const ROTATE_SIZE_OPTION: &str = "rotate-size";
 const SERVER_ADDRESS_OPTION: &str = "server-address";
+const SERVER_NAME_OPTION: &str = "server-name";
+const SESSION_BACKEND_OPTION: &str = "session-backend";
+const SESSION_TTL_OPTION: &str = "session-ttl-secs";
+const SKIP_IDLE_OPTION: &str = "skip-idle";
+const SSH_KEY_FILE_OPTION: &str = "ssh-key-file";
+const TAG_OPTION: &str = "tag";
+const TITLE_OPTION: &str = "title";
+const TLS_CA_FILE_OPTION: &str = "tls-ca-file";
+const TLS_CERT_FILE_OPTION: &str = "tls-cert-file";
 const TLS_IDENTITY_FILE_OPTION: &str = "tls-identity-file";
+const TLS_KEY_FILE_OPTION: &str = "tls-key-file";
 const TLS_OPTION: &str = "tls";
+const TLS_PIN_CERT_OPTION: &str = "tls-pin-cert";
+const TLS_TRUST_ON_FIRST_USE_OPTION: &str = "tls-trust-on-first-use";
+const TOKEN_FILE_OPTION: &str = "token-file";
+const TOKENS_FILE_OPTION: &str = "tokens-file";
+const TRUSTED_PROXY_OPTION: &str = "trusted-proxy";
 
 const DEFAULT_LISTEN_ADDRESS: &str = "127.0.0.1:4144";
 const DEFAULT_CONNECT_ADDRESS: &str = "127.0.0.1:4144";
 const DEFAULT_WEB_LISTEN_ADDRESS: &str = "127.0.0.1:4145";
 const DEFAULT_READ_TIMEOUT: std::time::Duration =
     std::time::Duration::from_secs(120);
+const DEFAULT_TLS_RELOAD_INTERVAL: std::time::Duration =
+    std::time::Duration::from_secs(60);
 const DEFAULT_AUTH_TYPE: crate::protocol::AuthType =
     crate::protocol::AuthType::Plain;
 const DEFAULT_TLS: bool = false;
+const DEFAULT_COOKIE_SAME_SITE: SameSite = SameSite::Lax;
+const DEFAULT_SESSION_BACKEND: SessionBackend = SessionBackend::Disk;
+const DEFAULT_ROLE: Role = Role::Admin;
+const DEFAULT_SESSION_TTL: std::time::Duration =
+    std::time::Duration::from_secs(30 * 24 * 60 * 60);
+const DEFAULT_SESSION_GC_INTERVAL: std::time::Duration =
+    std::time::Duration::from_secs(60 * 60);
+const DEFAULT_SESSION_SQLITE_FILENAME: &str = "web-sessions.sqlite";
+const DEFAULT_TOKEN_KEY_FILENAME: &str = "oauth-token-key";
+const DEFAULT_ESCAPE_KEY: &str = "^]";
 const DEFAULT_TTYREC_FILENAME: &str = "teleterm.ttyrec";
 
 pub trait Config: std::fmt::Debug {
@@ -80,6 +128,13 @@ pub struct Client {
     #[serde(default = "default_username")]
     pub username: Option<String>,
 
+    pub ssh_key_file: Option<String>,
+
+    pub token_file: Option<String>,
+
+    #[serde(deserialize_with = "profile", default)]
+    pub profile: Option<String>,
+
     #[serde(
         deserialize_with = "connect_address",
         default = "default_connect_address"
@@ -88,6 +143,14 @@ pub struct Client {
 
     #[serde(default = "default_tls")]
     pub tls: bool,
+
+    pub tls_ca_file: Option<String>,
+
+    #[serde(default)]
+    pub tls_pinned_certs: Vec<String>,
+
+    #[serde(default)]
+    pub tls_trust_on_first_use: bool,
 }
 
 impl Client {
@@ -99,13 +162,30 @@ impl Client {
         &self.connect_address.1
     }
 
+    pub fn tls_connector(&self) -> Result<crate::client::tls::Connector> {
+        crate::client::tls::Connector::new(
+            self.tls_ca_file.as_ref().map(std::string::String::as_str),
+            &self.tls_pinned_certs,
+            self.tls_trust_on_first_use,
+        )
+    }
+
     pub fn cmd<'a, 'b>(app: clap::App<'a, 'b>) -> clap::App<'a, 'b> {
         let login_plain_help = "Use the 'plain' authentication method (default), with username USERNAME (defaults to $USER)";
         let login_recurse_center_help =
             "Use the 'recurse_center' authentication method";
+        let login_password_help = "Use the 'password' authentication method, with username USERNAME (defaults to $USER). The password will be prompted for.";
+        let login_ssh_key_help = "Use the 'ssh_key' authentication method, with username USERNAME (defaults to $USER). The login challenge is signed with a key from ssh-agent, or from --ssh-key-file.";
+        let login_token_help = "Use the 'token' authentication method, with the api token read from --token-file, or from the TELETERM_TOKEN environment variable";
+        let token_file_help = "File containing the api token to use with the 'token' authentication method, as created by `tt token`";
+        let ssh_key_file_help = "Private key to sign ssh_key login challenges with, if the matching key isn't available from ssh-agent. Only ed25519 and rsa keys are supported, and the key must not be encrypted.";
+        let profile_help = "Name to store oauth login state under, so that logins to different servers don't overwrite each other";
         let connect_address_help =
             "Host and port to connect to (defaults to localhost:4144)";
         let tls_help = "Connect to the server using TLS";
+        let tls_ca_file_help = "File containing additional CA certificates (in PEM format) to trust when verifying the server's certificate. Implies --tls.";
+        let tls_pin_cert_help = "Only accept a server certificate with this SHA-256 fingerprint, instead of verifying the certificate chain. Can be given multiple times. Implies --tls.";
+        let tls_trust_on_first_use_help = "Trust the server's certificate the first time it is seen, and require it to stay the same on later connections, instead of verifying the certificate chain. Implies --tls.";
 
         app.arg(
             clap::Arg::with_name(LOGIN_PLAIN_OPTION)
@@ -120,6 +200,65 @@ impl Client {
                 .conflicts_with(LOGIN_PLAIN_OPTION)
                 .help(login_recurse_center_help),
         )
+        .arg(
+            clap::Arg::with_name(LOGIN_PASSWORD_OPTION)
+                .long(LOGIN_PASSWORD_OPTION)
+                .takes_value(true)
+                .min_values(0)
+                .max_values(1)
+                .value_name("USERNAME")
+                .conflicts_with_all(&[
+                    LOGIN_PLAIN_OPTION,
+                    LOGIN_RECURSE_CENTER_OPTION,
+                ])
+                .help(login_password_help),
+        )
+        .arg(
+            clap::Arg::with_name(LOGIN_SSH_KEY_OPTION)
+                .long(LOGIN_SSH_KEY_OPTION)
+                .takes_value(true)
+                .min_values(0)
+                .max_values(1)
+                .value_name("USERNAME")
+                .conflicts_with_all(&[
+                    LOGIN_PLAIN_OPTION,
+                    LOGIN_RECURSE_CENTER_OPTION,
+                    LOGIN_PASSWORD_OPTION,
+                ])
+                .help(login_ssh_key_help),
+        )
+        .arg(
+            clap::Arg::with_name(SSH_KEY_FILE_OPTION)
+                .long(SSH_KEY_FILE_OPTION)
+                .takes_value(true)
+                .value_name("FILE")
+                .help(ssh_key_file_help),
+        )
+        .arg(
+            clap::Arg::with_name(LOGIN_TOKEN_OPTION)
+                .long(LOGIN_TOKEN_OPTION)
+                .conflicts_with_all(&[
+                    LOGIN_PLAIN_OPTION,
+                    LOGIN_RECURSE_CENTER_OPTION,
+                    LOGIN_PASSWORD_OPTION,
+                    LOGIN_SSH_KEY_OPTION,
+                ])
+                .help(login_token_help),
+        )
+        .arg(
+            clap::Arg::with_name(TOKEN_FILE_OPTION)
+                .long(TOKEN_FILE_OPTION)
+                .takes_value(true)
+                .value_name("FILE")
+                .help(token_file_help),
+        )
+        .arg(
+            clap::Arg::with_name(PROFILE_OPTION)
+                .long(PROFILE_OPTION)
+                .takes_value(true)
+                .value_name("NAME")
+                .help(profile_help),
+        )
         .arg(
             clap::Arg::with_name(CONNECT_ADDRESS_OPTION)
                 .long(CONNECT_ADDRESS_OPTION)
@@ -132,6 +271,28 @@ impl Client {
                 .long(TLS_OPTION)
                 .help(tls_help),
         )
+        .arg(
+            clap::Arg::with_name(TLS_CA_FILE_OPTION)
+                .long(TLS_CA_FILE_OPTION)
+                .takes_value(true)
+                .value_name("FILE")
+                .help(tls_ca_file_help),
+        )
+        .arg(
+            clap::Arg::with_name(TLS_PIN_CERT_OPTION)
+                .long(TLS_PIN_CERT_OPTION)
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("SHA256")
+                .help(tls_pin_cert_help),
+        )
+        .arg(
+            clap::Arg::with_name(TLS_TRUST_ON_FIRST_USE_OPTION)
+                .long(TLS_TRUST_ON_FIRST_USE_OPTION)
+                .conflicts_with(TLS_PIN_CERT_OPTION)
+                .help(tls_trust_on_first_use_help),
+        )
     }
 
     pub fn merge_args<'a>(
@@ -148,6 +309,35 @@ impl Client {
             self.auth = crate::protocol::AuthType::Plain;
             self.username = username;
         }
+        if matches.is_present(LOGIN_PASSWORD_OPTION) {
+            self.auth = crate::protocol::AuthType::Password;
+            if let Some(username) = matches.value_of(LOGIN_PASSWORD_OPTION) {
+                self.username = Some(username.to_string());
+            }
+        }
+        if matches.is_present(LOGIN_SSH_KEY_OPTION) {
+            self.auth = crate::protocol::AuthType::SshKey;
+            if let Some(username) = matches.value_of(LOGIN_SSH_KEY_OPTION) {
+                self.username = Some(username.to_string());
+            }
+        }
+        if matches.is_present(SSH_KEY_FILE_OPTION) {
+            self.ssh_key_file = matches
+                .value_of(SSH_KEY_FILE_OPTION)
+                .map(std::string::ToString::to_string);
+        }
+        if matches.is_present(LOGIN_TOKEN_OPTION) {
+            self.auth = crate::protocol::AuthType::Token;
+        }
+        if matches.is_present(TOKEN_FILE_OPTION) {
+            self.token_file = matches
+                .value_of(TOKEN_FILE_OPTION)
+                .map(std::string::ToString::to_string);
+        }
+        if matches.is_present(PROFILE_OPTION) {
+            let profile = matches.value_of(PROFILE_OPTION).unwrap();
+            self.profile = Some(to_profile(profile)?);
+        }
         if matches.is_present(CONNECT_ADDRESS_OPTION) {
             let address = matches.value_of(CONNECT_ADDRESS_OPTION).unwrap();
             self.connect_address = to_connect_address(address)?;
@@ -155,6 +345,26 @@ impl Client {
         if matches.is_present(TLS_OPTION) {
             self.tls = true;
         }
+        if matches.is_present(TLS_CA_FILE_OPTION) {
+            self.tls = true;
+            self.tls_ca_file = matches
+                .value_of(TLS_CA_FILE_OPTION)
+                .map(std::string::ToString::to_string);
+        }
+        if matches.is_present(TLS_PIN_CERT_OPTION) {
+            self.tls = true;
+            self.tls_pinned_certs = matches
+                .values_of(TLS_PIN_CERT_OPTION)
+                .unwrap()
+                .map(std::string::ToString::to_string)
+                .collect();
+            self.tls_trust_on_first_use = false;
+        }
+        if matches.is_present(TLS_TRUST_ON_FIRST_USE_OPTION) {
+            self.tls = true;
+            self.tls_pinned_certs = vec![];
+            self.tls_trust_on_first_use = true;
+        }
         Ok(())
     }
 }
@@ -164,8 +374,14 @@ impl Default for Client {
         Self {
             auth: default_auth_type(),
             username: default_username(),
+            ssh_key_file: None,
+            token_file: None,
+            profile: None,
             connect_address: default_connect_address(),
             tls: default_tls(),
+            tls_ca_file: None,
+            tls_pinned_certs: vec![],
+            tls_trust_on_first_use: false,
         }
     }
 }
@@ -200,6 +416,31 @@ where
         .map_err(serde::de::Error::custom)
 }
 
+fn profile<'a, D>(
+    deserializer: D,
+) -> std::result::Result<Option<String>, D::Error>
+where
+    D: serde::de::Deserializer<'a>,
+{
+    to_profile(&<String>::deserialize(deserializer)?)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+// profile names end up in file names
+fn to_profile(name: &str) -> Result<String> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(Error::InvalidProfile {
+            name: name.to_string(),
+        });
+    }
+    Ok(name.to_string())
+}
+
 fn default_connect_address() -> (String, std::net::SocketAddr) {
     to_connect_address(DEFAULT_CONNECT_ADDRESS).unwrap()
 }
@@ -244,6 +485,17 @@ pub struct Server {
 
     pub tls_identity_file: Option<String>,
 
+    pub tls_cert_file: Option<String>,
+
+    pub tls_key_file: Option<String>,
+
+    #[serde(
+        rename = "tls_reload_interval_secs",
+        deserialize_with = "tls_reload_interval",
+        default = "default_tls_reload_interval"
+    )]
+    pub tls_reload_interval: std::time::Duration,
+
     #[serde(
         deserialize_with = "allowed_login_methods",
         default = "default_allowed_login_methods"
@@ -251,6 +503,23 @@ pub struct Server {
     pub allowed_login_methods:
         std::collections::HashSet<crate::protocol::AuthType>,
 
+    pub credentials_file: Option<String>,
+
+    #[serde(default)]
+    pub allow_unencrypted_passwords: bool,
+
+    pub authorized_keys_file: Option<String>,
+
+    pub server_name: Option<String>,
+
+    pub tokens_file: Option<String>,
+
+    #[serde(default = "default_role")]
+    pub default_role: Role,
+
+    #[serde(default)]
+    pub roles: std::collections::HashMap<String, Role>,
+
     #[serde(deserialize_with = "uid", default)]
     pub uid: Option<users::uid_t>,
 
@@ -259,12 +528,75 @@ pub struct Server {
 }
 
 impl Server {
+    pub fn password_verifier(
+        &self,
+    ) -> Option<std::sync::Arc<dyn crate::auth::Verifier>> {
+        self.credentials_file.as_ref().map(|filename| {
+            std::sync::Arc::new(crate::auth::password::Credentials::new(
+                filename,
+            )) as std::sync::Arc<dyn crate::auth::Verifier>
+        })
+    }
+
+    pub fn authorized_keys(
+        &self,
+    ) -> Result<Option<std::sync::Arc<crate::auth::ssh_key::AuthorizedKeys>>>
+    {
+        if let Some(filename) = &self.authorized_keys_file {
+            let server_name = self
+                .server_name
+                .as_ref()
+                .context(crate::error::MissingServerName)?;
+            Ok(Some(std::sync::Arc::new(
+                crate::auth::ssh_key::AuthorizedKeys::new(
+                    filename,
+                    server_name,
+                ),
+            )))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn tokens(
+        &self,
+    ) -> Option<std::sync::Arc<crate::auth::token::Tokens>> {
+        self.tokens_file.as_ref().map(|filename| {
+            std::sync::Arc::new(crate::auth::token::Tokens::new(filename))
+        })
+    }
+
+    pub fn roles(&self) -> Roles {
+        Roles {
+            default: self.default_role,
+            assignments: self.roles.clone(),
+        }
+    }
+
+    pub fn tls_identity(
+        &self,
+    ) -> Result<Option<crate::server::tls::Identity>> {
+        tls_identity(
+            self.tls_identity_file.as_ref(),
+            self.tls_cert_file.as_ref(),
+            self.tls_key_file.as_ref(),
+        )
+    }
+
     pub fn cmd<'a, 'b>(app: clap::App<'a, 'b>) -> clap::App<'a, 'b> {
         let listen_address_help =
             "Host and port to listen on (defaults to localhost:4144)";
         let read_timeout_help = "Number of idle seconds to wait before disconnecting a client (defaults to 30)";
         let tls_identity_file_help = "File containing the TLS certificate and private key to use for accepting TLS connections. Must be in pfx format. The server will only allow connections over TLS if this option is set.";
-        let allowed_login_methods_help = "Comma separated list containing the auth methods this server should allow. Allows everything by default, valid values are plain, recurse_center";
+        let tls_cert_file_help = "File containing the TLS certificate chain to use for accepting TLS connections, in PEM format. Must be used along with --tls-key-file, and conflicts with --tls-identity-file.";
+        let tls_key_file_help = "File containing the TLS private key to use for accepting TLS connections, in PEM format. Must be used along with --tls-cert-file.";
+        let allowed_login_methods_help = "Comma separated list containing the auth methods this server should allow. Allows everything by default, valid values are plain, recurse_center, password, ssh_key, token";
+        let credentials_file_help = "File containing the Argon2 password hashes of users allowed to log in with the 'password' authentication method, as managed by `tt passwd`. Password logins are refused if this is not set.";
+        let allow_unencrypted_passwords_help = "Allow the 'password' authentication method even when the server isn't using TLS. Passwords will be sent over the network in the clear.";
+        let authorized_keys_file_help = "File containing the ssh public keys of users allowed to log in with the 'ssh_key' authentication method, in authorized_keys format. %u is replaced with the username, so this can be per user. Ssh key logins are refused if this is not set.";
+        let server_name_help = "Hostname that clients use to connect to this server. Required when using the 'ssh_key' authentication method, since clients sign the name of the server they connected to along with the login challenge.";
+        let tokens_file_help = "File containing the hashed api tokens accepted by the 'token' authentication method, as managed by `tt token`. Token logins are refused if this is not set.";
+        let default_role_help = "Role given to users who aren't assigned one in the [server.roles] section of the config file. Valid values are watcher (can list and watch streams, but not stream), streamer (can stream, but not list or watch streams), and admin (can do both). Defaults to admin.";
         app.arg(
             clap::Arg::with_name(LISTEN_ADDRESS_OPTION)
                 .long(LISTEN_ADDRESS_OPTION)
@@ -286,6 +618,23 @@ impl Server {
                 .value_name("FILE")
                 .help(tls_identity_file_help),
         )
+        .arg(
+            clap::Arg::with_name(TLS_CERT_FILE_OPTION)
+                .long(TLS_CERT_FILE_OPTION)
+                .takes_value(true)
+                .value_name("FILE")
+                .requires(TLS_KEY_FILE_OPTION)
+                .conflicts_with(TLS_IDENTITY_FILE_OPTION)
+                .help(tls_cert_file_help),
+        )
+        .arg(
+            clap::Arg::with_name(TLS_KEY_FILE_OPTION)
+                .long(TLS_KEY_FILE_OPTION)
+                .takes_value(true)
+                .value_name("FILE")
+                .requires(TLS_CERT_FILE_OPTION)
+                .help(tls_key_file_help),
+        )
         .arg(
             clap::Arg::with_name(ALLOWED_LOGIN_METHODS_OPTION)
                 .long(ALLOWED_LOGIN_METHODS_OPTION)
@@ -294,6 +643,46 @@ impl Server {
                 .value_name("AUTH_METHODS")
                 .help(allowed_login_methods_help),
         )
+        .arg(
+            clap::Arg::with_name(CREDENTIALS_FILE_OPTION)
+                .long(CREDENTIALS_FILE_OPTION)
+                .takes_value(true)
+                .value_name("FILE")
+                .help(credentials_file_help),
+        )
+        .arg(
+            clap::Arg::with_name(ALLOW_UNENCRYPTED_PASSWORDS_OPTION)
+                .long(ALLOW_UNENCRYPTED_PASSWORDS_OPTION)
+                .help(allow_unencrypted_passwords_help),
+        )
+        .arg(
+            clap::Arg::with_name(AUTHORIZED_KEYS_FILE_OPTION)
+                .long(AUTHORIZED_KEYS_FILE_OPTION)
+                .takes_value(true)
+                .value_name("FILE")
+                .help(authorized_keys_file_help),
+        )
+        .arg(
+            clap::Arg::with_name(SERVER_NAME_OPTION)
+                .long(SERVER_NAME_OPTION)
+                .takes_value(true)
+                .value_name("HOST")
+                .help(server_name_help),
+        )
+        .arg(
+            clap::Arg::with_name(TOKENS_FILE_OPTION)
+                .long(TOKENS_FILE_OPTION)
+                .takes_value(true)
+                .value_name("FILE")
+                .help(tokens_file_help),
+        )
+        .arg(
+            clap::Arg::with_name(DEFAULT_ROLE_OPTION)
+                .long(DEFAULT_ROLE_OPTION)
+                .takes_value(true)
+                .value_name("ROLE")
+                .help(default_role_help),
+        )
     }
 
     pub fn merge_args<'a>(
@@ -321,6 +710,17 @@ impl Server {
                     .unwrap()
                     .to_string(),
             );
+            self.tls_cert_file = None;
+            self.tls_key_file = None;
+        }
+        if matches.is_present(TLS_CERT_FILE_OPTION) {
+            self.tls_identity_file = None;
+            self.tls_cert_file = Some(
+                matches.value_of(TLS_CERT_FILE_OPTION).unwrap().to_string(),
+            );
+            self.tls_key_file = Some(
+                matches.value_of(TLS_KEY_FILE_OPTION).unwrap().to_string(),
+            );
         }
         if matches.is_present(ALLOWED_LOGIN_METHODS_OPTION) {
             self.allowed_login_methods = matches
@@ -331,6 +731,34 @@ impl Server {
                     std::collections::HashSet<crate::protocol::AuthType>,
                 >>()?;
         }
+        if matches.is_present(CREDENTIALS_FILE_OPTION) {
+            self.credentials_file = matches
+                .value_of(CREDENTIALS_FILE_OPTION)
+                .map(std::string::ToString::to_string);
+        }
+        if matches.is_present(ALLOW_UNENCRYPTED_PASSWORDS_OPTION) {
+            self.allow_unencrypted_passwords = true;
+        }
+        if matches.is_present(AUTHORIZED_KEYS_FILE_OPTION) {
+            self.authorized_keys_file = matches
+                .value_of(AUTHORIZED_KEYS_FILE_OPTION)
+                .map(std::string::ToString::to_string);
+        }
+        if matches.is_present(SERVER_NAME_OPTION) {
+            self.server_name = matches
+                .value_of(SERVER_NAME_OPTION)
+                .map(std::string::ToString::to_string);
+        }
+        if matches.is_present(TOKENS_FILE_OPTION) {
+            self.tokens_file = matches
+                .value_of(TOKENS_FILE_OPTION)
+                .map(std::string::ToString::to_string);
+        }
+        if matches.is_present(DEFAULT_ROLE_OPTION) {
+            self.default_role = Role::try_from(
+                matches.value_of(DEFAULT_ROLE_OPTION).unwrap(),
+            )?;
+        }
         Ok(())
     }
 }
@@ -341,7 +769,17 @@ impl Default for Server {
             listen_address: default_listen_address(),
             read_timeout: default_read_timeout(),
             tls_identity_file: None,
+            tls_cert_file: None,
+            tls_key_file: None,
+            tls_reload_interval: default_tls_reload_interval(),
             allowed_login_methods: default_allowed_login_methods(),
+            credentials_file: None,
+            allow_unencrypted_passwords: false,
+            authorized_keys_file: None,
+            server_name: None,
+            tokens_file: None,
+            default_role: default_role(),
+            roles: std::collections::HashMap::new(),
             uid: None,
             gid: None,
         }
@@ -381,6 +819,52 @@ fn default_read_timeout() -> std::time::Duration {
     DEFAULT_READ_TIMEOUT
 }
 
+fn tls_reload_interval<'a, D>(
+    deserializer: D,
+) -> std::result::Result<std::time::Duration, D::Error>
+where
+    D: serde::de::Deserializer<'a>,
+{
+    Ok(std::time::Duration::from_secs(u64::deserialize(
+        deserializer,
+    )?))
+}
+
+fn default_tls_reload_interval() -> std::time::Duration {
+    DEFAULT_TLS_RELOAD_INTERVAL
+}
+
+// a pem certificate without its key (or the other way around) is a
+// configuration mistake, and silently falling back to plaintext would hide it
+fn tls_identity(
+    identity_file: Option<&String>,
+    cert_file: Option<&String>,
+    key_file: Option<&String>,
+) -> Result<Option<crate::server::tls::Identity>> {
+    if let Some(filename) = identity_file {
+        return Ok(Some(crate::server::tls::Identity::Pkcs12 {
+            filename: filename.clone(),
+        }));
+    }
+    match (cert_file, key_file) {
+        (Some(cert_filename), Some(key_filename)) => {
+            Ok(Some(crate::server::tls::Identity::Pem {
+                cert_filename: cert_filename.clone(),
+                key_filename: key_filename.clone(),
+            }))
+        }
+        (Some(_), None) => Err(Error::IncompleteTlsIdentity {
+            set: "tls_cert_file",
+            missing: "tls_key_file",
+        }),
+        (None, Some(_)) => Err(Error::IncompleteTlsIdentity {
+            set: "tls_key_file",
+            missing: "tls_cert_file",
+        }),
+        (None, None) => Ok(None),
+    }
+}
+
 fn allowed_login_methods<'a, D>(
     deserializer: D,
 ) -> std::result::Result<
@@ -554,6 +1038,135 @@ where
     deserializer.deserialize_any(StringOrInt)
 }
 
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SameSite {
+    Strict,
+    Lax,
+}
+
+impl SameSite {
+    fn try_from(s: &str) -> Result<Self> {
+        match s {
+            "strict" => Ok(Self::Strict),
+            "lax" => Ok(Self::Lax),
+            _ => Err(Error::InvalidSameSite {
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionBackend {
+    Disk,
+    Memory,
+    Sqlite,
+}
+
+impl SessionBackend {
+    fn try_from(s: &str) -> Result<Self> {
+        match s {
+            "disk" => Ok(Self::Disk),
+            "memory" => Ok(Self::Memory),
+            "sqlite" => Ok(Self::Sqlite),
+            _ => Err(Error::InvalidSessionBackend {
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+// roles aren't cumulative: streamers can only stream, and can't list or
+// watch other streams (including their own), so admin is the only role which
+// can do both
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Watcher,
+    Streamer,
+    Admin,
+}
+
+impl Role {
+    fn try_from(s: &str) -> Result<Self> {
+        match s {
+            "watcher" => Ok(Self::Watcher),
+            "streamer" => Ok(Self::Streamer),
+            "admin" => Ok(Self::Admin),
+            _ => Err(Error::InvalidRole {
+                value: s.to_string(),
+            }),
+        }
+    }
+
+    pub fn can_stream(self) -> bool {
+        match self {
+            Self::Streamer | Self::Admin => true,
+            Self::Watcher => false,
+        }
+    }
+
+    pub fn can_watch(self) -> bool {
+        match self {
+            Self::Watcher | Self::Admin => true,
+            Self::Streamer => false,
+        }
+    }
+
+    // the role allowing everything that either role allows
+    fn union(self, other: Self) -> Self {
+        if self == other {
+            self
+        } else {
+            Self::Admin
+        }
+    }
+}
+
+// maps users to roles. keys in the [server.roles] section are either
+// usernames or "group:NAME" for groups reported by the oauth provider (for
+// recurse_center, the batches the user has been a part of).
+#[derive(Debug, Clone)]
+pub struct Roles {
+    default: Role,
+    assignments: std::collections::HashMap<String, Role>,
+}
+
+impl Roles {
+    // a role assigned to the username directly takes precedence over roles
+    // assigned to the user's groups, and users in several groups get the
+    // permissions of all of them. plain logins aren't verified at all, so
+    // anyone could claim an assigned username that way - those only ever
+    // get the default role. this includes plain logins through tt web,
+    // which checks them against its own password file, since the server has
+    // no way to know that it did.
+    pub fn role(
+        &self,
+        auth_type: crate::protocol::AuthType,
+        username: &str,
+        groups: &[String],
+    ) -> Role {
+        if auth_type == crate::protocol::AuthType::Plain {
+            return self.default;
+        }
+        if let Some(role) = self.assignments.get(username) {
+            return *role;
+        }
+        groups
+            .iter()
+            .filter_map(|group| {
+                self.assignments.get(&format!("group:{}", group))
+            })
+            .copied()
+            .fold(None, |acc: Option<Role>, role| {
+                Some(acc.map_or(role, |acc| acc.union(role)))
+            })
+            .unwrap_or(self.default)
+    }
+}
+
 #[derive(serde::Deserialize, Debug)]
 pub struct Web {
     #[serde(
@@ -565,12 +1178,57 @@ pub struct Web {
     #[serde(default = "default_web_public_address")]
     pub public_address: String,
 
+    pub tls_identity_file: Option<String>,
+
+    pub tls_cert_file: Option<String>,
+
+    pub tls_key_file: Option<String>,
+
+    #[serde(default)]
+    pub trusted_proxies: Vec<std::net::IpAddr>,
+
+    #[serde(default = "default_cookie_same_site")]
+    pub cookie_same_site: SameSite,
+
+    pub password_file: Option<String>,
+
+    #[serde(default = "default_session_backend")]
+    pub session_backend: SessionBackend,
+
+    #[serde(
+        rename = "session_ttl_secs",
+        deserialize_with = "duration_secs",
+        default = "default_session_ttl"
+    )]
+    pub session_ttl: std::time::Duration,
+
+    #[serde(
+        rename = "session_gc_interval_secs",
+        deserialize_with = "duration_secs",
+        default = "default_session_gc_interval"
+    )]
+    pub session_gc_interval: std::time::Duration,
+
+    #[serde(default = "default_session_sqlite_file")]
+    pub session_sqlite_file: String,
+
     #[serde(
         deserialize_with = "connect_address",
         default = "default_connect_address"
     )]
     pub server_address: (String, std::net::SocketAddr),
 
+    #[serde(default = "default_tls")]
+    pub server_tls: bool,
+
+    pub server_tls_ca_file: Option<String>,
+
+    #[serde(default)]
+    pub server_tls_pinned_certs: Vec<String>,
+
+    #[serde(default)]
+    pub server_tls_trust_on_first_use: bool,
+
     #[serde(
         deserialize_with = "allowed_login_methods",
         default = "default_allowed_login_methods"
@@ -580,6 +1238,42 @@ pub struct Web {
 }
 
 impl Web {
+    pub fn password_verifier(
+        &self,
+    ) -> Option<std::sync::Arc<dyn crate::auth::Verifier>> {
+        self.password_file.as_ref().map(|filename| {
+            std::sync::Arc::new(crate::auth::htpasswd::Htpasswd::new(
+                filename,
+            )) as std::sync::Arc<dyn crate::auth::Verifier>
+        })
+    }
+
+    pub fn tls_identity(
+        &self,
+    ) -> Result<Option<crate::server::tls::Identity>> {
+        tls_identity(
+            self.tls_identity_file.as_ref(),
+            self.tls_cert_file.as_ref(),
+            self.tls_key_file.as_ref(),
+        )
+    }
+
+    pub fn server_tls_connector(
+        &self,
+    ) -> Result<Option<crate::client::tls::Connector>> {
+        if !self.server_tls {
+            return Ok(None);
+        }
+        crate::client::tls::Connector::new(
+            self.server_tls_ca_file
+                .as_ref()
+                .map(std::string::String::as_str),
+            &self.server_tls_pinned_certs,
+            self.server_tls_trust_on_first_use,
+        )
+        .map(Some)
+    }
+
     pub fn cmd<'a, 'b>(app: clap::App<'a, 'b>) -> clap::App<'a, 'b> {
         let listen_address_help =
             "Host and port to listen on (defaults to localhost:4145)";
@@ -587,7 +1281,21 @@ impl Web {
             "Host and port that the web server will be publicly available on (defaults to the listen address)";
         let server_address_help =
             "Host and port of the teleterm server (defaults to localhost:4144)";
-        let allowed_login_methods_help = "Comma separated list containing the auth methods this server should allow. Allows everything by default, valid values are plain, recurse_center";
+        let tls_identity_file_help = "File containing the TLS certificate and private key to use for serving HTTPS. Must be in pfx format.";
+        let tls_cert_file_help = "File containing the TLS certificate chain to use for serving HTTPS, in PEM format. Must be used along with --tls-key-file, and conflicts with --tls-identity-file.";
+        let tls_key_file_help = "File containing the TLS private key to use for serving HTTPS, in PEM format. Must be used along with --tls-cert-file.";
+        let trusted_proxy_help = "IP address of a reverse proxy whose X-Forwarded-Proto header should be trusted. Can be given multiple times.";
+        let cookie_same_site_help =
+            "SameSite attribute for the session cookie (defaults to lax)";
+        let password_file_help = "htpasswd file containing the passwords of users allowed to log in with the 'plain' authentication method. Plain logins are refused if this is not set.";
+        let session_backend_help =
+            "Where to store login sessions (defaults to disk)";
+        let session_ttl_help = "Number of seconds a login session lasts after it was last updated (defaults to 30 days)";
+        let tls_help = "Connect to the teleterm server using TLS";
+        let tls_ca_file_help = "File containing additional CA certificates (in PEM format) to trust when verifying the teleterm server's certificate. Implies --tls.";
+        let tls_pin_cert_help = "Only accept a teleterm server certificate with this SHA-256 fingerprint, instead of verifying the certificate chain. Can be given multiple times. Implies --tls.";
+        let tls_trust_on_first_use_help = "Trust the teleterm server's certificate the first time it is seen, and require it to stay the same on later connections, instead of verifying the certificate chain. Implies --tls.";
+        let allowed_login_methods_help = "Comma separated list containing the auth methods this server should allow. Allows everything by default, valid values are plain, recurse_center, password";
         app.arg(
             clap::Arg::with_name(LISTEN_ADDRESS_OPTION)
                 .long(LISTEN_ADDRESS_OPTION)
@@ -603,27 +1311,117 @@ impl Web {
                 .help(public_address_help),
         )
         .arg(
-            clap::Arg::with_name(SERVER_ADDRESS_OPTION)
-                .long(SERVER_ADDRESS_OPTION)
+            clap::Arg::with_name(TLS_IDENTITY_FILE_OPTION)
+                .long(TLS_IDENTITY_FILE_OPTION)
                 .takes_value(true)
-                .value_name("HOST:PORT")
-                .help(server_address_help),
+                .value_name("FILE")
+                .help(tls_identity_file_help),
         )
         .arg(
-            clap::Arg::with_name(ALLOWED_LOGIN_METHODS_OPTION)
-                .long(ALLOWED_LOGIN_METHODS_OPTION)
-                .use_delimiter(true)
+            clap::Arg::with_name(TLS_CERT_FILE_OPTION)
+                .long(TLS_CERT_FILE_OPTION)
                 .takes_value(true)
-                .value_name("AUTH_METHODS")
-                .help(allowed_login_methods_help),
+                .value_name("FILE")
+                .requires(TLS_KEY_FILE_OPTION)
+                .conflicts_with(TLS_IDENTITY_FILE_OPTION)
+                .help(tls_cert_file_help),
         )
-    }
-
-    pub fn merge_args<'a>(
-        &mut self,
-        matches: &clap::ArgMatches<'a>,
-    ) -> Result<()> {
-        if matches.is_present(LISTEN_ADDRESS_OPTION) {
+        .arg(
+            clap::Arg::with_name(TLS_KEY_FILE_OPTION)
+                .long(TLS_KEY_FILE_OPTION)
+                .takes_value(true)
+                .value_name("FILE")
+                .requires(TLS_CERT_FILE_OPTION)
+                .help(tls_key_file_help),
+        )
+        .arg(
+            clap::Arg::with_name(TRUSTED_PROXY_OPTION)
+                .long(TRUSTED_PROXY_OPTION)
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("ADDR")
+                .help(trusted_proxy_help),
+        )
+        .arg(
+            clap::Arg::with_name(COOKIE_SAME_SITE_OPTION)
+                .long(COOKIE_SAME_SITE_OPTION)
+                .takes_value(true)
+                .possible_values(&["strict", "lax"])
+                .value_name("POLICY")
+                .help(cookie_same_site_help),
+        )
+        .arg(
+            clap::Arg::with_name(PASSWORD_FILE_OPTION)
+                .long(PASSWORD_FILE_OPTION)
+                .takes_value(true)
+                .value_name("FILE")
+                .help(password_file_help),
+        )
+        .arg(
+            clap::Arg::with_name(SESSION_BACKEND_OPTION)
+                .long(SESSION_BACKEND_OPTION)
+                .takes_value(true)
+                .possible_values(&["disk", "memory", "sqlite"])
+                .value_name("BACKEND")
+                .help(session_backend_help),
+        )
+        .arg(
+            clap::Arg::with_name(SESSION_TTL_OPTION)
+                .long(SESSION_TTL_OPTION)
+                .takes_value(true)
+                .value_name("SECS")
+                .help(session_ttl_help),
+        )
+        .arg(
+            clap::Arg::with_name(SERVER_ADDRESS_OPTION)
+                .long(SERVER_ADDRESS_OPTION)
+                .takes_value(true)
+                .value_name("HOST:PORT")
+                .help(server_address_help),
+        )
+        .arg(
+            clap::Arg::with_name(TLS_OPTION)
+                .long(TLS_OPTION)
+                .help(tls_help),
+        )
+        .arg(
+            clap::Arg::with_name(TLS_CA_FILE_OPTION)
+                .long(TLS_CA_FILE_OPTION)
+                .takes_value(true)
+                .value_name("FILE")
+                .help(tls_ca_file_help),
+        )
+        .arg(
+            clap::Arg::with_name(TLS_PIN_CERT_OPTION)
+                .long(TLS_PIN_CERT_OPTION)
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("SHA256")
+                .help(tls_pin_cert_help),
+        )
+        .arg(
+            clap::Arg::with_name(TLS_TRUST_ON_FIRST_USE_OPTION)
+                .long(TLS_TRUST_ON_FIRST_USE_OPTION)
+                .conflicts_with(TLS_PIN_CERT_OPTION)
+                .help(tls_trust_on_first_use_help),
+        )
+        .arg(
+            clap::Arg::with_name(ALLOWED_LOGIN_METHODS_OPTION)
+                .long(ALLOWED_LOGIN_METHODS_OPTION)
+                .use_delimiter(true)
+                .takes_value(true)
+                .value_name("AUTH_METHODS")
+                .help(allowed_login_methods_help),
+        )
+    }
+
+    pub fn merge_args<'a>(
+        &mut self,
+        matches: &clap::ArgMatches<'a>,
+    ) -> Result<()> {
+        if matches.is_present(LISTEN_ADDRESS_OPTION) {
             self.listen_address = matches
                 .value_of(LISTEN_ADDRESS_OPTION)
                 .unwrap()
@@ -637,10 +1435,81 @@ impl Web {
             self.public_address =
                 matches.value_of(LISTEN_ADDRESS_OPTION).unwrap().to_string();
         }
+        if matches.is_present(TLS_IDENTITY_FILE_OPTION) {
+            self.tls_identity_file = Some(
+                matches
+                    .value_of(TLS_IDENTITY_FILE_OPTION)
+                    .unwrap()
+                    .to_string(),
+            );
+            self.tls_cert_file = None;
+            self.tls_key_file = None;
+        }
+        if matches.is_present(TLS_CERT_FILE_OPTION) {
+            self.tls_identity_file = None;
+            self.tls_cert_file = Some(
+                matches.value_of(TLS_CERT_FILE_OPTION).unwrap().to_string(),
+            );
+            self.tls_key_file = Some(
+                matches.value_of(TLS_KEY_FILE_OPTION).unwrap().to_string(),
+            );
+        }
+        if matches.is_present(TRUSTED_PROXY_OPTION) {
+            self.trusted_proxies = matches
+                .values_of(TRUSTED_PROXY_OPTION)
+                .unwrap()
+                .map(|addr| addr.parse().context(crate::error::ParseAddr))
+                .collect::<Result<_>>()?;
+        }
+        if matches.is_present(COOKIE_SAME_SITE_OPTION) {
+            self.cookie_same_site = SameSite::try_from(
+                matches.value_of(COOKIE_SAME_SITE_OPTION).unwrap(),
+            )?;
+        }
+        if matches.is_present(PASSWORD_FILE_OPTION) {
+            self.password_file = matches
+                .value_of(PASSWORD_FILE_OPTION)
+                .map(std::string::ToString::to_string);
+        }
+        if matches.is_present(SESSION_BACKEND_OPTION) {
+            self.session_backend = SessionBackend::try_from(
+                matches.value_of(SESSION_BACKEND_OPTION).unwrap(),
+            )?;
+        }
+        if matches.is_present(SESSION_TTL_OPTION) {
+            let s = matches.value_of(SESSION_TTL_OPTION).unwrap();
+            self.session_ttl = s
+                .parse()
+                .map(std::time::Duration::from_secs)
+                .context(crate::error::ParseSessionTtl { input: s })?;
+        }
         if matches.is_present(SERVER_ADDRESS_OPTION) {
             let address = matches.value_of(SERVER_ADDRESS_OPTION).unwrap();
             self.server_address = to_connect_address(address)?;
         }
+        if matches.is_present(TLS_OPTION) {
+            self.server_tls = true;
+        }
+        if matches.is_present(TLS_CA_FILE_OPTION) {
+            self.server_tls = true;
+            self.server_tls_ca_file = matches
+                .value_of(TLS_CA_FILE_OPTION)
+                .map(std::string::ToString::to_string);
+        }
+        if matches.is_present(TLS_PIN_CERT_OPTION) {
+            self.server_tls = true;
+            self.server_tls_pinned_certs = matches
+                .values_of(TLS_PIN_CERT_OPTION)
+                .unwrap()
+                .map(std::string::ToString::to_string)
+                .collect();
+            self.server_tls_trust_on_first_use = false;
+        }
+        if matches.is_present(TLS_TRUST_ON_FIRST_USE_OPTION) {
+            self.server_tls = true;
+            self.server_tls_pinned_certs = vec![];
+            self.server_tls_trust_on_first_use = true;
+        }
         if matches.is_present(ALLOWED_LOGIN_METHODS_OPTION) {
             self.allowed_login_methods = matches
                 .values_of(ALLOWED_LOGIN_METHODS_OPTION)
@@ -659,7 +1528,21 @@ impl Default for Web {
         Self {
             listen_address: default_web_listen_address(),
             public_address: default_web_public_address(),
+            tls_identity_file: None,
+            tls_cert_file: None,
+            tls_key_file: None,
+            trusted_proxies: vec![],
+            cookie_same_site: default_cookie_same_site(),
+            password_file: None,
+            session_backend: default_session_backend(),
+            session_ttl: default_session_ttl(),
+            session_gc_interval: default_session_gc_interval(),
+            session_sqlite_file: default_session_sqlite_file(),
             server_address: default_connect_address(),
+            server_tls: default_tls(),
+            server_tls_ca_file: None,
+            server_tls_pinned_certs: vec![],
+            server_tls_trust_on_first_use: false,
             allowed_login_methods: default_allowed_login_methods(),
         }
     }
@@ -673,6 +1556,45 @@ fn default_web_public_address() -> String {
     DEFAULT_WEB_LISTEN_ADDRESS.to_string()
 }
 
+fn default_cookie_same_site() -> SameSite {
+    DEFAULT_COOKIE_SAME_SITE
+}
+
+fn default_session_backend() -> SessionBackend {
+    DEFAULT_SESSION_BACKEND
+}
+
+fn default_role() -> Role {
+    DEFAULT_ROLE
+}
+
+fn duration_secs<'a, D>(
+    deserializer: D,
+) -> std::result::Result<std::time::Duration, D::Error>
+where
+    D: serde::de::Deserializer<'a>,
+{
+    Ok(std::time::Duration::from_secs(u64::deserialize(
+        deserializer,
+    )?))
+}
+
+fn default_session_ttl() -> std::time::Duration {
+    DEFAULT_SESSION_TTL
+}
+
+fn default_session_gc_interval() -> std::time::Duration {
+    DEFAULT_SESSION_GC_INTERVAL
+}
+
+fn default_session_sqlite_file() -> String {
+    crate::dirs::Dirs::new()
+        .data_file(DEFAULT_SESSION_SQLITE_FILENAME, false)
+        .unwrap()
+        .to_string_lossy()
+        .to_string()
+}
+
 #[derive(serde::Deserialize, Debug)]
 pub struct Command {
     #[serde(default = "default_command")]
@@ -735,16 +1657,173 @@ fn default_args() -> Vec<String> {
     vec![]
 }
 
+#[derive(serde::Deserialize, Debug)]
+pub struct Stream {
+    #[serde(deserialize_with = "escape_key", default = "default_escape_key")]
+    pub escape_key: u8,
+
+    #[serde(deserialize_with = "regexes", default)]
+    pub redact: Vec<regex::Regex>,
+
+    #[serde(default)]
+    pub title: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Stream {
+    pub fn cmd<'a, 'b>(app: clap::App<'a, 'b>) -> clap::App<'a, 'b> {
+        let escape_key_help = "Key which starts a streaming command, in caret notation (defaults to ^]). Press it followed by 'p' to pause or resume broadcasting, 't' to edit the stream title, description, and tags, or twice to send it to the running command. 'm' is passed through, so that it still adds a bookmark when streaming tt record.";
+        let redact_help = "Regex matching text which should be hidden from watchers. Can be given multiple times.";
+        let title_help = "Title to display for the stream in the list of streams (defaults to the terminal title)";
+        let description_help =
+            "Description to display for the stream in the list of streams";
+        let tag_help = "Tag to attach to the stream, which watchers can use to find it. Can be given multiple times.";
+        app.arg(
+            clap::Arg::with_name(ESCAPE_KEY_OPTION)
+                .long(ESCAPE_KEY_OPTION)
+                .takes_value(true)
+                .value_name("KEY")
+                .help(escape_key_help),
+        )
+        .arg(
+            clap::Arg::with_name(REDACT_OPTION)
+                .long(REDACT_OPTION)
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("REGEX")
+                .help(redact_help),
+        )
+        .arg(
+            clap::Arg::with_name(TITLE_OPTION)
+                .long(TITLE_OPTION)
+                .takes_value(true)
+                .value_name("TITLE")
+                .help(title_help),
+        )
+        .arg(
+            clap::Arg::with_name(DESCRIPTION_OPTION)
+                .long(DESCRIPTION_OPTION)
+                .takes_value(true)
+                .value_name("DESCRIPTION")
+                .help(description_help),
+        )
+        .arg(
+            clap::Arg::with_name(TAG_OPTION)
+                .long(TAG_OPTION)
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("TAG")
+                .help(tag_help),
+        )
+    }
+
+    pub fn merge_args<'a>(
+        &mut self,
+        matches: &clap::ArgMatches<'a>,
+    ) -> Result<()> {
+        if matches.is_present(ESCAPE_KEY_OPTION) {
+            self.escape_key =
+                to_escape_key(matches.value_of(ESCAPE_KEY_OPTION).unwrap())?;
+        }
+        if matches.is_present(REDACT_OPTION) {
+            self.redact = matches
+                .values_of(REDACT_OPTION)
+                .unwrap()
+                .map(to_regex)
+                .collect::<Result<_>>()?;
+        }
+        if matches.is_present(TITLE_OPTION) {
+            self.title = matches.value_of(TITLE_OPTION).unwrap().to_string();
+        }
+        if matches.is_present(DESCRIPTION_OPTION) {
+            self.description =
+                matches.value_of(DESCRIPTION_OPTION).unwrap().to_string();
+        }
+        if matches.is_present(TAG_OPTION) {
+            self.tags = matches
+                .values_of(TAG_OPTION)
+                .unwrap()
+                .map(std::string::ToString::to_string)
+                .collect();
+        }
+        Ok(())
+    }
+}
+
+impl Default for Stream {
+    fn default() -> Self {
+        Self {
+            escape_key: default_escape_key(),
+            redact: vec![],
+            title: "".to_string(),
+            description: "".to_string(),
+            tags: vec![],
+        }
+    }
+}
+
+fn escape_key<'a, D>(deserializer: D) -> std::result::Result<u8, D::Error>
+where
+    D: serde::de::Deserializer<'a>,
+{
+    to_escape_key(&<String>::deserialize(deserializer)?)
+        .map_err(serde::de::Error::custom)
+}
+
+fn default_escape_key() -> u8 {
+    to_escape_key(DEFAULT_ESCAPE_KEY).unwrap()
+}
+
+fn to_escape_key(key: &str) -> Result<u8> {
+    match key.as_bytes() {
+        [b'^', c] if (b'?'..=b'_').contains(&c.to_ascii_uppercase()) => {
+            Ok(c.to_ascii_uppercase() ^ 0x40)
+        }
+        [c] if c.is_ascii_control() => Ok(*c),
+        _ => Err(Error::ParseEscapeKey {
+            key: key.to_string(),
+        }),
+    }
+}
+
+fn regexes<'a, D>(
+    deserializer: D,
+) -> std::result::Result<Vec<regex::Regex>, D::Error>
+where
+    D: serde::de::Deserializer<'a>,
+{
+    <Vec<String>>::deserialize(deserializer)?
+        .iter()
+        .map(|regex| to_regex(regex))
+        .collect::<Result<_>>()
+        .map_err(serde::de::Error::custom)
+}
+
+fn to_regex(regex: &str) -> Result<regex::Regex> {
+    regex::Regex::new(regex).context(crate::error::ParseRegex { regex })
+}
+
 #[derive(serde::Deserialize, Debug)]
 pub struct Ttyrec {
     #[serde(default = "default_ttyrec_filename")]
     pub filename: String,
+
+    #[serde(default)]
+    format: Option<RecordingFormat>,
 }
 
 impl Ttyrec {
     pub fn cmd<'a, 'b>(app: clap::App<'a, 'b>) -> clap::App<'a, 'b> {
         let filename_help =
             "TTYrec file to use (defaults to teleterm.ttyrec)";
+        let format_help = "Format of the recording, either ttyrec or asciicast (defaults to asciicast for files ending in .cast, and ttyrec otherwise)";
         app.arg(
             clap::Arg::with_name(FILENAME_OPTION)
                 .long(FILENAME_OPTION)
@@ -752,6 +1831,25 @@ impl Ttyrec {
                 .value_name("FILE")
                 .help(filename_help),
         )
+        .arg(
+            clap::Arg::with_name(FORMAT_OPTION)
+                .long(FORMAT_OPTION)
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(&["ttyrec", "asciicast"])
+                .help(format_help),
+        )
+    }
+
+    pub fn format(&self) -> RecordingFormat {
+        self.format_for(&self.filename)
+    }
+
+    // for commands which can operate on other files than the configured
+    // one. an explicitly configured format still applies to all of them.
+    pub fn format_for(&self, filename: &str) -> RecordingFormat {
+        self.format
+            .unwrap_or_else(|| RecordingFormat::from_filename(filename))
     }
 
     pub fn merge_args<'a>(
@@ -762,6 +1860,11 @@ impl Ttyrec {
             self.filename =
                 matches.value_of(FILENAME_OPTION).unwrap().to_string();
         }
+        if matches.is_present(FORMAT_OPTION) {
+            self.format = Some(RecordingFormat::try_from(
+                matches.value_of(FORMAT_OPTION).unwrap(),
+            )?);
+        }
         Ok(())
     }
 }
@@ -770,14 +1873,162 @@ impl Default for Ttyrec {
     fn default() -> Self {
         Self {
             filename: default_ttyrec_filename(),
+            format: None,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingFormat {
+    Ttyrec,
+    Asciicast,
+}
+
+impl RecordingFormat {
+    pub fn try_from(s: &str) -> Result<Self> {
+        match s {
+            "ttyrec" => Ok(Self::Ttyrec),
+            "asciicast" => Ok(Self::Asciicast),
+            _ => Err(Error::InvalidRecordingFormat {
+                value: s.to_string(),
+            }),
+        }
+    }
+
+    // .cast is the extension that asciinema uses
+    pub fn from_filename(filename: &str) -> Self {
+        if filename.ends_with(".cast") {
+            Self::Asciicast
+        } else {
+            Self::Ttyrec
         }
     }
+
+    // files with other names can still be played when given explicitly,
+    // but this is how recordings are picked out of a directory
+    pub fn has_extension(filename: &str) -> bool {
+        filename.ends_with(".ttyrec") || filename.ends_with(".cast")
+    }
 }
 
 fn default_ttyrec_filename() -> String {
     DEFAULT_TTYREC_FILENAME.to_string()
 }
 
+#[derive(serde::Deserialize, Debug)]
+pub struct Record {
+    #[serde(deserialize_with = "escape_key", default = "default_escape_key")]
+    pub escape_key: u8,
+
+    #[serde(default)]
+    pub record_input: bool,
+
+    #[serde(default)]
+    pub append: bool,
+
+    #[serde(default)]
+    pub rotate_size: Option<u64>,
+
+    #[serde(default, deserialize_with = "rotate_duration")]
+    pub rotate_duration: Option<std::time::Duration>,
+}
+
+impl Record {
+    pub fn cmd<'a, 'b>(app: clap::App<'a, 'b>) -> clap::App<'a, 'b> {
+        let escape_key_help = "Key which starts a recording command, in caret notation (defaults to ^]). Press it followed by 'm' to add a bookmark at the current point in the recording, or twice to send it to the running command.";
+        let record_input_help = "Also record the keys typed during the session, with anything typed at a password prompt masked out. For ttyrec files, these are stored in a separate file next to the recording.";
+        let append_help = "Continue an existing recording rather than replacing it. With a filename containing %N, this continues the last file in the sequence.";
+        let rotate_size_help = "Start a new file once the current one reaches this many bytes (the filename must contain %N)";
+        let rotate_duration_help = "Start a new file once the current one has been recording for this many seconds (the filename must contain %N)";
+        app.arg(
+            clap::Arg::with_name(ESCAPE_KEY_OPTION)
+                .long(ESCAPE_KEY_OPTION)
+                .takes_value(true)
+                .value_name("KEY")
+                .help(escape_key_help),
+        )
+        .arg(
+            clap::Arg::with_name(RECORD_INPUT_OPTION)
+                .long(RECORD_INPUT_OPTION)
+                .help(record_input_help),
+        )
+        .arg(
+            clap::Arg::with_name(APPEND_OPTION)
+                .long(APPEND_OPTION)
+                .help(append_help),
+        )
+        .arg(
+            clap::Arg::with_name(ROTATE_SIZE_OPTION)
+                .long(ROTATE_SIZE_OPTION)
+                .takes_value(true)
+                .value_name("BYTES")
+                .help(rotate_size_help),
+        )
+        .arg(
+            clap::Arg::with_name(ROTATE_DURATION_OPTION)
+                .long(ROTATE_DURATION_OPTION)
+                .takes_value(true)
+                .value_name("SECS")
+                .help(rotate_duration_help),
+        )
+    }
+
+    pub fn merge_args<'a>(
+        &mut self,
+        matches: &clap::ArgMatches<'a>,
+    ) -> Result<()> {
+        if matches.is_present(ESCAPE_KEY_OPTION) {
+            self.escape_key =
+                to_escape_key(matches.value_of(ESCAPE_KEY_OPTION).unwrap())?;
+        }
+        if matches.is_present(RECORD_INPUT_OPTION) {
+            self.record_input = true;
+        }
+        if matches.is_present(APPEND_OPTION) {
+            self.append = true;
+        }
+        if matches.is_present(ROTATE_SIZE_OPTION) {
+            let s = matches.value_of(ROTATE_SIZE_OPTION).unwrap();
+            self.rotate_size = Some(
+                s.parse()
+                    .context(crate::error::ParseRotateSize { input: s })?,
+            );
+        }
+        if matches.is_present(ROTATE_DURATION_OPTION) {
+            let s = matches.value_of(ROTATE_DURATION_OPTION).unwrap();
+            self.rotate_duration =
+                Some(s.parse().map(std::time::Duration::from_secs).context(
+                    crate::error::ParseRotateDuration { input: s },
+                )?);
+        }
+        // these would otherwise start a new file for every frame
+        if self.rotate_size == Some(0) {
+            return Err(Error::InvalidRotation {
+                name: ROTATE_SIZE_OPTION.to_string(),
+            });
+        }
+        if self.rotate_duration == Some(std::time::Duration::default()) {
+            return Err(Error::InvalidRotation {
+                name: ROTATE_DURATION_OPTION.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for Record {
+    fn default() -> Self {
+        Self {
+            escape_key: default_escape_key(),
+            record_input: false,
+            append: false,
+            rotate_size: None,
+            rotate_duration: None,
+        }
+    }
+}
+
 #[derive(serde::Deserialize, Debug)]
 pub struct Play {
     #[serde(default)]
@@ -788,6 +2039,18 @@ pub struct Play {
 
     #[serde(default, deserialize_with = "max_frame_length")]
     pub max_frame_length: Option<std::time::Duration>,
+
+    #[serde(default)]
+    pub skip_idle: bool,
+
+    #[serde(
+        default = "default_idle_threshold",
+        deserialize_with = "duration_secs"
+    )]
+    pub idle_threshold: std::time::Duration,
+
+    #[serde(default = "default_idle_playback_ratio")]
+    pub idle_playback_ratio: f32,
 }
 
 impl Play {
@@ -797,6 +2060,12 @@ impl Play {
             "Speed to play back the ttyrec at (defaults to 1.0)";
         let max_frame_length_help =
             "Clamp frame duration at this number of seconds";
+        let skip_idle_help =
+            "Fast-forward through stretches with no output (can also be toggled during playback)";
+        let idle_threshold_help =
+            "Gaps between frames longer than this number of seconds are considered idle (defaults to 5)";
+        let idle_playback_ratio_help =
+            "Speed to play back idle stretches at when skipping them (defaults to 100.0)";
         app.arg(
             clap::Arg::with_name(PLAY_AT_START_OPTION)
                 .long(PLAY_AT_START_OPTION)
@@ -816,6 +2085,25 @@ impl Play {
                 .value_name("SECS")
                 .help(max_frame_length_help),
         )
+        .arg(
+            clap::Arg::with_name(SKIP_IDLE_OPTION)
+                .long(SKIP_IDLE_OPTION)
+                .help(skip_idle_help),
+        )
+        .arg(
+            clap::Arg::with_name(IDLE_THRESHOLD_OPTION)
+                .long(IDLE_THRESHOLD_OPTION)
+                .takes_value(true)
+                .value_name("SECS")
+                .help(idle_threshold_help),
+        )
+        .arg(
+            clap::Arg::with_name(IDLE_PLAYBACK_RATIO_OPTION)
+                .long(IDLE_PLAYBACK_RATIO_OPTION)
+                .takes_value(true)
+                .value_name("RATIO")
+                .help(idle_playback_ratio_help),
+        )
     }
 
     pub fn merge_args<'a>(
@@ -838,6 +2126,31 @@ impl Play {
             .map(|len| len.parse().map(std::time::Duration::from_secs))
             .transpose()
             .context(crate::error::ParseMaxFrameLength)?;
+        if matches.is_present(SKIP_IDLE_OPTION) {
+            self.skip_idle = true;
+        }
+        if matches.is_present(IDLE_THRESHOLD_OPTION) {
+            let s = matches.value_of(IDLE_THRESHOLD_OPTION).unwrap();
+            self.idle_threshold = s
+                .parse()
+                .map(std::time::Duration::from_secs)
+                .context(crate::error::ParseIdleThreshold { input: s })?;
+        }
+        if matches.is_present(IDLE_PLAYBACK_RATIO_OPTION) {
+            self.idle_playback_ratio = matches
+                .value_of(IDLE_PLAYBACK_RATIO_OPTION)
+                .unwrap()
+                .to_string()
+                .parse()
+                .context(crate::error::ParseFloat {
+                    name: IDLE_PLAYBACK_RATIO_OPTION,
+                })?;
+        }
+        validate_playback_ratio(PLAYBACK_RATIO_OPTION, self.playback_ratio)?;
+        validate_playback_ratio(
+            IDLE_PLAYBACK_RATIO_OPTION,
+            self.idle_playback_ratio,
+        )?;
         Ok(())
     }
 }
@@ -848,6 +2161,75 @@ impl Default for Play {
             play_at_start: false,
             playback_ratio: default_playback_ratio(),
             max_frame_length: None,
+            skip_idle: false,
+            idle_threshold: default_idle_threshold(),
+            idle_playback_ratio: default_idle_playback_ratio(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct Render {
+    #[serde(default = "default_playback_ratio")]
+    pub playback_ratio: f32,
+
+    #[serde(default, deserialize_with = "max_frame_length")]
+    pub max_frame_length: Option<std::time::Duration>,
+}
+
+impl Render {
+    pub fn cmd<'a, 'b>(app: clap::App<'a, 'b>) -> clap::App<'a, 'b> {
+        let playback_ratio_help =
+            "Speed to render the recording at (defaults to 1.0)";
+        let max_frame_length_help =
+            "Clamp idle time between frames at this number of seconds";
+        app.arg(
+            clap::Arg::with_name(PLAYBACK_RATIO_OPTION)
+                .long(PLAYBACK_RATIO_OPTION)
+                .takes_value(true)
+                .value_name("RATIO")
+                .help(playback_ratio_help),
+        )
+        .arg(
+            clap::Arg::with_name(MAX_FRAME_LENGTH_OPTION)
+                .long(MAX_FRAME_LENGTH_OPTION)
+                .takes_value(true)
+                .value_name("SECS")
+                .help(max_frame_length_help),
+        )
+    }
+
+    pub fn merge_args<'a>(
+        &mut self,
+        matches: &clap::ArgMatches<'a>,
+    ) -> Result<()> {
+        if matches.is_present(PLAYBACK_RATIO_OPTION) {
+            self.playback_ratio = matches
+                .value_of(PLAYBACK_RATIO_OPTION)
+                .unwrap()
+                .to_string()
+                .parse()
+                .context(crate::error::ParseFloat {
+                    name: PLAYBACK_RATIO_OPTION,
+                })?;
+        }
+        if matches.is_present(MAX_FRAME_LENGTH_OPTION) {
+            self.max_frame_length = matches
+                .value_of(MAX_FRAME_LENGTH_OPTION)
+                .map(|len| len.parse().map(std::time::Duration::from_secs))
+                .transpose()
+                .context(crate::error::ParseMaxFrameLength)?;
+        }
+        validate_playback_ratio(PLAYBACK_RATIO_OPTION, self.playback_ratio)?;
+        Ok(())
+    }
+}
+
+impl Default for Render {
+    fn default() -> Self {
+        Self {
+            playback_ratio: default_playback_ratio(),
+            max_frame_length: None,
         }
     }
 }
@@ -856,6 +2238,27 @@ fn default_playback_ratio() -> f32 {
     1.0
 }
 
+// frame durations are divided by these, which panics for anything that
+// isn't a positive number
+fn validate_playback_ratio(name: &str, ratio: f32) -> Result<()> {
+    if ratio.is_finite() && ratio > 0.0 {
+        Ok(())
+    } else {
+        Err(Error::InvalidPlaybackRatio {
+            name: name.to_string(),
+            ratio,
+        })
+    }
+}
+
+fn default_idle_threshold() -> std::time::Duration {
+    std::time::Duration::from_secs(5)
+}
+
+fn default_idle_playback_ratio() -> f32 {
+    100.0
+}
+
 fn max_frame_length<'a, D>(
     deserializer: D,
 ) -> std::result::Result<Option<std::time::Duration>, D::Error>
@@ -867,6 +2270,17 @@ where
     )?)))
 }
 
+fn rotate_duration<'a, D>(
+    deserializer: D,
+) -> std::result::Result<Option<std::time::Duration>, D::Error>
+where
+    D: serde::de::Deserializer<'a>,
+{
+    Ok(Some(std::time::Duration::from_secs(u64::deserialize(
+        deserializer,
+    )?)))
+}
+
 pub fn oauth_configs<'a, D>(
     deserializer: D,
 ) -> std::result::Result<
@@ -895,7 +2309,7 @@ where
             let auth_client =
                 crate::protocol::AuthClient::try_from(key.as_str())
                     .map_err(serde::de::Error::custom)?;
-            let real_config = match auth_type {
+            let mut real_config = match auth_type {
                 crate::protocol::AuthType::RecurseCenter => {
                     let client_id = config
                         .client_id
@@ -931,6 +2345,42 @@ where
                 }
                 _ => unreachable!(),
             };
+            // only used by cli clients, since the web interface can always
+            // redirect
+            if let Some(url) = config.device_authorization_url {
+                real_config.set_device_authorization_url(url);
+            }
+            // setting a key file implies using it
+            let token_encryption =
+                config.token_encryption.unwrap_or_else(|| {
+                    if config.token_key_file.is_some() {
+                        TokenEncryption::KeyFile
+                    } else {
+                        TokenEncryption::None
+                    }
+                });
+            real_config.set_token_encryption(match token_encryption {
+                TokenEncryption::None => {
+                    crate::auth::token_cache::Encryption::None
+                }
+                TokenEncryption::KeyFile => {
+                    let key_file =
+                        config.token_key_file.unwrap_or_else(|| {
+                            crate::dirs::Dirs::new()
+                                .config_file(
+                                    DEFAULT_TOKEN_KEY_FILENAME,
+                                    false,
+                                )
+                                .unwrap()
+                                .to_string_lossy()
+                                .to_string()
+                        });
+                    crate::auth::token_cache::Encryption::KeyFile(key_file)
+                }
+                TokenEncryption::Keyring => {
+                    crate::auth::token_cache::Encryption::Keyring
+                }
+            });
             auth_type_configs.insert(auth_client, real_config);
         }
         all_configs.insert(auth_type, auth_type_configs);
@@ -954,6 +2404,23 @@ struct OauthConfig {
 
     #[serde(deserialize_with = "url", default)]
     redirect_url: Option<url::Url>,
+
+    #[serde(deserialize_with = "url", default)]
+    device_authorization_url: Option<url::Url>,
+
+    #[serde(default)]
+    token_encryption: Option<TokenEncryption>,
+
+    #[serde(default)]
+    token_key_file: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum TokenEncryption {
+    None,
+    KeyFile,
+    Keyring,
 }
 
 fn url<'a, D>(
@@ -967,3 +2434,108 @@ where
         .transpose()
         .map_err(serde::de::Error::custom)?)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tls_identity() {
+        let id = "id.p12".to_string();
+        let cert = "cert.pem".to_string();
+        let key = "key.pem".to_string();
+        assert!(tls_identity(None, None, None).unwrap().is_none());
+        match tls_identity(Some(&id), Some(&cert), None).unwrap() {
+            Some(crate::server::tls::Identity::Pkcs12 { filename }) => {
+                assert_eq!(filename, id)
+            }
+            identity => panic!("unexpected identity {:?}", identity),
+        }
+        match tls_identity(None, Some(&cert), Some(&key)).unwrap() {
+            Some(crate::server::tls::Identity::Pem {
+                cert_filename,
+                key_filename,
+            }) => {
+                assert_eq!(cert_filename, cert);
+                assert_eq!(key_filename, key);
+            }
+            identity => panic!("unexpected identity {:?}", identity),
+        }
+        assert!(tls_identity(None, Some(&cert), None).is_err());
+        assert!(tls_identity(None, None, Some(&key)).is_err());
+    }
+
+    #[test]
+    fn test_validate_playback_ratio() {
+        assert!(validate_playback_ratio("ratio", 1.0).is_ok());
+        assert!(validate_playback_ratio("ratio", 0.25).is_ok());
+        assert!(validate_playback_ratio("ratio", 0.0).is_err());
+        assert!(validate_playback_ratio("ratio", -1.0).is_err());
+        assert!(validate_playback_ratio("ratio", std::f32::NAN).is_err());
+        assert!(validate_playback_ratio("ratio", std::f32::INFINITY).is_err());
+    }
+
+    #[test]
+    fn test_record_rotation() {
+        let merge = |args: &[&str]| {
+            let matches = Record::cmd(clap::App::new("tt"))
+                .get_matches_from_safe(
+                    std::iter::once("tt").chain(args.iter().copied()),
+                )
+                .unwrap();
+            let mut record = Record::default();
+            record.merge_args(&matches).map(|()| record)
+        };
+        let record = merge(&["--rotate-size", "1000"]).unwrap();
+        assert_eq!(record.rotate_size, Some(1000));
+        let record = merge(&["--rotate-duration", "60"]).unwrap();
+        assert_eq!(
+            record.rotate_duration,
+            Some(std::time::Duration::from_secs(60))
+        );
+        assert!(merge(&["--rotate-size", "0"]).is_err());
+        assert!(merge(&["--rotate-duration", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_roles() {
+        let mut assignments = std::collections::HashMap::new();
+        assignments.insert("doy".to_string(), Role::Admin);
+        assignments.insert("group:streamers".to_string(), Role::Streamer);
+        assignments.insert("group:watchers".to_string(), Role::Watcher);
+        let roles = Roles {
+            default: Role::Watcher,
+            assignments,
+        };
+        let password = crate::protocol::AuthType::Password;
+        let plain = crate::protocol::AuthType::Plain;
+        let rc = crate::protocol::AuthType::RecurseCenter;
+
+        assert_eq!(roles.role(password, "doy", &[]), Role::Admin);
+        assert_eq!(roles.role(password, "someoneelse", &[]), Role::Watcher);
+        // plain logins can't claim assigned roles
+        assert_eq!(roles.role(plain, "doy", &[]), Role::Watcher);
+        assert_eq!(
+            roles.role(plain, "doy", &["streamers".to_string()]),
+            Role::Watcher
+        );
+
+        assert_eq!(
+            roles.role(rc, "someoneelse", &["streamers".to_string()]),
+            Role::Streamer
+        );
+        assert_eq!(
+            roles.role(
+                rc,
+                "someoneelse",
+                &["streamers".to_string(), "watchers".to_string()]
+            ),
+            Role::Admin
+        );
+        // usernames take precedence over groups
+        assert_eq!(
+            roles.role(rc, "doy", &["watchers".to_string()]),
+            Role::Admin
+        );
+    }
+}
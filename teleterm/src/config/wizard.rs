@@ -22,12 +22,45 @@ pub fn run() -> Result<Option<config::Config>> {
     let auth_type = prompt_auth_type(
         "How would you like to authenticate to this server?",
     )?;
+    let needs_username = auth_type
+        == crate::protocol::AuthType::Password.name()
+        || auth_type == crate::protocol::AuthType::SshKey.name();
+    let username = if needs_username {
+        if !tls && auth_type == crate::protocol::AuthType::Password.name() {
+            println!(
+                "Warning: your password will be sent to the server \
+                 unencrypted."
+            );
+        }
+        prompt_string(
+            "Which username would you like to log in as?",
+            super::default_username()
+                .as_ref()
+                .map(std::string::String::as_str),
+        )?
+    } else {
+        None
+    };
+    let token_file = if auth_type == crate::protocol::AuthType::Token.name() {
+        prompt_string(
+            "Which file contains your api token? (leave empty to use the \
+             TELETERM_TOKEN environment variable)",
+            None,
+        )?
+    } else {
+        None
+    };
 
-    write_config_file(&connect_address, tls, &auth_type).and_then(
-        |config_filename| {
-            Some(super::config_from_filename(&config_filename)).transpose()
-        },
+    write_config_file(
+        &connect_address,
+        tls,
+        &auth_type,
+        username.as_ref().map(std::string::String::as_str),
+        token_file.as_ref().map(std::string::String::as_str),
     )
+    .and_then(|config_filename| {
+        Some(super::config_from_filename(&config_filename)).transpose()
+    })
 }
 
 fn touch_config_file() -> Result<()> {
@@ -46,8 +79,10 @@ fn write_config_file(
     connect_address: &str,
     tls: bool,
     auth_type: &str,
+    username: Option<&str>,
+    token_file: Option<&str>,
 ) -> Result<std::path::PathBuf> {
-    let contents = format!(
+    let mut contents = format!(
         r#"[client]
 connect_address = "{}"
 tls = {}
@@ -55,6 +90,12 @@ auth = "{}"
 "#,
         connect_address, tls, auth_type
     );
+    if let Some(username) = username {
+        contents.push_str(&format!("username = {:?}\n", username));
+    }
+    if let Some(token_file) = token_file {
+        contents.push_str(&format!("token_file = {:?}\n", token_file));
+    }
     let config_filename = crate::dirs::Dirs::new()
         .config_file(super::CONFIG_FILENAME, false)
         .unwrap();
@@ -126,6 +167,26 @@ fn prompt_addr(msg: &str) -> Result<String> {
     }
 }
 
+fn prompt_string(msg: &str, default: Option<&str>) -> Result<Option<String>> {
+    if let Some(default) = default {
+        print!("{} [{}]: ", msg, default);
+    } else {
+        print!("{}: ", msg);
+    }
+    std::io::stdout()
+        .flush()
+        .context(crate::error::FlushTerminal)?;
+    let mut response = String::new();
+    std::io::stdin()
+        .read_line(&mut response)
+        .context(crate::error::ReadTerminal)?;
+
+    match response.trim() {
+        "" => Ok(default.map(std::string::ToString::to_string)),
+        s => Ok(Some(s.to_string())),
+    }
+}
+
 fn prompt_auth_type(msg: &str) -> Result<String> {
     let auth_type_names: Vec<_> = crate::protocol::AuthType::iter()
         .map(crate::protocol::AuthType::name)
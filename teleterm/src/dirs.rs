@@ -1,4 +1,5 @@
 use crate::prelude::*;
+use std::os::unix::fs::DirBuilderExt as _;
 
 pub struct Dirs {
     project_dirs: Option<directories::ProjectDirs>,
@@ -11,13 +12,17 @@ impl Dirs {
         }
     }
 
+    // the data dir holds cached login credentials, so it shouldn't be
+    // readable by anyone else
     pub fn create_all(&self) -> Result<()> {
         if let Some(filename) = self.data_dir() {
-            std::fs::create_dir_all(filename).with_context(|| {
-                crate::error::CreateDir {
+            std::fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(filename)
+                .with_context(|| crate::error::CreateDir {
                     filename: filename.to_string_lossy(),
-                }
-            })?;
+                })?;
         }
         Ok(())
     }
@@ -76,6 +81,11 @@ impl Dirs {
         }
     }
 
+    // the directory that data_file returns paths in when must_exist is false
+    pub fn writable_data_dir(&self) -> &std::path::Path {
+        self.data_dir().unwrap_or_else(|| self.global_data_dir())
+    }
+
     pub fn data_file(
         &self,
         name: &str,
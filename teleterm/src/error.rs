@@ -4,6 +4,9 @@ pub enum Error {
     #[snafu(display("failed to accept: {}", source))]
     Acceptor { source: tokio::io::Error },
 
+    #[snafu(display("invalid username or password"))]
+    AuthenticationFailed,
+
     #[snafu(display(
         "oauth configuration for auth type {:?} not found",
         ty
@@ -22,6 +25,11 @@ pub enum Error {
         source: tokio::io::Error,
     },
 
+    #[snafu(display("failed to run blocking task: {}", source))]
+    BlockingTask {
+        source: tokio_threadpool::BlockingError,
+    },
+
     #[snafu(display("config file {} doesn't exist", name))]
     ConfigFileDoesntExist { name: String },
 
@@ -50,9 +58,15 @@ pub enum Error {
     #[snafu(display("failed to create tls acceptor: {}", source))]
     CreateAcceptor { source: native_tls::Error },
 
+    #[snafu(display("failed to create tls certificate: {}", source))]
+    CreateCertificate { source: native_tls::Error },
+
     #[snafu(display("failed to create tls connector: {}", source))]
     CreateConnector { source: native_tls::Error },
 
+    #[snafu(display("failed to create pkcs12 bundle: {}", source))]
+    CreatePkcs12 { source: openssl::error::ErrorStack },
+
     #[snafu(display("failed to create directory {}: {}", filename, source))]
     CreateDir {
         filename: String,
@@ -71,9 +85,29 @@ pub enum Error {
         source: std::io::Error,
     },
 
+    #[snafu(display("failed to decrypt cached token: {}", source))]
+    DecryptToken { source: openssl::error::ErrorStack },
+
+    #[snafu(display("encrypted token cache file was truncated"))]
+    DecryptTokenTruncated,
+
+    #[snafu(display("failed to disable mouse reporting: {}", source))]
+    DisableMouseMode { source: crossterm::ErrorKind },
+
     #[snafu(display("received EOF from server"))]
     EOF,
 
+    #[snafu(display("failed to enable mouse reporting: {}", source))]
+    EnableMouseMode { source: crossterm::ErrorKind },
+
+    #[snafu(display("failed to encrypt token: {}", source))]
+    EncryptToken { source: openssl::error::ErrorStack },
+
+    #[snafu(display(
+        "encrypted ssh private keys are not supported (add the key to ssh-agent instead)"
+    ))]
+    EncryptedSshKey,
+
     #[snafu(display(
         "failed to retrieve access token from authorization code: {:?}",
         msg
@@ -89,6 +123,12 @@ pub enum Error {
         // >
     },
 
+    #[snafu(display(
+        "failed to retrieve access token from device code: {:?}",
+        msg
+    ))]
+    ExchangeDeviceCode { msg: String },
+
     #[snafu(display(
         "failed to retrieve access token from refresh token: {:?}",
         msg
@@ -110,9 +150,17 @@ pub enum Error {
     ))]
     ExtraMessageData { data: Vec<u8> },
 
+    #[snafu(display(
+        "couldn't find the pty for the command, so input at password prompts can't be masked"
+    ))]
+    FindPty,
+
     #[snafu(display("failed to write to stdout: {}", source))]
     FlushTerminal { source: tokio::io::Error },
 
+    #[snafu(display("failed to get peer certificate: {}", source))]
+    GetPeerCertificate { source: native_tls::Error },
+
     #[snafu(display(
         "failed to get recurse center profile data: {}",
         source
@@ -122,9 +170,38 @@ pub enum Error {
     #[snafu(display("failed to get terminal size: {}", source))]
     GetTerminalSize { source: crossterm::ErrorKind },
 
+    #[snafu(display(
+        "terminal size {}x{} is too large to render as a gif",
+        cols,
+        rows
+    ))]
+    GifTooLarge { rows: u16, cols: u16 },
+
     #[snafu(display("failed to find any resolvable addresses"))]
     HasResolvedAddr,
 
+    #[snafu(display("failed to hash password: {}", source))]
+    HashPassword { source: argon2::Error },
+
+    #[snafu(display(
+        "client protocol version {} doesn't match server version {}",
+        client,
+        server
+    ))]
+    IncompatibleProtocolVersion { client: u8, server: u8 },
+
+    #[snafu(display("{} is set, but {} is not", set, missing))]
+    IncompleteTlsIdentity {
+        set: &'static str,
+        missing: &'static str,
+    },
+
+    #[snafu(display("invalid asciicast terminal size: {}", size))]
+    InvalidAsciicastSize { size: String },
+
+    #[snafu(display("invalid asciicast event time: {}", time))]
+    InvalidAsciicastTime { time: f64 },
+
     #[snafu(display("invalid auth client {}", ty))]
     InvalidAuthClient { ty: u8 },
 
@@ -140,9 +217,67 @@ pub enum Error {
     #[snafu(display("invalid message type {}", ty))]
     InvalidMessageType { ty: u8 },
 
+    #[snafu(display("{} must be a positive number (got {})", name, ratio))]
+    InvalidPlaybackRatio { name: String, ratio: f32 },
+
+    #[snafu(display(
+        "invalid profile name {} (must only contain letters, numbers, '-', and '_')",
+        name
+    ))]
+    InvalidProfile { name: String },
+
+    #[snafu(display("invalid recording format: {}", value))]
+    InvalidRecordingFormat { value: String },
+
+    #[snafu(display("invalid render format: {}", value))]
+    InvalidRenderFormat { value: String },
+
+    #[snafu(display(
+        "invalid role {} (expected watcher, streamer, or admin)",
+        value
+    ))]
+    InvalidRole { value: String },
+
+    #[snafu(display("{} must be greater than zero", name))]
+    InvalidRotation { name: String },
+
+    #[snafu(display(
+        "invalid samesite policy {} (expected strict or lax)",
+        value
+    ))]
+    InvalidSameSite { value: String },
+
+    #[snafu(display(
+        "invalid session backend {} (expected disk, memory, or sqlite)",
+        value
+    ))]
+    InvalidSessionBackend { value: String },
+
+    #[snafu(display("invalid response from ssh-agent"))]
+    InvalidSshAgentResponse,
+
+    #[snafu(display("invalid ssh private key file"))]
+    InvalidSshKey,
+
+    #[snafu(display("token encryption key must be 32 bytes"))]
+    InvalidTokenKey,
+
     #[snafu(display("invalid watch id {}", id))]
     InvalidWatchId { id: String },
 
+    #[snafu(display(
+        "failed to look up key in keyring: {}: {}",
+        status,
+        stderr
+    ))]
+    KeyringLookup {
+        status: std::process::ExitStatus,
+        stderr: String,
+    },
+
+    #[snafu(display("failed to store key in keyring: {}", status))]
+    KeyringStore { status: std::process::ExitStatus },
+
     #[snafu(display(
         "packet length must be at least {} bytes (got {})",
         expected,
@@ -157,15 +292,56 @@ pub enum Error {
     ))]
     LenTooBig { len: u32, expected: usize },
 
+    #[snafu(display("server did not send a tls certificate"))]
+    MissingPeerCertificate,
+
+    #[snafu(display("no certificates found in pem certificate file"))]
+    MissingPemCertificate,
+
     #[snafu(display("couldn't find name in argv"))]
     MissingArgv,
 
+    #[snafu(display(
+        "no credentials file configured (see the credentials_file option)"
+    ))]
+    MissingCredentialsFile,
+
+    #[snafu(display("authorized_keys_file is set, but server_name is not"))]
+    MissingServerName,
+
+    #[snafu(display(
+        "no ssh key found (set ssh_key_file or add a key to ssh-agent)"
+    ))]
+    MissingSshKey,
+
+    #[snafu(display(
+        "no api token found (set token_file or the TELETERM_TOKEN environment variable)"
+    ))]
+    MissingToken,
+
+    #[snafu(display(
+        "no tokens file configured (see the tokens_file option)"
+    ))]
+    MissingTokensFile,
+
+    #[snafu(display(
+        "{} is encrypted, but no token encryption key is configured",
+        filename
+    ))]
+    MissingTokenKey { filename: String },
+
+    #[snafu(display("no recordings found in {}", dir))]
+    NoRecordings { dir: String },
+
     #[snafu(display(
         "detected argv path {} was not a valid filename",
         path
     ))]
     NotAFileName { path: String },
 
+    #[snafu(display("user {} is not allowed to {}", username, action))]
+    NotAllowed { username: String, action: String },
+
     #[snafu(display(
         "missing oauth configuration item {} for section oauth.{}.{}",
         field,
@@ -178,6 +354,14 @@ pub enum Error {
         auth_client: crate::protocol::AuthClient,
     },
 
+    #[snafu(display("device code expired before login was completed"))]
+    OauthDeviceCodeExpired,
+
+    #[snafu(display(
+        "the oauth redirect port can only be changed for localhost redirect urls"
+    ))]
+    OauthRedirectNotLoopback,
+
     #[snafu(display("failed to open file {}: {}", filename, source))]
     OpenFile {
         filename: String,
@@ -193,6 +377,16 @@ pub enum Error {
     #[snafu(display("failed to open link in browser: {}", source))]
     OpenLink { source: std::io::Error },
 
+    #[snafu(display(
+        "failed to open session database {}: {}",
+        filename,
+        source
+    ))]
+    OpenSessionDatabase {
+        filename: String,
+        source: rusqlite::Error,
+    },
+
     #[snafu(display("failed to parse address"))]
     ParseAddress,
 
@@ -202,6 +396,9 @@ pub enum Error {
     #[snafu(display("{}", source))]
     ParseArgs { source: clap::Error },
 
+    #[snafu(display("failed to parse asciicast: {}", source))]
+    ParseAsciicast { source: serde_json::Error },
+
     #[snafu(display("failed to parse buffer size {}: {}", input, source))]
     ParseBufferSize {
         input: String,
@@ -211,6 +408,12 @@ pub enum Error {
     #[snafu(display("failed to parse config file: {}", source))]
     ParseConfigFile { source: config::ConfigError },
 
+    #[snafu(display(
+        "failed to parse escape key {} (expected a control character like ^])",
+        key
+    ))]
+    ParseEscapeKey { key: String },
+
     #[snafu(display("failed to parse incoming http request"))]
     ParseHttpRequest,
 
@@ -249,12 +452,28 @@ pub enum Error {
         source: std::num::ParseFloatError,
     },
 
+    #[snafu(display(
+        "failed to parse idle threshold {}: {}",
+        input,
+        source
+    ))]
+    ParseIdleThreshold {
+        input: String,
+        source: std::num::ParseIntError,
+    },
+
     #[snafu(display("failed to parse response json: {}", source))]
     ParseJson { source: reqwest::Error },
 
     #[snafu(display("failed to parse max frame length: {}", source))]
     ParseMaxFrameLength { source: std::num::ParseIntError },
 
+    #[snafu(display("failed to parse pem certificate file: {}", source))]
+    ParsePemCertificate { source: openssl::error::ErrorStack },
+
+    #[snafu(display("failed to parse pem key file: {}", source))]
+    ParsePemKey { source: openssl::error::ErrorStack },
+
     #[snafu(display(
         "failed to parse port {} from address: {}",
         string,
@@ -271,12 +490,69 @@ pub enum Error {
         source: std::num::ParseIntError,
     },
 
+    #[snafu(display("failed to parse regex {}: {}", regex, source))]
+    ParseRegex { regex: String, source: regex::Error },
+
+    #[snafu(display(
+        "failed to parse rotation duration {}: {}",
+        input,
+        source
+    ))]
+    ParseRotateDuration {
+        input: String,
+        source: std::num::ParseIntError,
+    },
+
+    #[snafu(display("failed to parse rotation size {}: {}", input, source))]
+    ParseRotateSize {
+        input: String,
+        source: std::num::ParseIntError,
+    },
+
+    #[snafu(display("failed to parse session ttl {}: {}", input, source))]
+    ParseSessionTtl {
+        input: String,
+        source: std::num::ParseIntError,
+    },
+
+    #[snafu(display("failed to parse ssh private key: {}", source))]
+    ParseSshKey { source: openssl::error::ErrorStack },
+
     #[snafu(display("failed to parse string {:?}: {}", string, source))]
     ParseString {
         string: Vec<u8>,
         source: std::string::FromUtf8Error,
     },
 
+    #[snafu(display("failed to parse time {}: {}", input, source))]
+    ParseTimeRange {
+        input: String,
+        source: std::num::ParseIntError,
+    },
+
+    #[snafu(display("failed to parse token cache file: {}", source))]
+    ParseTokenCache { source: base64::DecodeError },
+
+    #[snafu(display("failed to parse token encryption key: {}", source))]
+    ParseTokenKey { source: base64::DecodeError },
+
+    #[snafu(display(
+        "password logins are only allowed over tls connections"
+    ))]
+    PasswordLoginRequiresTls,
+
+    #[snafu(display("passwords do not match"))]
+    PasswordMismatch,
+
+    #[snafu(display("failed to query session database: {}", source))]
+    QuerySessionDatabase { source: rusqlite::Error },
+
+    #[snafu(display("recording ended in the middle of a frame"))]
+    RecordingTruncated,
+
+    #[snafu(display("failed to request device authorization: {}", source))]
+    RequestDeviceAuthorization { source: reqwest::Error },
+
     #[snafu(display("rate limit exceeded"))]
     RateLimited,
 
@@ -285,6 +561,12 @@ pub enum Error {
         source: tokio::sync::mpsc::error::UnboundedRecvError,
     },
 
+    #[snafu(display("failed to read directory {}: {}", filename, source))]
+    ReadDirSync {
+        filename: String,
+        source: std::io::Error,
+    },
+
     #[snafu(display("failed to read from file: {}", source))]
     ReadFile { source: tokio::io::Error },
 
@@ -300,6 +582,9 @@ pub enum Error {
     #[snafu(display("failed to read packet: {}", source))]
     ReadPacket { source: tokio::io::Error },
 
+    #[snafu(display("failed to read password: {}", source))]
+    ReadPassword { source: std::io::Error },
+
     #[snafu(display("failed to read from socket: {}", source))]
     ReadSocket { source: tokio::io::Error },
 
@@ -309,6 +594,12 @@ pub enum Error {
     #[snafu(display("failed to read ttyrec: {}", source))]
     ReadTtyrec { source: ttyrec::Error },
 
+    #[snafu(display("failed to remove file {}: {}", filename, source))]
+    RemoveFileSync {
+        filename: String,
+        source: std::io::Error,
+    },
+
     #[snafu(display("failed to poll for terminal resizing: {}", source))]
     Resize {
         source: tokio_terminal_resize::Error,
@@ -326,6 +617,18 @@ pub enum Error {
         source: std::io::Error,
     },
 
+    #[snafu(display(
+        "filename {} must contain %N to rotate recordings",
+        filename
+    ))]
+    RotateWithoutSequence { filename: String },
+
+    #[snafu(display("failed to run secret-tool: {}", source))]
+    RunKeyring { source: std::io::Error },
+
+    #[snafu(display("failed to seek in file: {}", source))]
+    SeekFile { source: std::io::Error },
+
     #[snafu(display("failed to serialize message as json: {}", source))]
     SerializeMessage { source: serde_json::Error },
 
@@ -335,6 +638,19 @@ pub enum Error {
     #[snafu(display("couldn't connect to server"))]
     ServerDisconnected,
 
+    #[snafu(display(
+        "failed to set permissions on {}: {}",
+        filename,
+        source
+    ))]
+    SetPermissions {
+        filename: String,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("SIGHUP handler failed: {}", source))]
+    SigHupHandler { source: std::io::Error },
+
     #[snafu(display("SIGWINCH handler failed: {}", source))]
     SigWinchHandler { source: std::io::Error },
 
@@ -354,6 +670,15 @@ pub enum Error {
         source: tokio::sync::mpsc::error::RecvError,
     },
 
+    #[snafu(display("failed to communicate with ssh-agent: {}", source))]
+    SshAgent { source: std::io::Error },
+
+    #[snafu(display("ssh-agent failed to handle the request"))]
+    SshAgentFailure,
+
+    #[snafu(display("failed to use ssh key: {}", source))]
+    SshKeyCrypto { source: openssl::error::ErrorStack },
+
     #[snafu(display("poll subprocess failed: {}", source))]
     Subprocess {
         source: tokio_pty_process_stream::Error,
@@ -380,15 +705,46 @@ pub enum Error {
     #[snafu(display("timeout"))]
     Timeout,
 
+    #[snafu(display("device authorization poll timer failed: {}", source))]
+    TimerDevicePoll { source: tokio::timer::Error },
+
     #[snafu(display("heartbeat timer failed: {}", source))]
     TimerHeartbeat { source: tokio::timer::Error },
 
     #[snafu(display("read timeout timer failed: {}", source))]
     TimerReadTimeout { source: tokio::timer::Error },
 
+    #[snafu(display("tls identity reload timer failed: {}", source))]
+    TimerReloadIdentity { source: tokio::timer::Error },
+
     #[snafu(display("reconnect timer failed: {}", source))]
     TimerReconnect { source: tokio::timer::Error },
 
+    #[snafu(display("rotation timer failed: {}", source))]
+    TimerRotate { source: tokio::timer::Error },
+
+    #[snafu(display("session gc timer failed: {}", source))]
+    TimerSessionGc { source: tokio::timer::Error },
+
+    #[snafu(display(
+        "tls certificate for {} (sha256 fingerprint {}) does not match any pinned certificate",
+        host,
+        fingerprint
+    ))]
+    TlsCertificatePinMismatch { host: String, fingerprint: String },
+
+    #[snafu(display(
+        "tls certificate for {} (sha256 fingerprint {}) does not match the previously trusted certificate in {}",
+        host,
+        fingerprint,
+        filename
+    ))]
+    TlsKnownHostMismatch {
+        host: String,
+        fingerprint: String,
+        filename: String,
+    },
+
     #[snafu(display("failed to switch to alternate screen: {}", source))]
     ToAlternateScreen { source: crossterm::ErrorKind },
 
@@ -398,12 +754,29 @@ pub enum Error {
     ))]
     ToRawMode { source: crossterm::ErrorKind },
 
+    #[snafu(display("too many failed logins, try again later"))]
+    TooManyFailedLogins,
+
     #[snafu(display("unauthenticated message: {:?}", message))]
     UnauthenticatedMessage { message: crate::protocol::Message },
 
     #[snafu(display("unexpected message: {:?}", message))]
     UnexpectedMessage { message: crate::protocol::Message },
 
+    #[snafu(display(
+        "couldn't determine output format for {} (use --output-format)",
+        filename
+    ))]
+    UnknownRenderFormat { filename: String },
+
+    #[snafu(display("unsupported asciicast version: {}", version))]
+    UnsupportedAsciicastVersion { version: u32 },
+
+    #[snafu(display(
+        "unsupported ssh key type (only ed25519 and rsa keys are supported)"
+    ))]
+    UnsupportedSshKeyType,
+
     #[snafu(display("failed to find group with gid {}", gid))]
     UnknownGid { gid: users::gid_t },
 
@@ -447,9 +820,6 @@ pub enum Error {
 
     #[snafu(display("failed to write to terminal: {}", source))]
     WriteTerminalCrossterm { source: crossterm::ErrorKind },
-
-    #[snafu(display("failed to write ttyrec: {}", source))]
-    WriteTtyrec { source: ttyrec::Error },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
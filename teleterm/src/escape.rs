@@ -0,0 +1,150 @@
+use std::io::Read as _;
+use tokio::io::AsyncRead as _;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    // a key which should be passed through to the running command
+    Input(u8),
+    // a key which was typed after the escape key
+    Command(u8),
+}
+
+// tracks escape key sequences typed by the user. pressing the escape key
+// twice sends the escape key itself through to the running command.
+pub struct Filter {
+    escape_key: u8,
+    escaped: bool,
+}
+
+impl Filter {
+    pub fn new(escape_key: u8) -> Self {
+        Self {
+            escape_key,
+            escaped: false,
+        }
+    }
+
+    // returns None for the escape key itself, since what it means depends
+    // on the key after it
+    pub fn key(&mut self, c: u8) -> Option<Key> {
+        if self.escaped {
+            self.escaped = false;
+            if c == self.escape_key {
+                Some(Key::Input(c))
+            } else {
+                Some(Key::Command(c))
+            }
+        } else if c == self.escape_key {
+            self.escaped = true;
+            None
+        } else {
+            Some(Key::Input(c))
+        }
+    }
+}
+
+pub trait Process {
+    // handles any escape sequences in input, and appends the bytes which
+    // should be passed through to the running command to output
+    fn process(
+        &mut self,
+        input: &[u8],
+        output: &mut std::collections::VecDeque<u8>,
+    );
+}
+
+// stdin, with escape sequences pulled out of it before it reaches the
+// running command
+pub struct Input<P: Process> {
+    stdin: crate::async_stdin::Stdin,
+    process: P,
+    // filtering can produce more bytes than were read, so they may not all
+    // fit into the caller's buffer at once
+    buffered: std::collections::VecDeque<u8>,
+}
+
+impl<P: Process> Input<P> {
+    pub fn new(process: P) -> Self {
+        Self {
+            // TODO: tokio::io::stdin is broken (it's blocking)
+            // see https://github.com/tokio-rs/tokio/issues/589
+            // stdin: tokio::io::stdin(),
+            stdin: crate::async_stdin::Stdin::new(),
+            process,
+            buffered: std::collections::VecDeque::new(),
+        }
+    }
+
+    fn take_buffered(&mut self, buf: &mut [u8]) -> usize {
+        let n = buf.len().min(self.buffered.len());
+        for (dst, src) in buf.iter_mut().zip(self.buffered.drain(..n)) {
+            *dst = src;
+        }
+        n
+    }
+}
+
+impl<P: Process> std::io::Read for Input<P> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            if !self.buffered.is_empty() {
+                return Ok(self.take_buffered(buf));
+            }
+            let n = self.stdin.read(buf)?;
+            if n == 0 {
+                return Ok(0);
+            }
+            self.process.process(&buf[..n], &mut self.buffered);
+        }
+    }
+}
+
+impl<P: Process> tokio::io::AsyncRead for Input<P> {
+    fn poll_read(
+        &mut self,
+        buf: &mut [u8],
+    ) -> std::result::Result<futures::Async<usize>, tokio::io::Error> {
+        // if everything we read was part of an escape sequence, we can't
+        // return a zero-length read (since that would signal eof), so keep
+        // reading until we get either real input or NotReady
+        loop {
+            if !self.buffered.is_empty() {
+                return Ok(futures::Async::Ready(self.take_buffered(buf)));
+            }
+            let n = futures::try_ready!(self.stdin.poll_read(buf));
+            if n == 0 {
+                return Ok(futures::Async::Ready(0));
+            }
+            self.process.process(&buf[..n], &mut self.buffered);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn keys(filter: &mut Filter, input: &[u8]) -> Vec<Key> {
+        input.iter().filter_map(|&c| filter.key(c)).collect()
+    }
+
+    #[test]
+    fn test_filter() {
+        let mut f = Filter::new(b'\x1d');
+        assert_eq!(
+            keys(&mut f, b"ab"),
+            vec![Key::Input(b'a'), Key::Input(b'b')]
+        );
+        assert_eq!(
+            keys(&mut f, b"a\x1dmb"),
+            vec![Key::Input(b'a'), Key::Command(b'm'), Key::Input(b'b')]
+        );
+        assert_eq!(keys(&mut f, b"\x1d\x1d"), vec![Key::Input(b'\x1d')]);
+        // escape sequences can be split across reads
+        assert_eq!(keys(&mut f, b"a\x1d"), vec![Key::Input(b'a')]);
+        assert_eq!(
+            keys(&mut f, b"pb"),
+            vec![Key::Command(b'p'), Key::Input(b'b')]
+        );
+    }
+}
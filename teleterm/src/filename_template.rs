@@ -0,0 +1,191 @@
+// recording filenames can contain these, so that each file in a series of
+// rotated recordings (or each run of a recording started on a schedule)
+// gets its own name:
+//
+//   %Y, %m, %d: the local date that the file was started on
+//   %H, %M, %S: the local time that the file was started at
+//   %N: a sequence number, starting at 001
+//   %%: a literal %
+//
+// anything else after a % is left as is, so that filenames which happen to
+// contain a % still work when rotation isn't being used
+#[derive(Debug, Clone, PartialEq)]
+enum Item {
+    Literal(String),
+    Time(&'static str),
+    Sequence,
+}
+
+pub struct FilenameTemplate {
+    items: Vec<Item>,
+}
+
+impl FilenameTemplate {
+    pub fn new(template: &str) -> Self {
+        let mut items = vec![];
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                literal.push(c);
+                continue;
+            }
+            let item = match chars.next() {
+                Some('%') => {
+                    literal.push('%');
+                    continue;
+                }
+                Some('Y') => Item::Time("%Y"),
+                Some('m') => Item::Time("%m"),
+                Some('d') => Item::Time("%d"),
+                Some('H') => Item::Time("%H"),
+                Some('M') => Item::Time("%M"),
+                Some('S') => Item::Time("%S"),
+                Some('N') => Item::Sequence,
+                Some(c) => {
+                    literal.push('%');
+                    literal.push(c);
+                    continue;
+                }
+                None => {
+                    literal.push('%');
+                    continue;
+                }
+            };
+            if !literal.is_empty() {
+                items.push(Item::Literal(std::mem::replace(
+                    &mut literal,
+                    String::new(),
+                )));
+            }
+            items.push(item);
+        }
+        if !literal.is_empty() {
+            items.push(Item::Literal(literal));
+        }
+        Self { items }
+    }
+
+    pub fn has_sequence(&self) -> bool {
+        self.items.iter().any(|item| *item == Item::Sequence)
+    }
+
+    pub fn render<Tz: chrono::TimeZone>(
+        &self,
+        time: &chrono::DateTime<Tz>,
+        seq: u32,
+    ) -> String
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        self.items
+            .iter()
+            .map(|item| match item {
+                Item::Literal(s) => s.clone(),
+                Item::Time(fmt) => time.format(fmt).to_string(),
+                Item::Sequence => format!("{:03}", seq),
+            })
+            .collect()
+    }
+
+    // the file to start recording to. when appending, this is the last file
+    // in the sequence which already exists, so that a restarted recording
+    // picks up where it left off, and otherwise it is the first one which
+    // doesn't exist yet, so that existing recordings aren't overwritten.
+    pub fn first(&self, append: bool) -> (String, u32) {
+        let now = chrono::Local::now();
+        if append {
+            let mut seq = 1;
+            while self.has_sequence()
+                && std::path::Path::new(&self.render(&now, seq + 1)).exists()
+            {
+                seq += 1;
+            }
+            (self.render(&now, seq), seq)
+        } else {
+            self.unused(&now, 1)
+        }
+    }
+
+    // the file to rotate to after the file with the given sequence number
+    pub fn next(&self, seq: u32) -> (String, u32) {
+        self.unused(&chrono::Local::now(), seq + 1)
+    }
+
+    fn unused(
+        &self,
+        now: &chrono::DateTime<chrono::Local>,
+        mut seq: u32,
+    ) -> (String, u32) {
+        // without a sequence number, there is only one possible filename
+        // for a given time
+        while self.has_sequence()
+            && std::path::Path::new(&self.render(now, seq)).exists()
+        {
+            seq += 1;
+        }
+        (self.render(now, seq), seq)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone as _;
+
+    #[test]
+    fn test_render() {
+        let time = chrono::Utc.ymd(2019, 11, 5).and_hms(9, 4, 30);
+        let template =
+            FilenameTemplate::new("console-%Y-%m-%d_%H%M%S.%N.ttyrec");
+        assert!(template.has_sequence());
+        assert_eq!(
+            template.render(&time, 7),
+            "console-2019-11-05_090430.007.ttyrec"
+        );
+
+        let template = FilenameTemplate::new("100%%-%d.cast");
+        assert!(!template.has_sequence());
+        assert_eq!(template.render(&time, 1), "100%-05.cast");
+
+        let template = FilenameTemplate::new("plain.ttyrec");
+        assert_eq!(template.render(&time, 3), "plain.ttyrec");
+
+        let template = FilenameTemplate::new("odd-%q-100%");
+        assert!(!template.has_sequence());
+        assert_eq!(template.render(&time, 1), "odd-%q-100%");
+    }
+
+    #[test]
+    fn test_first_next() {
+        let dir = std::env::temp_dir().join(format!(
+            "teleterm-test-filename-template-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+
+        let template = FilenameTemplate::new(&path("rec-%N.ttyrec"));
+        assert_eq!(template.first(false), (path("rec-001.ttyrec"), 1));
+        assert_eq!(template.first(true), (path("rec-001.ttyrec"), 1));
+        assert_eq!(template.next(1), (path("rec-002.ttyrec"), 2));
+
+        std::fs::write(path("rec-001.ttyrec"), b"").unwrap();
+        std::fs::write(path("rec-002.ttyrec"), b"").unwrap();
+        std::fs::write(path("rec-004.ttyrec"), b"").unwrap();
+        // new recordings never overwrite existing files
+        assert_eq!(template.first(false), (path("rec-003.ttyrec"), 3));
+        assert_eq!(template.next(2), (path("rec-003.ttyrec"), 3));
+        assert_eq!(template.next(3), (path("rec-005.ttyrec"), 5));
+        // appending continues the last file of the contiguous sequence
+        assert_eq!(template.first(true), (path("rec-002.ttyrec"), 2));
+
+        // without %N, there is only ever one file to use
+        let template = FilenameTemplate::new(&path("plain.ttyrec"));
+        std::fs::write(path("plain.ttyrec"), b"").unwrap();
+        assert_eq!(template.first(false), (path("plain.ttyrec"), 1));
+        assert_eq!(template.first(true), (path("plain.ttyrec"), 1));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
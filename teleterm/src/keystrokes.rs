@@ -0,0 +1,271 @@
+use crate::prelude::*;
+use std::io::{Read as _, Write as _};
+use std::os::unix::fs::{OpenOptionsExt as _, PermissionsExt as _};
+
+// keystrokes recorded alongside a ttyrec are kept in a separate file next to
+// it, so that the recording itself stays readable by other ttyrec tools.
+// each line is an asciicast input event, so the same data can be stored
+// directly in asciicast recordings.
+const EXTENSION: &str = "input";
+
+// a sequence of input which was typed while the running command had echo
+// turned off is recorded as this, with each character replaced
+const MASK: char = '*';
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keystroke {
+    pub time: std::time::Duration,
+    pub data: Vec<u8>,
+}
+
+#[derive(Default)]
+pub struct Keystrokes {
+    keystrokes: Vec<Keystroke>,
+}
+
+impl Keystrokes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(ttyrec_filename: &str) -> Result<Self> {
+        let filename = sidecar_filename(ttyrec_filename);
+        let mut keystrokes = Self::new();
+        if std::path::Path::new(&filename).exists() {
+            let mut file = std::fs::File::open(&filename).context(
+                crate::error::OpenFileSync {
+                    filename: filename.clone(),
+                },
+            )?;
+            let mut contents = vec![];
+            file.read_to_end(&mut contents)
+                .context(crate::error::ReadFileSync)?;
+            for line in contents.split(|&c| c == b'\n') {
+                if let Some((time, crate::asciicast::Event::Input(data))) =
+                    crate::asciicast::parse_event(line)?
+                {
+                    keystrokes.add(time, &data);
+                }
+            }
+        }
+        Ok(keystrokes)
+    }
+
+    pub fn list(&self) -> &[Keystroke] {
+        &self.keystrokes
+    }
+
+    pub fn add(&mut self, time: std::time::Duration, data: &[u8]) {
+        let pos = self
+            .keystrokes
+            .iter()
+            .rposition(|keystroke| keystroke.time <= time)
+            .map_or(0, |pos| pos + 1);
+        self.keystrokes.insert(
+            pos,
+            Keystroke {
+                time,
+                data: data.to_vec(),
+            },
+        );
+    }
+
+    // the keystrokes typed in the given window of time before time
+    pub fn recent(
+        &self,
+        time: std::time::Duration,
+        window: std::time::Duration,
+    ) -> &[Keystroke] {
+        let start = self
+            .keystrokes
+            .iter()
+            .position(|keystroke| keystroke.time + window > time)
+            .unwrap_or_else(|| self.keystrokes.len());
+        let end = self
+            .keystrokes
+            .iter()
+            .position(|keystroke| keystroke.time > time)
+            .unwrap_or_else(|| self.keystrokes.len());
+        &self.keystrokes[start..end]
+    }
+}
+
+// appends keystrokes to the sidecar file while recording a ttyrec
+pub struct Log {
+    file: std::fs::File,
+}
+
+impl Log {
+    // when not appending, any existing keystrokes are replaced, since
+    // they would point at unrelated parts of the new recording. the file is
+    // only readable by its owner, since it contains everything typed during
+    // the session.
+    pub fn open(ttyrec_filename: &str, append: bool) -> Result<Self> {
+        let filename = sidecar_filename(ttyrec_filename);
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(append)
+            .truncate(!append)
+            .mode(0o600)
+            .open(&filename)
+            .context(crate::error::CreateFileSync {
+                filename: filename.clone(),
+            })?;
+        // the mode only applies to newly created files
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .context(crate::error::SetPermissions { filename })?;
+        Ok(Self { file })
+    }
+
+    pub fn append(
+        &mut self,
+        time: std::time::Duration,
+        data: &[u8],
+    ) -> Result<()> {
+        self.file
+            .write_all(&crate::asciicast::format_event(
+                time,
+                &crate::asciicast::Event::Input(data.to_vec()),
+            ))
+            .context(crate::error::WriteFileSync)
+    }
+}
+
+// keystrokes from a previous recording to the same file would point at
+// unrelated parts of the new recording
+pub fn clear(ttyrec_filename: &str) -> Result<()> {
+    let filename = sidecar_filename(ttyrec_filename);
+    match std::fs::remove_file(&filename) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).context(crate::error::RemoveFileSync { filename }),
+    }
+}
+
+pub fn sidecar_filename(ttyrec_filename: &str) -> String {
+    format!("{}.{}", ttyrec_filename, EXTENSION)
+}
+
+// hides what was typed while still showing that keys were pressed. control
+// characters are left alone, since they are what ends a password prompt.
+pub fn mask(data: &[u8]) -> Vec<u8> {
+    String::from_utf8_lossy(data)
+        .chars()
+        .map(|c| if c.is_control() { c } else { MASK })
+        .collect::<String>()
+        .into_bytes()
+}
+
+// a short human readable description of a chunk of input, like what
+// screencast tools show
+pub fn describe(data: &[u8]) -> String {
+    match data {
+        b"\r" | b"\n" => return "⏎".to_string(),
+        b"\t" => return "⇥".to_string(),
+        b"\x7f" | b"\x08" => return "⌫".to_string(),
+        b"\x1b" => return "Esc".to_string(),
+        b"\x1b[A" | b"\x1bOA" => return "↑".to_string(),
+        b"\x1b[B" | b"\x1bOB" => return "↓".to_string(),
+        b"\x1b[C" | b"\x1bOC" => return "→".to_string(),
+        b"\x1b[D" | b"\x1bOD" => return "←".to_string(),
+        _ => {}
+    }
+
+    let mut desc = String::new();
+    for c in String::from_utf8_lossy(data).chars() {
+        match c {
+            '\r' | '\n' => desc.push('⏎'),
+            '\t' => desc.push('⇥'),
+            '\x7f' | '\x08' => desc.push('⌫'),
+            '\x1b' => desc.push_str("Esc "),
+            '\x00'..='\x1f' => {
+                desc.push('^');
+                desc.push(std::char::from_u32(u32::from(c) + 0x40).unwrap());
+            }
+            c if c.is_control() => {}
+            c => desc.push(c),
+        }
+    }
+    desc
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_recent() {
+        let secs = std::time::Duration::from_secs;
+        let mut keystrokes = Keystrokes::new();
+        keystrokes.add(secs(1), b"a");
+        keystrokes.add(secs(5), b"c");
+        keystrokes.add(secs(3), b"b");
+        let data = |keys: &[Keystroke]| -> Vec<Vec<u8>> {
+            keys.iter().map(|key| key.data.clone()).collect()
+        };
+        assert_eq!(
+            data(keystrokes.recent(secs(0), secs(2))),
+            Vec::<Vec<u8>>::new()
+        );
+        assert_eq!(
+            data(keystrokes.recent(secs(3), secs(3))),
+            vec![b"a".to_vec(), b"b".to_vec()]
+        );
+        assert_eq!(
+            data(keystrokes.recent(secs(4), secs(2))),
+            vec![b"b".to_vec()]
+        );
+        assert_eq!(
+            data(keystrokes.recent(secs(10), secs(2))),
+            Vec::<Vec<u8>>::new()
+        );
+    }
+
+    #[test]
+    fn test_mask() {
+        assert_eq!(mask(b"hunter2\r"), b"*******\r".to_vec());
+        assert_eq!(mask("pä\x7f".as_bytes()), b"**\x7f".to_vec());
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(describe(b"ls -l\r"), "ls -l⏎");
+        assert_eq!(describe(b"\x1b[A"), "↑");
+        assert_eq!(describe(b"\x03"), "^C");
+        assert_eq!(describe(b"\x1b:wq\r"), "Esc :wq⏎");
+        assert_eq!(describe(b"a\x7f"), "a⌫");
+    }
+
+    #[test]
+    fn test_log() {
+        let ttyrec_filename = std::env::temp_dir()
+            .join(format!("teleterm-test-log-{}", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        let filename = sidecar_filename(&ttyrec_filename);
+        std::fs::write(&filename, b"").unwrap();
+        std::fs::set_permissions(
+            &filename,
+            std::fs::Permissions::from_mode(0o644),
+        )
+        .unwrap();
+
+        let mut log = Log::open(&ttyrec_filename, true).unwrap();
+        log.append(std::time::Duration::from_secs(1), b"ls\r")
+            .unwrap();
+        drop(log);
+        let mode = std::fs::metadata(&filename).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let keystrokes = Keystrokes::load(&ttyrec_filename).unwrap();
+        assert_eq!(
+            keystrokes.list(),
+            &[Keystroke {
+                time: std::time::Duration::from_secs(1),
+                data: b"ls\r".to_vec(),
+            }][..]
+        );
+        std::fs::remove_file(&filename).unwrap();
+    }
+}
@@ -17,16 +17,24 @@ const _DUMMY_DEPENDENCY: &str = include_str!("../Cargo.toml");
 
 mod prelude;
 
+mod asciicast;
 mod async_stdin;
 mod auth;
+mod bookmarks;
 mod client;
 mod cmd;
 mod config;
 mod dirs;
 mod error;
+mod escape;
+mod filename_template;
 mod key_reader;
+mod keystrokes;
 mod oauth;
 mod protocol;
+mod recording;
+mod redact;
+mod render;
 mod server;
 mod session_list;
 mod term;
@@ -1,18 +1,75 @@
 use crate::prelude::*;
 use oauth2::TokenResponse as _;
 
-// this needs to be fixed because we listen for it in a hardcoded place
+// clients listen on this port by default, but if it's not available, they
+// can listen somewhere else and tell us which port they used instead
 pub const CLI_REDIRECT_URL: &str = "http://localhost:44141/oauth";
 
+const DEVICE_CODE_GRANT_TYPE: &str =
+    "urn:ietf:params:oauth:grant-type:device_code";
+const DEFAULT_DEVICE_POLL_INTERVAL: std::time::Duration =
+    std::time::Duration::from_secs(5);
+
 pub struct Oauth {
+    config: Config,
     client: oauth2::basic::BasicClient,
     user_id: String,
 }
 
+// the state of an in progress device authorization grant (rfc 8628)
+#[derive(Debug, Clone)]
+pub struct DeviceAuthorization {
+    device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    interval: std::time::Duration,
+    expires_at: std::time::Instant,
+}
+
+#[derive(serde::Deserialize)]
+struct DeviceAuthorizationResponse {
+    device_code: String,
+    user_code: String,
+    verification_uri: String,
+    verification_uri_complete: Option<String>,
+    expires_in: u64,
+    interval: Option<u64>,
+}
+
+#[derive(serde::Deserialize)]
+struct DeviceTokenResponse {
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+    error: Option<String>,
+}
+
 impl Oauth {
     pub fn new(config: Config, user_id: String) -> Self {
-        let client = config.into_basic_client();
-        Self { client, user_id }
+        let client = config.clone().into_basic_client();
+        Self {
+            config,
+            client,
+            user_id,
+        }
+    }
+
+    // only loopback redirects can be moved, since those are the only ones
+    // where the client is the thing listening on the other end
+    pub fn set_redirect_port(&mut self, port: u16) -> Result<()> {
+        let mut url = self.config.redirect_url.clone();
+        match url.host_str() {
+            Some("localhost") | Some("127.0.0.1") => {}
+            _ => return Err(Error::OauthRedirectNotLoopback),
+        }
+        url.set_port(Some(port))
+            .map_err(|_| Error::OauthRedirectNotLoopback)?;
+        self.config.redirect_url = url;
+        self.client = self.config.clone().into_basic_client();
+        Ok(())
+    }
+
+    pub fn supports_device_authorization(&self) -> bool {
+        self.config.device_authorization_url.is_some()
     }
 
     pub fn generate_authorize_url(&self) -> String {
@@ -27,11 +84,133 @@ impl Oauth {
         &self.user_id
     }
 
+    pub fn request_device_authorization(
+        &self,
+    ) -> Box<
+        dyn futures::Future<Item = DeviceAuthorization, Error = Error> + Send,
+    > {
+        let url = self.config.device_authorization_url.clone().unwrap();
+        let params = [
+            ("client_id", self.config.client_id.clone()),
+            ("client_secret", self.config.client_secret.clone()),
+        ];
+        let fut = reqwest::r#async::Client::new()
+            .post(url)
+            .form(&params)
+            .send()
+            .and_then(reqwest::r#async::Response::error_for_status)
+            .context(crate::error::RequestDeviceAuthorization)
+            .and_then(|mut res| res.json().context(crate::error::ParseJson))
+            .map(|res: DeviceAuthorizationResponse| DeviceAuthorization {
+                device_code: res.device_code,
+                user_code: res.user_code,
+                verification_uri: res
+                    .verification_uri_complete
+                    .unwrap_or(res.verification_uri),
+                interval: res.interval.map_or(
+                    DEFAULT_DEVICE_POLL_INTERVAL,
+                    std::time::Duration::from_secs,
+                ),
+                expires_at: std::time::Instant::now()
+                    + std::time::Duration::from_secs(res.expires_in),
+            });
+        Box::new(fut)
+    }
+
+    // polls the token endpoint until the user finishes authorizing the
+    // device in their browser
+    pub fn get_access_token_from_device_code(
+        &self,
+        device: DeviceAuthorization,
+    ) -> Box<dyn futures::Future<Item = String, Error = Error> + Send> {
+        let token_cache_file = self.server_token_file(false).unwrap();
+        let token_cache = self.config.token_cache.clone();
+        let token_url = self.config.token_url.clone();
+        let params = vec![
+            ("grant_type", DEVICE_CODE_GRANT_TYPE.to_string()),
+            ("device_code", device.device_code.clone()),
+            ("client_id", self.config.client_id.clone()),
+            ("client_secret", self.config.client_secret.clone()),
+        ];
+        let expires_at = device.expires_at;
+        let fut =
+            futures::future::loop_fn(device.interval, move |interval| {
+                let token_url = token_url.clone();
+                let params = params.clone();
+                tokio::timer::Delay::new(std::time::Instant::now() + interval)
+                    .context(crate::error::TimerDevicePoll)
+                    .and_then(move |_| {
+                        reqwest::r#async::Client::new()
+                            .post(token_url)
+                            .form(&params)
+                            .send()
+                            .context(crate::error::RequestDeviceAuthorization)
+                    })
+                    .and_then(|mut res| {
+                        res.json().context(crate::error::ParseJson)
+                    })
+                    .and_then(move |res: DeviceTokenResponse| {
+                        if let Some(access_token) = res.access_token {
+                            return Ok(futures::future::Loop::Break((
+                                access_token,
+                                res.refresh_token,
+                            )));
+                        }
+                        match res
+                            .error
+                            .as_ref()
+                            .map(std::string::String::as_str)
+                        {
+                            Some("authorization_pending") => {}
+                            // the spec says to back off by 5 seconds
+                            Some("slow_down") => {
+                                return Ok(futures::future::Loop::Continue(
+                                    interval + DEFAULT_DEVICE_POLL_INTERVAL,
+                                ));
+                            }
+                            Some("expired_token") => {
+                                return Err(Error::OauthDeviceCodeExpired);
+                            }
+                            e => {
+                                return Err(Error::ExchangeDeviceCode {
+                                    msg: e
+                                        .unwrap_or("no error given")
+                                        .to_string(),
+                                });
+                            }
+                        }
+                        if std::time::Instant::now() >= expires_at {
+                            return Err(Error::OauthDeviceCodeExpired);
+                        }
+                        Ok(futures::future::Loop::Continue(interval))
+                    })
+            })
+            .and_then(move |(access_token, refresh_token)| {
+                // without a refresh token, the user will just need to go
+                // through the device flow again next time
+                let fut: Box<
+                    dyn futures::Future<Item = (), Error = Error> + Send,
+                > = if let Some(refresh_token) = refresh_token {
+                    write_token_cache(
+                        &token_cache,
+                        token_cache_file,
+                        &refresh_token,
+                        &access_token,
+                    )
+                } else {
+                    Box::new(futures::future::ok(()))
+                };
+                fut.map(move |_| access_token)
+            });
+        Box::new(fut)
+    }
+
     pub fn get_access_token_from_auth_code(
         &self,
         code: &str,
     ) -> Box<dyn futures::Future<Item = String, Error = Error> + Send> {
         let token_cache_file = self.server_token_file(false).unwrap();
+        let token_cache = self.config.token_cache.clone();
         let fut = self
             .client
             .exchange_code(oauth2::AuthorizationCode::new(code.to_string()))
@@ -40,8 +219,8 @@ impl Oauth {
                 let msg = stringify_oauth2_http_error(&e);
                 Error::ExchangeCode { msg }
             })
-            .and_then(|token| {
-                cache_refresh_token(token_cache_file, &token)
+            .and_then(move |token| {
+                cache_refresh_token(&token_cache, token_cache_file, &token)
                     .map(move |_| token.access_token().secret().to_string())
             });
         Box::new(fut)
@@ -51,8 +230,9 @@ impl Oauth {
         self,
     ) -> Box<dyn futures::Future<Item = String, Error = Error> + Send> {
         let token_cache_file = self.server_token_file(false).unwrap();
-        let fut = load_refresh_token(&token_cache_file).and_then(
-            move |refresh_token| {
+        let token_cache = self.config.token_cache.clone();
+        let fut = load_refresh_token(&token_cache, &token_cache_file)
+            .and_then(move |refresh_token| {
                 // XXX
                 let refresh_token = refresh_token.unwrap();
                 self.client
@@ -65,14 +245,16 @@ impl Oauth {
                         Error::ExchangeRefreshToken { msg }
                     })
                     .and_then(move |token| {
-                        cache_refresh_token(token_cache_file, &token).map(
-                            move |_| {
-                                token.access_token().secret().to_string()
-                            },
+                        cache_refresh_token(
+                            &token_cache,
+                            token_cache_file,
+                            &token,
                         )
+                        .map(move |_| {
+                            token.access_token().secret().to_string()
+                        })
                     })
-            },
-        );
+            });
         Box::new(fut)
     }
 
@@ -86,43 +268,47 @@ impl Oauth {
 }
 
 fn load_refresh_token(
+    token_cache: &crate::auth::token_cache::TokenCache,
     token_cache_file: &std::path::Path,
 ) -> Box<dyn futures::Future<Item = Option<String>, Error = Error> + Send> {
+    let token_cache = token_cache.clone();
     let token_cache_file = token_cache_file.to_path_buf();
-    Box::new(
-        tokio::fs::File::open(token_cache_file.clone())
-            .with_context(move || crate::error::OpenFile {
-                filename: token_cache_file.to_string_lossy().to_string(),
+    Box::new(futures::future::lazy(move || {
+        token_cache.read(&token_cache_file).map(|contents| {
+            contents.and_then(|contents| {
+                contents
+                    .lines()
+                    .next()
+                    .map(std::string::ToString::to_string)
             })
-            .and_then(|file| {
-                tokio::io::lines(std::io::BufReader::new(file))
-                    .into_future()
-                    .map_err(|(e, _)| e)
-                    .context(crate::error::ReadFile)
-            })
-            .map(|(refresh_token, _)| refresh_token),
-    )
+        })
+    }))
 }
 
 fn cache_refresh_token(
+    token_cache: &crate::auth::token_cache::TokenCache,
     token_cache_file: std::path::PathBuf,
     token: &oauth2::basic::BasicTokenResponse,
 ) -> Box<dyn futures::Future<Item = (), Error = Error> + Send> {
-    let token_data = format!(
-        "{}\n{}\n",
+    write_token_cache(
+        token_cache,
+        token_cache_file,
         token.refresh_token().unwrap().secret(),
         token.access_token().secret(),
-    );
-    let fut = tokio::fs::File::create(token_cache_file.clone())
-        .with_context(move || crate::error::CreateFile {
-            filename: token_cache_file.to_string_lossy().to_string(),
-        })
-        .and_then(|file| {
-            tokio::io::write_all(file, token_data)
-                .context(crate::error::WriteFile)
-        })
-        .map(|_| ());
-    Box::new(fut)
+    )
+}
+
+fn write_token_cache(
+    token_cache: &crate::auth::token_cache::TokenCache,
+    token_cache_file: std::path::PathBuf,
+    refresh_token: &str,
+    access_token: &str,
+) -> Box<dyn futures::Future<Item = (), Error = Error> + Send> {
+    let token_cache = token_cache.clone();
+    let token_data = format!("{}\n{}\n", refresh_token, access_token);
+    Box::new(futures::future::lazy(move || {
+        token_cache.write(&token_cache_file, &token_data)
+    }))
 }
 
 #[derive(Debug, Clone)]
@@ -132,6 +318,8 @@ pub struct Config {
     auth_url: url::Url,
     token_url: url::Url,
     redirect_url: url::Url,
+    device_authorization_url: Option<url::Url>,
+    token_cache: crate::auth::token_cache::TokenCache,
 }
 
 impl Config {
@@ -148,6 +336,8 @@ impl Config {
             auth_url,
             token_url,
             redirect_url,
+            device_authorization_url: None,
+            token_cache: crate::auth::token_cache::TokenCache::plain(),
         }
     }
 
@@ -155,6 +345,18 @@ impl Config {
         self.redirect_url = url;
     }
 
+    pub fn set_device_authorization_url(&mut self, url: url::Url) {
+        self.device_authorization_url = Some(url);
+    }
+
+    pub fn set_token_encryption(
+        &mut self,
+        encryption: crate::auth::token_cache::Encryption,
+    ) {
+        self.token_cache =
+            crate::auth::token_cache::TokenCache::new(encryption);
+    }
+
     fn into_basic_client(self) -> oauth2::basic::BasicClient {
         oauth2::basic::BasicClient::new(
             oauth2::ClientId::new(self.client_id),
@@ -189,3 +391,116 @@ fn stringify_oauth2_http_error(
         oauth2::RequestTokenError::Other(s) => format!("Other({})", s),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // serves the given responses to token requests in order, and returns
+    // the token url along with a count of the requests made
+    fn token_server(
+        runtime: &mut tokio::runtime::Runtime,
+        responses: &[&'static str],
+    ) -> (url::Url, std::sync::Arc<std::sync::Mutex<usize>>) {
+        let responses = std::sync::Arc::new(std::sync::Mutex::new(
+            responses
+                .iter()
+                .copied()
+                .collect::<std::collections::VecDeque<_>>(),
+        ));
+        let requests = std::sync::Arc::new(std::sync::Mutex::new(0));
+        let server_requests = requests.clone();
+        let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(
+            move || {
+                let responses = responses.clone();
+                let requests = server_requests.clone();
+                hyper::service::service_fn_ok(move |_| {
+                    *requests.lock().unwrap() += 1;
+                    let body = responses.lock().unwrap().pop_front().unwrap();
+                    hyper::Response::builder()
+                        .header("content-type", "application/json")
+                        .body(hyper::Body::from(body))
+                        .unwrap()
+                })
+            },
+        );
+        let url =
+            url::Url::parse(&format!("http://{}/token", server.local_addr()))
+                .unwrap();
+        runtime.spawn(server.map_err(|e| panic!("{}", e)));
+        (url, requests)
+    }
+
+    fn device_code(
+        runtime: &mut tokio::runtime::Runtime,
+        token_url: url::Url,
+        expires_in: std::time::Duration,
+    ) -> Result<String> {
+        let url = url::Url::parse("http://localhost/").unwrap();
+        let config = Config::new(
+            "client".to_string(),
+            "secret".to_string(),
+            url.clone(),
+            token_url,
+            url,
+        );
+        let device = DeviceAuthorization {
+            device_code: "device".to_string(),
+            user_code: "user".to_string(),
+            verification_uri: "http://localhost/device".to_string(),
+            interval: std::time::Duration::from_millis(10),
+            expires_at: std::time::Instant::now() + expires_in,
+        };
+        runtime.block_on(
+            Oauth::new(config, "test".to_string())
+                .get_access_token_from_device_code(device),
+        )
+    }
+
+    #[test]
+    fn test_device_code() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let minute = std::time::Duration::from_secs(60);
+
+        let (url, requests) = token_server(
+            &mut runtime,
+            &[
+                r#"{"error":"authorization_pending"}"#,
+                r#"{"error":"authorization_pending"}"#,
+                r#"{"access_token":"access"}"#,
+            ],
+        );
+        assert_eq!(device_code(&mut runtime, url, minute).unwrap(), "access");
+        assert_eq!(*requests.lock().unwrap(), 3);
+
+        let (url, requests) = token_server(
+            &mut runtime,
+            &[
+                r#"{"error":"authorization_pending"}"#,
+                r#"{"error":"authorization_pending"}"#,
+            ],
+        );
+        let expired = std::time::Duration::from_secs(0);
+        match device_code(&mut runtime, url, expired) {
+            Err(Error::OauthDeviceCodeExpired) => {}
+            res => panic!("unexpected result {:?}", res),
+        }
+        assert_eq!(*requests.lock().unwrap(), 1);
+
+        let (url, _) =
+            token_server(&mut runtime, &[r#"{"error":"expired_token"}"#]);
+        match device_code(&mut runtime, url, minute) {
+            Err(Error::OauthDeviceCodeExpired) => {}
+            res => panic!("unexpected result {:?}", res),
+        }
+
+        let (url, _) =
+            token_server(&mut runtime, &[r#"{"error":"access_denied"}"#]);
+        match device_code(&mut runtime, url, minute) {
+            Err(Error::ExchangeDeviceCode { msg }) => {
+                assert_eq!(msg, "access_denied")
+            }
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+}
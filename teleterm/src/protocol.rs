@@ -13,6 +13,9 @@ pub struct Session {
     pub idle_time: u32,
     pub title: String,
     pub watchers: u32,
+    pub paused: bool,
+    pub description: String,
+    pub tags: Vec<String>,
 }
 
 pub struct FramedReader<T: tokio::io::AsyncRead>(
@@ -49,7 +52,9 @@ impl<T: tokio::io::AsyncWrite> FramedWriter<T> {
     }
 }
 
-pub const PROTO_VERSION: u8 = 1;
+// bump this whenever the serialization of any message changes, since the
+// format has no way of skipping fields that the other side doesn't know about
+pub const PROTO_VERSION: u8 = 2;
 
 #[repr(u8)]
 #[derive(
@@ -116,6 +121,9 @@ impl std::convert::TryFrom<&str> for AuthClient {
 pub enum AuthType {
     Plain = 0,
     RecurseCenter,
+    Password,
+    SshKey,
+    Token,
 }
 
 impl AuthType {
@@ -123,12 +131,17 @@ impl AuthType {
         match self {
             Self::Plain => "plain",
             Self::RecurseCenter => "recurse_center",
+            Self::Password => "password",
+            Self::SshKey => "ssh_key",
+            Self::Token => "token",
         }
     }
 
     pub fn is_oauth(self) -> bool {
         match self {
-            Self::Plain => false,
+            Self::Plain | Self::Password | Self::SshKey | Self::Token => {
+                false
+            }
             Self::RecurseCenter => true,
         }
     }
@@ -162,6 +175,9 @@ impl std::convert::TryFrom<u8> for AuthType {
         Ok(match n {
             0 => Self::Plain,
             1 => Self::RecurseCenter,
+            2 => Self::Password,
+            3 => Self::SshKey,
+            4 => Self::Token,
             _ => return Err(Error::InvalidAuthType { ty: n }),
         })
     }
@@ -174,17 +190,74 @@ impl std::convert::TryFrom<&str> for AuthType {
         Ok(match s {
             s if Self::Plain.name() == s => Self::Plain,
             s if Self::RecurseCenter.name() == s => Self::RecurseCenter,
+            s if Self::Password.name() == s => Self::Password,
+            s if Self::SshKey.name() == s => Self::SshKey,
+            s if Self::Token.name() == s => Self::Token,
             _ => return Err(Error::InvalidAuthTypeStr { ty: s.to_string() }),
         })
     }
 }
 
-#[derive(
-    Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize,
-)]
+#[derive(Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
 pub enum Auth {
-    Plain { username: String },
-    RecurseCenter { id: Option<String> },
+    Plain {
+        username: String,
+    },
+    // the profile is only used locally to decide where to store the id,
+    // and is never sent to the server
+    RecurseCenter {
+        id: Option<String>,
+        profile: Option<String>,
+    },
+    Password {
+        username: String,
+        password: String,
+    },
+    // the key file and server name are only used locally to answer the
+    // server's challenge, and are never sent to the server
+    SshKey {
+        username: String,
+        key_file: Option<String>,
+        server_name: String,
+    },
+    Token {
+        token: String,
+    },
+}
+
+// written by hand so that passwords and tokens don't end up in log output
+impl std::fmt::Debug for Auth {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Plain { username } => {
+                f.debug_struct("Plain").field("username", username).finish()
+            }
+            Self::RecurseCenter { id, profile } => f
+                .debug_struct("RecurseCenter")
+                .field("id", id)
+                .field("profile", profile)
+                .finish(),
+            Self::Password { username, .. } => f
+                .debug_struct("Password")
+                .field("username", username)
+                .field("password", &"<redacted>")
+                .finish(),
+            Self::SshKey {
+                username,
+                key_file,
+                server_name,
+            } => f
+                .debug_struct("SshKey")
+                .field("username", username)
+                .field("key_file", key_file)
+                .field("server_name", server_name)
+                .finish(),
+            Self::Token { .. } => f
+                .debug_struct("Token")
+                .field("token", &"<redacted>")
+                .finish(),
+        }
+    }
 }
 
 impl Auth {
@@ -194,9 +267,35 @@ impl Auth {
         }
     }
 
-    pub fn recurse_center(id: Option<&str>) -> Self {
+    pub fn recurse_center(id: Option<&str>, profile: Option<&str>) -> Self {
         Self::RecurseCenter {
             id: id.map(std::string::ToString::to_string),
+            profile: profile.map(std::string::ToString::to_string),
+        }
+    }
+
+    pub fn password(username: &str, password: &str) -> Self {
+        Self::Password {
+            username: username.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    pub fn ssh_key(
+        username: &str,
+        key_file: Option<&str>,
+        server_name: &str,
+    ) -> Self {
+        Self::SshKey {
+            username: username.to_string(),
+            key_file: key_file.map(std::string::ToString::to_string),
+            server_name: server_name.to_string(),
+        }
+    }
+
+    pub fn token(token: &str) -> Self {
+        Self::Token {
+            token: token.to_string(),
         }
     }
 
@@ -211,6 +310,15 @@ impl Auth {
         self.auth_type().oauth_client(config, self.oauth_id())
     }
 
+    pub fn oauth_profile(&self) -> Option<&str> {
+        match self {
+            Self::RecurseCenter { profile, .. } => {
+                profile.as_ref().map(std::string::String::as_str)
+            }
+            _ => None,
+        }
+    }
+
     pub fn oauth_id(&self) -> Option<&str> {
         match self {
             Self::RecurseCenter { id, .. } => {
@@ -228,6 +336,9 @@ impl Auth {
         match self {
             Self::Plain { .. } => AuthType::Plain,
             Self::RecurseCenter { .. } => AuthType::RecurseCenter,
+            Self::Password { .. } => AuthType::Password,
+            Self::SshKey { .. } => AuthType::SshKey,
+            Self::Token { .. } => AuthType::Token,
         }
     }
 }
@@ -250,6 +361,12 @@ pub enum MessageType {
     OauthCliResponse,
     OauthWebRequest,
     OauthWebResponse,
+    Paused,
+    Metadata,
+    SshKeyChallenge,
+    SshKeyResponse,
+    OauthCliDeviceRequest,
+    OauthCliDeviceResponse,
 }
 
 impl std::convert::TryFrom<u8> for MessageType {
@@ -272,6 +389,12 @@ impl std::convert::TryFrom<u8> for MessageType {
             12 => Self::OauthCliResponse,
             13 => Self::OauthWebRequest,
             14 => Self::OauthWebResponse,
+            15 => Self::Paused,
+            16 => Self::Metadata,
+            17 => Self::SshKeyChallenge,
+            18 => Self::SshKeyResponse,
+            19 => Self::OauthCliDeviceRequest,
+            20 => Self::OauthCliDeviceResponse,
             _ => return Err(Error::InvalidMessageType { ty: n }),
         })
     }
@@ -314,8 +437,11 @@ pub enum Message {
         url: String,
         id: String,
     },
+    // redirect_port is set when the client couldn't listen on the port in
+    // the configured redirect url, and so listened somewhere else instead
     OauthCliResponse {
         code: String,
+        redirect_port: Option<u16>,
     },
     OauthWebRequest {
         id: String,
@@ -323,6 +449,27 @@ pub enum Message {
     OauthWebResponse {
         access_token: String,
     },
+    Paused {
+        paused: bool,
+    },
+    Metadata {
+        title: String,
+        description: String,
+        tags: Vec<String>,
+    },
+    SshKeyChallenge {
+        challenge: Vec<u8>,
+    },
+    SshKeyResponse {
+        public_key: Vec<u8>,
+        signature: Vec<u8>,
+    },
+    OauthCliDeviceRequest {
+        url: String,
+        user_code: String,
+        id: String,
+    },
+    OauthCliDeviceResponse,
 }
 
 impl Message {
@@ -396,9 +543,13 @@ impl Message {
         }
     }
 
-    pub fn oauth_cli_response(code: &str) -> Self {
+    pub fn oauth_cli_response(
+        code: &str,
+        redirect_port: Option<u16>,
+    ) -> Self {
         Self::OauthCliResponse {
             code: code.to_string(),
+            redirect_port,
         }
     }
 
@@ -412,6 +563,47 @@ impl Message {
         }
     }
 
+    pub fn paused(paused: bool) -> Self {
+        Self::Paused { paused }
+    }
+
+    pub fn metadata(title: &str, description: &str, tags: &[String]) -> Self {
+        Self::Metadata {
+            title: title.to_string(),
+            description: description.to_string(),
+            tags: tags.to_vec(),
+        }
+    }
+
+    pub fn ssh_key_challenge(challenge: &[u8]) -> Self {
+        Self::SshKeyChallenge {
+            challenge: challenge.to_vec(),
+        }
+    }
+
+    pub fn ssh_key_response(public_key: &[u8], signature: &[u8]) -> Self {
+        Self::SshKeyResponse {
+            public_key: public_key.to_vec(),
+            signature: signature.to_vec(),
+        }
+    }
+
+    pub fn oauth_cli_device_request(
+        url: &str,
+        user_code: &str,
+        id: &str,
+    ) -> Self {
+        Self::OauthCliDeviceRequest {
+            url: url.to_string(),
+            user_code: user_code.to_string(),
+            id: id.to_string(),
+        }
+    }
+
+    pub fn oauth_cli_device_response() -> Self {
+        Self::OauthCliDeviceResponse
+    }
+
     pub fn message_type(&self) -> MessageType {
         match self {
             Self::Login { .. } => MessageType::Login,
@@ -429,6 +621,16 @@ impl Message {
             Self::OauthCliResponse { .. } => MessageType::OauthCliResponse,
             Self::OauthWebRequest { .. } => MessageType::OauthWebRequest,
             Self::OauthWebResponse { .. } => MessageType::OauthWebResponse,
+            Self::Paused { .. } => MessageType::Paused,
+            Self::Metadata { .. } => MessageType::Metadata,
+            Self::SshKeyChallenge { .. } => MessageType::SshKeyChallenge,
+            Self::SshKeyResponse { .. } => MessageType::SshKeyResponse,
+            Self::OauthCliDeviceRequest { .. } => {
+                MessageType::OauthCliDeviceRequest
+            }
+            Self::OauthCliDeviceResponse { .. } => {
+                MessageType::OauthCliDeviceResponse
+            }
         }
     }
 
@@ -480,6 +682,9 @@ impl Message {
             Self::OauthWebResponse { .. } => {
                 "OauthWebResponse {{ .. }}".to_string()
             }
+            Self::OauthCliDeviceRequest { .. } => {
+                "OauthCliDeviceRequest {{ .. }}".to_string()
+            }
 
             _ => format!("{:?}", self),
         }
@@ -582,6 +787,9 @@ impl From<&Message> for Packet {
         fn write_u8(val: u8, data: &mut Vec<u8>) {
             data.extend_from_slice(&val.to_be_bytes());
         }
+        fn write_bool(val: bool, data: &mut Vec<u8>) {
+            write_u8(u8::from(val), data);
+        }
         fn write_bytes(val: &[u8], data: &mut Vec<u8>) {
             write_u32(u32_from_usize(val.len()), data);
             data.extend_from_slice(val);
@@ -589,6 +797,12 @@ impl From<&Message> for Packet {
         fn write_str(val: &str, data: &mut Vec<u8>) {
             write_bytes(val.as_bytes(), data);
         }
+        fn write_strs(val: &[String], data: &mut Vec<u8>) {
+            write_u32(u32_from_usize(val.len()), data);
+            for s in val {
+                write_str(s, data);
+            }
+        }
         fn write_size(val: crate::term::Size, data: &mut Vec<u8>) {
             write_u16(val.rows, data);
             write_u16(val.cols, data);
@@ -601,6 +815,9 @@ impl From<&Message> for Packet {
             write_u32(val.idle_time, data);
             write_str(&val.title, data);
             write_u32(val.watchers, data);
+            write_bool(val.paused, data);
+            write_str(&val.description, data);
+            write_strs(&val.tags, data);
         }
         fn write_sessions(val: &[Session], data: &mut Vec<u8>) {
             write_u32(u32_from_usize(val.len()), data);
@@ -614,10 +831,20 @@ impl From<&Message> for Packet {
                 Auth::Plain { username } => {
                     write_str(username, data);
                 }
-                Auth::RecurseCenter { id } => {
+                Auth::RecurseCenter { id, .. } => {
                     let id = id.as_ref().map_or("", |s| s.as_str());
                     write_str(id, data);
                 }
+                Auth::Password { username, password } => {
+                    write_str(username, data);
+                    write_str(password, data);
+                }
+                Auth::SshKey { username, .. } => {
+                    write_str(username, data);
+                }
+                Auth::Token { token } => {
+                    write_str(token, data);
+                }
             }
         }
 
@@ -664,8 +891,12 @@ impl From<&Message> for Packet {
                 write_str(url, &mut data);
                 write_str(id, &mut data);
             }
-            Message::OauthCliResponse { code } => {
+            Message::OauthCliResponse {
+                code,
+                redirect_port,
+            } => {
                 write_str(code, &mut data);
+                write_u16(redirect_port.unwrap_or(0), &mut data);
             }
             Message::OauthWebRequest { id } => {
                 write_str(id, &mut data);
@@ -673,6 +904,34 @@ impl From<&Message> for Packet {
             Message::OauthWebResponse { access_token } => {
                 write_str(access_token, &mut data);
             }
+            Message::Paused { paused } => {
+                write_bool(*paused, &mut data);
+            }
+            Message::Metadata {
+                title,
+                description,
+                tags,
+            } => {
+                write_str(title, &mut data);
+                write_str(description, &mut data);
+                write_strs(tags, &mut data);
+            }
+            Message::SshKeyChallenge { challenge } => {
+                write_bytes(challenge, &mut data);
+            }
+            Message::SshKeyResponse {
+                public_key,
+                signature,
+            } => {
+                write_bytes(public_key, &mut data);
+                write_bytes(signature, &mut data);
+            }
+            Message::OauthCliDeviceRequest { url, user_code, id } => {
+                write_str(url, &mut data);
+                write_str(user_code, &mut data);
+                write_str(id, &mut data);
+            }
+            Message::OauthCliDeviceResponse => {}
         }
 
         Self { ty, data }
@@ -722,6 +981,10 @@ impl std::convert::TryFrom<Packet> for Message {
             );
             Ok((val, rest))
         }
+        fn read_bool(data: &[u8]) -> Result<(bool, &[u8])> {
+            let (val, rest) = read_u8(data)?;
+            Ok((val != 0, rest))
+        }
         fn read_bytes(data: &[u8]) -> Result<(Vec<u8>, &[u8])> {
             let (len, data) = read_u32(data)?;
             if len as usize > data.len() {
@@ -743,6 +1006,16 @@ impl std::convert::TryFrom<Packet> for Message {
                 })?;
             Ok((val, rest))
         }
+        fn read_strs(data: &[u8]) -> Result<(Vec<String>, &[u8])> {
+            let mut val = vec![];
+            let (len, mut data) = read_u32(data)?;
+            for _ in 0..len {
+                let (subval, subdata) = read_str(data)?;
+                val.push(subval);
+                data = subdata;
+            }
+            Ok((val, data))
+        }
         fn read_size(data: &[u8]) -> Result<(crate::term::Size, &[u8])> {
             let (rows, data) = read_u16(data)?;
             let (cols, data) = read_u16(data)?;
@@ -756,6 +1029,9 @@ impl std::convert::TryFrom<Packet> for Message {
             let (idle_time, data) = read_u32(data)?;
             let (title, data) = read_str(data)?;
             let (watchers, data) = read_u32(data)?;
+            let (paused, data) = read_bool(data)?;
+            let (description, data) = read_str(data)?;
+            let (tags, data) = read_strs(data)?;
             Ok((
                 Session {
                     id,
@@ -765,6 +1041,9 @@ impl std::convert::TryFrom<Packet> for Message {
                     idle_time,
                     title,
                     watchers,
+                    paused,
+                    description,
+                    tags,
                 },
                 data,
             ))
@@ -791,7 +1070,27 @@ impl std::convert::TryFrom<Packet> for Message {
                 AuthType::RecurseCenter => {
                     let (id, data) = read_str(data)?;
                     let id = if id == "" { None } else { Some(id) };
-                    let auth = Auth::RecurseCenter { id };
+                    let auth = Auth::RecurseCenter { id, profile: None };
+                    (auth, data)
+                }
+                AuthType::Password => {
+                    let (username, data) = read_str(data)?;
+                    let (password, data) = read_str(data)?;
+                    let auth = Auth::Password { username, password };
+                    (auth, data)
+                }
+                AuthType::SshKey => {
+                    let (username, data) = read_str(data)?;
+                    let auth = Auth::SshKey {
+                        username,
+                        key_file: None,
+                        server_name: String::new(),
+                    };
+                    (auth, data)
+                }
+                AuthType::Token => {
+                    let (token, data) = read_str(data)?;
+                    let auth = Auth::Token { token };
                     (auth, data)
                 }
             };
@@ -862,8 +1161,20 @@ impl std::convert::TryFrom<Packet> for Message {
             }
             MessageType::OauthCliResponse => {
                 let (code, data) = read_str(data)?;
+                let (redirect_port, data) = read_u16(data)?;
+                let redirect_port = if redirect_port == 0 {
+                    None
+                } else {
+                    Some(redirect_port)
+                };
 
-                (Self::OauthCliResponse { code }, data)
+                (
+                    Self::OauthCliResponse {
+                        code,
+                        redirect_port,
+                    },
+                    data,
+                )
             }
             MessageType::OauthWebRequest => {
                 let (id, data) = read_str(data)?;
@@ -875,6 +1186,52 @@ impl std::convert::TryFrom<Packet> for Message {
 
                 (Self::OauthWebResponse { access_token }, data)
             }
+            MessageType::Paused => {
+                let (paused, data) = read_bool(data)?;
+
+                (Self::Paused { paused }, data)
+            }
+            MessageType::Metadata => {
+                let (title, data) = read_str(data)?;
+                let (description, data) = read_str(data)?;
+                let (tags, data) = read_strs(data)?;
+
+                (
+                    Self::Metadata {
+                        title,
+                        description,
+                        tags,
+                    },
+                    data,
+                )
+            }
+            MessageType::SshKeyChallenge => {
+                let (challenge, data) = read_bytes(data)?;
+
+                (Self::SshKeyChallenge { challenge }, data)
+            }
+            MessageType::SshKeyResponse => {
+                let (public_key, data) = read_bytes(data)?;
+                let (signature, data) = read_bytes(data)?;
+
+                (
+                    Self::SshKeyResponse {
+                        public_key,
+                        signature,
+                    },
+                    data,
+                )
+            }
+            MessageType::OauthCliDeviceRequest => {
+                let (url, data) = read_str(data)?;
+                let (user_code, data) = read_str(data)?;
+                let (id, data) = read_str(data)?;
+
+                (Self::OauthCliDeviceRequest { url, user_code, id }, data)
+            }
+            MessageType::OauthCliDeviceResponse => {
+                (Self::OauthCliDeviceResponse, data)
+            }
         };
 
         if !rest.is_empty() {
@@ -1024,17 +1381,48 @@ mod test {
             Message::login(
                 &Auth::RecurseCenter {
                     id: Some("some-random-id".to_string()),
+                    profile: None,
                 },
                 AuthClient::Cli,
                 "screen",
                 crate::term::Size { rows: 24, cols: 80 },
             ),
             Message::login(
-                &Auth::RecurseCenter { id: None },
+                &Auth::RecurseCenter {
+                    id: None,
+                    profile: None,
+                },
                 AuthClient::Cli,
                 "screen",
                 crate::term::Size { rows: 24, cols: 80 },
             ),
+            Message::login(
+                &Auth::SshKey {
+                    username: "doy".to_string(),
+                    key_file: None,
+                    server_name: String::new(),
+                },
+                AuthClient::Cli,
+                "screen",
+                crate::term::Size { rows: 24, cols: 80 },
+            ),
+            Message::login(
+                &Auth::Token {
+                    token: "tt_abcdef".to_string(),
+                },
+                AuthClient::Cli,
+                "screen",
+                crate::term::Size { rows: 24, cols: 80 },
+            ),
+            Message::login(
+                &Auth::Password {
+                    username: "doy".to_string(),
+                    password: "hunter2".to_string(),
+                },
+                AuthClient::Web,
+                "screen",
+                crate::term::Size { rows: 24, cols: 80 },
+            ),
             Message::start_streaming(),
             Message::start_watching("some-session-id"),
             Message::heartbeat(),
@@ -1050,6 +1438,9 @@ mod test {
                 idle_time: 123,
                 title: "it's my terminal title".to_string(),
                 watchers: 0,
+                paused: false,
+                description: "".to_string(),
+                tags: vec![],
             }]),
             Message::sessions(&[
                 Session {
@@ -1060,6 +1451,9 @@ mod test {
                     idle_time: 123,
                     title: "it's my terminal title".to_string(),
                     watchers: 0,
+                    paused: false,
+                    description: "doing some stuff".to_string(),
+                    tags: vec!["rust".to_string()],
                 },
                 Session {
                     id: "some-other-session-id".to_string(),
@@ -1069,12 +1463,37 @@ mod test {
                     idle_time: 68,
                     title: "some other terminal title".to_string(),
                     watchers: 0,
+                    paused: true,
+                    description: "".to_string(),
+                    tags: vec!["rust".to_string(), "teleterm".to_string()],
                 },
             ]),
             Message::disconnected(),
             Message::error("error message"),
             Message::resize(crate::term::Size { rows: 25, cols: 81 }),
             Message::logged_in("doy"),
+            Message::paused(true),
+            Message::paused(false),
+            Message::metadata("", "", &[]),
+            Message::metadata(
+                "my stream",
+                "working on teleterm",
+                &["rust".to_string(), "teleterm".to_string()],
+            ),
+            Message::ssh_key_challenge(b"some random bytes"),
+            Message::ssh_key_response(b"\x00\x00\x00\x0bssh-ed25519", b""),
+            Message::oauth_cli_request(
+                "https://example.com/oauth/authorize",
+                "some-id",
+            ),
+            Message::oauth_cli_response("some-code", None),
+            Message::oauth_cli_response("some-code", Some(51234)),
+            Message::oauth_cli_device_request(
+                "https://example.com/device",
+                "ABCD-EFGH",
+                "some-id",
+            ),
+            Message::oauth_cli_device_response(),
         ]
     }
 
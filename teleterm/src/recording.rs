@@ -0,0 +1,1331 @@
+use crate::prelude::*;
+use std::io::{BufRead as _, Read as _, Seek as _};
+use tokio::io::AsyncWrite as _;
+
+// each frame in a ttyrec file is preceded by a 12 byte header (seconds,
+// microseconds, and data length, as little endian u32s)
+const HEADER_LEN: u64 = 12;
+
+// a keyframe is taken after this many frames or this many bytes of terminal
+// output, whichever comes first, which bounds how much of the file needs to
+// be replayed when seeking
+const KEYFRAME_FRAMES: usize = 1000;
+const KEYFRAME_BYTES: usize = 1024 * 1024;
+
+// once there are more keyframes than this, every other one is dropped and
+// the intervals are doubled, so that memory usage stays bounded no matter
+// how long the recording is
+const MAX_KEYFRAMES: usize = 1024;
+
+struct FrameInfo {
+    offset: u64,
+    time: std::time::Duration,
+    // the size of the recorded terminal after this frame
+    size: crate::term::Size,
+}
+
+struct FileInfo {
+    filename: String,
+    format: crate::config::RecordingFormat,
+    // the size given in the file's header, for formats which have one
+    size: Option<crate::term::Size>,
+    first_frame: usize,
+    // frame times in the file are relative to its own first frame
+    time_offset: std::time::Duration,
+}
+
+// the whole parser is kept rather than just the screen contents, since
+// the contents don't include things like the scroll region, the current
+// attributes, or the terminal modes, all of which affect how the frames
+// after it are drawn
+struct Keyframe {
+    idx: usize,
+    parser: vt100::Parser,
+}
+
+// the position in the file that frames are currently being decoded from,
+// along with the terminal state after processing frame idx
+struct Cursor {
+    format: crate::config::RecordingFormat,
+    file_idx: usize,
+    file: std::io::BufReader<std::fs::File>,
+    parser: vt100::Parser,
+    idx: usize,
+}
+
+// an index of a ttyrec (or asciicast) file which can be built incrementally
+// as the file is read. only the offset and timestamp of each frame are kept
+// in memory, along with periodic snapshots of the screen contents, and
+// frames are decoded from the file again when they are needed. several
+// files can be added one after another, in which case they are treated as
+// one continuous recording.
+pub struct Recording {
+    files: Vec<FileInfo>,
+    size: crate::term::Size,
+
+    frames: Vec<FrameInfo>,
+    keyframes: Vec<Keyframe>,
+    next_offset: u64,
+
+    indexer: vt100::Parser,
+    keyframe_frames: usize,
+    keyframe_bytes: usize,
+    frames_since_keyframe: usize,
+    bytes_since_keyframe: usize,
+
+    cursor: Option<Cursor>,
+}
+
+impl Recording {
+    pub fn new(
+        filename: &str,
+        format: crate::config::RecordingFormat,
+        size: crate::term::Size,
+    ) -> Self {
+        Self {
+            files: vec![FileInfo {
+                filename: filename.to_string(),
+                format,
+                size: None,
+                first_frame: 0,
+                time_offset: std::time::Duration::default(),
+            }],
+            size,
+
+            frames: vec![],
+            keyframes: vec![],
+            next_offset: 0,
+
+            indexer: vt100::Parser::new(size.rows, size.cols, 0),
+            keyframe_frames: KEYFRAME_FRAMES,
+            keyframe_bytes: KEYFRAME_BYTES,
+            frames_since_keyframe: 0,
+            bytes_since_keyframe: 0,
+
+            cursor: None,
+        }
+    }
+
+    // continues the recording with the frames from another file, which
+    // start right after the last frame so far
+    pub fn add_file(
+        &mut self,
+        filename: &str,
+        format: crate::config::RecordingFormat,
+    ) {
+        self.files.push(FileInfo {
+            filename: filename.to_string(),
+            format,
+            size: None,
+            first_frame: self.len(),
+            time_offset: self
+                .frames
+                .last()
+                .map_or_else(std::time::Duration::default, |frame| {
+                    frame.time
+                }),
+        });
+        self.next_offset = 0;
+    }
+
+    pub fn num_files(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn filename(&self, file_idx: usize) -> &str {
+        &self.files[file_idx].filename
+    }
+
+    // the index of the first frame of the given file, which may be past
+    // the end of the frames read so far if the file has no frames
+    pub fn first_frame(&self, file_idx: usize) -> usize {
+        self.files[file_idx].first_frame
+    }
+
+    // when the given file starts, relative to the start of the recording
+    pub fn start_time(&self, file_idx: usize) -> std::time::Duration {
+        self.files[file_idx].time_offset
+    }
+
+    // the file that the given frame was read from
+    pub fn file_idx(&self, idx: usize) -> usize {
+        self.files
+            .iter()
+            .rposition(|file| file.first_frame <= idx)
+            .unwrap()
+    }
+
+    // frames must be added in the order they appear in the file. time is
+    // relative to the first frame in the file.
+    pub fn add_frame(&mut self, time: std::time::Duration, data: &[u8]) {
+        self.add_frame_at(
+            self.next_offset,
+            HEADER_LEN + data.len() as u64,
+            time,
+            data,
+        );
+    }
+
+    // the size of the terminal at the start of the current file, for
+    // formats which store it separately from the frames. this does nothing
+    // once frames have been added from the current file.
+    pub fn set_initial_size(&mut self, size: crate::term::Size) {
+        let file = self.files.last_mut().unwrap();
+        if file.first_frame < self.frames.len() || file.size.is_some() {
+            return;
+        }
+        file.size = Some(size);
+        if self.frames.is_empty() {
+            self.size = size;
+            self.indexer = vt100::Parser::new(size.rows, size.cols, 0);
+        } else {
+            self.indexer.set_size(size.rows, size.cols);
+        }
+    }
+
+    // for formats where the position of each frame can't be calculated
+    // from the frames before it (asciicast files can contain other kinds of
+    // events between frames)
+    pub fn add_frame_at(
+        &mut self,
+        offset: u64,
+        len: u64,
+        time: std::time::Duration,
+        data: &[u8],
+    ) {
+        let idx = self.frames.len();
+        let file = self.files.last().unwrap();
+        let first_in_file = idx == file.first_frame;
+        let time = file.time_offset + time;
+        process(&mut self.indexer, data);
+        let (rows, cols) = self.indexer.screen().size();
+        let size = crate::term::Size { rows, cols };
+        self.frames.push(FrameInfo { offset, time, size });
+        self.next_offset = offset + len;
+
+        self.frames_since_keyframe += 1;
+        self.bytes_since_keyframe += data.len();
+        if first_in_file
+            || self.frames_since_keyframe >= self.keyframe_frames
+            || self.bytes_since_keyframe >= self.keyframe_bytes
+        {
+            self.keyframes.push(Keyframe {
+                idx,
+                parser: self.indexer.clone(),
+            });
+            self.frames_since_keyframe = 0;
+            self.bytes_since_keyframe = 0;
+
+            if self.keyframes.len() > MAX_KEYFRAMES {
+                self.thin_keyframes();
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    // the time since the start of the recording that the frame was written
+    pub fn time(&self, idx: usize) -> Option<std::time::Duration> {
+        self.frames.get(idx).map(|frame| frame.time)
+    }
+
+    // the size of the recorded terminal after the given frame
+    pub fn size(&self, idx: usize) -> Option<crate::term::Size> {
+        self.frames.get(idx).map(|frame| frame.size)
+    }
+
+    // the time between the previous frame and this one
+    pub fn dur(&self, idx: usize) -> Option<std::time::Duration> {
+        let time = self.time(idx)?;
+        if idx == 0 {
+            Some(std::time::Duration::default())
+        } else {
+            Some(time - self.frames[idx - 1].time)
+        }
+    }
+
+    // the last frame written at or before the given time
+    pub fn frame_at(&self, time: std::time::Duration) -> usize {
+        let count = self
+            .frames
+            .binary_search_by(|frame| {
+                if frame.time <= time {
+                    std::cmp::Ordering::Less
+                } else {
+                    std::cmp::Ordering::Greater
+                }
+            })
+            .unwrap_err();
+        count.saturating_sub(1)
+    }
+
+    // the first frame after start which was written more than threshold
+    // after the frame before it
+    pub fn next_idle(
+        &self,
+        start: usize,
+        threshold: std::time::Duration,
+    ) -> Option<usize> {
+        (start + 1..self.len())
+            .find(|&idx| self.dur(idx).map_or(false, |dur| dur > threshold))
+    }
+
+    // the full contents of the screen after the given frame
+    pub fn screen(&mut self, idx: usize) -> Result<Vec<u8>> {
+        let cursor = self.cursor_at(idx)?;
+        Ok(cursor.parser.screen().contents_formatted())
+    }
+
+    // the contents of the screen after the given frame, cropped to fit in a
+    // terminal of the given size
+    pub fn screen_cropped(
+        &mut self,
+        idx: usize,
+        size: crate::term::Size,
+    ) -> Result<Vec<u8>> {
+        let cursor = self.cursor_at(idx)?;
+        let screen = cursor.parser.screen();
+        let mut data = b"\x1b[m\x1b[H\x1b[J".to_vec();
+        for (row, contents) in
+            (1..=size.rows).zip(screen.rows_formatted(0, size.cols))
+        {
+            data.extend_from_slice(format!("\x1b[{};1H", row).as_bytes());
+            data.extend_from_slice(&contents);
+            data.extend_from_slice(b"\x1b[m");
+        }
+        let (row, col) = screen.cursor_position();
+        if screen.hide_cursor() || row >= size.rows || col >= size.cols {
+            data.extend_from_slice(b"\x1b[?25l");
+        } else {
+            data.extend_from_slice(
+                format!("\x1b[?25h\x1b[{};{}H", row + 1, col + 1).as_bytes(),
+            );
+        }
+        Ok(data)
+    }
+
+    // the data needed to draw the given frame over the previous one
+    pub fn diff(&mut self, idx: usize) -> Result<Vec<u8>> {
+        if idx == 0 {
+            return self.screen(idx);
+        }
+
+        assert!(idx < self.len());
+        let cursor = self.cursor_at(idx - 1)?;
+        let prev = cursor.parser.screen().clone();
+        cursor.step()?;
+        Ok(cursor.parser.screen().contents_diff(&prev))
+    }
+
+    // returns the indexes of the frames in start..end whose screen contents
+    // match the regex
+    pub fn matches(
+        &mut self,
+        re: &regex::bytes::Regex,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<usize>> {
+        let mut matches = vec![];
+        for idx in start..end.min(self.len()) {
+            let cursor = self.cursor_at(idx)?;
+            if re.is_match(&cursor.parser.screen().contents_formatted()) {
+                matches.push(idx);
+            }
+        }
+        Ok(matches)
+    }
+
+    fn cursor_at(&mut self, idx: usize) -> Result<&mut Cursor> {
+        assert!(idx < self.len());
+
+        let keyframe = self
+            .keyframes
+            .iter()
+            .rev()
+            .find(|keyframe| keyframe.idx <= idx)
+            .unwrap();
+        // continuing on from the current position is always at least as
+        // fast as going back to the nearest keyframe
+        let reuse = self.cursor.as_ref().map_or(false, |cursor| {
+            cursor.idx <= idx && cursor.idx >= keyframe.idx
+        });
+        if !reuse {
+            // if the keyframe is the last frame we know about so far, the
+            // next frame will be written right after it
+            let (file_idx, offset) =
+                self.frames.get(keyframe.idx + 1).map_or_else(
+                    || (self.files.len() - 1, self.next_offset),
+                    |frame| (self.file_idx(keyframe.idx + 1), frame.offset),
+                );
+            let parser = keyframe.parser.clone();
+            let keyframe_file_idx = self.file_idx(keyframe.idx);
+            let file = match self.cursor.take() {
+                Some(cursor) if cursor.file_idx == file_idx => cursor.file,
+                _ => self.open_file(file_idx)?,
+            };
+            let mut cursor = Cursor {
+                format: self.files[file_idx].format,
+                file_idx,
+                file,
+                parser,
+                idx: keyframe.idx,
+            };
+            // when the keyframe is the last frame of the previous file, the
+            // size from the header of this one hasn't been applied yet (see
+            // below)
+            if file_idx != keyframe_file_idx {
+                if let Some(size) = self.files[file_idx].size {
+                    cursor.parser.set_size(size.rows, size.cols);
+                }
+            }
+            cursor.seek(offset)?;
+            self.cursor = Some(cursor);
+        }
+
+        while self.cursor.as_ref().unwrap().idx < idx {
+            let next = self.cursor.as_ref().unwrap().idx + 1;
+            let file_idx = self.file_idx(next);
+            if file_idx != self.cursor.as_ref().unwrap().file_idx {
+                let file = self.open_file(file_idx)?;
+                let info = &self.files[file_idx];
+                let cursor = self.cursor.as_mut().unwrap();
+                cursor.file = file;
+                cursor.file_idx = file_idx;
+                cursor.format = info.format;
+                if let Some(size) = info.size {
+                    cursor.parser.set_size(size.rows, size.cols);
+                }
+                cursor.seek(self.frames[next].offset)?;
+            }
+            self.cursor.as_mut().unwrap().step()?;
+        }
+        Ok(self.cursor.as_mut().unwrap())
+    }
+
+    fn open_file(
+        &self,
+        file_idx: usize,
+    ) -> Result<std::io::BufReader<std::fs::File>> {
+        let filename = &self.files[file_idx].filename;
+        Ok(std::io::BufReader::new(
+            std::fs::File::open(filename).context(
+                crate::error::OpenFileSync {
+                    filename: filename.clone(),
+                },
+            )?,
+        ))
+    }
+
+    fn thin_keyframes(&mut self) {
+        let mut i = 0;
+        self.keyframes.retain(|_| {
+            i += 1;
+            i % 2 == 1
+        });
+        self.keyframe_frames *= 2;
+        self.keyframe_bytes *= 2;
+    }
+}
+
+// like vt100::Parser::process, but also resizes the terminal when the data
+// contains a resize sequence (see crate::term::Size::resize_sequence)
+fn process(parser: &mut vt100::Parser, data: &[u8]) {
+    let mut pos = 0;
+    for (range, size) in crate::term::find_resizes(data) {
+        parser.process(&data[pos..range.start]);
+        parser.set_size(size.rows, size.cols);
+        pos = range.end;
+    }
+    parser.process(&data[pos..]);
+}
+
+impl Cursor {
+    fn seek(&mut self, offset: u64) -> Result<()> {
+        self.file
+            .seek(std::io::SeekFrom::Start(offset))
+            .context(crate::error::SeekFile)?;
+        Ok(())
+    }
+
+    fn step(&mut self) -> Result<()> {
+        let data = match self.format {
+            crate::config::RecordingFormat::Ttyrec => {
+                read_ttyrec_frame(&mut self.file)?
+                    .context(crate::error::RecordingTruncated)?
+                    .1
+            }
+            crate::config::RecordingFormat::Asciicast => {
+                self.read_asciicast_frame()?
+            }
+        };
+        process(&mut self.parser, &data);
+        self.idx += 1;
+        Ok(())
+    }
+
+    fn read_asciicast_frame(&mut self) -> Result<Vec<u8>> {
+        let mut line = vec![];
+        loop {
+            line.clear();
+            let n = self
+                .file
+                .read_until(b'\n', &mut line)
+                .context(crate::error::ReadFileSync)?;
+            if n == 0 {
+                return Err(Error::RecordingTruncated);
+            }
+            // resize events are stored as frames too, in the same form
+            // that ttyrec files use
+            match crate::asciicast::parse_event(&line)? {
+                Some((_, crate::asciicast::Event::Output(data))) => {
+                    return Ok(data)
+                }
+                Some((_, crate::asciicast::Event::Resize(size))) => {
+                    return Ok(size.resize_sequence())
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// returns None at the end of the file
+pub fn read_ttyrec_frame<R: std::io::Read>(
+    r: &mut R,
+) -> Result<Option<(std::time::Duration, Vec<u8>)>> {
+    let mut header = [0; HEADER_LEN as usize];
+    let mut read = 0;
+    while read < header.len() {
+        let n = r
+            .read(&mut header[read..])
+            .context(crate::error::ReadFileSync)?;
+        if n == 0 {
+            break;
+        }
+        read += n;
+    }
+    if read == 0 {
+        return Ok(None);
+    }
+    if read < header.len() {
+        return Err(Error::RecordingTruncated);
+    }
+
+    let secs =
+        u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+    let micros =
+        u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    let len =
+        u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+    let mut data = vec![0; len as usize];
+    r.read_exact(&mut data)
+        .context(crate::error::ReadFileSync)?;
+    let time = std::time::Duration::from_secs(secs.into())
+        + std::time::Duration::from_micros(micros.into());
+    Ok(Some((time, data)))
+}
+
+#[allow(clippy::cast_possible_truncation)]
+pub fn ttyrec_frame(time: std::time::Duration, data: &[u8]) -> Vec<u8> {
+    let mut frame = vec![];
+    frame.extend_from_slice(&(time.as_secs() as u32).to_le_bytes());
+    frame.extend_from_slice(&time.subsec_micros().to_le_bytes());
+    frame.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    frame.extend_from_slice(data);
+    frame
+}
+
+// writes a ttyrec file with the same interface as crate::asciicast::Writer.
+// frame times are taken relative to base rather than always being the
+// current time, so that a recording can be continued from where an
+// existing file left off.
+pub struct TtyrecWriter<W: tokio::io::AsyncWrite> {
+    output: W,
+    base: std::time::Duration,
+    start: std::time::Instant,
+    to_write: std::collections::VecDeque<u8>,
+}
+
+impl<W: tokio::io::AsyncWrite> TtyrecWriter<W> {
+    pub fn new(output: W, base: std::time::Duration) -> Self {
+        Self {
+            output,
+            base,
+            start: std::time::Instant::now(),
+            to_write: std::collections::VecDeque::new(),
+        }
+    }
+
+    // returns the number of bytes added to the file
+    pub fn frame(&mut self, data: &[u8]) -> usize {
+        let frame = ttyrec_frame(self.base + self.start.elapsed(), data);
+        let len = frame.len();
+        self.to_write.extend(frame);
+        len
+    }
+
+    pub fn needs_write(&self) -> bool {
+        !self.to_write.is_empty()
+    }
+
+    pub fn poll_write(&mut self) -> futures::Poll<(), Error> {
+        while !self.to_write.is_empty() {
+            let (a, b) = self.to_write.as_slices();
+            let buf = if a.is_empty() { b } else { a };
+            let n = futures::try_ready!(self
+                .output
+                .poll_write(buf)
+                .context(crate::error::WriteFile));
+            self.to_write.drain(..n);
+        }
+        Ok(futures::Async::Ready(()))
+    }
+}
+
+// the part of an existing recording which new frames can be appended to
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedSpan {
+    pub first: std::time::Duration,
+    pub last: std::time::Duration,
+    // the length of the file up to the end of the last complete frame.
+    // anything after this was cut off partway through being written (if
+    // the recording was killed, for instance), and has to be removed before
+    // appending.
+    pub len: u64,
+}
+
+impl RecordedSpan {
+    // ttyrec timestamps come from the wall clock, so they can go backwards
+    // if the clock was changed while recording
+    pub fn duration(&self) -> std::time::Duration {
+        self.last.checked_sub(self.first).unwrap_or_default()
+    }
+}
+
+pub fn recorded_span(
+    filename: &str,
+    format: crate::config::RecordingFormat,
+) -> Result<Option<RecordedSpan>> {
+    if !std::path::Path::new(filename).exists() {
+        return Ok(None);
+    }
+    let file =
+        std::io::BufReader::new(std::fs::File::open(filename).context(
+            crate::error::OpenFileSync {
+                filename: filename.to_string(),
+            },
+        )?);
+    match format {
+        crate::config::RecordingFormat::Ttyrec => ttyrec_span(file),
+        crate::config::RecordingFormat::Asciicast => asciicast_span(file),
+    }
+}
+
+fn ttyrec_span<R: std::io::Read>(
+    mut file: R,
+) -> Result<Option<RecordedSpan>> {
+    let mut span: Option<RecordedSpan> = None;
+    loop {
+        let (time, data) = match read_ttyrec_frame(&mut file) {
+            Ok(Some(frame)) => frame,
+            Ok(None) | Err(Error::RecordingTruncated) => break,
+            Err(Error::ReadFileSync { ref source })
+                if source.kind() == std::io::ErrorKind::UnexpectedEof =>
+            {
+                break
+            }
+            Err(e) => return Err(e),
+        };
+        let len = HEADER_LEN + data.len() as u64;
+        span = Some(match span {
+            Some(span) => RecordedSpan {
+                last: time,
+                len: span.len + len,
+                ..span
+            },
+            None => RecordedSpan {
+                first: time,
+                last: time,
+                len,
+            },
+        });
+    }
+    Ok(span)
+}
+
+// lines are only complete once their trailing newline has been written, so
+// truncating to the last complete line also leaves the file ending with a
+// newline
+fn asciicast_span<R: std::io::BufRead>(
+    mut file: R,
+) -> Result<Option<RecordedSpan>> {
+    let mut line = vec![];
+    let n = file
+        .read_until(b'\n', &mut line)
+        .context(crate::error::ReadFileSync)?;
+    if line.last() != Some(&b'\n') {
+        // an empty file (or one which was killed while writing the header)
+        // can just be started over
+        return Ok(None);
+    }
+    crate::asciicast::parse_header(&line)?;
+    let mut len = n as u64;
+    let mut last = std::time::Duration::default();
+    loop {
+        line.clear();
+        let n = file
+            .read_until(b'\n', &mut line)
+            .context(crate::error::ReadFileSync)?;
+        if line.last() != Some(&b'\n') {
+            break;
+        }
+        if let Some((time, _)) = crate::asciicast::parse_event(&line)? {
+            last = time;
+        }
+        len += n as u64;
+    }
+    // the header counts as the start, even if nothing was recorded
+    Ok(Some(RecordedSpan {
+        first: std::time::Duration::default(),
+        last,
+        len,
+    }))
+}
+
+// used when a ttyrec file doesn't record its size and we aren't running in
+// a terminal to take the size from
+const DEFAULT_SIZE: crate::term::Size =
+    crate::term::Size { rows: 24, cols: 80 };
+
+// the parts of a recording that both formats can represent, read into
+// memory all at once. event times are relative to the start of the
+// recording.
+pub struct Transcript {
+    pub size: crate::term::Size,
+    pub timestamp: Option<u64>,
+    pub events: Vec<(std::time::Duration, crate::asciicast::Event)>,
+}
+
+pub fn read_transcript(
+    filename: &str,
+    format: crate::config::RecordingFormat,
+) -> Result<Transcript> {
+    match format {
+        crate::config::RecordingFormat::Ttyrec => {
+            read_ttyrec_transcript(filename)
+        }
+        crate::config::RecordingFormat::Asciicast => {
+            read_asciicast_transcript(filename)
+        }
+    }
+}
+
+fn read_ttyrec_transcript(filename: &str) -> Result<Transcript> {
+    let mut file =
+        std::io::BufReader::new(std::fs::File::open(filename).context(
+            crate::error::OpenFileSync {
+                filename: filename.to_string(),
+            },
+        )?);
+
+    let mut size = None;
+    let mut start = None;
+    let mut events = vec![];
+    while let Some((time, data)) = read_ttyrec_frame(&mut file)? {
+        let start = *start.get_or_insert(time);
+        let time = time.checked_sub(start).unwrap_or_default();
+
+        // resize sequences become real resize events, except for one at the
+        // very start of the recording, which is the initial size
+        let mut pos = 0;
+        for (range, new_size) in crate::term::find_resizes(&data) {
+            if range.start > pos {
+                events.push((
+                    time,
+                    crate::asciicast::Event::Output(
+                        data[pos..range.start].to_vec(),
+                    ),
+                ));
+            }
+            if size.is_none() && events.is_empty() {
+                size = Some(new_size);
+            } else {
+                events
+                    .push((time, crate::asciicast::Event::Resize(new_size)));
+            }
+            pos = range.end;
+        }
+        if pos < data.len() {
+            events.push((
+                time,
+                crate::asciicast::Event::Output(data[pos..].to_vec()),
+            ));
+        }
+    }
+
+    for bookmark in crate::bookmarks::Bookmarks::load(filename)?.list() {
+        events.push((
+            bookmark.time,
+            crate::asciicast::Event::Marker(bookmark.name.clone()),
+        ));
+    }
+    for keystroke in crate::keystrokes::Keystrokes::load(filename)?.list() {
+        events.push((
+            keystroke.time,
+            crate::asciicast::Event::Input(keystroke.data.clone()),
+        ));
+    }
+    // stable, so markers and input end up after any output at the same time
+    events.sort_by_key(|(time, _)| *time);
+
+    Ok(Transcript {
+        size: size.unwrap_or_else(|| {
+            crate::term::Size::get().unwrap_or(DEFAULT_SIZE)
+        }),
+        timestamp: start.map(|start| start.as_secs()),
+        events,
+    })
+}
+
+fn read_asciicast_transcript(filename: &str) -> Result<Transcript> {
+    let file =
+        std::io::BufReader::new(std::fs::File::open(filename).context(
+            crate::error::OpenFileSync {
+                filename: filename.to_string(),
+            },
+        )?);
+    let mut lines = file.split(b'\n');
+
+    let header = if let Some(line) = lines.next() {
+        crate::asciicast::parse_header(
+            &line.context(crate::error::ReadFileSync)?,
+        )?
+    } else {
+        return Err(Error::RecordingTruncated);
+    };
+    let mut events = vec![];
+    for line in lines {
+        if let Some(event) = crate::asciicast::parse_event(
+            &line.context(crate::error::ReadFileSync)?,
+        )? {
+            events.push(event);
+        }
+    }
+
+    Ok(Transcript {
+        size: header.size(),
+        timestamp: header.timestamp,
+        events,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryFrom as _;
+    use std::io::Write as _;
+
+    fn test_frames() -> Vec<Vec<u8>> {
+        (0..100)
+            .map(|i| {
+                if i % 17 == 0 {
+                    b"\x1b[H\x1b[J".to_vec()
+                } else {
+                    format!("line {}\r\n", i).into_bytes()
+                }
+            })
+            .collect()
+    }
+
+    fn write_recording(name: &str) -> (String, Recording) {
+        let filename = test_filename(name);
+        let mut file = std::fs::File::create(&filename).unwrap();
+        let size = crate::term::Size { rows: 24, cols: 80 };
+        let mut recording = Recording::new(
+            &filename,
+            crate::config::RecordingFormat::Ttyrec,
+            size,
+        );
+        recording.keyframe_frames = 10;
+        for (i, data) in test_frames().iter().enumerate() {
+            let time =
+                std::time::Duration::from_secs(u64::try_from(i).unwrap());
+            file.write_all(&ttyrec_frame(time, data)).unwrap();
+            recording.add_frame(time, data);
+        }
+        (filename, recording)
+    }
+
+    fn test_filename(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("teleterm-test-{}-{}", name, std::process::id()))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    fn expected_screens() -> Vec<Vec<u8>> {
+        let mut parser = vt100::Parser::new(24, 80, 0);
+        test_frames()
+            .iter()
+            .map(|data| {
+                parser.process(data);
+                parser.screen().contents_formatted()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_keyframe_state() {
+        // the scroll region and attributes set before a keyframe need to
+        // still apply to the frames after it
+        let mut frames = vec![b"\x1b[2;4r".to_vec(), b"\x1b[1;31m".to_vec()];
+        for i in 0..20 {
+            frames.push(format!("\x1b[4;1H\nline {}", i).into_bytes());
+        }
+
+        let filename = test_filename("keyframe-state");
+        let mut file = std::fs::File::create(&filename).unwrap();
+        let mut recording = Recording::new(
+            &filename,
+            crate::config::RecordingFormat::Ttyrec,
+            crate::term::Size { rows: 24, cols: 80 },
+        );
+        recording.keyframe_frames = 3;
+        let mut parser = vt100::Parser::new(24, 80, 0);
+        let mut expected = vec![];
+        for (i, data) in frames.iter().enumerate() {
+            let time =
+                std::time::Duration::from_secs(u64::try_from(i).unwrap());
+            file.write_all(&ttyrec_frame(time, data)).unwrap();
+            recording.add_frame(time, data);
+            parser.process(data);
+            expected.push(parser.screen().contents_formatted());
+        }
+
+        for idx in (0..frames.len()).rev() {
+            assert_eq!(recording.screen(idx).unwrap(), expected[idx]);
+        }
+        std::fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn test_read_ttyrec_frame() {
+        let time = std::time::Duration::from_micros(1_500_000_123_456);
+        let data = ttyrec_frame(time, b"hello");
+        let mut r = std::io::Cursor::new(data.clone());
+        assert_eq!(
+            read_ttyrec_frame(&mut r).unwrap(),
+            Some((time, b"hello".to_vec()))
+        );
+        assert_eq!(read_ttyrec_frame(&mut r).unwrap(), None);
+        let mut r = std::io::Cursor::new(&data[..8]);
+        assert!(read_ttyrec_frame(&mut r).is_err());
+        let mut r = std::io::Cursor::new(&data[..14]);
+        assert!(read_ttyrec_frame(&mut r).is_err());
+    }
+
+    #[test]
+    fn test_recorded_span() {
+        let secs = std::time::Duration::from_secs;
+        let span = |first, last, len| {
+            Some(RecordedSpan {
+                first: secs(first),
+                last: secs(last),
+                len,
+            })
+        };
+
+        let (filename, _) = write_recording("span");
+        let len = std::fs::metadata(&filename).unwrap().len();
+        assert_eq!(
+            recorded_span(&filename, crate::config::RecordingFormat::Ttyrec)
+                .unwrap(),
+            span(0, 99, len)
+        );
+        std::fs::remove_file(&filename).unwrap();
+        assert_eq!(
+            recorded_span(&filename, crate::config::RecordingFormat::Ttyrec)
+                .unwrap(),
+            None
+        );
+
+        // a partially written frame at the end isn't included
+        let mut contents = ttyrec_frame(secs(1), b"abc");
+        contents.extend(ttyrec_frame(secs(2), b"def"));
+        let complete = contents.len() as u64;
+        contents.extend(&ttyrec_frame(secs(3), b"ghi")[..14]);
+        assert_eq!(
+            ttyrec_span(std::io::Cursor::new(&contents)).unwrap(),
+            span(1, 2, complete)
+        );
+        contents.truncate(complete as usize + 5);
+        assert_eq!(
+            ttyrec_span(std::io::Cursor::new(&contents)).unwrap(),
+            span(1, 2, complete)
+        );
+
+        // the clock went backwards while recording
+        let mut contents = ttyrec_frame(secs(10), b"abc");
+        contents.extend(ttyrec_frame(secs(4), b"def"));
+        let backwards = ttyrec_span(std::io::Cursor::new(&contents))
+            .unwrap()
+            .unwrap();
+        assert_eq!(backwards, span(10, 4, contents.len() as u64).unwrap());
+        assert_eq!(backwards.duration(), secs(0));
+        assert_eq!(span(1, 2, 0).unwrap().duration(), secs(1));
+
+        let mut contents =
+            crate::asciicast::format_header(&crate::asciicast::Header::new(
+                crate::term::Size { rows: 24, cols: 80 },
+                None,
+            ));
+        let header_len = contents.len() as u64;
+        assert_eq!(
+            asciicast_span(std::io::Cursor::new(&contents)).unwrap(),
+            span(0, 0, header_len)
+        );
+        assert_eq!(
+            asciicast_span(std::io::Cursor::new(
+                &contents[..contents.len() - 1]
+            ))
+            .unwrap(),
+            None
+        );
+        for time in &[1, 5, 12] {
+            contents.extend(crate::asciicast::format_event(
+                secs(*time),
+                &crate::asciicast::Event::Output(b"a".to_vec()),
+            ));
+        }
+        let complete = contents.len() as u64;
+        assert_eq!(
+            asciicast_span(std::io::Cursor::new(&contents)).unwrap(),
+            span(0, 12, complete)
+        );
+
+        // a line without its trailing newline isn't included, even if it
+        // is otherwise complete
+        let event = crate::asciicast::format_event(
+            secs(13),
+            &crate::asciicast::Event::Output(b"b".to_vec()),
+        );
+        contents.extend(&event[..event.len() - 1]);
+        assert_eq!(
+            asciicast_span(std::io::Cursor::new(&contents)).unwrap(),
+            span(0, 12, complete)
+        );
+        contents.truncate(complete as usize + 5);
+        assert_eq!(
+            asciicast_span(std::io::Cursor::new(&contents)).unwrap(),
+            span(0, 12, complete)
+        );
+    }
+
+    #[test]
+    fn test_asciicast() {
+        let filename = test_filename("asciicast");
+        let size = crate::term::Size { rows: 24, cols: 80 };
+        let mut file = std::fs::File::create(&filename).unwrap();
+        let header = crate::asciicast::format_header(
+            &crate::asciicast::Header::new(size, None),
+        );
+        file.write_all(&header).unwrap();
+        let mut offset = header.len() as u64;
+        let mut recording = Recording::new(
+            &filename,
+            crate::config::RecordingFormat::Asciicast,
+            size,
+        );
+        recording.keyframe_frames = 10;
+        for (i, data) in test_frames().iter().enumerate() {
+            let time =
+                std::time::Duration::from_secs(u64::try_from(i).unwrap());
+            // events other than output shouldn't count as frames
+            let marker = crate::asciicast::format_event(
+                time,
+                &crate::asciicast::Event::Marker("marker".to_string()),
+            );
+            file.write_all(&marker).unwrap();
+            offset += marker.len() as u64;
+            let line = crate::asciicast::format_event(
+                time,
+                &crate::asciicast::Event::Output(data.clone()),
+            );
+            file.write_all(&line).unwrap();
+            recording.add_frame_at(offset, line.len() as u64, time, data);
+            offset += line.len() as u64;
+        }
+
+        let expected = expected_screens();
+        for &idx in &[0, 55, 12, 99, 98, 3] {
+            assert_eq!(recording.screen(idx).unwrap(), expected[idx]);
+        }
+        std::fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn test_resize() {
+        let filename = test_filename("resize");
+        let mut file = std::fs::File::create(&filename).unwrap();
+        let small = crate::term::Size { rows: 24, cols: 80 };
+        let big = crate::term::Size {
+            rows: 50,
+            cols: 200,
+        };
+        let mut recording = Recording::new(
+            &filename,
+            crate::config::RecordingFormat::Ttyrec,
+            small,
+        );
+        recording.keyframe_frames = 10;
+        let mut parser = vt100::Parser::new(small.rows, small.cols, 0);
+        let mut expected = vec![];
+        for i in 0..50 {
+            let mut data = format!("line {}\r\n", i).into_bytes();
+            if i == 23 {
+                data.extend(big.resize_sequence());
+                data.extend(b"after the resize");
+                parser.process(format!("line {}\r\n", i).as_bytes());
+                parser.set_size(big.rows, big.cols);
+                parser.process(b"after the resize");
+            } else {
+                parser.process(&data);
+            }
+            expected.push(parser.screen().contents_formatted());
+            let time =
+                std::time::Duration::from_secs(u64::try_from(i).unwrap());
+            file.write_all(&ttyrec_frame(time, &data)).unwrap();
+            recording.add_frame(time, &data);
+        }
+
+        assert_eq!(recording.size(0), Some(small));
+        assert_eq!(recording.size(22), Some(small));
+        assert_eq!(recording.size(23), Some(big));
+        assert_eq!(recording.size(49), Some(big));
+        for &idx in &[45, 0, 23, 22, 31, 49] {
+            assert_eq!(recording.screen(idx).unwrap(), expected[idx]);
+        }
+        std::fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn test_seek() {
+        let (filename, mut recording) = write_recording("seek");
+        let expected = expected_screens();
+        assert_eq!(recording.len(), 100);
+        assert_eq!(recording.keyframes.len(), 10);
+        for &idx in &[0, 5, 99, 10, 9, 11, 50, 49, 0, 98] {
+            assert_eq!(recording.screen(idx).unwrap(), expected[idx]);
+        }
+        std::fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn test_multiple_files() {
+        let filenames =
+            vec![test_filename("files-1"), test_filename("files-2")];
+        let size = crate::term::Size { rows: 24, cols: 80 };
+        let mut recording = Recording::new(
+            &filenames[0],
+            crate::config::RecordingFormat::Ttyrec,
+            size,
+        );
+        recording.keyframe_frames = 10;
+        let frames = test_frames();
+        for (file_idx, (filename, chunk)) in
+            filenames.iter().zip(frames.chunks(55)).enumerate()
+        {
+            if file_idx > 0 {
+                recording.add_file(
+                    filename,
+                    crate::config::RecordingFormat::Ttyrec,
+                );
+            }
+            let mut file = std::fs::File::create(filename).unwrap();
+            for (i, data) in chunk.iter().enumerate() {
+                let time =
+                    std::time::Duration::from_secs(u64::try_from(i).unwrap());
+                file.write_all(&ttyrec_frame(time, data)).unwrap();
+                recording.add_frame(time, data);
+            }
+        }
+
+        assert_eq!(recording.len(), 100);
+        assert_eq!(recording.num_files(), 2);
+        assert_eq!(recording.first_frame(1), 55);
+        assert_eq!(recording.file_idx(54), 0);
+        assert_eq!(recording.file_idx(55), 1);
+        let secs = std::time::Duration::from_secs;
+        assert_eq!(recording.time(54), Some(secs(54)));
+        assert_eq!(recording.time(55), Some(secs(54)));
+        assert_eq!(recording.time(99), Some(secs(98)));
+
+        let expected = expected_screens();
+        for &idx in &[0, 54, 55, 99, 53, 56, 10, 70] {
+            assert_eq!(recording.screen(idx).unwrap(), expected[idx]);
+        }
+        // without the keyframe at the start of the second file, the cursor
+        // has to read on from the end of the first file
+        recording.thin_keyframes();
+        recording.thin_keyframes();
+        assert!(recording
+            .keyframes
+            .iter()
+            .all(|keyframe| keyframe.idx != 55));
+        for &idx in &[50, 60, 54, 99, 0, 56] {
+            assert_eq!(recording.screen(idx).unwrap(), expected[idx]);
+        }
+        for filename in filenames {
+            std::fs::remove_file(filename).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_file_sizes() {
+        let filenames = vec![
+            test_filename("file-sizes-1"),
+            test_filename("file-sizes-2"),
+        ];
+        let small = crate::term::Size { rows: 24, cols: 80 };
+        let big = crate::term::Size {
+            rows: 30,
+            cols: 100,
+        };
+        let mut recording = Recording::new(
+            &filenames[0],
+            crate::config::RecordingFormat::Ttyrec,
+            small,
+        );
+        recording.keyframe_frames = 3;
+        let mut parser = vt100::Parser::new(small.rows, small.cols, 0);
+        let mut expected = vec![];
+        for (file_idx, filename) in filenames.iter().enumerate() {
+            if file_idx > 0 {
+                recording.add_file(
+                    filename,
+                    crate::config::RecordingFormat::Ttyrec,
+                );
+                // as for the header of an asciicast file
+                recording.set_initial_size(big);
+                parser.set_size(big.rows, big.cols);
+            }
+            let mut file = std::fs::File::create(filename).unwrap();
+            for i in 0..4 {
+                let data = format!("file {} line {}\r\n", file_idx, i);
+                let time =
+                    std::time::Duration::from_secs(u64::try_from(i).unwrap());
+                file.write_all(&ttyrec_frame(time, data.as_bytes()))
+                    .unwrap();
+                recording.add_frame(time, data.as_bytes());
+                parser.process(data.as_bytes());
+                expected.push(parser.screen().contents_formatted());
+            }
+        }
+
+        // the last frame of the first file is a keyframe, and without the
+        // keyframe at the start of the second file, frames in the second
+        // file are read starting from it
+        assert!(recording.keyframes.iter().any(|keyframe| keyframe.idx == 3));
+        recording.keyframes.retain(|keyframe| keyframe.idx != 4);
+        for &idx in &[5, 0, 4, 7, 3, 6] {
+            recording.cursor = None;
+            assert_eq!(recording.screen(idx).unwrap(), expected[idx]);
+            let (rows, cols) =
+                recording.cursor.as_ref().unwrap().parser.screen().size();
+            let size = crate::term::Size { rows, cols };
+            assert_eq!(size, if idx < 4 { small } else { big });
+        }
+        for filename in filenames {
+            std::fs::remove_file(filename).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let (filename, mut recording) = write_recording("diff");
+        let expected = expected_screens();
+        let mut parser = vt100::Parser::new(24, 80, 0);
+        parser.process(&recording.screen(30).unwrap());
+        for idx in 31..60 {
+            parser.process(&recording.diff(idx).unwrap());
+            assert_eq!(parser.screen().contents_formatted(), expected[idx]);
+        }
+        std::fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn test_times() {
+        let (filename, recording) = write_recording("times");
+        assert_eq!(
+            recording.time(0),
+            Some(std::time::Duration::from_secs(0))
+        );
+        assert_eq!(recording.dur(0), Some(std::time::Duration::from_secs(0)));
+        assert_eq!(
+            recording.time(42),
+            Some(std::time::Duration::from_secs(42))
+        );
+        assert_eq!(
+            recording.dur(42),
+            Some(std::time::Duration::from_secs(1))
+        );
+        assert_eq!(recording.time(100), None);
+        std::fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn test_frame_at() {
+        let (filename, recording) = write_recording("frame_at");
+        let secs = std::time::Duration::from_secs;
+        let millis = std::time::Duration::from_millis;
+        assert_eq!(recording.frame_at(secs(0)), 0);
+        assert_eq!(recording.frame_at(millis(500)), 0);
+        assert_eq!(recording.frame_at(secs(1)), 1);
+        assert_eq!(recording.frame_at(millis(42999)), 42);
+        assert_eq!(recording.frame_at(secs(1000)), 99);
+        std::fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn test_next_idle() {
+        let size = crate::term::Size { rows: 24, cols: 80 };
+        let mut recording = Recording::new(
+            "unused",
+            crate::config::RecordingFormat::Ttyrec,
+            size,
+        );
+        for &secs in &[0, 1, 2, 10, 11, 12, 13, 30, 31] {
+            recording.add_frame(std::time::Duration::from_secs(secs), b"");
+        }
+        let threshold = std::time::Duration::from_secs(5);
+        assert_eq!(recording.next_idle(0, threshold), Some(3));
+        assert_eq!(recording.next_idle(2, threshold), Some(3));
+        assert_eq!(recording.next_idle(3, threshold), Some(7));
+        assert_eq!(recording.next_idle(7, threshold), None);
+        assert_eq!(recording.next_idle(100, threshold), None);
+    }
+
+    #[test]
+    fn test_thin_keyframes() {
+        let size = crate::term::Size { rows: 24, cols: 80 };
+        let mut recording = Recording::new(
+            "unused",
+            crate::config::RecordingFormat::Ttyrec,
+            size,
+        );
+        recording.keyframe_frames = 1;
+        for i in 0..=MAX_KEYFRAMES {
+            recording
+                .add_frame(std::time::Duration::from_secs(i as u64), b"");
+        }
+        assert_eq!(recording.keyframes.len(), MAX_KEYFRAMES / 2 + 1);
+        assert_eq!(recording.keyframe_frames, 2);
+        assert!(recording
+            .keyframes
+            .iter()
+            .enumerate()
+            .all(|(i, keyframe)| keyframe.idx == i * 2));
+    }
+}
@@ -0,0 +1,454 @@
+use crate::prelude::*;
+
+pub mod gif;
+pub mod html;
+pub mod svg;
+
+// frames closer together than this are merged - browsers won't display gif
+// frames any faster than this, and it keeps the svg and html output small
+const MIN_FRAME_INTERVAL: std::time::Duration =
+    std::time::Duration::from_millis(20);
+
+// how long the last frame stays on screen before the animation loops
+pub const END_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
+// the xterm defaults for the first 16 colors
+const ANSI_COLORS: [Rgb; 16] = [
+    (0x00, 0x00, 0x00),
+    (0xcd, 0x00, 0x00),
+    (0x00, 0xcd, 0x00),
+    (0xcd, 0xcd, 0x00),
+    (0x00, 0x00, 0xee),
+    (0xcd, 0x00, 0xcd),
+    (0x00, 0xcd, 0xcd),
+    (0xe5, 0xe5, 0xe5),
+    (0x7f, 0x7f, 0x7f),
+    (0xff, 0x00, 0x00),
+    (0x00, 0xff, 0x00),
+    (0xff, 0xff, 0x00),
+    (0x5c, 0x5c, 0xff),
+    (0xff, 0x00, 0xff),
+    (0x00, 0xff, 0xff),
+    (0xff, 0xff, 0xff),
+];
+const DEFAULT_FG: u8 = 7;
+const DEFAULT_BG: u8 = 0;
+
+pub type Rgb = (u8, u8, u8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Svg,
+    Html,
+    Gif,
+}
+
+impl Format {
+    pub fn try_from(s: &str) -> Result<Self> {
+        match s {
+            "svg" => Ok(Self::Svg),
+            "html" => Ok(Self::Html),
+            "gif" => Ok(Self::Gif),
+            _ => Err(Error::InvalidRenderFormat {
+                value: s.to_string(),
+            }),
+        }
+    }
+
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let ext = std::path::Path::new(filename)
+            .extension()?
+            .to_str()?
+            .to_lowercase();
+        match ext.as_str() {
+            "svg" => Some(Self::Svg),
+            "html" | "htm" => Some(Self::Html),
+            "gif" => Some(Self::Gif),
+            _ => None,
+        }
+    }
+}
+
+pub struct Options {
+    pub start: Option<std::time::Duration>,
+    pub end: Option<std::time::Duration>,
+    pub playback_ratio: f32,
+    pub max_frame_length: Option<std::time::Duration>,
+}
+
+// a screen to display, along with when to display it (relative to the start
+// of the rendered animation, after adjusting for speed and idle time)
+pub struct Frame {
+    pub time: std::time::Duration,
+    pub screen: vt100::Screen,
+}
+
+// feeds the recording through a terminal parser, and returns the screen
+// after each event that falls within the requested time range
+pub fn frames(
+    transcript: &crate::recording::Transcript,
+    options: &Options,
+) -> Vec<Frame> {
+    let mut parser =
+        vt100::Parser::new(transcript.size.rows, transcript.size.cols, 0);
+    let start = options.start.unwrap_or_default();
+    let mut frames = vec![];
+    let mut time = std::time::Duration::default();
+    let mut prev = start;
+    for (event_time, event) in &transcript.events {
+        let event_time = *event_time;
+        if options.end.map_or(false, |end| event_time > end) {
+            break;
+        }
+        // the screen as it was at the start time, which may be the result
+        // of output from before the requested range
+        if event_time >= start && frames.is_empty() {
+            frames.push(Frame {
+                time,
+                screen: parser.screen().clone(),
+            });
+        }
+
+        match event {
+            crate::asciicast::Event::Output(data) => {
+                parser.process(data);
+            }
+            crate::asciicast::Event::Resize(size) => {
+                parser.set_size(size.rows, size.cols);
+            }
+            _ => continue,
+        }
+        if event_time < start {
+            continue;
+        }
+
+        let mut dur = event_time - prev;
+        if let Some(max) = options.max_frame_length {
+            dur = dur.min(max);
+        }
+        time += dur.div_f32(options.playback_ratio);
+        prev = event_time;
+        push_frame(&mut frames, time, parser.screen());
+    }
+    if frames.is_empty() {
+        frames.push(Frame {
+            time,
+            screen: parser.screen().clone(),
+        });
+    }
+    frames
+}
+
+fn push_frame(
+    frames: &mut Vec<Frame>,
+    time: std::time::Duration,
+    screen: &vt100::Screen,
+) {
+    if let Some(last) = frames.last_mut() {
+        if time - last.time < MIN_FRAME_INTERVAL {
+            last.screen = screen.clone();
+            return;
+        }
+    }
+    frames.push(Frame {
+        time,
+        screen: screen.clone(),
+    });
+}
+
+// the total length of the animation, including the pause at the end
+pub fn duration(frames: &[Frame]) -> std::time::Duration {
+    frames
+        .last()
+        .map_or_else(std::time::Duration::default, |frame| frame.time)
+        + END_DELAY
+}
+
+// the largest screen size used by any frame, since the output has to be
+// able to hold all of them
+pub fn max_size(frames: &[Frame]) -> crate::term::Size {
+    frames.iter().fold(
+        crate::term::Size { rows: 0, cols: 0 },
+        |size, frame| {
+            let (rows, cols) = frame.screen.size();
+            crate::term::Size {
+                rows: size.rows.max(rows),
+                cols: size.cols.max(cols),
+            }
+        },
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Style {
+    pub fg: Rgb,
+    pub bg: Rgb,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl Style {
+    pub fn default_fg() -> Rgb {
+        palette_color(DEFAULT_FG)
+    }
+
+    pub fn default_bg() -> Rgb {
+        palette_color(DEFAULT_BG)
+    }
+}
+
+// the style a cell is drawn with, resolving default colors, bright bold
+// text, reverse video, and the cursor (which is drawn as a reversed cell)
+pub fn cell_style(
+    screen: &vt100::Screen,
+    row: u16,
+    col: u16,
+    cell: &vt100::Cell,
+) -> Style {
+    let fg = match cell.fgcolor() {
+        vt100::Color::Default => palette_color(DEFAULT_FG),
+        vt100::Color::Idx(idx) if idx < 8 && cell.bold() => {
+            palette_color(idx + 8)
+        }
+        vt100::Color::Idx(idx) => palette_color(idx),
+        vt100::Color::Rgb(r, g, b) => (r, g, b),
+    };
+    let bg = match cell.bgcolor() {
+        vt100::Color::Default => palette_color(DEFAULT_BG),
+        vt100::Color::Idx(idx) => palette_color(idx),
+        vt100::Color::Rgb(r, g, b) => (r, g, b),
+    };
+    let cursor =
+        !screen.hide_cursor() && screen.cursor_position() == (row, col);
+    let (fg, bg) = if cell.inverse() != cursor {
+        (bg, fg)
+    } else {
+        (fg, bg)
+    };
+    Style {
+        fg,
+        bg,
+        bold: cell.bold(),
+        italic: cell.italic(),
+        underline: cell.underline(),
+    }
+}
+
+// a sequence of cells on a single row which can be drawn together
+pub struct Run {
+    pub col: u16,
+    pub width: u16,
+    pub text: String,
+    pub style: Style,
+}
+
+// splits a row into runs of identically styled cells. wide characters are
+// always given a run to themselves, since fonts rarely draw them at exactly
+// twice the width of other characters.
+pub fn runs(screen: &vt100::Screen, row: u16) -> Vec<Run> {
+    let (_, cols) = screen.size();
+    let mut runs: Vec<Run> = vec![];
+    let mut col = 0;
+    while col < cols {
+        let cell = if let Some(cell) = screen.cell(row, col) {
+            cell
+        } else {
+            break;
+        };
+        let style = cell_style(screen, row, col, cell);
+        let width = if cell.is_wide() { 2 } else { 1 };
+        let contents = cell.contents();
+        let contents = if contents.is_empty() {
+            " ".to_string()
+        } else {
+            contents
+        };
+
+        match runs.last_mut() {
+            Some(run)
+                if width == 1
+                    && run.style == style
+                    && run.col + run.width == col
+                    && !run.wide() =>
+            {
+                run.width += 1;
+                run.text.push_str(&contents);
+            }
+            _ => runs.push(Run {
+                col,
+                width,
+                text: contents,
+                style,
+            }),
+        }
+        col += width;
+    }
+    runs
+}
+
+impl Run {
+    fn wide(&self) -> bool {
+        self.text.chars().count() < usize::from(self.width)
+    }
+
+    pub fn is_blank(&self) -> bool {
+        self.text.chars().all(|c| c == ' ')
+    }
+}
+
+// the standard xterm 256 color palette
+pub fn palette_color(idx: u8) -> Rgb {
+    match idx {
+        0..=15 => ANSI_COLORS[usize::from(idx)],
+        16..=231 => {
+            let idx = idx - 16;
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            (level(idx / 36), level(idx / 6 % 6), level(idx % 6))
+        }
+        _ => {
+            let v = 8 + (idx - 232) * 10;
+            (v, v, v)
+        }
+    }
+}
+
+pub fn hex(color: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.0, color.1, color.2)
+}
+
+// suitable for both html and svg
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            // control characters aren't allowed in xml at all
+            c if c.is_control() => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn transcript(events: &[(u64, &[u8])]) -> crate::recording::Transcript {
+        crate::recording::Transcript {
+            size: crate::term::Size { rows: 4, cols: 20 },
+            timestamp: None,
+            events: events
+                .iter()
+                .map(|(millis, data)| {
+                    (
+                        std::time::Duration::from_millis(*millis),
+                        crate::asciicast::Event::Output(data.to_vec()),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn options() -> Options {
+        Options {
+            start: None,
+            end: None,
+            playback_ratio: 1.0,
+            max_frame_length: None,
+        }
+    }
+
+    fn summarize(frames: &[Frame]) -> Vec<(u128, String)> {
+        frames
+            .iter()
+            .map(|frame| {
+                (
+                    frame.time.as_millis(),
+                    frame.screen.contents().trim_end().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_frames() {
+        let transcript = transcript(&[
+            (0, b"a"),
+            (5, b"b"),
+            (1000, b"c"),
+            (11000, b"d"),
+            (12000, b"e"),
+        ]);
+        assert_eq!(
+            summarize(&frames(&transcript, &options())),
+            vec![
+                (0, "ab".to_string()),
+                (1000, "abc".to_string()),
+                (11000, "abcd".to_string()),
+                (12000, "abcde".to_string()),
+            ]
+        );
+
+        let clamped = Options {
+            max_frame_length: Some(std::time::Duration::from_secs(2)),
+            playback_ratio: 2.0,
+            ..options()
+        };
+        assert_eq!(
+            summarize(&frames(&transcript, &clamped)),
+            vec![
+                (0, "ab".to_string()),
+                (500, "abc".to_string()),
+                (1500, "abcd".to_string()),
+                (2000, "abcde".to_string()),
+            ]
+        );
+
+        let range = Options {
+            start: Some(std::time::Duration::from_secs(1)),
+            end: Some(std::time::Duration::from_secs(11)),
+            ..options()
+        };
+        assert_eq!(
+            summarize(&frames(&transcript, &range)),
+            vec![(0, "abc".to_string()), (10000, "abcd".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_runs() {
+        let mut parser = vt100::Parser::new(2, 10, 0);
+        parser.process(b"ab\x1b[31mcd\x1b[m\xe3\x81\x82e\x1b[?25l");
+        let runs = runs(parser.screen(), 0);
+        let summary: Vec<_> = runs
+            .iter()
+            .map(|run| (run.col, run.width, run.text.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (0, 2, "ab"),
+                (2, 2, "cd"),
+                (4, 2, "\u{3042}"),
+                (6, 4, "e   ")
+            ]
+        );
+        assert_eq!(runs[1].style.fg, palette_color(1));
+        assert_eq!(runs[0].style.fg, Style::default_fg());
+    }
+
+    #[test]
+    fn test_palette_color() {
+        assert_eq!(palette_color(1), (0xcd, 0x00, 0x00));
+        assert_eq!(palette_color(16), (0, 0, 0));
+        assert_eq!(palette_color(196), (0xff, 0, 0));
+        assert_eq!(palette_color(231), (0xff, 0xff, 0xff));
+        assert_eq!(palette_color(232), (8, 8, 8));
+        assert_eq!(palette_color(255), (238, 238, 238));
+    }
+}
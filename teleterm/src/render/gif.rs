@@ -0,0 +1,288 @@
+use crate::prelude::*;
+use ::gif::SetParameter as _;
+use font8x8::UnicodeFonts as _;
+
+// glyphs are 8x8, and each row is drawn twice to get closer to the shape of
+// a real terminal cell
+const CELL_WIDTH: u16 = 8;
+const CELL_HEIGHT: u16 = 16;
+
+// gif frame delays are in hundredths of a second
+const DELAY_UNIT_MILLIS: u128 = 10;
+
+// the gif uses the xterm 256 color palette, so any colors outside of it
+// (from 24-bit color escapes) are drawn with the nearest palette color
+pub fn render<W: std::io::Write>(
+    frames: &[crate::render::Frame],
+    out: W,
+) -> Result<()> {
+    let size = crate::render::max_size(frames);
+    // gif dimensions are limited to 16 bits. every area drawn below is
+    // within the full image, so this is the only size that needs checking.
+    let (width, height) =
+        image_size(size).context(crate::error::GifTooLarge {
+            rows: size.rows,
+            cols: size.cols,
+        })?;
+
+    let palette: Vec<_> = (0..=255)
+        .flat_map(|idx| {
+            let (r, g, b) = crate::render::palette_color(idx);
+            vec![r, g, b]
+        })
+        .collect();
+    let mut encoder = ::gif::Encoder::new(out, width, height, &palette)
+        .context(crate::error::WriteFileSync)?;
+    encoder
+        .set(::gif::Repeat::Infinite)
+        .context(crate::error::WriteFileSync)?;
+
+    let mut colors = Colors::new();
+    let mut prev: Option<Vec<Vec<Option<Cell>>>> = None;
+    let mut pending: Option<(::gif::Frame, std::time::Duration)> = None;
+    for (i, frame) in frames.iter().enumerate() {
+        let delay = frames
+            .get(i + 1)
+            .map_or(crate::render::END_DELAY, |next| next.time - frame.time);
+        let cells = cells(&frame.screen, size);
+
+        // only the area that changed since the previous frame needs to be
+        // included, and frames which didn't change anything visible just
+        // extend how long the previous frame is shown for
+        let changed = prev.as_ref().map_or_else(
+            || Some((0, 0, size.rows, size.cols)),
+            |prev| changed_area(prev, &cells),
+        );
+        if let Some((top, left, bottom, right)) = changed {
+            if let Some((frame, delay)) = pending.take() {
+                write_frame(&mut encoder, frame, delay)?;
+            }
+            let gif_frame =
+                draw(&cells, top, left, bottom, right, &mut colors);
+            pending = Some((gif_frame, delay));
+        } else if let Some((_, pending_delay)) = &mut pending {
+            *pending_delay += delay;
+        }
+        prev = Some(cells);
+    }
+    if let Some((frame, delay)) = pending.take() {
+        write_frame(&mut encoder, frame, delay)?;
+    }
+    Ok(())
+}
+
+fn image_size(size: crate::term::Size) -> Option<(u16, u16)> {
+    Some((
+        size.cols.checked_mul(CELL_WIDTH)?,
+        size.rows.checked_mul(CELL_HEIGHT)?,
+    ))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Cell {
+    contents: String,
+    style: crate::render::Style,
+}
+
+// the contents of every cell of the image, with None for cells outside of
+// the screen (when the screen is smaller than the image)
+fn cells(
+    screen: &vt100::Screen,
+    size: crate::term::Size,
+) -> Vec<Vec<Option<Cell>>> {
+    (0..size.rows)
+        .map(|row| {
+            (0..size.cols)
+                .map(|col| {
+                    let cell = screen.cell(row, col)?;
+                    Some(Cell {
+                        contents: cell.contents(),
+                        style: crate::render::cell_style(
+                            screen, row, col, cell,
+                        ),
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// returns (top, left, bottom, right), in cells, with bottom and right being
+// exclusive
+fn changed_area(
+    prev: &[Vec<Option<Cell>>],
+    cells: &[Vec<Option<Cell>>],
+) -> Option<(u16, u16, u16, u16)> {
+    let mut area: Option<(u16, u16, u16, u16)> = None;
+    for (row, (prev_row, cells_row)) in
+        (0..).zip(prev.iter().zip(cells.iter()))
+    {
+        for (col, (prev_cell, cell)) in
+            (0..).zip(prev_row.iter().zip(cells_row.iter()))
+        {
+            if prev_cell == cell {
+                continue;
+            }
+            area = Some(area.map_or(
+                (row, col, row + 1, col + 1),
+                |(top, left, bottom, right)| {
+                    (
+                        top.min(row),
+                        left.min(col),
+                        bottom.max(row + 1),
+                        right.max(col + 1),
+                    )
+                },
+            ));
+        }
+    }
+    area
+}
+
+fn draw(
+    cells: &[Vec<Option<Cell>>],
+    top: u16,
+    left: u16,
+    bottom: u16,
+    right: u16,
+    colors: &mut Colors,
+) -> ::gif::Frame<'static> {
+    let width = usize::from((right - left) * CELL_WIDTH);
+    let height = usize::from((bottom - top) * CELL_HEIGHT);
+    let bg = colors.index(crate::render::Style::default_bg());
+    let mut buffer = vec![bg; width * height];
+
+    for row in top..bottom {
+        for col in left..right {
+            let cell = if let Some(cell) =
+                &cells[usize::from(row)][usize::from(col)]
+            {
+                cell
+            } else {
+                continue;
+            };
+            let fg = colors.index(cell.style.fg);
+            let bg = colors.index(cell.style.bg);
+            let glyph = glyph(&cell.contents);
+            let x0 = usize::from((col - left) * CELL_WIDTH);
+            let y0 = usize::from((row - top) * CELL_HEIGHT);
+            for y in 0..usize::from(CELL_HEIGHT) {
+                let bits = glyph[y / 2];
+                let underline =
+                    cell.style.underline && y == usize::from(CELL_HEIGHT) - 1;
+                for x in 0..usize::from(CELL_WIDTH) {
+                    // the leftmost pixel is the lowest bit. bold text is
+                    // drawn by smearing each pixel one to the right.
+                    let on = bits & (1 << x) != 0
+                        || (cell.style.bold
+                            && x > 0
+                            && bits & (1 << (x - 1)) != 0)
+                        || underline;
+                    buffer[(y0 + y) * width + x0 + x] =
+                        if on { fg } else { bg };
+                }
+            }
+        }
+    }
+
+    let mut frame = ::gif::Frame::default();
+    frame.left = left * CELL_WIDTH;
+    frame.top = top * CELL_HEIGHT;
+    frame.width = (right - left) * CELL_WIDTH;
+    frame.height = (bottom - top) * CELL_HEIGHT;
+    frame.buffer = std::borrow::Cow::Owned(buffer);
+    frame
+}
+
+fn write_frame<W: std::io::Write>(
+    encoder: &mut ::gif::Encoder<W>,
+    mut frame: ::gif::Frame,
+    delay: std::time::Duration,
+) -> Result<()> {
+    // browsers treat delays shorter than this as much longer, so clamp
+    // them rather than have short frames show up as slow ones
+    let delay = (delay.as_millis() / DELAY_UNIT_MILLIS).max(2);
+    frame.delay =
+        std::convert::TryFrom::try_from(delay).unwrap_or(std::u16::MAX);
+    encoder
+        .write_frame(&frame)
+        .context(crate::error::WriteFileSync)
+}
+
+fn glyph(contents: &str) -> [u8; 8] {
+    let c = contents.chars().next().unwrap_or(' ');
+    font8x8::BASIC_FONTS
+        .get(c)
+        .or_else(|| font8x8::LATIN_FONTS.get(c))
+        .or_else(|| font8x8::BOX_FONTS.get(c))
+        .or_else(|| font8x8::BLOCK_FONTS.get(c))
+        .or_else(|| font8x8::GREEK_FONTS.get(c))
+        .or_else(|| font8x8::MISC_FONTS.get(c))
+        .or_else(|| font8x8::BASIC_FONTS.get('?'))
+        .unwrap_or([0; 8])
+}
+
+// maps colors to the index of the nearest palette color, caching the result
+// since the same few colors are looked up over and over
+struct Colors {
+    cache: std::collections::HashMap<crate::render::Rgb, u8>,
+}
+
+impl Colors {
+    fn new() -> Self {
+        Self {
+            cache: std::collections::HashMap::new(),
+        }
+    }
+
+    fn index(&mut self, color: crate::render::Rgb) -> u8 {
+        *self.cache.entry(color).or_insert_with(|| {
+            (0..=255)
+                .min_by_key(|&idx| {
+                    let (r, g, b) = crate::render::palette_color(idx);
+                    let dist = |a: u8, b: u8| {
+                        let d = i32::from(a) - i32::from(b);
+                        d * d
+                    };
+                    dist(r, color.0) + dist(g, color.1) + dist(b, color.2)
+                })
+                .unwrap()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_colors() {
+        let mut colors = Colors::new();
+        assert_eq!(colors.index(crate::render::palette_color(1)), 1);
+        assert_eq!(colors.index(crate::render::palette_color(100)), 100);
+        assert_eq!(colors.index((0xfe, 0x01, 0x01)), 9);
+        assert_eq!(colors.index((0x80, 0x80, 0x80)), 244);
+    }
+
+    #[test]
+    fn test_image_size() {
+        assert_eq!(
+            image_size(crate::term::Size { rows: 24, cols: 80 }),
+            Some((640, 384))
+        );
+        assert_eq!(
+            image_size(crate::term::Size {
+                rows: 24,
+                cols: 8192
+            }),
+            None
+        );
+        assert_eq!(
+            image_size(crate::term::Size {
+                rows: 4096,
+                cols: 80
+            }),
+            None
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use crate::prelude::*;
+use std::fmt::Write as _;
+
+// a self-contained page with a small player, so that the output can be
+// opened directly or attached to a ticket without needing anything else
+const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>teleterm recording</title>
+<style>
+body { background: #333; margin: 1em; }
+.tt-screen {
+  display: inline-block;
+  margin: 0;
+  padding: 0.5em;
+  font-family: Menlo, Consolas, "DejaVu Sans Mono", monospace;
+  font-size: 15px;
+  line-height: 18px;
+  color: __FG__;
+  background: __BG__;
+}
+.tt-screen .b { font-weight: bold; }
+.tt-screen .i { font-style: italic; }
+.tt-screen .u { text-decoration: underline; }
+.tt-controls {
+  display: flex;
+  align-items: center;
+  gap: 0.5em;
+  margin-top: 0.5em;
+  color: #ccc;
+  font-family: sans-serif;
+}
+.tt-controls input { flex: 1; }
+</style>
+</head>
+<body>
+<div class="tt-player">
+<pre class="tt-screen" id="screen"></pre>
+<div class="tt-controls">
+<button id="toggle">pause</button>
+<input type="range" id="seek" min="0" max="__DURATION__" value="0">
+<span id="time"></span>
+</div>
+</div>
+<script>
+(function() {
+  var frames = __FRAMES__;
+  var duration = __DURATION__;
+  var screen = document.getElementById("screen");
+  var toggle = document.getElementById("toggle");
+  var seek = document.getElementById("seek");
+  var time = document.getElementById("time");
+  var playing = true;
+  var offset = 0;
+  var start = null;
+  var current = -1;
+
+  function format(ms) {
+    var secs = Math.floor(ms / 1000);
+    var mins = Math.floor(secs / 60);
+    secs = secs % 60;
+    return mins + ":" + (secs < 10 ? "0" : "") + secs;
+  }
+
+  function show(t) {
+    var lo = 0, hi = frames.length - 1;
+    while (lo < hi) {
+      var mid = Math.ceil((lo + hi) / 2);
+      if (frames[mid][0] <= t) { lo = mid; } else { hi = mid - 1; }
+    }
+    if (lo !== current) {
+      screen.innerHTML = frames[lo][1];
+      current = lo;
+    }
+    seek.value = t;
+    time.textContent = format(t) + " / " + format(duration);
+  }
+
+  function tick(now) {
+    if (playing) {
+      if (start === null) { start = now - offset; }
+      offset = (now - start) % duration;
+      show(offset);
+    }
+    window.requestAnimationFrame(tick);
+  }
+
+  toggle.addEventListener("click", function() {
+    playing = !playing;
+    start = null;
+    toggle.textContent = playing ? "pause" : "play";
+  });
+  seek.addEventListener("input", function() {
+    offset = Number(seek.value);
+    start = null;
+    show(offset);
+  });
+
+  show(0);
+  window.requestAnimationFrame(tick);
+})();
+</script>
+</body>
+</html>
+"#;
+
+pub fn render<W: std::io::Write>(
+    frames: &[crate::render::Frame],
+    mut out: W,
+) -> Result<()> {
+    let frames_json: Vec<_> = frames
+        .iter()
+        .map(|frame| {
+            serde_json::json!([
+                (frame.time.as_secs_f64() * 1000.0).round(),
+                screen_html(&frame.screen)
+            ])
+        })
+        .collect();
+    // a literal "</script>" in the recording would end the script early
+    let frames_json = serde_json::to_string(&frames_json)
+        .unwrap()
+        .replace("</", "<\\/");
+
+    let html = TEMPLATE
+        .replace(
+            "__FG__",
+            &crate::render::hex(crate::render::Style::default_fg()),
+        )
+        .replace(
+            "__BG__",
+            &crate::render::hex(crate::render::Style::default_bg()),
+        )
+        .replace(
+            "__DURATION__",
+            &crate::render::duration(frames).as_millis().to_string(),
+        )
+        .replace("__FRAMES__", &frames_json);
+
+    out.write_all(html.as_bytes())
+        .context(crate::error::WriteFileSync)?;
+    out.flush().context(crate::error::WriteFileSync)?;
+    Ok(())
+}
+
+fn screen_html(screen: &vt100::Screen) -> String {
+    let (rows, _) = screen.size();
+    let default_fg = crate::render::Style::default_fg();
+    let default_bg = crate::render::Style::default_bg();
+    let mut html = String::new();
+    for row in 0..rows {
+        if row > 0 {
+            html.push('\n');
+        }
+        for run in crate::render::runs(screen, row) {
+            let mut styles = vec![];
+            if run.style.fg != default_fg {
+                styles.push(format!(
+                    "color:{}",
+                    crate::render::hex(run.style.fg)
+                ));
+            }
+            if run.style.bg != default_bg {
+                styles.push(format!(
+                    "background:{}",
+                    crate::render::hex(run.style.bg)
+                ));
+            }
+            let mut classes = vec![];
+            if run.style.bold {
+                classes.push("b");
+            }
+            if run.style.italic {
+                classes.push("i");
+            }
+            if run.style.underline {
+                classes.push("u");
+            }
+
+            let text = crate::render::escape(&run.text);
+            if styles.is_empty() && classes.is_empty() {
+                html.push_str(&text);
+                continue;
+            }
+            html.push_str("<span");
+            if !styles.is_empty() {
+                write!(html, r#" style="{}""#, styles.join(";")).unwrap();
+            }
+            if !classes.is_empty() {
+                write!(html, r#" class="{}""#, classes.join(" ")).unwrap();
+            }
+            write!(html, ">{}</span>", text).unwrap();
+        }
+    }
+    html
+}
@@ -0,0 +1,137 @@
+use crate::prelude::*;
+use std::fmt::Write as _;
+
+const CELL_WIDTH: u32 = 9;
+const CELL_HEIGHT: u32 = 18;
+const FONT_SIZE: u32 = 15;
+// distance from the top of a cell to the text baseline
+const BASELINE: u32 = 14;
+
+// every frame is drawn into a single tall image, one below the other, and
+// a css animation steps through them by moving the image up one frame's
+// height at a time. this needs only one animation no matter how many
+// frames there are, unlike toggling the visibility of each frame.
+pub fn render<W: std::io::Write>(
+    frames: &[crate::render::Frame],
+    mut out: W,
+) -> Result<()> {
+    let size = crate::render::max_size(frames);
+    let width = u32::from(size.cols) * CELL_WIDTH;
+    let height = u32::from(size.rows) * CELL_HEIGHT;
+    let duration = crate::render::duration(frames);
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xml:space="preserve" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = width,
+        h = height
+    )
+    .unwrap();
+    svg.push_str("<style>\n");
+    writeln!(
+        svg,
+        "text{{font-family:Menlo,Consolas,\"DejaVu Sans Mono\",monospace;font-size:{}px;white-space:pre}}",
+        FONT_SIZE
+    )
+    .unwrap();
+    svg.push_str(
+        ".b{font-weight:bold}.i{font-style:italic}.u{text-decoration:underline}\n",
+    );
+    if frames.len() > 1 {
+        svg.push_str("@keyframes play{");
+        for (i, frame) in frames.iter().enumerate() {
+            write!(
+                svg,
+                "{:.3}%{{transform:translateY(-{}px)}}",
+                frame.time.as_secs_f64() / duration.as_secs_f64() * 100.0,
+                i as u64 * u64::from(height)
+            )
+            .unwrap();
+        }
+        svg.push_str("}\n");
+        writeln!(
+            svg,
+            ".frames{{animation:play {}ms step-end infinite}}",
+            duration.as_millis()
+        )
+        .unwrap();
+    }
+    svg.push_str("</style>\n");
+    writeln!(
+        svg,
+        r#"<rect width="100%" height="100%" fill="{}"/>"#,
+        crate::render::hex(crate::render::Style::default_bg())
+    )
+    .unwrap();
+
+    svg.push_str("<g class=\"frames\">\n");
+    for (i, frame) in frames.iter().enumerate() {
+        writeln!(
+            svg,
+            r#"<g transform="translate(0 {})">"#,
+            i as u64 * u64::from(height)
+        )
+        .unwrap();
+        draw_screen(&mut svg, &frame.screen);
+        svg.push_str("</g>\n");
+    }
+    svg.push_str("</g>\n</svg>\n");
+
+    out.write_all(svg.as_bytes())
+        .context(crate::error::WriteFileSync)?;
+    out.flush().context(crate::error::WriteFileSync)?;
+    Ok(())
+}
+
+fn draw_screen(svg: &mut String, screen: &vt100::Screen) {
+    let (rows, _) = screen.size();
+    let default_bg = crate::render::Style::default_bg();
+    for row in 0..rows {
+        let y = u32::from(row) * CELL_HEIGHT;
+        for run in crate::render::runs(screen, row) {
+            let x = u32::from(run.col) * CELL_WIDTH;
+            let width = u32::from(run.width) * CELL_WIDTH;
+            if run.style.bg != default_bg {
+                writeln!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+                    x,
+                    y,
+                    width,
+                    CELL_HEIGHT,
+                    crate::render::hex(run.style.bg)
+                )
+                .unwrap();
+            }
+            if run.is_blank() && !run.style.underline {
+                continue;
+            }
+
+            let mut classes = vec![];
+            if run.style.bold {
+                classes.push("b");
+            }
+            if run.style.italic {
+                classes.push("i");
+            }
+            if run.style.underline {
+                classes.push("u");
+            }
+            write!(
+                svg,
+                r#"<text x="{}" y="{}" fill="{}" textLength="{}""#,
+                x,
+                y + BASELINE,
+                crate::render::hex(run.style.fg),
+                width
+            )
+            .unwrap();
+            if !classes.is_empty() {
+                write!(svg, r#" class="{}""#, classes.join(" ")).unwrap();
+            }
+            writeln!(svg, ">{}</text>", crate::render::escape(&run.text))
+                .unwrap();
+        }
+    }
+}
@@ -49,6 +49,13 @@ struct TerminalInfo {
     size: crate::term::Size,
 }
 
+#[derive(Debug, Clone, Default)]
+struct StreamMetadata {
+    title: String,
+    description: String,
+    tags: Vec<String>,
+}
+
 #[allow(clippy::large_enum_variant)]
 // XXX https://github.com/rust-lang/rust/issues/64362
 #[allow(dead_code)]
@@ -57,15 +64,20 @@ enum ConnectionState {
     LoggingIn {
         auth_type: crate::protocol::AuthType,
         term_info: TerminalInfo,
+        oauth_device: Option<crate::oauth::DeviceAuthorization>,
     },
     LoggedIn {
+        auth_type: crate::protocol::AuthType,
         username: String,
+        groups: Vec<String>,
         term_info: TerminalInfo,
     },
     Streaming {
         username: String,
         term_info: TerminalInfo,
         term: vt100::Parser,
+        paused: bool,
+        metadata: StreamMetadata,
     },
     Watching {
         username: String,
@@ -139,6 +151,41 @@ impl ConnectionState {
         }
     }
 
+    fn metadata(&self) -> Option<&StreamMetadata> {
+        match self {
+            Self::Accepted => None,
+            Self::LoggingIn { .. } => None,
+            Self::LoggedIn { .. } => None,
+            Self::Streaming { metadata, .. } => Some(metadata),
+            Self::Watching { .. } => None,
+        }
+    }
+
+    fn metadata_mut(&mut self) -> Option<&mut StreamMetadata> {
+        match self {
+            Self::Accepted => None,
+            Self::LoggingIn { .. } => None,
+            Self::LoggedIn { .. } => None,
+            Self::Streaming { metadata, .. } => Some(metadata),
+            Self::Watching { .. } => None,
+        }
+    }
+
+    fn paused(&self) -> bool {
+        match self {
+            Self::Streaming { paused, .. } => *paused,
+            _ => false,
+        }
+    }
+
+    fn set_paused(&mut self, new_paused: bool) {
+        if let Self::Streaming { paused, .. } = self {
+            *paused = new_paused;
+        } else {
+            unreachable!()
+        }
+    }
+
     fn watch_id(&self) -> Option<&str> {
         match self {
             Self::Accepted => None,
@@ -151,13 +198,16 @@ impl ConnectionState {
 
     fn login_plain(
         &mut self,
+        auth_type: crate::protocol::AuthType,
         username: &str,
         term_type: &str,
         size: crate::term::Size,
     ) {
         if let Self::Accepted = self {
             *self = Self::LoggedIn {
+                auth_type,
                 username: username.to_string(),
+                groups: vec![],
                 term_info: TerminalInfo {
                     term: term_type.to_string(),
                     size,
@@ -168,7 +218,7 @@ impl ConnectionState {
         }
     }
 
-    fn login_oauth_start(
+    fn login_start(
         &mut self,
         auth_type: crate::protocol::AuthType,
         term_type: &str,
@@ -181,16 +231,28 @@ impl ConnectionState {
                     term: term_type.to_string(),
                     size,
                 },
+                oauth_device: None,
             };
         } else {
             unreachable!()
         }
     }
 
+    fn take_oauth_device(
+        &mut self,
+    ) -> Option<crate::oauth::DeviceAuthorization> {
+        if let Self::LoggingIn { oauth_device, .. } = self {
+            oauth_device.take()
+        } else {
+            None
+        }
+    }
+
     fn stream(&mut self) {
         if let Self::LoggedIn {
             username,
             term_info,
+            ..
         } = std::mem::replace(self, Self::Accepted)
         {
             let size = term_info.size;
@@ -198,6 +260,8 @@ impl ConnectionState {
                 username,
                 term_info,
                 term: vt100::Parser::new(size.rows, size.cols, 0),
+                paused: false,
+                metadata: StreamMetadata::default(),
             };
         } else {
             unreachable!()
@@ -208,6 +272,7 @@ impl ConnectionState {
         if let Self::LoggedIn {
             username,
             term_info,
+            ..
         } = std::mem::replace(self, Self::Accepted)
         {
             *self = Self::Watching {
@@ -221,6 +286,13 @@ impl ConnectionState {
     }
 }
 
+// the challenge we sent to a client logging in with an ssh key, which it
+// must sign with one of that user's authorized keys
+struct SshKeyChallenge {
+    username: String,
+    challenge: Vec<u8>,
+}
+
 struct Connection<
     S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static,
 > {
@@ -232,6 +304,7 @@ struct Connection<
     state: ConnectionState,
     last_activity: std::time::Instant,
     oauth_client: Option<crate::oauth::Oauth>,
+    ssh_key_challenge: Option<SshKeyChallenge>,
 }
 
 impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
@@ -255,6 +328,7 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
             state: ConnectionState::new(),
             last_activity: std::time::Instant::now(),
             oauth_client: None,
+            ssh_key_challenge: None,
         }
     }
 
@@ -265,6 +339,7 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
             ConnectionState::LoggedIn {
                 username,
                 term_info,
+                ..
             } => (username, term_info),
             ConnectionState::Streaming {
                 username,
@@ -277,10 +352,16 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
                 ..
             } => (username, term_info),
         };
-        let title = self
-            .state
-            .term()
-            .map_or("", |parser| parser.screen().title());
+        let metadata = self.state.metadata();
+        // a title set explicitly by the streamer takes precedence over one
+        // set by the running program via terminal escape sequences
+        let title = metadata
+            .map(|metadata| metadata.title.as_str())
+            .filter(|title| !title.is_empty())
+            .or_else(|| {
+                self.state.term().map(|parser| parser.screen().title())
+            })
+            .unwrap_or("");
 
         // i don't really care if things break for a connection that has been
         // idle for 136 years
@@ -295,6 +376,13 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
                 .as_secs() as u32,
             title: title.to_string(),
             watchers,
+            paused: self.state.paused(),
+            description: metadata
+                .map(|metadata| metadata.description.clone())
+                .unwrap_or_default(),
+            tags: metadata
+                .map(|metadata| metadata.tags.clone())
+                .unwrap_or_default(),
         })
     }
 
@@ -324,6 +412,13 @@ pub struct Server<
         crate::protocol::AuthType,
         crate::oauth::Config,
     >,
+    password_verifier: Option<std::sync::Arc<dyn crate::auth::Verifier>>,
+    failed_logins: std::sync::Arc<crate::auth::FailedLogins>,
+    authorized_keys:
+        Option<std::sync::Arc<crate::auth::ssh_key::AuthorizedKeys>>,
+    tokens: Option<std::sync::Arc<crate::auth::token::Tokens>>,
+    roles: crate::config::Roles,
+    allow_unencrypted_passwords: bool,
 }
 
 impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
@@ -339,6 +434,13 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
             crate::protocol::AuthType,
             crate::oauth::Config,
         >,
+        password_verifier: Option<std::sync::Arc<dyn crate::auth::Verifier>>,
+        authorized_keys: Option<
+            std::sync::Arc<crate::auth::ssh_key::AuthorizedKeys>,
+        >,
+        tokens: Option<std::sync::Arc<crate::auth::token::Tokens>>,
+        roles: crate::config::Roles,
+        allow_unencrypted_passwords: bool,
     ) -> Self {
         Self {
             read_timeout,
@@ -350,6 +452,14 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
             ),
             allowed_auth_types,
             oauth_configs,
+            password_verifier,
+            failed_logins: std::sync::Arc::new(
+                crate::auth::FailedLogins::default(),
+            ),
+            authorized_keys,
+            tokens,
+            roles,
+            allow_unencrypted_passwords,
         }
     }
 
@@ -387,11 +497,60 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
                     conn.id,
                     username
                 );
-                conn.state.login_plain(username, term_type, size);
+                conn.state.login_plain(ty, username, term_type, size);
                 conn.send_message(crate::protocol::Message::logged_in(
                     username,
                 ));
             }
+            crate::protocol::Auth::Password { username, password } => {
+                return self.handle_password_login(
+                    conn, auth, username, password, term_type, size,
+                );
+            }
+            crate::protocol::Auth::SshKey { username, .. } => {
+                // like passwords, refuse ssh key logins when there are no
+                // keys to check them against
+                if self.authorized_keys.is_none() {
+                    return Err(Error::AuthTypeNotAllowed { ty });
+                }
+                log::info!(
+                    "{}: login_start({}, {})",
+                    auth.name(),
+                    conn.id,
+                    username
+                );
+                let challenge = crate::auth::ssh_key::challenge();
+                conn.state.login_start(ty, term_type, size);
+                conn.send_message(
+                    crate::protocol::Message::ssh_key_challenge(&challenge),
+                );
+                conn.ssh_key_challenge = Some(SshKeyChallenge {
+                    username: username.clone(),
+                    challenge,
+                });
+            }
+            crate::protocol::Auth::Token { token } => {
+                let tokens = self
+                    .tokens
+                    .as_ref()
+                    .context(crate::error::AuthTypeNotAllowed { ty })?;
+                let username = if let Some(username) = tokens.verify(token)? {
+                    username
+                } else {
+                    log::warn!("{}: failed login({})", auth.name(), conn.id);
+                    return Err(Error::AuthenticationFailed);
+                };
+                log::info!(
+                    "{}: login({}, {})",
+                    auth.name(),
+                    conn.id,
+                    username
+                );
+                conn.state.login_plain(ty, &username, term_type, size);
+                conn.send_message(crate::protocol::Message::logged_in(
+                    &username,
+                ));
+            }
             oauth if oauth.is_oauth() => {
                 log::info!(
                     "{}: login(oauth({}.{}), {:?})",
@@ -417,6 +576,86 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
         Ok(None)
     }
 
+    fn handle_password_login(
+        &mut self,
+        conn: &mut Connection<S>,
+        auth: &crate::protocol::Auth,
+        username: &str,
+        password: &str,
+        term_type: &str,
+        size: crate::term::Size,
+    ) -> Result<
+        Option<
+            Box<
+                dyn futures::Future<
+                        Item = (ConnectionState, crate::protocol::Message),
+                        Error = Error,
+                    > + Send,
+            >,
+        >,
+    > {
+        let ty = auth.auth_type();
+        // the password is sent as is, so don't let it go over the network in
+        // the clear unless the server was explicitly configured to allow it
+        if !self.allow_unencrypted_passwords {
+            return Err(Error::PasswordLoginRequiresTls);
+        }
+        // the auth type is allowed by default, so refuse it here rather
+        // than accepting any password when there is nothing to check it
+        // against
+        let verifier = self
+            .password_verifier
+            .as_ref()
+            .context(crate::error::AuthTypeNotAllowed { ty })?
+            .clone();
+        if self.failed_logins.locked(username) {
+            log::warn!(
+                "{}: too many failed logins({}, {})",
+                auth.name(),
+                conn.id,
+                username
+            );
+            return Err(Error::TooManyFailedLogins);
+        }
+
+        log::info!("{}: login_start({}, {})", auth.name(), conn.id, username);
+        conn.state.login_start(ty, term_type, size);
+
+        let failed_logins = self.failed_logins.clone();
+        let name = auth.name();
+        let id = conn.id.clone();
+        let username = username.to_string();
+        let term_type = term_type.to_string();
+        let fut = crate::auth::verify_blocking(verifier, &username, password)
+            .and_then(move |verified| {
+                if !verified {
+                    log::warn!(
+                        "{}: failed login({}, {})",
+                        name,
+                        id,
+                        username
+                    );
+                    failed_logins.record(&username);
+                    return Err(Error::AuthenticationFailed);
+                }
+                failed_logins.clear(&username);
+                log::info!("{}: login({}, {})", name, id, username);
+                Ok((
+                    ConnectionState::LoggedIn {
+                        auth_type: ty,
+                        username: username.clone(),
+                        groups: vec![],
+                        term_info: TerminalInfo {
+                            term: term_type,
+                            size,
+                        },
+                    },
+                    crate::protocol::Message::logged_in(&username),
+                ))
+            });
+        Ok(Some(Box::new(fut)))
+    }
+
     fn handle_oauth_login_cli(
         &mut self,
         conn: &mut Connection<S>,
@@ -448,29 +687,55 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
                 .get_access_token_from_refresh_token()
                 .and_then(move |access_token| match ty {
                     crate::protocol::AuthType::RecurseCenter => {
-                        crate::auth::recurse_center::get_username(
-                            &access_token,
-                        )
+                        crate::auth::recurse_center::get_user(&access_token)
                     }
                     _ => unreachable!(),
                 })
-                .map(move |username| {
+                .map(move |user| {
                     (
                         ConnectionState::LoggedIn {
-                            username: username.clone(),
+                            auth_type: ty,
+                            username: user.name.clone(),
+                            groups: user.groups,
                             term_info: TerminalInfo {
                                 term: term_type,
                                 size,
                             },
                         },
-                        crate::protocol::Message::logged_in(&username),
+                        crate::protocol::Message::logged_in(&user.name),
+                    )
+                });
+            Ok(Some(Box::new(fut)))
+        } else if client.supports_device_authorization() {
+            // the device code is stored in the LoggingIn state, and we start
+            // polling for the access token once the client has shown the
+            // user code to the user
+            conn.state.login_start(ty, term_type, size);
+            let term_info = conn.state.term_info().unwrap().clone();
+            let user_id = client.user_id().to_string();
+            let fut =
+                client.request_device_authorization().map(move |device| {
+                    let msg =
+                        crate::protocol::Message::oauth_cli_device_request(
+                            &device.verification_uri,
+                            &device.user_code,
+                            &user_id,
+                        );
+                    (
+                        ConnectionState::LoggingIn {
+                            auth_type: ty,
+                            term_info,
+                            oauth_device: Some(device),
+                        },
+                        msg,
                     )
                 });
+            conn.oauth_client = Some(client);
             Ok(Some(Box::new(fut)))
         } else {
             conn.oauth_client = Some(client);
             let client = conn.oauth_client.as_ref().unwrap();
-            conn.state.login_oauth_start(ty, term_type, size);
+            conn.state.login_start(ty, term_type, size);
             let authorize_url = client.generate_authorize_url();
             let user_id = client.user_id().to_string();
             conn.send_message(crate::protocol::Message::oauth_cli_request(
@@ -520,7 +785,11 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
                 Error::InvalidWatchId { id: id.to_string() }
             })?;
             let (rows, cols) = term.screen().size();
-            let data = term.screen().contents_formatted();
+            let data = if stream_conn.state.paused() {
+                paused_screen(crate::term::Size { rows, cols })
+            } else {
+                term.screen().contents_formatted()
+            };
 
             log::info!("{}: watch({}, {})", conn.id, username, id);
             conn.state.watch(&id);
@@ -551,10 +820,20 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
         conn: &mut Connection<S>,
         data: &[u8],
     ) -> Result<()> {
+        let paused = conn.state.paused();
         let parser = conn.state.term_mut().unwrap();
 
         let screen = parser.screen().clone();
         parser.process(data);
+        conn.last_activity = std::time::Instant::now();
+
+        // keep the server's copy of the screen up to date while paused, so
+        // that watchers can be sent all of it on unpause, but don't send any
+        // of the output itself
+        if paused {
+            return Ok(());
+        }
+
         let diff = parser.screen().contents_diff(&screen);
         for watch_conn in self.watchers_mut() {
             let watch_id = watch_conn.state.watch_id().unwrap();
@@ -565,11 +844,79 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
             }
         }
 
+        Ok(())
+    }
+
+    fn handle_message_paused(
+        &mut self,
+        conn: &mut Connection<S>,
+        paused: bool,
+    ) -> Result<()> {
+        if conn.state.paused() == paused {
+            return Ok(());
+        }
+
+        let username = conn.state.username().unwrap();
+        if paused {
+            log::info!("{}: pause({})", conn.id, username);
+        } else {
+            log::info!("{}: unpause({})", conn.id, username);
+        }
+        conn.state.set_paused(paused);
+
+        // on unpause, watchers are sent the whole current screen (and its
+        // size, since resizes aren't sent while paused either)
+        let screen = conn.state.term().unwrap().screen();
+        let (rows, cols) = screen.size();
+        let size = crate::term::Size { rows, cols };
+        let data = if paused {
+            paused_screen(size)
+        } else {
+            screen.contents_formatted()
+        };
+        for watch_conn in self.watchers_mut() {
+            let watch_id = watch_conn.state.watch_id().unwrap();
+            if conn.id == watch_id {
+                if !paused {
+                    watch_conn
+                        .send_message(crate::protocol::Message::resize(size));
+                }
+                watch_conn.send_message(
+                    crate::protocol::Message::terminal_output(&data),
+                );
+            }
+        }
+
         conn.last_activity = std::time::Instant::now();
 
         Ok(())
     }
 
+    fn handle_message_metadata(
+        &mut self,
+        conn: &mut Connection<S>,
+        title: String,
+        description: String,
+        tags: Vec<String>,
+    ) -> Result<()> {
+        let username = conn.state.username().unwrap();
+        log::info!(
+            "{}: metadata({}, {:?}, {:?}, {:?})",
+            conn.id,
+            username,
+            title,
+            description,
+            tags
+        );
+
+        let metadata = conn.state.metadata_mut().unwrap();
+        metadata.title = title;
+        metadata.description = description;
+        metadata.tags = tags;
+
+        Ok(())
+    }
+
     fn handle_message_list_sessions(
         &mut self,
         conn: &mut Connection<S>,
@@ -613,6 +960,11 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
             parser.set_size(size.rows, size.cols);
         }
 
+        // watchers are sent the new size on unpause
+        if conn.state.paused() {
+            return Ok(());
+        }
+
         for watch_conn in self.watchers_mut() {
             let watch_id = watch_conn.state.watch_id().unwrap();
             if conn.id == watch_id {
@@ -628,6 +980,7 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
         &mut self,
         conn: &mut Connection<S>,
         code: &str,
+        redirect_port: Option<u16>,
     ) -> Result<
         Option<
             Box<
@@ -638,11 +991,19 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
             >,
         >,
     > {
-        let client = conn.oauth_client.take().ok_or_else(|| {
+        let mut client = conn.oauth_client.take().ok_or_else(|| {
             Error::UnexpectedMessage {
-                message: crate::protocol::Message::oauth_cli_response(code),
+                message: crate::protocol::Message::oauth_cli_response(
+                    code,
+                    redirect_port,
+                ),
             }
         })?;
+        // the redirect url used when exchanging the code has to match the
+        // one the code was issued for
+        if let Some(port) = redirect_port {
+            client.set_redirect_port(port)?;
+        }
 
         let ty = conn.state.auth_type().unwrap();
         let term_info = conn.state.term_info().unwrap().clone();
@@ -650,23 +1011,121 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
             .get_access_token_from_auth_code(code)
             .and_then(move |access_token| match ty {
                 crate::protocol::AuthType::RecurseCenter => {
-                    crate::auth::recurse_center::get_username(&access_token)
+                    crate::auth::recurse_center::get_user(&access_token)
                 }
                 _ => unreachable!(),
             })
-            .map(|username| {
+            .map(|user| {
                 (
                     ConnectionState::LoggedIn {
+                        auth_type: ty,
                         term_info,
-                        username: username.clone(),
+                        username: user.name.clone(),
+                        groups: user.groups,
                     },
-                    crate::protocol::Message::logged_in(&username),
+                    crate::protocol::Message::logged_in(&user.name),
+                )
+            });
+
+        Ok(Some(Box::new(fut)))
+    }
+
+    fn handle_message_oauth_cli_device_response(
+        &mut self,
+        conn: &mut Connection<S>,
+    ) -> Result<
+        Option<
+            Box<
+                dyn futures::Future<
+                        Item = (ConnectionState, crate::protocol::Message),
+                        Error = Error,
+                    > + Send,
+            >,
+        >,
+    > {
+        let client = conn.oauth_client.take();
+        let device = conn.state.take_oauth_device();
+        let (client, device) = match (client, device) {
+            (Some(client), Some(device)) => (client, device),
+            _ => {
+                let message =
+                    crate::protocol::Message::oauth_cli_device_response();
+                return Err(Error::UnexpectedMessage { message });
+            }
+        };
+
+        let ty = conn.state.auth_type().unwrap();
+        let term_info = conn.state.term_info().unwrap().clone();
+        let fut = client
+            .get_access_token_from_device_code(device)
+            .and_then(move |access_token| match ty {
+                crate::protocol::AuthType::RecurseCenter => {
+                    crate::auth::recurse_center::get_user(&access_token)
+                }
+                _ => unreachable!(),
+            })
+            .map(|user| {
+                (
+                    ConnectionState::LoggedIn {
+                        auth_type: ty,
+                        term_info,
+                        username: user.name.clone(),
+                        groups: user.groups,
+                    },
+                    crate::protocol::Message::logged_in(&user.name),
                 )
             });
 
         Ok(Some(Box::new(fut)))
     }
 
+    fn handle_message_ssh_key_response(
+        &mut self,
+        conn: &mut Connection<S>,
+        public_key: &[u8],
+        signature: &[u8],
+    ) -> Result<
+        Option<
+            Box<
+                dyn futures::Future<
+                        Item = (ConnectionState, crate::protocol::Message),
+                        Error = Error,
+                    > + Send,
+            >,
+        >,
+    > {
+        let SshKeyChallenge {
+            username,
+            challenge,
+        } = conn.ssh_key_challenge.take().ok_or_else(|| {
+            Error::UnexpectedMessage {
+                message: crate::protocol::Message::ssh_key_response(
+                    public_key, signature,
+                ),
+            }
+        })?;
+
+        let authorized_keys = self.authorized_keys.as_ref().unwrap();
+        if !authorized_keys
+            .verify(&username, &challenge, public_key, signature)?
+        {
+            log::warn!("ssh_key: failed login({}, {})", conn.id, username);
+            return Err(Error::AuthenticationFailed);
+        }
+        log::info!("ssh_key: login({}, {})", conn.id, username);
+
+        let term_info = conn.state.term_info().unwrap().clone();
+        Ok(Some(Box::new(futures::future::ok((
+            ConnectionState::LoggedIn {
+                auth_type: crate::protocol::AuthType::SshKey,
+                term_info,
+                username: username.clone(),
+                groups: vec![],
+            },
+            crate::protocol::Message::logged_in(&username),
+        )))))
+    }
+
     fn handle_accepted_message(
         &mut self,
         conn: &mut Connection<S>,
@@ -683,18 +1142,29 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
     > {
         match message {
             crate::protocol::Message::Login {
+                proto_version,
                 auth,
                 auth_client,
                 term_type,
                 size,
-                ..
-            } => self.handle_message_login(
-                conn,
-                &auth,
-                auth_client,
-                &term_type,
-                size,
-            ),
+            } => {
+                // messages are sent without any field names or lengths, so
+                // a client using a different version could only ever
+                // misread them
+                if proto_version != crate::protocol::PROTO_VERSION {
+                    return Err(Error::IncompatibleProtocolVersion {
+                        client: proto_version,
+                        server: crate::protocol::PROTO_VERSION,
+                    });
+                }
+                self.handle_message_login(
+                    conn,
+                    &auth,
+                    auth_client,
+                    &term_type,
+                    size,
+                )
+            }
             m => Err(Error::UnauthenticatedMessage { message: m }),
         }
     }
@@ -714,9 +1184,25 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
         >,
     > {
         match message {
-            crate::protocol::Message::OauthCliResponse { code } => {
-                self.handle_message_oauth_cli_response(conn, &code)
+            crate::protocol::Message::OauthCliResponse {
+                code,
+                redirect_port,
+            } => self.handle_message_oauth_cli_response(
+                conn,
+                &code,
+                redirect_port,
+            ),
+            crate::protocol::Message::OauthCliDeviceResponse => {
+                self.handle_message_oauth_cli_device_response(conn)
             }
+            crate::protocol::Message::SshKeyResponse {
+                public_key,
+                signature,
+            } => self.handle_message_ssh_key_response(
+                conn,
+                &public_key,
+                &signature,
+            ),
             m => Err(Error::UnauthenticatedMessage { message: m }),
         }
     }
@@ -734,18 +1220,70 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
                 self.handle_message_resize(conn, size)
             }
             crate::protocol::Message::ListSessions => {
+                self.check_role(
+                    conn,
+                    crate::config::Role::can_watch,
+                    "list",
+                )?;
                 self.handle_message_list_sessions(conn)
             }
             crate::protocol::Message::StartStreaming => {
+                self.check_role(
+                    conn,
+                    crate::config::Role::can_stream,
+                    "stream",
+                )?;
                 self.handle_message_start_streaming(conn)
             }
             crate::protocol::Message::StartWatching { id } => {
+                self.check_role(
+                    conn,
+                    crate::config::Role::can_watch,
+                    "watch",
+                )?;
                 self.handle_message_start_watching(conn, id)
             }
             m => Err(crate::error::Error::UnexpectedMessage { message: m }),
         }
     }
 
+    fn check_role(
+        &self,
+        conn: &Connection<S>,
+        allowed: fn(crate::config::Role) -> bool,
+        action: &str,
+    ) -> Result<()> {
+        let (auth_type, username, groups) =
+            if let ConnectionState::LoggedIn {
+                auth_type,
+                username,
+                groups,
+                ..
+            } = &conn.state
+            {
+                (*auth_type, username, groups)
+            } else {
+                unreachable!()
+            };
+
+        let role = self.roles.role(auth_type, username, groups);
+        if allowed(role) {
+            Ok(())
+        } else {
+            log::warn!(
+                "{}: {}({}) denied for role {:?}",
+                conn.id,
+                action,
+                username,
+                role
+            );
+            Err(Error::NotAllowed {
+                username: username.to_string(),
+                action: action.to_string(),
+            })
+        }
+    }
+
     fn handle_streaming_message(
         &mut self,
         conn: &mut Connection<S>,
@@ -761,6 +1299,14 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
             crate::protocol::Message::TerminalOutput { data } => {
                 self.handle_message_terminal_output(conn, &data)
             }
+            crate::protocol::Message::Paused { paused } => {
+                self.handle_message_paused(conn, paused)
+            }
+            crate::protocol::Message::Metadata {
+                title,
+                description,
+                tags,
+            } => self.handle_message_metadata(conn, title, description, tags),
             m => Err(crate::error::Error::UnexpectedMessage { message: m }),
         }
     }
@@ -885,11 +1431,22 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
                 Err(e) => classify_connection_error(e),
             },
             Some(ReadSocket::Processing(_, fut)) => {
-                let (state, msg) = component_future::try_ready!(fut.poll());
+                let res = match fut.poll() {
+                    Ok(futures::Async::Ready(res)) => Ok(res),
+                    Ok(futures::Async::NotReady) => {
+                        return Ok(component_future::Async::NotReady);
+                    }
+                    Err(e) => Err(e),
+                };
                 if let Some(ReadSocket::Processing(s, _)) = conn.rsock.take()
                 {
-                    conn.state = state;
-                    conn.send_message(msg);
+                    match res {
+                        Ok((state, msg)) => {
+                            conn.state = state;
+                            conn.send_message(msg);
+                        }
+                        Err(e) => conn.close(Err(e)),
+                    }
                     conn.rsock = Some(ReadSocket::Connected(s));
                 } else {
                     unreachable!()
@@ -1104,6 +1661,15 @@ fn classify_connection_error(e: Error) -> component_future::Poll<(), Error> {
     }
 }
 
+// placeholder sent to watchers in place of the actual terminal contents
+// while the streamer has broadcasting paused
+fn paused_screen(size: crate::term::Size) -> Vec<u8> {
+    let msg = "(stream paused)";
+    let row = size.rows / 2 + 1;
+    let col = (size.cols as usize).saturating_sub(msg.len()) / 2 + 1;
+    format!("\x1b[m\x1b[H\x1b[2J\x1b[{};{}H{}", row, col, msg).into_bytes()
+}
+
 #[must_use = "futures do nothing unless polled"]
 impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
     futures::Future for Server<S>
@@ -1115,3 +1681,189 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
         component_future::poll_future(self, Self::POLL_FNS)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    type Socket = std::io::Cursor<Vec<u8>>;
+
+    fn server() -> Server<Socket> {
+        Server::new(
+            Box::new(futures::stream::empty()),
+            std::time::Duration::from_secs(30),
+            std::collections::HashSet::new(),
+            std::collections::HashMap::new(),
+            None,
+            None,
+            None,
+            crate::config::Server::default().roles(),
+            false,
+        )
+    }
+
+    fn connection(username: &str) -> Connection<Socket> {
+        let mut conn = Connection::new(std::io::Cursor::new(vec![]));
+        conn.state.login_plain(
+            crate::protocol::AuthType::Plain,
+            username,
+            "xterm",
+            crate::term::Size { rows: 24, cols: 80 },
+        );
+        conn
+    }
+
+    #[test]
+    fn test_protocol_version() {
+        let mut server = server();
+        let size = crate::term::Size { rows: 24, cols: 80 };
+        let login = |proto_version| crate::protocol::Message::Login {
+            proto_version,
+            auth: crate::protocol::Auth::plain("user"),
+            auth_client: crate::protocol::AuthClient::Cli,
+            term_type: "xterm".to_string(),
+            size,
+        };
+        server
+            .allowed_auth_types
+            .insert(crate::protocol::AuthType::Plain);
+
+        let mut conn = Connection::new(std::io::Cursor::new(vec![]));
+        match server.handle_accepted_message(&mut conn, login(1)).err() {
+            Some(Error::IncompatibleProtocolVersion {
+                client,
+                server: version,
+            }) => {
+                assert_eq!(client, 1);
+                assert_eq!(version, crate::protocol::PROTO_VERSION);
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+        assert_eq!(conn.state.username(), None);
+
+        let mut conn = Connection::new(std::io::Cursor::new(vec![]));
+        server
+            .handle_accepted_message(
+                &mut conn,
+                login(crate::protocol::PROTO_VERSION),
+            )
+            .unwrap();
+        assert_eq!(conn.state.username(), Some("user"));
+    }
+
+    #[test]
+    fn test_unencrypted_passwords() {
+        let mut server = server();
+        let login = crate::protocol::Message::Login {
+            proto_version: crate::protocol::PROTO_VERSION,
+            auth: crate::protocol::Auth::password("user", "hunter2"),
+            auth_client: crate::protocol::AuthClient::Cli,
+            term_type: "xterm".to_string(),
+            size: crate::term::Size { rows: 24, cols: 80 },
+        };
+        server
+            .allowed_auth_types
+            .insert(crate::protocol::AuthType::Password);
+
+        let mut conn = Connection::new(std::io::Cursor::new(vec![]));
+        match server
+            .handle_accepted_message(&mut conn, login.clone())
+            .err()
+        {
+            Some(Error::PasswordLoginRequiresTls) => {}
+            err => panic!("unexpected error {:?}", err),
+        }
+
+        // gets as far as looking for the credentials file once allowed
+        server.allow_unencrypted_passwords = true;
+        let mut conn = Connection::new(std::io::Cursor::new(vec![]));
+        match server.handle_accepted_message(&mut conn, login).err() {
+            Some(Error::AuthTypeNotAllowed { .. }) => {}
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_roles() {
+        let mut server = server();
+        server.roles = crate::config::Server {
+            default_role: crate::config::Role::Streamer,
+            ..crate::config::Server::default()
+        }
+        .roles();
+
+        // streamers can't see what is being streamed at all
+        let mut conn = connection("streamer");
+        match server
+            .handle_logged_in_message(
+                &mut conn,
+                crate::protocol::Message::list_sessions(),
+            )
+            .err()
+        {
+            Some(Error::NotAllowed { .. }) => {}
+            err => panic!("unexpected error {:?}", err),
+        }
+        assert!(conn.to_send.is_empty());
+
+        let mut conn = connection("streamer");
+        server
+            .handle_logged_in_message(
+                &mut conn,
+                crate::protocol::Message::start_streaming(),
+            )
+            .unwrap();
+        assert!(conn.state.term().is_some());
+    }
+
+    #[test]
+    fn test_paused_stream() {
+        let mut server = server();
+        let mut streamer = connection("streamer");
+        streamer.state.stream();
+        let mut watcher = connection("watcher");
+        watcher.state.watch(&streamer.id);
+        let watch_id = watcher.id.clone();
+        server.connections.insert(watch_id.clone(), watcher);
+
+        server.handle_message_paused(&mut streamer, true).unwrap();
+        server
+            .connections
+            .get_mut(&watch_id)
+            .unwrap()
+            .to_send
+            .clear();
+
+        // nothing the streamer sends while paused reaches watchers
+        server
+            .handle_message_terminal_output(&mut streamer, b"secret")
+            .unwrap();
+        let size = crate::term::Size {
+            rows: 30,
+            cols: 100,
+        };
+        server.handle_message_resize(&mut streamer, size).unwrap();
+        assert!(server.connections[&watch_id].to_send.is_empty());
+
+        // but it is all applied to the screen they get sent on unpause
+        server.handle_message_paused(&mut streamer, false).unwrap();
+        let sent: Vec<_> = server
+            .connections
+            .get_mut(&watch_id)
+            .unwrap()
+            .to_send
+            .drain(..)
+            .collect();
+        let screen = streamer.state.term().unwrap().screen();
+        assert!(screen.contents().contains("secret"));
+        assert_eq!(
+            sent,
+            vec![
+                crate::protocol::Message::resize(size),
+                crate::protocol::Message::terminal_output(
+                    &screen.contents_formatted()
+                ),
+            ]
+        );
+    }
+}
@@ -1,13 +1,120 @@
 use crate::prelude::*;
+use std::io::Read as _;
+
+#[derive(Debug, Clone)]
+pub enum Identity {
+    Pkcs12 {
+        filename: String,
+    },
+    Pem {
+        cert_filename: String,
+        key_filename: String,
+    },
+}
+
+impl Identity {
+    pub fn acceptor(&self) -> Result<tokio_tls::TlsAcceptor> {
+        let identity = match self {
+            Self::Pkcs12 { filename } => {
+                let identity = read_file(filename)?;
+                native_tls::Identity::from_pkcs12(&identity, "")
+                    .context(crate::error::ParseIdentity)?
+            }
+            Self::Pem {
+                cert_filename,
+                key_filename,
+            } => {
+                let cert = read_file(cert_filename)?;
+                let key = read_file(key_filename)?;
+                let identity = pem_to_pkcs12(&cert, &key)?;
+                native_tls::Identity::from_pkcs12(&identity, "")
+                    .context(crate::error::ParseIdentity)?
+            }
+        };
+        let acceptor = native_tls::TlsAcceptor::new(identity)
+            .context(crate::error::CreateAcceptor)?;
+
+        Ok(tokio_tls::TlsAcceptor::from(acceptor))
+    }
+
+    // reads the files making up this identity without parsing them
+    pub fn check_readable(&self) -> Result<()> {
+        for filename in self.filenames() {
+            read_file(filename)?;
+        }
+        Ok(())
+    }
+
+    fn filenames(&self) -> Vec<&String> {
+        match self {
+            Self::Pkcs12 { filename } => vec![filename],
+            Self::Pem {
+                cert_filename,
+                key_filename,
+            } => vec![cert_filename, key_filename],
+        }
+    }
+
+    // the most recent modification time of any of the files making up this
+    // identity - used to detect when a certificate has been renewed
+    fn modified(&self) -> Option<std::time::SystemTime> {
+        self.filenames()
+            .iter()
+            .filter_map(|filename| {
+                std::fs::metadata(filename)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+            })
+            .max()
+    }
+}
+
+fn read_file(filename: &str) -> Result<Vec<u8>> {
+    let mut file = std::fs::File::open(filename)
+        .context(crate::error::OpenFileSync { filename })?;
+    let mut contents = vec![];
+    file.read_to_end(&mut contents)
+        .context(crate::error::ReadFileSync)?;
+    Ok(contents)
+}
+
+// native_tls can only load identities from pkcs12 bundles, so convert the
+// pem files into one in memory
+fn pem_to_pkcs12(cert: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    let mut certs = openssl::x509::X509::stack_from_pem(cert)
+        .context(crate::error::ParsePemCertificate)?;
+    if certs.is_empty() {
+        return Err(Error::MissingPemCertificate);
+    }
+    let cert = certs.remove(0);
+    let key = openssl::pkey::PKey::private_key_from_pem(key)
+        .context(crate::error::ParsePemKey)?;
+
+    let mut chain =
+        openssl::stack::Stack::new().context(crate::error::CreatePkcs12)?;
+    for cert in certs {
+        chain.push(cert).context(crate::error::CreatePkcs12)?;
+    }
+
+    let mut builder = openssl::pkcs12::Pkcs12::builder();
+    builder.ca(chain);
+    let pkcs12 = builder
+        .build("", "teleterm", &key, &cert)
+        .context(crate::error::CreatePkcs12)?;
+    pkcs12.to_der().context(crate::error::CreatePkcs12)
+}
 
 pub struct Server {
     server: super::Server<tokio_tls::TlsStream<tokio::net::TcpStream>>,
     acceptor: Box<
-        dyn futures::Stream<
-                Item = tokio_tls::Accept<tokio::net::TcpStream>,
-                Error = Error,
-            > + Send,
+        dyn futures::Stream<Item = tokio::net::TcpStream, Error = Error>
+            + Send,
     >,
+    identity: Identity,
+    identity_modified: Option<std::time::SystemTime>,
+    tls_acceptor: tokio_tls::TlsAcceptor,
+    reload_timer: tokio::timer::Interval,
+    sighup: Box<dyn futures::Stream<Item = (), Error = Error> + Send>,
     sock_w: tokio::sync::mpsc::Sender<
         tokio_tls::TlsStream<tokio::net::TcpStream>,
     >,
@@ -17,11 +124,12 @@ pub struct Server {
 impl Server {
     pub fn new(
         acceptor: Box<
-            dyn futures::Stream<
-                    Item = tokio_tls::Accept<tokio::net::TcpStream>,
-                    Error = Error,
-                > + Send,
+            dyn futures::Stream<Item = tokio::net::TcpStream, Error = Error>
+                + Send,
         >,
+        identity: Identity,
+        tls_acceptor: tokio_tls::TlsAcceptor,
+        reload_interval: std::time::Duration,
         read_timeout: std::time::Duration,
         allowed_login_methods: std::collections::HashSet<
             crate::protocol::AuthType,
@@ -30,8 +138,15 @@ impl Server {
             crate::protocol::AuthType,
             crate::oauth::Config,
         >,
+        password_verifier: Option<std::sync::Arc<dyn crate::auth::Verifier>>,
+        authorized_keys: Option<
+            std::sync::Arc<crate::auth::ssh_key::AuthorizedKeys>,
+        >,
+        tokens: Option<std::sync::Arc<crate::auth::token::Tokens>>,
+        roles: crate::config::Roles,
     ) -> Self {
         let (tls_sock_w, tls_sock_r) = tokio::sync::mpsc::channel(100);
+        let identity_modified = identity.modified();
         Self {
             server: super::Server::new(
                 Box::new(
@@ -40,12 +155,48 @@ impl Server {
                 read_timeout,
                 allowed_login_methods,
                 oauth_configs,
+                password_verifier,
+                authorized_keys,
+                tokens,
+                roles,
+                // connections to this server are always encrypted
+                true,
             ),
             acceptor,
+            identity,
+            identity_modified,
+            tls_acceptor,
+            reload_timer: tokio::timer::Interval::new_interval(
+                reload_interval,
+            ),
+            sighup: Box::new(
+                tokio_signal::unix::Signal::new(tokio_signal::unix::SIGHUP)
+                    .flatten_stream()
+                    .map(|_| ())
+                    .context(crate::error::SigHupHandler),
+            ),
             sock_w: tls_sock_w,
             accepting_sockets: vec![],
         }
     }
+
+    // only newly accepted connections use the new acceptor - connections
+    // which have already finished their handshake keep going as they were
+    fn reload_identity(&mut self) {
+        self.identity_modified = self.identity.modified();
+        match self.identity.acceptor() {
+            Ok(acceptor) => {
+                log::info!("reloaded tls identity");
+                self.tls_acceptor = acceptor;
+            }
+            Err(e) => {
+                log::warn!(
+                    "failed to reload tls identity, continuing to use the old one: {}",
+                    e
+                );
+            }
+        }
+    }
 }
 
 impl Server {
@@ -57,15 +208,37 @@ impl Server {
             (),
             Error,
         >] = &[
+        &Self::poll_sighup,
+        &Self::poll_reload_timer,
         &Self::poll_accept,
         &Self::poll_handshake_connections,
         &Self::poll_server,
     ];
 
+    fn poll_sighup(&mut self) -> component_future::Poll<(), Error> {
+        component_future::try_ready!(self.sighup.poll()).unwrap();
+        log::info!("received SIGHUP, reloading tls identity");
+        self.reload_identity();
+        Ok(component_future::Async::DidWork)
+    }
+
+    fn poll_reload_timer(&mut self) -> component_future::Poll<(), Error> {
+        component_future::try_ready!(self
+            .reload_timer
+            .poll()
+            .context(crate::error::TimerReloadIdentity))
+        .unwrap();
+        if self.identity.modified() != self.identity_modified {
+            log::info!("tls identity files changed, reloading");
+            self.reload_identity();
+        }
+        Ok(component_future::Async::DidWork)
+    }
+
     fn poll_accept(&mut self) -> component_future::Poll<(), Error> {
         if let Some(sock) = component_future::try_ready!(self.acceptor.poll())
         {
-            self.accepting_sockets.push(sock);
+            self.accepting_sockets.push(self.tls_acceptor.accept(sock));
             Ok(component_future::Async::DidWork)
         } else {
             Err(Error::SocketChannelClosed)
@@ -129,3 +302,70 @@ impl futures::Future for Server {
         component_future::poll_future(self, Self::POLL_FNS)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_filename(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("teleterm-test-{}-{}", name, std::process::id()))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    fn self_signed() -> (Vec<u8>, Vec<u8>) {
+        let rsa = openssl::rsa::Rsa::generate(2048).unwrap();
+        let key = openssl::pkey::PKey::from_rsa(rsa).unwrap();
+        let mut name = openssl::x509::X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "localhost").unwrap();
+        let name = name.build();
+        let mut builder = openssl::x509::X509Builder::new().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder
+            .set_not_before(
+                &openssl::asn1::Asn1Time::days_from_now(0).unwrap(),
+            )
+            .unwrap();
+        builder
+            .set_not_after(
+                &openssl::asn1::Asn1Time::days_from_now(1).unwrap(),
+            )
+            .unwrap();
+        builder
+            .sign(&key, openssl::hash::MessageDigest::sha256())
+            .unwrap();
+        let cert = builder.build();
+        (
+            cert.to_pem().unwrap(),
+            key.private_key_to_pem_pkcs8().unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_pem_identity() {
+        let (cert, key) = self_signed();
+        assert!(pem_to_pkcs12(&cert, &key).is_ok());
+        assert!(pem_to_pkcs12(b"", &key).is_err());
+        assert!(pem_to_pkcs12(&cert, b"not a key").is_err());
+
+        let cert_filename = test_filename("tls-cert");
+        let key_filename = test_filename("tls-key");
+        std::fs::write(&cert_filename, &cert).unwrap();
+        let identity = Identity::Pem {
+            cert_filename: cert_filename.clone(),
+            key_filename: key_filename.clone(),
+        };
+        assert!(identity.check_readable().is_err());
+        std::fs::write(&key_filename, &key).unwrap();
+        assert!(identity.check_readable().is_ok());
+        assert!(identity.acceptor().is_ok());
+        assert!(identity.modified().is_some());
+
+        std::fs::remove_file(cert_filename).unwrap();
+        std::fs::remove_file(key_filename).unwrap();
+    }
+}
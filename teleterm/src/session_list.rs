@@ -154,6 +154,25 @@ impl SessionList {
     }
 }
 
+// a session matches if every word in the filter appears (case-insensitively)
+// in its username, title, description, or one of its tags
+pub fn matches_filter(
+    session: &crate::protocol::Session,
+    filter: &str,
+) -> bool {
+    let fields: Vec<_> =
+        [&session.username, &session.title, &session.description]
+            .iter()
+            .copied()
+            .chain(session.tags.iter())
+            .map(|field| field.to_lowercase())
+            .collect();
+    filter
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .all(|word| fields.iter().any(|field| field.contains(&word)))
+}
+
 #[cfg(test)]
 #[allow(clippy::cognitive_complexity)]
 #[allow(clippy::redundant_clone)]
@@ -170,6 +189,9 @@ mod test {
             idle_time: idle,
             title: "title".to_string(),
             watchers: 0,
+            paused: false,
+            description: "".to_string(),
+            tags: vec![],
         }
     }
 
@@ -339,4 +361,21 @@ mod test {
         let id = list.id_for('t');
         assert!(id.is_none());
     }
+
+    #[test]
+    fn test_matches_filter() {
+        let mut s = session("doy", 0);
+        s.title = "hacking on teleterm".to_string();
+        s.description = "adding stream metadata".to_string();
+        s.tags = vec!["Rust".to_string(), "open-source".to_string()];
+
+        assert!(matches_filter(&s, ""));
+        assert!(matches_filter(&s, "doy"));
+        assert!(matches_filter(&s, "TELETERM"));
+        assert!(matches_filter(&s, "metadata"));
+        assert!(matches_filter(&s, "rust"));
+        assert!(matches_filter(&s, "rust doy"));
+        assert!(!matches_filter(&s, "rust sartak"));
+        assert!(!matches_filter(&s, "python"));
+    }
 }
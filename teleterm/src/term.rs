@@ -16,6 +16,54 @@ impl Size {
     pub fn fits_in(self, other: Self) -> bool {
         self.rows <= other.rows && self.cols <= other.cols
     }
+
+    // ttyrec files have nowhere to store the terminal size, so it's written
+    // in-band using xterm's window resize sequence, at the start of every
+    // recording and whenever the size changes. note that this is a real
+    // escape sequence: players which just write the recording to the
+    // terminal will resize the window of any terminal which honors it
+    // (xterm, and many others), and only terminals which don't will ignore
+    // it.
+    pub fn resize_sequence(self) -> Vec<u8> {
+        format!("{}{};{}t", RESIZE_PREFIX, self.rows, self.cols).into_bytes()
+    }
+}
+
+const RESIZE_PREFIX: &str = "\x1b[8;";
+
+// returns the location of each resize sequence in data, along with the size
+// that it sets
+pub fn find_resizes(data: &[u8]) -> Vec<(std::ops::Range<usize>, Size)> {
+    let prefix = RESIZE_PREFIX.as_bytes();
+    let mut resizes = vec![];
+    let mut i = 0;
+    while let Some(pos) =
+        data[i..].windows(prefix.len()).position(|w| w == prefix)
+    {
+        let start = i + pos;
+        let params = start + prefix.len();
+        if let Some((size, len)) = parse_resize_params(&data[params..]) {
+            resizes.push((start..params + len, size));
+            i = params + len;
+        } else {
+            i = params;
+        }
+    }
+    resizes
+}
+
+fn parse_resize_params(data: &[u8]) -> Option<(Size, usize)> {
+    let end = data
+        .iter()
+        .position(|&c| !(c.is_ascii_digit() || c == b';'))?;
+    if data[end] != b't' {
+        return None;
+    }
+    let params = std::str::from_utf8(&data[..end]).ok()?;
+    let mut parts = params.splitn(2, ';');
+    let rows = parts.next()?.parse().ok()?;
+    let cols = parts.next()?.parse().ok()?;
+    Some((Size { rows, cols }, end + 1))
 }
 
 impl std::fmt::Display for Size {
@@ -23,3 +71,130 @@ impl std::fmt::Display for Size {
         std::fmt::Display::fmt(&format!("{}x{}", self.cols, self.rows), f)
     }
 }
+
+// fds above this are not checked when looking for the pty, since we never
+// have anywhere near this many files open
+const MAX_FD: std::os::unix::io::RawFd = 1024;
+
+// the master sides of all of the ptys we currently have open
+pub fn pty_masters() -> std::collections::HashSet<std::os::unix::io::RawFd> {
+    (3..MAX_FD).filter(|&fd| is_pty_master(fd)).collect()
+}
+
+// the master side of a pty shares its terminal settings with the side that
+// the command is using, which lets us see when the command turns off echo.
+pub struct PtyMaster {
+    fd: std::os::unix::io::RawFd,
+}
+
+impl PtyMaster {
+    pub fn new(fd: std::os::unix::io::RawFd) -> Self {
+        Self { fd }
+    }
+
+    // tokio-pty-process-stream doesn't give us access to the pty it creates
+    // for the running command, so this picks out the one that was opened
+    // since `before` was taken (from pty_masters(), just before spawning the
+    // command). if there isn't exactly one, there's no way to tell which
+    // pty belongs to the command.
+    pub fn opened_since(
+        before: &std::collections::HashSet<std::os::unix::io::RawFd>,
+    ) -> Option<Self> {
+        let mut opened =
+            pty_masters().into_iter().filter(|fd| !before.contains(fd));
+        let fd = opened.next()?;
+        if opened.next().is_some() {
+            return None;
+        }
+        Some(Self::new(fd))
+    }
+
+    // password prompts turn off echo but still read a line at a time, while
+    // full screen programs turn off both (and echo their input themselves).
+    // if the terminal settings can't be read, this assumes the worst, since
+    // masking too much is better than recording a password.
+    pub fn reading_password(&self) -> bool {
+        let mut termios = std::mem::MaybeUninit::<libc::termios>::uninit();
+        // safe because tcgetattr only writes to the struct we pass in, and
+        // we only read it if tcgetattr succeeded
+        let termios = unsafe {
+            if libc::tcgetattr(self.fd, termios.as_mut_ptr()) != 0 {
+                return true;
+            }
+            termios.assume_init()
+        };
+        termios.c_lflag & libc::ECHO == 0
+            && termios.c_lflag & libc::ICANON != 0
+    }
+}
+
+// ptsname only succeeds for the master side of a pty. ptsname_r is used
+// because ptsname returns a static buffer, which isn't safe to use from the
+// threadpool.
+fn is_pty_master(fd: std::os::unix::io::RawFd) -> bool {
+    let mut buf = [0 as libc::c_char; 64];
+    // safe because ptsname_r writes at most buf.len() bytes into buf
+    unsafe {
+        libc::isatty(fd) == 1
+            && libc::ptsname_r(fd, buf.as_mut_ptr(), buf.len()) == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_find_resizes() {
+        let size = Size { rows: 24, cols: 80 };
+        assert_eq!(size.resize_sequence(), b"\x1b[8;24;80t".to_vec());
+        assert_eq!(
+            find_resizes(&size.resize_sequence()),
+            vec![(0..10, size)]
+        );
+        assert_eq!(
+            find_resizes(b"ab\x1b[8;1;2tc\x1b[8;5t\x1b[8;3;4t"),
+            vec![
+                (2..10, Size { rows: 1, cols: 2 }),
+                (17..25, Size { rows: 3, cols: 4 })
+            ]
+        );
+        assert_eq!(find_resizes(b"\x1b[8;24;80"), vec![]);
+        assert_eq!(find_resizes(b"\x1b[8;99999;80t"), vec![]);
+    }
+
+    fn open_pty() -> std::os::unix::io::RawFd {
+        // safe because these only operate on the fd they return
+        unsafe {
+            let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+            assert!(fd >= 0);
+            assert_eq!(libc::grantpt(fd), 0);
+            assert_eq!(libc::unlockpt(fd), 0);
+            fd
+        }
+    }
+
+    #[test]
+    fn test_pty_master() {
+        // some other pty which was already open when the command started
+        let other = open_pty();
+        let before = pty_masters();
+        assert!(before.contains(&other));
+        assert!(PtyMaster::opened_since(&before).is_none());
+
+        let command = open_pty();
+        let pty = PtyMaster::opened_since(&before).unwrap();
+        assert_eq!(pty.fd, command);
+
+        // ambiguous, so don't pick either one
+        let another = open_pty();
+        assert!(PtyMaster::opened_since(&before).is_none());
+
+        // safe because nothing else uses these fds
+        unsafe {
+            libc::close(other);
+            libc::close(command);
+            libc::close(another);
+        }
+    }
+}
@@ -1,8 +1,8 @@
-mod disk_session;
 mod list;
 mod login;
 mod logout;
 mod oauth;
+mod session;
 mod view;
 mod watch;
 mod ws;
@@ -11,11 +11,66 @@ use crate::prelude::*;
 
 use gotham::router::builder::{DefineSingleRoute as _, DrawRoutes as _};
 use gotham::state::FromState as _;
+use rand::Rng as _;
+
+const CSRF_COOKIE: &str = "teleterm_csrf";
+const CSRF_HEADER: &str = "x-csrf-token";
+const CSRF_TOKEN_LEN: usize = 32;
+
+pub(crate) trait ServerStream:
+    tokio::io::AsyncRead + tokio::io::AsyncWrite + Send
+{
+}
+
+impl<T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send> ServerStream
+    for T
+{
+}
+
+// all of the handlers connect to the teleterm server the same way, so this
+// hides whether or not that connection uses tls behind a boxed stream type
+#[derive(Clone)]
+struct ServerConnector {
+    address: (String, std::net::SocketAddr),
+    tls: Option<crate::client::tls::Connector>,
+}
+
+impl ServerConnector {
+    fn connector(&self) -> crate::client::Connector<Box<dyn ServerStream>> {
+        let (host, address) = self.address.clone();
+        let tls = self.tls.clone();
+        Box::new(move || {
+            if let Some(tls) = &tls {
+                Box::new(
+                    tls.connect(&host, address).map(|stream| {
+                        Box::new(stream) as Box<dyn ServerStream>
+                    }),
+                )
+            } else {
+                Box::new(
+                    tokio::net::tcp::TcpStream::connect(&address)
+                        .context(crate::error::Connect { address })
+                        .map(|stream| {
+                            Box::new(stream) as Box<dyn ServerStream>
+                        }),
+                )
+            }
+        })
+    }
+}
 
 #[derive(Clone, gotham_derive::StateData)]
 struct Config {
-    server_address: (String, std::net::SocketAddr),
+    server: ServerConnector,
     public_address: String,
+    tls: bool,
+    trusted_proxies: Vec<std::net::IpAddr>,
+    cookie_same_site: crate::config::SameSite,
+    sessions: std::sync::Arc<dyn session::Store>,
+    session_ttl: std::time::Duration,
+    password_verifier: Option<std::sync::Arc<dyn crate::auth::Verifier>>,
+    failed_logins: std::sync::Arc<crate::auth::FailedLogins>,
+    passwords: std::sync::Arc<Passwords>,
     allowed_login_methods:
         std::collections::HashSet<crate::protocol::AuthType>,
     oauth_configs: std::collections::HashMap<
@@ -25,6 +80,30 @@ struct Config {
 }
 
 impl Config {
+    // session cookies can only be marked as secure if we know that the
+    // browser will be talking to us over https, either directly or through
+    // a reverse proxy
+    fn secure_cookies(&self) -> bool {
+        self.tls || !self.trusted_proxies.is_empty()
+    }
+
+    fn request_is_secure(&self, state: &gotham::state::State) -> bool {
+        if self.tls {
+            return true;
+        }
+
+        let trusted = gotham::state::client_addr(state)
+            .map_or(false, |addr| self.trusted_proxies.contains(&addr.ip()));
+        if !trusted {
+            return false;
+        }
+
+        hyper::HeaderMap::borrow_from(state)
+            .get("x-forwarded-proto")
+            .and_then(|proto| proto.to_str().ok())
+            .map_or(false, |proto| proto.eq_ignore_ascii_case("https"))
+    }
+
     fn allowed_oauth_login_methods(
         &self,
     ) -> impl Iterator<Item = crate::protocol::AuthType> + '_ {
@@ -35,10 +114,118 @@ impl Config {
     }
 }
 
+// password logins are checked by the teleterm server on every connection,
+// so the password has to be kept for as long as the login is. it is only
+// ever kept in memory, and the session just records an id to look it up
+// by, so that passwords never reach the session store.
+#[derive(Default)]
+struct Passwords {
+    passwords: std::sync::Mutex<
+        std::collections::HashMap<
+            String,
+            (String, String, std::time::Instant),
+        >,
+    >,
+}
+
+impl Passwords {
+    fn insert(
+        &self,
+        username: &str,
+        password: &str,
+        ttl: std::time::Duration,
+    ) -> String {
+        let id = new_csrf_token();
+        let mut passwords = self.passwords.lock().unwrap();
+        // the sessions these belong to have expired by now
+        passwords.retain(|_, (_, _, since)| since.elapsed() < ttl);
+        passwords.insert(
+            id.clone(),
+            (
+                username.to_string(),
+                password.to_string(),
+                std::time::Instant::now(),
+            ),
+        );
+        id
+    }
+
+    fn get(&self, id: &str, username: &str) -> Option<String> {
+        self.passwords
+            .lock()
+            .unwrap()
+            .get(id)
+            .filter(|(password_username, ..)| password_username == username)
+            .map(|(_, password, _)| password.clone())
+    }
+
+    fn remove(&self, id: &str) {
+        self.passwords.lock().unwrap().remove(id);
+    }
+
+    fn remove_user(&self, username: &str) {
+        self.passwords.lock().unwrap().retain(
+            |_, (password_username, ..)| password_username != username,
+        );
+    }
+}
+
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub(crate) struct LoginState {
-    auth: crate::protocol::Auth,
+    auth_type: crate::protocol::AuthType,
     username: String,
+    #[serde(default)]
+    password_id: Option<String>,
+}
+
+impl LoginState {
+    fn new(
+        config: &Config,
+        auth: &crate::protocol::Auth,
+        username: &str,
+    ) -> Self {
+        let password_id =
+            if let crate::protocol::Auth::Password { password, .. } = auth {
+                Some(config.passwords.insert(
+                    username,
+                    password,
+                    config.session_ttl,
+                ))
+            } else {
+                None
+            };
+        Self {
+            auth_type: auth.auth_type(),
+            username: username.to_string(),
+            password_id,
+        }
+    }
+
+    // returns None if the login can't be used to connect to the server any
+    // more, for instance if the web server was restarted since a password
+    // login
+    fn auth(&self, config: &Config) -> Option<crate::protocol::Auth> {
+        match self.auth_type {
+            crate::protocol::AuthType::Plain => {
+                Some(crate::protocol::Auth::plain(&self.username))
+            }
+            crate::protocol::AuthType::Password => {
+                let id = self.password_id.as_ref()?;
+                let password = config.passwords.get(id, &self.username)?;
+                Some(crate::protocol::Auth::password(
+                    &self.username,
+                    &password,
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    fn logout(&self, config: &Config) {
+        if let Some(id) = &self.password_id {
+            config.passwords.remove(id);
+        }
+    }
 }
 
 #[derive(Default, serde::Deserialize, serde::Serialize)]
@@ -46,10 +233,77 @@ struct SessionData {
     login: Option<LoginState>,
 }
 
+// the csrf token is kept in a cookie of its own rather than in the session,
+// so that a session doesn't have to be stored for every anonymous visitor
+fn csrf_cookie(state: &gotham::state::State) -> Option<String> {
+    hyper::HeaderMap::borrow_from(state)
+        .get_all(hyper::header::COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(';'))
+        .filter_map(|cookie| {
+            let mut parts = cookie.trim().splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(CSRF_COOKIE), Some(value))
+                    if value.len() == CSRF_TOKEN_LEN =>
+                {
+                    Some(value.to_string())
+                }
+                _ => None,
+            }
+        })
+        .next()
+}
+
+fn new_csrf_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(CSRF_TOKEN_LEN)
+        .collect()
+}
+
+fn csrf_set_cookie(config: &Config, token: &str) -> String {
+    let same_site = match config.cookie_same_site {
+        crate::config::SameSite::Strict => "Strict",
+        crate::config::SameSite::Lax => "Lax",
+    };
+    format!(
+        "{}={}; Path=/; HttpOnly; SameSite={}{}",
+        CSRF_COOKIE,
+        token,
+        same_site,
+        if config.secure_cookies() {
+            "; Secure"
+        } else {
+            ""
+        }
+    )
+}
+
+// requests which change the login state must include the token which was
+// embedded in the page as a header. a cross-site request has no way of
+// reading either the page or the cookie, so it can't send a matching one.
+fn csrf_token_valid(state: &gotham::state::State) -> bool {
+    let expected = if let Some(token) = csrf_cookie(state) {
+        token
+    } else {
+        return false;
+    };
+    hyper::HeaderMap::borrow_from(state)
+        .get(CSRF_HEADER)
+        .and_then(|token| token.to_str().ok())
+        .map_or(false, |token| {
+            token.len() == expected.len()
+                && openssl::memcmp::eq(token.as_bytes(), expected.as_bytes())
+        })
+}
+
 #[derive(Debug, serde::Serialize)]
 struct WebConfig<'a> {
     username: Option<&'a str>,
     public_address: &'a str,
+    secure: bool,
+    csrf_token: Option<&'a str>,
     allowed_login_methods:
         &'a std::collections::HashSet<crate::protocol::AuthType>,
     oauth_login_urls:
@@ -57,7 +311,12 @@ struct WebConfig<'a> {
 }
 
 impl<'a> WebConfig<'a> {
-    fn new(config: &'a Config, session: &'a SessionData) -> Result<Self> {
+    fn new(
+        config: &'a Config,
+        session: &'a SessionData,
+        secure: bool,
+        csrf_token: &'a str,
+    ) -> Result<Self> {
         let mut oauth_login_urls = std::collections::HashMap::new();
         for ty in config.allowed_oauth_login_methods() {
             let oauth_config = config
@@ -73,6 +332,8 @@ impl<'a> WebConfig<'a> {
                 .as_ref()
                 .map(|login| login.username.as_str()),
             public_address: &config.public_address,
+            secure,
+            csrf_token: Some(csrf_token),
             allowed_login_methods: &config.allowed_login_methods,
             oauth_login_urls,
         })
@@ -87,7 +348,16 @@ impl Server {
     pub fn new(
         listen_address: std::net::SocketAddr,
         public_address: String,
+        tls_acceptor: Option<tokio_tls::TlsAcceptor>,
+        trusted_proxies: Vec<std::net::IpAddr>,
+        cookie_same_site: crate::config::SameSite,
+        session_backend: crate::config::SessionBackend,
+        session_ttl: std::time::Duration,
+        session_gc_interval: std::time::Duration,
+        session_sqlite_file: &str,
+        password_verifier: Option<std::sync::Arc<dyn crate::auth::Verifier>>,
         server_address: (String, std::net::SocketAddr),
+        server_tls: Option<crate::client::tls::Connector>,
         allowed_login_methods: std::collections::HashSet<
             crate::protocol::AuthType,
         >,
@@ -95,19 +365,56 @@ impl Server {
             crate::protocol::AuthType,
             crate::oauth::Config,
         >,
-    ) -> Self {
+    ) -> Result<Self> {
+        let sessions = session::store(session_backend, session_sqlite_file)?;
         let data = Config {
-            server_address,
+            server: ServerConnector {
+                address: server_address,
+                tls: server_tls,
+            },
             public_address,
+            tls: tls_acceptor.is_some(),
+            trusted_proxies,
+            cookie_same_site,
+            sessions: sessions.clone(),
+            session_ttl,
+            password_verifier,
+            failed_logins: std::sync::Arc::new(
+                crate::auth::FailedLogins::default(),
+            ),
+            passwords: std::sync::Arc::new(Passwords::default()),
             allowed_login_methods,
             oauth_configs,
         };
-        Self {
-            server: Box::new(gotham::init_server(
-                listen_address,
-                router(&data),
-            )),
-        }
+        let server: Box<dyn futures::Future<Item = (), Error = ()> + Send> =
+            if let Some(tls_acceptor) = tls_acceptor {
+                let listener = tokio::net::TcpListener::bind(&listen_address)
+                    .context(crate::error::Bind {
+                        address: listen_address,
+                    })?;
+                Box::new(gotham::bind_server(
+                    listener,
+                    router(&data),
+                    move |socket| {
+                        tls_acceptor.accept(socket).map_err(|e| {
+                            log::warn!(
+                                "failed to accept tls connection: {}",
+                                e
+                            )
+                        })
+                    },
+                ))
+            } else {
+                Box::new(gotham::init_server(listen_address, router(&data)))
+            };
+        let gc =
+            session::Gc::new(sessions, session_gc_interval).or_else(|e| {
+                log::error!("stopping web session gc: {}", e);
+                futures::future::empty()
+            });
+        Ok(Self {
+            server: Box::new(server.select(gc).map(|_| ()).map_err(|_| ())),
+        })
     }
 }
 
@@ -121,19 +428,31 @@ impl futures::Future for Server {
 }
 
 fn router(data: &Config) -> impl gotham::handler::NewHandler {
+    let same_site = match data.cookie_same_site {
+        crate::config::SameSite::Strict => {
+            gotham::middleware::session::SameSiteEnum::Strict
+        }
+        crate::config::SameSite::Lax => {
+            gotham::middleware::session::SameSiteEnum::Lax
+        }
+    };
+    // gotham always marks the session cookie as HttpOnly
+    let mut session_middleware =
+        gotham::middleware::session::NewSessionMiddleware::new(
+            session::Backend::new(data.sessions.clone(), data.session_ttl),
+        )
+        .with_cookie_name("teleterm")
+        .with_same_site(same_site);
+    if !data.secure_cookies() {
+        session_middleware = session_middleware.insecure();
+    }
+
     let (chain, pipeline) = gotham::pipeline::single::single_pipeline(
         gotham::pipeline::new_pipeline()
             .add(gotham::middleware::state::StateMiddleware::new(
                 data.clone(),
             ))
-            .add(
-                gotham::middleware::session::NewSessionMiddleware::new(
-                    disk_session::DiskSession,
-                )
-                .insecure()
-                .with_cookie_name("teleterm")
-                .with_session_type::<SessionData>(),
-            )
+            .add(session_middleware.with_session_type::<SessionData>())
             .build(),
     );
     gotham::router::builder::build_router(chain, pipeline, |route| {
@@ -155,16 +474,16 @@ fn router(data: &Config) -> impl gotham::handler::NewHandler {
             .get("/watch")
             .with_query_string_extractor::<watch::QueryParams>()
             .to(watch::run);
-        route
-            .get("/login")
-            .with_query_string_extractor::<login::QueryParams>()
-            .to(login::run);
+        route.post("/login").to(login::run);
         route
             .get("/oauth/:method")
             .with_path_extractor::<oauth::PathParts>()
             .with_query_string_extractor::<oauth::QueryParams>()
             .to(oauth::run);
-        route.get("/logout").to(logout::run);
+        route
+            .post("/logout")
+            .with_query_string_extractor::<logout::QueryParams>()
+            .to(logout::run);
     })
 }
 
@@ -185,12 +504,18 @@ fn serve_template(
     content_type: &'static str,
     name: &'static str,
 ) -> impl gotham::handler::Handler + Copy {
-    move |state| {
+    move |state: gotham::state::State| {
+        let (csrf_token, new_csrf_token) = match csrf_cookie(&state) {
+            Some(token) => (token, false),
+            None => (new_csrf_token(), true),
+        };
         let config = Config::borrow_from(&state);
         let session = gotham::middleware::session::SessionData::<
             crate::web::SessionData,
         >::borrow_from(&state);
-        let web_config = match WebConfig::new(config, session) {
+        let secure = config.request_is_secure(&state);
+        let web_config = WebConfig::new(config, session, secure, &csrf_token);
+        let web_config = match web_config {
             Ok(config) => config,
             Err(e) => {
                 // this means that the server configuration is incorrect, and
@@ -205,10 +530,15 @@ fn serve_template(
             }
         };
         let rendered = view::HANDLEBARS.render(name, &web_config).unwrap();
-        let response = hyper::Response::builder()
-            .header("Content-Type", content_type)
-            .body(hyper::Body::from(rendered))
-            .unwrap();
+        let mut response = hyper::Response::builder();
+        response.header("Content-Type", content_type);
+        if new_csrf_token {
+            response.header(
+                hyper::header::SET_COOKIE,
+                csrf_set_cookie(config, &csrf_token),
+            );
+        }
+        let response = response.body(hyper::Body::from(rendered)).unwrap();
         (state, response)
     }
 }
@@ -8,8 +8,11 @@ pub fn run(
     let session = gotham::middleware::session::SessionData::<
         crate::web::SessionData,
     >::borrow_from(&state);
-    let auth = if let Some(login) = &session.login {
-        &login.auth
+    let config = crate::web::Config::borrow_from(&state);
+    let auth = if let Some(auth) =
+        session.login.as_ref().and_then(|login| login.auth(config))
+    {
+        auth
     } else {
         return (
             state,
@@ -20,19 +23,11 @@ pub fn run(
         );
     };
 
-    let config = crate::web::Config::borrow_from(&state);
-
-    let (_, address) = config.server_address;
-    let connector: crate::client::Connector<_> = Box::new(move || {
-        Box::new(
-            tokio::net::tcp::TcpStream::connect(&address)
-                .context(crate::error::Connect { address }),
-        )
-    });
+    let connector = config.server.connector();
     let client = crate::client::Client::raw(
         "teleterm-web",
         connector,
-        auth,
+        &auth,
         crate::protocol::AuthClient::Web,
     );
 
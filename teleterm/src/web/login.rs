@@ -3,13 +3,16 @@ use crate::prelude::*;
 use gotham::handler::IntoHandlerError as _;
 use gotham::state::FromState as _;
 
-#[derive(
-    serde::Deserialize,
-    gotham_derive::StateData,
-    gotham_derive::StaticResponseExtender,
-)]
-pub struct QueryParams {
+#[derive(serde::Deserialize)]
+struct Request {
     username: String,
+    password: String,
+    #[serde(default = "default_auth_type")]
+    auth_type: crate::protocol::AuthType,
+}
+
+fn default_auth_type() -> crate::protocol::AuthType {
+    crate::protocol::AuthType::Plain
 }
 
 #[derive(serde::Serialize)]
@@ -17,29 +20,110 @@ struct Response {
     username: String,
 }
 
-pub fn run(
-    mut state: gotham::state::State,
-) -> Box<
+type HandlerFuture = Box<
     dyn futures::Future<
             Item = (gotham::state::State, hyper::Response<hyper::Body>),
             Error = (gotham::state::State, gotham::handler::HandlerError),
         > + Send,
-> {
-    let username = {
-        let query_params = QueryParams::borrow_from(&state);
-        query_params.username.clone()
-    };
+>;
 
-    let config = crate::web::Config::borrow_from(&state);
+pub fn run(mut state: gotham::state::State) -> HandlerFuture {
+    if !crate::web::csrf_token_valid(&state) {
+        return error(state, hyper::StatusCode::FORBIDDEN);
+    }
+
+    let body = hyper::Body::take_from(&mut state);
+    Box::new(body.concat2().then(move |body| {
+        let request: Request = match body
+            .ok()
+            .and_then(|body| serde_json::from_slice(&body).ok())
+        {
+            Some(request) => request,
+            None => return error(state, hyper::StatusCode::BAD_REQUEST),
+        };
+
+        let config = crate::web::Config::borrow_from(&state);
+        if !config.allowed_login_methods.contains(&request.auth_type) {
+            return error(state, hyper::StatusCode::FORBIDDEN);
+        }
+        match request.auth_type {
+            crate::protocol::AuthType::Plain => {}
+            // password logins are checked by the teleterm server itself
+            crate::protocol::AuthType::Password => {
+                let auth = crate::protocol::Auth::password(
+                    &request.username,
+                    &request.password,
+                );
+                return login(state, auth);
+            }
+            _ => return error(state, hyper::StatusCode::BAD_REQUEST),
+        }
+
+        // plain logins are only allowed if we have some way to check them
+        let verifier = if let Some(verifier) = &config.password_verifier {
+            verifier.clone()
+        } else {
+            log::warn!("failed web login for {}", request.username);
+            return error(state, hyper::StatusCode::FORBIDDEN);
+        };
+        if config.failed_logins.locked(&request.username) {
+            log::warn!("too many failed web logins for {}", request.username);
+            return error(state, hyper::StatusCode::TOO_MANY_REQUESTS);
+        }
 
-    let (_, address) = config.server_address;
-    let connector: crate::client::Connector<_> = Box::new(move || {
+        let failed_logins = config.failed_logins.clone();
         Box::new(
-            tokio::net::tcp::TcpStream::connect(&address)
-                .context(crate::error::Connect { address }),
+            crate::auth::verify_blocking(
+                verifier,
+                &request.username,
+                &request.password,
+            )
+            .then(move |verified| match verified {
+                Ok(true) => {
+                    failed_logins.clear(&request.username);
+                    login(
+                        state,
+                        crate::protocol::Auth::plain(&request.username),
+                    )
+                }
+                Ok(false) => {
+                    failed_logins.record(&request.username);
+                    log::warn!("failed web login for {}", request.username);
+                    error(state, hyper::StatusCode::FORBIDDEN)
+                }
+                Err(e) => {
+                    log::error!(
+                        "failed to verify password for {}: {}",
+                        request.username,
+                        e
+                    );
+                    error(state, hyper::StatusCode::INTERNAL_SERVER_ERROR)
+                }
+            }),
         )
-    });
-    let auth = crate::protocol::Auth::plain(&username);
+    }))
+}
+
+fn error(
+    state: gotham::state::State,
+    status: hyper::StatusCode,
+) -> HandlerFuture {
+    Box::new(futures::future::ok((
+        state,
+        hyper::Response::builder()
+            .status(status)
+            .body(hyper::Body::empty())
+            .unwrap(),
+    )))
+}
+
+fn login(
+    mut state: gotham::state::State,
+    auth: crate::protocol::Auth,
+) -> HandlerFuture {
+    let config = crate::web::Config::borrow_from(&state);
+
+    let connector = config.server.connector();
     let client = crate::client::Client::raw(
         "teleterm-web",
         connector,
@@ -50,24 +134,29 @@ pub fn run(
     let (w_login, r_login) = tokio::sync::oneshot::channel();
 
     tokio::spawn(
-        Client::new(client, auth, w_login)
+        Client::new(client, w_login)
             // XXX if this happens, we might not have sent anything on the
             // channel, and so the wait might block forever
             .map_err(|e| log::error!("error logging in: {}", e)),
     );
 
-    Box::new(r_login.then(|res| {
+    Box::new(r_login.then(move |res| {
         let session = gotham::middleware::session::SessionData::<
             crate::web::SessionData,
         >::borrow_mut_from(&mut state);
         match res {
             Ok(login) => {
+                let login = login.map(|username| {
+                    let config = crate::web::Config::borrow_from(&state);
+                    crate::web::LoginState::new(config, &auth, &username)
+                });
                 let session = gotham::middleware::session::SessionData::<
                     crate::web::SessionData,
                 >::borrow_mut_from(&mut state);
 
                 match login {
                     Ok(login) => {
+                        let username = login.username.clone();
                         session.login = Some(login);
                         futures::future::ok((
                             state,
@@ -77,6 +166,17 @@ pub fn run(
                             )),
                         ))
                     }
+                    Err(Error::Server { message }) => {
+                        session.login = None;
+                        log::warn!("server refused login: {}", message);
+                        futures::future::ok((
+                            state,
+                            hyper::Response::builder()
+                                .status(hyper::StatusCode::FORBIDDEN)
+                                .body(hyper::Body::empty())
+                                .unwrap(),
+                        ))
+                    }
                     Err(e) => {
                         session.login = None;
                         log::error!("error logging in: {}", e);
@@ -107,8 +207,7 @@ pub(crate) struct Client<
     S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static,
 > {
     client: crate::client::Client<S>,
-    auth: crate::protocol::Auth,
-    w_login: Option<tokio::sync::oneshot::Sender<Result<super::LoginState>>>,
+    w_login: Option<tokio::sync::oneshot::Sender<Result<String>>>,
 }
 
 impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
@@ -116,12 +215,10 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
 {
     pub(crate) fn new(
         client: crate::client::Client<S>,
-        auth: crate::protocol::Auth,
-        w_login: tokio::sync::oneshot::Sender<Result<super::LoginState>>,
+        w_login: tokio::sync::oneshot::Sender<Result<String>>,
     ) -> Self {
         Self {
             client,
-            auth,
             w_login: Some(w_login),
         }
     }
@@ -150,10 +247,7 @@ impl<S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static>
                         Err(Error::Server { message: msg })
                     }
                     crate::protocol::Message::LoggedIn { username } => {
-                        Ok(super::LoginState {
-                            auth: self.auth.clone(),
-                            username,
-                        })
+                        Ok(username)
                     }
                     _ => {
                         return Ok(component_future::Async::DidWork);
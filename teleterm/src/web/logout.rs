@@ -1,12 +1,53 @@
 use gotham::state::FromState as _;
 
+#[derive(
+    serde::Deserialize,
+    gotham_derive::StateData,
+    gotham_derive::StaticResponseExtender,
+)]
+pub struct QueryParams {
+    #[serde(default)]
+    everywhere: bool,
+}
+
 pub fn run(
     mut state: gotham::state::State,
 ) -> (gotham::state::State, hyper::Response<hyper::Body>) {
+    if !crate::web::csrf_token_valid(&state) {
+        return (
+            state,
+            hyper::Response::builder()
+                .status(hyper::StatusCode::FORBIDDEN)
+                .body(hyper::Body::empty())
+                .unwrap(),
+        );
+    }
+
     let session = gotham::middleware::session::SessionData::<
         crate::web::SessionData,
     >::take_from(&mut state);
 
+    let everywhere = QueryParams::borrow_from(&state).everywhere;
+    if let Some(login) = &session.login {
+        let config = crate::web::Config::borrow_from(&state);
+        login.logout(config);
+        if everywhere {
+            config.passwords.remove_user(&login.username);
+            match config.sessions.remove_user(&login.username) {
+                Ok(count) => log::info!(
+                    "removed {} sessions for {}",
+                    count,
+                    login.username
+                ),
+                Err(e) => log::warn!(
+                    "failed to remove sessions for {}: {}",
+                    login.username,
+                    e
+                ),
+            }
+        }
+    }
+
     session.discard(&mut state).unwrap();
 
     (state, hyper::Response::new(hyper::Body::empty()))
@@ -0,0 +1,200 @@
+mod disk;
+mod memory;
+mod sqlite;
+
+use crate::prelude::*;
+
+pub use disk::DiskStore;
+pub use memory::MemoryStore;
+pub use sqlite::SqliteStore;
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct Session {
+    pub content: Vec<u8>,
+    pub username: Option<String>,
+    pub expires: u64,
+}
+
+impl Session {
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires <= now
+    }
+}
+
+// storage for the raw session data managed by gotham's session middleware.
+// the username of the logged in user (if any) is stored alongside the data
+// so that all of a user's sessions can be found without having to decode
+// every session.
+pub trait Store: Send + Sync {
+    fn persist(&self, id: &str, session: &Session) -> Result<()>;
+    fn read(&self, id: &str) -> Result<Option<Session>>;
+    fn remove(&self, id: &str) -> Result<()>;
+    fn remove_expired(&self, now: u64) -> Result<usize>;
+    fn remove_user(&self, username: &str) -> Result<usize>;
+}
+
+pub fn store(
+    backend: crate::config::SessionBackend,
+    sqlite_file: &str,
+) -> Result<std::sync::Arc<dyn Store>> {
+    Ok(match backend {
+        crate::config::SessionBackend::Disk => {
+            std::sync::Arc::new(DiskStore::new())
+        }
+        crate::config::SessionBackend::Memory => {
+            std::sync::Arc::new(MemoryStore::new())
+        }
+        crate::config::SessionBackend::Sqlite => {
+            std::sync::Arc::new(SqliteStore::new(sqlite_file)?)
+        }
+    })
+}
+
+#[derive(Clone)]
+pub struct Backend {
+    store: std::sync::Arc<dyn Store>,
+    ttl: std::time::Duration,
+}
+
+impl Backend {
+    pub fn new(
+        store: std::sync::Arc<dyn Store>,
+        ttl: std::time::Duration,
+    ) -> Self {
+        Self { store, ttl }
+    }
+}
+
+impl gotham::middleware::session::NewBackend for Backend {
+    type Instance = Self;
+
+    fn new_backend(&self) -> std::io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+impl gotham::middleware::session::Backend for Backend {
+    fn persist_session(
+        &self,
+        identifier: gotham::middleware::session::SessionIdentifier,
+        content: &[u8],
+    ) -> std::result::Result<(), gotham::middleware::session::SessionError>
+    {
+        let session = Session {
+            content: content.to_vec(),
+            username: session_username(content),
+            expires: now() + self.ttl.as_secs(),
+        };
+        self.store
+            .persist(&identifier.value, &session)
+            .map_err(backend_error)
+    }
+
+    fn read_session(
+        &self,
+        identifier: gotham::middleware::session::SessionIdentifier,
+    ) -> Box<
+        dyn futures::Future<
+                Item = Option<Vec<u8>>,
+                Error = gotham::middleware::session::SessionError,
+            > + Send,
+    > {
+        let res = match self.store.read(&identifier.value) {
+            Ok(Some(session)) => {
+                if session.is_expired(now()) {
+                    self.store
+                        .remove(&identifier.value)
+                        .map(|_| None)
+                        .map_err(backend_error)
+                } else {
+                    Ok(Some(session.content))
+                }
+            }
+            Ok(None) => Ok(None),
+            Err(e) => Err(backend_error(e)),
+        };
+        Box::new(futures::future::result(res))
+    }
+
+    fn drop_session(
+        &self,
+        identifier: gotham::middleware::session::SessionIdentifier,
+    ) -> std::result::Result<(), gotham::middleware::session::SessionError>
+    {
+        self.store.remove(&identifier.value).map_err(backend_error)
+    }
+}
+
+pub struct Gc {
+    store: std::sync::Arc<dyn Store>,
+    timer: tokio::timer::Interval,
+}
+
+impl Gc {
+    pub fn new(
+        store: std::sync::Arc<dyn Store>,
+        interval: std::time::Duration,
+    ) -> Self {
+        Self {
+            store,
+            timer: tokio::timer::Interval::new(
+                std::time::Instant::now(),
+                interval,
+            ),
+        }
+    }
+}
+
+impl Gc {
+    const POLL_FNS:
+        &'static [&'static dyn for<'a> Fn(
+            &'a mut Self,
+        )
+            -> component_future::Poll<
+            (),
+            Error,
+        >] = &[&Self::poll_timer];
+
+    fn poll_timer(&mut self) -> component_future::Poll<(), Error> {
+        component_future::try_ready!(self
+            .timer
+            .poll()
+            .context(crate::error::TimerSessionGc))
+        .unwrap();
+        match self.store.remove_expired(now()) {
+            Ok(0) => {}
+            Ok(count) => log::info!("removed {} expired web sessions", count),
+            Err(e) => log::warn!("failed to remove expired sessions: {}", e),
+        }
+        Ok(component_future::Async::DidWork)
+    }
+}
+
+impl futures::Future for Gc {
+    type Item = ();
+    type Error = Error;
+
+    fn poll(&mut self) -> futures::Poll<Self::Item, Self::Error> {
+        component_future::poll_future(self, Self::POLL_FNS)
+    }
+}
+
+// gotham serializes session data with bincode, so we can peek at it to see
+// who (if anyone) is logged in
+fn session_username(content: &[u8]) -> Option<String> {
+    bincode::deserialize::<crate::web::SessionData>(content)
+        .ok()
+        .and_then(|data| data.login)
+        .map(|login| login.username)
+}
+
+fn backend_error(e: Error) -> gotham::middleware::session::SessionError {
+    gotham::middleware::session::SessionError::Backend(format!("{}", e))
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
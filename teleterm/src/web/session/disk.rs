@@ -0,0 +1,236 @@
+use crate::prelude::*;
+
+use std::io::{Read as _, Write as _};
+use std::os::unix::fs::OpenOptionsExt as _;
+
+const FILE_PREFIX: &str = "web-";
+// sessions are written to a temporary file first, which must not look like
+// a session file itself, or garbage collection could remove it mid-write
+const TMP_FILE_PREFIX: &str = ".tmp-web-";
+
+// stores each session as a separate web-<id> file in the data directory
+pub struct DiskStore {
+    dir: std::path::PathBuf,
+}
+
+impl DiskStore {
+    pub fn new() -> Self {
+        Self::in_dir(crate::dirs::Dirs::new().writable_data_dir())
+    }
+
+    fn in_dir(dir: &std::path::Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    fn file_for_id(&self, id: &str) -> std::path::PathBuf {
+        self.dir.join(format!("{}{}", FILE_PREFIX, id))
+    }
+
+    fn read_file(
+        &self,
+        filename: &std::path::Path,
+    ) -> Result<Option<super::Session>> {
+        let mut file = std::fs::File::open(filename).with_context(|| {
+            crate::error::OpenFileSync {
+                filename: filename.to_string_lossy(),
+            }
+        })?;
+        let mut contents = vec![];
+        file.read_to_end(&mut contents)
+            .context(crate::error::ReadFileSync)?;
+
+        // session files written by older versions just contain the raw
+        // session data, without a username or expiration time - treat these
+        // as invalid so that they get cleaned up
+        Ok(serde_json::from_slice(&contents).ok())
+    }
+
+    // returns false if the file was already gone
+    fn remove_file(&self, filename: &std::path::Path) -> Result<bool> {
+        match std::fs::remove_file(filename) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e).with_context(|| crate::error::RemoveFileSync {
+                filename: filename.to_string_lossy(),
+            }),
+        }
+    }
+
+    // a problem with one session file (including it being removed by a
+    // concurrent request while we're looking at it) shouldn't stop the rest
+    // from being cleaned up
+    fn remove_matching(
+        &self,
+        f: impl Fn(Option<&super::Session>) -> bool,
+    ) -> Result<usize> {
+        let entries = std::fs::read_dir(&self.dir).with_context(|| {
+            crate::error::ReadDirSync {
+                filename: self.dir.to_string_lossy(),
+            }
+        })?;
+
+        let mut count = 0;
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    log::warn!(
+                        "failed to read entry in {}: {}",
+                        self.dir.to_string_lossy(),
+                        e
+                    );
+                    continue;
+                }
+            };
+            let is_session = entry
+                .file_name()
+                .to_str()
+                .map_or(false, |name| name.starts_with(FILE_PREFIX));
+            if !is_session {
+                continue;
+            }
+
+            let filename = entry.path();
+            match self.remove_if(&filename, &f) {
+                Ok(true) => count += 1,
+                Ok(false) => {}
+                Err(e) => log::warn!("{}", e),
+            }
+        }
+        Ok(count)
+    }
+
+    fn remove_if(
+        &self,
+        filename: &std::path::Path,
+        f: impl Fn(Option<&super::Session>) -> bool,
+    ) -> Result<bool> {
+        let session = match self.read_file(filename) {
+            Ok(session) => session,
+            // removed since we listed the directory
+            Err(_) if !filename.exists() => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        if !f(session.as_ref()) {
+            return Ok(false);
+        }
+        self.remove_file(filename)
+    }
+}
+
+impl super::Store for DiskStore {
+    // session files identify logged in users, so only we should be able to
+    // read them
+    fn persist(&self, id: &str, session: &super::Session) -> Result<()> {
+        let filename = self.file_for_id(id);
+        let tmp_filename =
+            self.dir.join(format!("{}{}", TMP_FILE_PREFIX, id));
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&tmp_filename)
+            .with_context(|| crate::error::CreateFileSync {
+                filename: tmp_filename.to_string_lossy(),
+            })?;
+        file.write_all(&serde_json::to_vec(session).unwrap())
+            .context(crate::error::WriteFileSync)?;
+        file.sync_all().context(crate::error::WriteFileSync)?;
+        std::fs::rename(&tmp_filename, &filename)
+            .context(crate::error::WriteFileSync)?;
+        Ok(())
+    }
+
+    fn read(&self, id: &str) -> Result<Option<super::Session>> {
+        let filename = self.file_for_id(id);
+        if !filename.exists() {
+            return Ok(None);
+        }
+        self.read_file(&filename)
+    }
+
+    fn remove(&self, id: &str) -> Result<()> {
+        self.remove_file(&self.file_for_id(id))?;
+        Ok(())
+    }
+
+    fn remove_expired(&self, now: u64) -> Result<usize> {
+        self.remove_matching(|session| {
+            session.map_or(true, |session| session.is_expired(now))
+        })
+    }
+
+    fn remove_user(&self, username: &str) -> Result<usize> {
+        self.remove_matching(|session| {
+            session
+                .and_then(|session| session.username.as_ref())
+                .map_or(false, |session_username| {
+                    session_username == username
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::Store as _;
+    use super::*;
+    use std::os::unix::fs::PermissionsExt as _;
+
+    fn session(
+        username: Option<&str>,
+        expires: u64,
+    ) -> super::super::Session {
+        super::super::Session {
+            content: b"data".to_vec(),
+            username: username.map(std::string::ToString::to_string),
+            expires,
+        }
+    }
+
+    #[test]
+    fn test_disk_store() {
+        let dir = std::env::temp_dir()
+            .join(format!("teleterm-test-sessions-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = DiskStore::in_dir(&dir);
+        store.persist("a", &session(Some("doy"), 10)).unwrap();
+        store.persist("b", &session(Some("doy"), 20)).unwrap();
+        store.persist("c", &session(Some("sartak"), 20)).unwrap();
+        store.persist("d", &session(None, 30)).unwrap();
+        // left behind by an older version
+        std::fs::write(dir.join("web-e"), b"garbage").unwrap();
+        // not a session file at all
+        std::fs::write(dir.join("other"), b"other").unwrap();
+
+        let mode = std::fs::metadata(store.file_for_id("a"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        assert_eq!(store.read("a").unwrap().unwrap().content, b"data");
+        assert!(store.read("e").unwrap().is_none());
+        assert!(store.read("f").unwrap().is_none());
+
+        assert_eq!(store.remove_expired(15).unwrap(), 2);
+        assert!(store.read("a").unwrap().is_none());
+        assert!(store.read("b").unwrap().is_some());
+        assert!(dir.join("other").exists());
+
+        assert_eq!(store.remove_user("doy").unwrap(), 1);
+        assert!(store.read("b").unwrap().is_none());
+        assert!(store.read("c").unwrap().is_some());
+        assert!(store.read("d").unwrap().is_some());
+
+        store.remove("c").unwrap();
+        assert!(store.read("c").unwrap().is_none());
+        // already removed
+        store.remove("c").unwrap();
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
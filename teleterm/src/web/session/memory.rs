@@ -0,0 +1,93 @@
+use crate::prelude::*;
+
+// sessions are lost when the server restarts, which is mostly useful for
+// testing or for deployments which don't have a writable data directory
+pub struct MemoryStore {
+    sessions:
+        std::sync::Mutex<std::collections::HashMap<String, super::Session>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self {
+            sessions: std::sync::Mutex::new(std::collections::HashMap::new()),
+        }
+    }
+}
+
+impl super::Store for MemoryStore {
+    fn persist(&self, id: &str, session: &super::Session) -> Result<()> {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.insert(id.to_string(), session.clone());
+        Ok(())
+    }
+
+    fn read(&self, id: &str) -> Result<Option<super::Session>> {
+        let sessions = self.sessions.lock().unwrap();
+        Ok(sessions.get(id).cloned())
+    }
+
+    fn remove(&self, id: &str) -> Result<()> {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.remove(id);
+        Ok(())
+    }
+
+    fn remove_expired(&self, now: u64) -> Result<usize> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let before = sessions.len();
+        sessions.retain(|_, session| !session.is_expired(now));
+        Ok(before - sessions.len())
+    }
+
+    fn remove_user(&self, username: &str) -> Result<usize> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let before = sessions.len();
+        sessions.retain(|_, session| {
+            session.username.as_ref().map(std::string::String::as_str)
+                != Some(username)
+        });
+        Ok(before - sessions.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::Store as _;
+    use super::*;
+
+    fn session(
+        username: Option<&str>,
+        expires: u64,
+    ) -> super::super::Session {
+        super::super::Session {
+            content: b"data".to_vec(),
+            username: username.map(std::string::ToString::to_string),
+            expires,
+        }
+    }
+
+    #[test]
+    fn test_memory_store() {
+        let store = MemoryStore::new();
+        store.persist("a", &session(Some("doy"), 10)).unwrap();
+        store.persist("b", &session(Some("doy"), 20)).unwrap();
+        store.persist("c", &session(Some("sartak"), 20)).unwrap();
+        store.persist("d", &session(None, 30)).unwrap();
+
+        assert_eq!(store.read("a").unwrap().unwrap().content, b"data");
+        assert!(store.read("e").unwrap().is_none());
+
+        assert_eq!(store.remove_expired(15).unwrap(), 1);
+        assert!(store.read("a").unwrap().is_none());
+        assert!(store.read("b").unwrap().is_some());
+
+        assert_eq!(store.remove_user("doy").unwrap(), 1);
+        assert!(store.read("b").unwrap().is_none());
+        assert!(store.read("c").unwrap().is_some());
+        assert!(store.read("d").unwrap().is_some());
+
+        store.remove("c").unwrap();
+        assert!(store.read("c").unwrap().is_none());
+    }
+}
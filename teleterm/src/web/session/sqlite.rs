@@ -0,0 +1,157 @@
+use crate::prelude::*;
+
+pub struct SqliteStore {
+    conn: std::sync::Mutex<rusqlite::Connection>,
+}
+
+impl SqliteStore {
+    pub fn new(filename: &str) -> Result<Self> {
+        let conn = rusqlite::Connection::open(filename)
+            .context(crate::error::OpenSessionDatabase { filename })?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS sessions (
+                id TEXT PRIMARY KEY,
+                content BLOB NOT NULL,
+                username TEXT,
+                expires INTEGER NOT NULL
+            )",
+            rusqlite::NO_PARAMS,
+        )
+        .context(crate::error::QuerySessionDatabase)?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS sessions_username
+                ON sessions (username)",
+            rusqlite::NO_PARAMS,
+        )
+        .context(crate::error::QuerySessionDatabase)?;
+        Ok(Self {
+            conn: std::sync::Mutex::new(conn),
+        })
+    }
+}
+
+impl super::Store for SqliteStore {
+    fn persist(&self, id: &str, session: &super::Session) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO sessions (id, content, username, expires)
+                VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![
+                id,
+                session.content,
+                session.username,
+                to_sql_time(session.expires),
+            ],
+        )
+        .context(crate::error::QuerySessionDatabase)?;
+        Ok(())
+    }
+
+    fn read(&self, id: &str) -> Result<Option<super::Session>> {
+        let conn = self.conn.lock().unwrap();
+        let res = conn.query_row(
+            "SELECT content, username, expires FROM sessions WHERE id = ?1",
+            rusqlite::params![id],
+            |row| {
+                Ok(super::Session {
+                    content: row.get(0)?,
+                    username: row.get(1)?,
+                    expires: from_sql_time(row.get(2)?),
+                })
+            },
+        );
+        match res {
+            Ok(session) => Ok(Some(session)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e).context(crate::error::QuerySessionDatabase),
+        }
+    }
+
+    fn remove(&self, id: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM sessions WHERE id = ?1",
+            rusqlite::params![id],
+        )
+        .context(crate::error::QuerySessionDatabase)?;
+        Ok(())
+    }
+
+    fn remove_expired(&self, now: u64) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM sessions WHERE expires <= ?1",
+            rusqlite::params![to_sql_time(now)],
+        )
+        .context(crate::error::QuerySessionDatabase)
+    }
+
+    fn remove_user(&self, username: &str) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM sessions WHERE username = ?1",
+            rusqlite::params![username],
+        )
+        .context(crate::error::QuerySessionDatabase)
+    }
+}
+
+// sqlite integers are signed, but timestamps won't be anywhere near large
+// enough for that to matter
+#[allow(clippy::cast_possible_wrap)]
+fn to_sql_time(time: u64) -> i64 {
+    time as i64
+}
+
+#[allow(clippy::cast_sign_loss)]
+fn from_sql_time(time: i64) -> u64 {
+    time as u64
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::Store as _;
+    use super::*;
+
+    fn session(
+        username: Option<&str>,
+        expires: u64,
+    ) -> super::super::Session {
+        super::super::Session {
+            content: b"data".to_vec(),
+            username: username.map(std::string::ToString::to_string),
+            expires,
+        }
+    }
+
+    #[test]
+    fn test_sqlite_store() {
+        let store = SqliteStore::new(":memory:").unwrap();
+        store.persist("a", &session(Some("doy"), 10)).unwrap();
+        store.persist("b", &session(Some("doy"), 20)).unwrap();
+        store.persist("c", &session(Some("sartak"), 20)).unwrap();
+        store.persist("d", &session(None, 30)).unwrap();
+
+        let session_a = store.read("a").unwrap().unwrap();
+        assert_eq!(session_a.content, b"data");
+        assert_eq!(session_a.username, Some("doy".to_string()));
+        assert_eq!(session_a.expires, 10);
+        assert!(store.read("e").unwrap().is_none());
+
+        // persisting again replaces the existing session
+        store.persist("a", &session(Some("doy"), 12)).unwrap();
+        assert_eq!(store.read("a").unwrap().unwrap().expires, 12);
+
+        assert_eq!(store.remove_expired(15).unwrap(), 1);
+        assert!(store.read("a").unwrap().is_none());
+        assert!(store.read("b").unwrap().is_some());
+
+        assert_eq!(store.remove_user("doy").unwrap(), 1);
+        assert!(store.read("b").unwrap().is_none());
+        assert!(store.read("c").unwrap().is_some());
+        assert!(store.read("d").unwrap().is_some());
+
+        store.remove("c").unwrap();
+        assert!(store.read("c").unwrap().is_none());
+    }
+}